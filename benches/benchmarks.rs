@@ -162,6 +162,7 @@ fn bench_index_reading(c: &mut Criterion) {
 }
 
 fn bench_protocol(c: &mut Criterion) {
+    use fxi::index::types::Language;
     use fxi::server::protocol::{
         ContentMatch, ContentSearchResponse, Response, read_message_with_id, write_message_with_id,
     };
@@ -176,6 +177,9 @@ fn bench_protocol(c: &mut Criterion) {
             match_end: 18,
             context_before: vec![(i as u32, format!("// context before line {i}"))],
             context_after: vec![(i as u32 + 2, format!("// context after line {i}"))],
+            enclosing_scope: None,
+            duplicates: vec![],
+            language: Language::Rust,
         })
         .collect();
     let response = Response::ContentSearch(ContentSearchResponse {
@@ -183,6 +187,10 @@ fn bench_protocol(c: &mut Criterion) {
         duration_ms: 12.5,
         files_with_matches: 100,
         resolved_root: Some(PathBuf::from("/home/user/project")),
+        suggestions: vec![],
+        explain: None,
+        facets: None,
+        warnings: Vec::new(),
     });
 
     let mut encoded = Vec::new();
@@ -6,4 +6,15 @@ fuzz_target!(|data: &str| {
     // Fuzz the query parser with arbitrary strings
     // This should not panic or cause undefined behavior
     let _ = fxi::query::parse_query(data);
+
+    // Also fuzz the include/exclude filter syntax (negated fields, comma
+    // lists, brace alternations) by embedding the input in field values
+    for field in ["path", "file", "ext", "lang"] {
+        let query = fxi::query::parse_query(&format!("{field}:{data} -{field}:{data}"));
+        let filters = &query.filters;
+        let _ = filters.has_any();
+        for value in filters.path.include.iter().chain(&filters.path.exclude) {
+            assert!(!value.is_empty());
+        }
+    }
 });
@@ -1,16 +1,21 @@
+use crate::index::content_store::{self, CompressedContent};
+use crate::index::git;
+use crate::index::minhash::{self, Signature};
 use crate::index::reader::IndexReader;
-use crate::index::types::{DocFlags, IndexConfig, IndexMeta, Language, SegmentId};
+use crate::index::types::{DocFlags, GitState, IndexConfig, IndexMeta, Language, SegmentId};
 use crate::index::writer::ChunkedIndexWriter;
+use crate::utils::hash::content_hash;
 use crate::utils::{
-    extract_tokens_and_positions, extract_trigrams, find_codebase_root, get_index_dir, is_binary,
-    is_minified, remove_index,
+    extract_tokens_and_positions, extract_trigrams, find_codebase_root, get_index_dir,
+    get_rev_index_dir, is_binary, is_minified, remove_index,
 };
 use anyhow::{Context, Result};
 use ignore::WalkBuilder;
 use indicatif::{ProgressBar, ProgressStyle};
 use memmap2::Mmap;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -44,6 +49,68 @@ pub fn is_known_binary_ext(ext: &str) -> bool {
     )
 }
 
+/// Directory names the walkers never descend into
+fn is_skipped_dir_name(name: &str) -> bool {
+    matches!(
+        name,
+        ".git" | "node_modules" | "target" | ".codesearch" | "__pycache__" | ".venv" | "venv"
+    )
+}
+
+/// The tree walk every index build and update uses: hidden files, ignore
+/// files (`.ignore`, `.gitignore`, `.git/info/exclude`, global excludes)
+/// and common non-code directories are skipped
+fn walker(start: &Path) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(start);
+    builder
+        .hidden(true)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .filter_entry(|entry| !is_skipped_dir_name(&entry.file_name().to_string_lossy()));
+    builder
+}
+
+/// Whether the tree walk would reach a path, answered without walking the
+/// tree: each directory on the way to it is listed once, one level deep,
+/// with the walk's own filters
+struct WalkFilter<'a> {
+    root: &'a Path,
+    /// Names the walk yields in each listed directory (relative to `root`)
+    listed: HashMap<PathBuf, HashSet<OsString>>,
+}
+
+impl<'a> WalkFilter<'a> {
+    fn new(root: &'a Path) -> Self {
+        Self {
+            root,
+            listed: HashMap::new(),
+        }
+    }
+
+    fn walks(&mut self, rel_path: &Path) -> bool {
+        let mut dir = PathBuf::new();
+        for component in rel_path.components() {
+            let root = self.root;
+            let names = self.listed.entry(dir.clone()).or_insert_with(|| {
+                let start = root.join(&dir);
+                walker(&start)
+                    .max_depth(Some(1))
+                    .build()
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.depth() == 1)
+                    .map(|entry| entry.file_name().to_os_string())
+                    .collect()
+            });
+            if !names.contains(component.as_os_str()) {
+                return false;
+            }
+            dir.push(component);
+        }
+        true
+    }
+}
+
 /// Result of processing a single file (computed in parallel)
 pub struct ProcessedFile {
     pub rel_path: PathBuf,
@@ -51,16 +118,28 @@ pub struct ProcessedFile {
     pub size: u64,
     pub language: Language,
     pub flags: DocFlags,
+    /// See [`crate::index::types::Document::content_hash`]
+    pub content_hash: u64,
     pub trigrams: Vec<u32>,
     pub tokens: Vec<String>,
     pub line_offsets: Vec<u32>,
     /// Token positions for positional phrase queries:
     /// (index into `tokens`, word_position)
     pub token_positions: Vec<(u32, u32)>,
+    /// Compressed snapshot for the segment's content store, when the index
+    /// keeps one
+    pub content: Option<CompressedContent>,
+    /// MinHash of `trigrams` (None for files too small to compare)
+    pub minhash: Option<Signature>,
 }
 
 /// Process a single file's content (can run in parallel)
-fn process_file_content(rel_path: PathBuf, content: &[u8], mtime: u64) -> Option<ProcessedFile> {
+pub(crate) fn process_file_content(
+    rel_path: PathBuf,
+    content: &[u8],
+    mtime: u64,
+    store_content: bool,
+) -> Option<ProcessedFile> {
     // Check if binary
     if is_binary(content) {
         return None;
@@ -90,16 +169,25 @@ fn process_file_content(rel_path: PathBuf, content: &[u8], mtime: u64) -> Option
     // Build line map
     let line_offsets = build_line_map(content);
 
+    let stored = if store_content {
+        Some(content_store::compress(content)?)
+    } else {
+        None
+    };
+
     Some(ProcessedFile {
         rel_path,
         mtime,
         size: content.len() as u64,
         language,
         flags,
+        content_hash: content_hash(content),
+        minhash: minhash::signature(&trigrams),
         trigrams,
         tokens,
         line_offsets,
         token_positions,
+        content: stored,
     })
 }
 
@@ -123,7 +211,7 @@ impl std::ops::Deref for FileBytes {
 }
 
 /// Build line offset map from content using fast memchr search
-fn build_line_map(content: &[u8]) -> Vec<u32> {
+pub(crate) fn build_line_map(content: &[u8]) -> Vec<u32> {
     use memchr::memchr_iter;
 
     // Pre-allocate: estimate ~50 chars per line on average
@@ -146,6 +234,7 @@ pub fn build_index(root_path: &Path, force: bool) -> Result<()> {
 }
 
 /// Build or rebuild the search index with custom chunk size
+#[allow(dead_code)]
 pub fn build_index_with_chunk_size(
     root_path: &Path,
     force: bool,
@@ -165,17 +254,36 @@ pub fn build_index_with_options(
     force: bool,
     silent: bool,
     chunk_size_override: Option<usize>,
+) -> Result<()> {
+    build_index_with_config(root_path, force, silent, chunk_size_override, None)
+}
+
+/// Build or rebuild the search index. `store_content` turns the compressed
+/// content store on or off; None keeps the existing index's setting.
+pub fn build_index_with_config(
+    root_path: &Path,
+    force: bool,
+    silent: bool,
+    chunk_size_override: Option<usize>,
+    store_content: Option<bool>,
 ) -> Result<()> {
     let root = root_path.canonicalize().context("Invalid path")?;
     let index_path = get_index_dir(&root)?;
 
+    let config = IndexConfig {
+        store_content: store_content.unwrap_or_else(|| stores_content(&index_path)),
+        ..IndexConfig::default()
+    };
+    let max_file_size = config.max_file_size;
+    let store_content = config.store_content;
+
     // Check if we should force rebuild
     if force && index_path.exists() {
         remove_index(&root).context("Failed to remove existing index")?;
     }
 
-    let config = IndexConfig::default();
-    let max_file_size = config.max_file_size;
+    // Captured before any file is read; see record_git_state
+    let git_before = git::snapshot(&root);
 
     if !silent {
         println!("Indexing: {}", root.display());
@@ -264,27 +372,7 @@ pub fn build_index_with_options(
             shared: Arc::clone(&entries),
         };
 
-        WalkBuilder::new(&root)
-            .hidden(true)
-            .git_ignore(true)
-            .git_global(true)
-            .git_exclude(true)
-            .filter_entry(|entry| {
-                let name = entry.file_name().to_string_lossy();
-                // Skip common non-code directories
-                !matches!(
-                    name.as_ref(),
-                    ".git"
-                        | "node_modules"
-                        | "target"
-                        | ".codesearch"
-                        | "__pycache__"
-                        | ".venv"
-                        | "venv"
-                )
-            })
-            .build_parallel()
-            .visit(&mut builder);
+        walker(&root).build_parallel().visit(&mut builder);
 
         drop(builder);
         Arc::try_unwrap(entries).unwrap().into_inner().unwrap()
@@ -442,7 +530,7 @@ pub fn build_index_with_options(
                     .unwrap_or(0);
 
                 // Process file content (trigrams, tokens, line map)
-                let result = process_file_content(rel_path.clone(), &content, mtime);
+                let result = process_file_content(rel_path.clone(), &content, mtime, store_content);
 
                 if result.is_some() {
                     total_processed_clone.fetch_add(1, Ordering::Relaxed);
@@ -540,6 +628,170 @@ pub fn build_index_with_options(
         eprintln!("({} files could not be read)", errors);
     }
 
+    record_git_state(&root, git_before)?;
+
+    Ok(())
+}
+
+/// Whether a path listed by git passes the work tree walk's filters:
+/// no hidden or skipped directories, no known binary extension
+pub(crate) fn is_indexed_tree_path(path: &Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    !is_known_binary_ext(ext)
+        && path.components().all(|c| {
+            let name = c.as_os_str().to_string_lossy();
+            !name.starts_with('.') && !is_skipped_dir_name(&name)
+        })
+}
+
+/// Build an index of a git revision without checking it out. Files are
+/// listed with `git ls-tree` and read through `git cat-file --batch`; the
+/// index is stored per commit next to the work tree index. A commit never
+/// changes, so an existing revision index is kept unless `force` is set.
+pub fn build_rev_index(
+    root_path: &Path,
+    rev: &str,
+    force: bool,
+    silent: bool,
+    store_content: bool,
+) -> Result<()> {
+    let root = root_path.canonicalize().context("Invalid path")?;
+    let commit = git::resolve_commit(&root, rev)?;
+    let short = &commit[..commit.len().min(12)];
+    let index_path = get_rev_index_dir(&root, &commit)?;
+
+    if index_path.exists() {
+        if !force {
+            if !silent {
+                println!("Index for {} ({}) is up to date", rev, short);
+            }
+            return Ok(());
+        }
+        fs::remove_dir_all(&index_path).context("Failed to remove existing index")?;
+    }
+
+    let config = IndexConfig {
+        store_content,
+        ..IndexConfig::default()
+    };
+    let max_file_size = config.max_file_size;
+
+    if !silent {
+        println!("Indexing: {} @ {} ({})", root.display(), rev, short);
+    }
+
+    // Same filters as the work tree walk; sizes come from the tree listing
+    let entries: Vec<PathBuf> = git::ls_tree(&root, &commit)?
+        .into_iter()
+        .filter(|entry| entry.size > 0 && entry.size <= max_file_size)
+        .map(|entry| entry.path)
+        .filter(|path| is_indexed_tree_path(path))
+        .collect();
+
+    // Every file of a commit gets the commit time as its mtime
+    let mtime = git::commit_time(&root, &commit).unwrap_or(0);
+    let blobs = git::BlobReader::new(&root, &commit);
+
+    let progress_bar = if !silent {
+        let pb = ProgressBar::new(entries.len() as u64);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) {msg}")
+                .unwrap()
+                .progress_chars("█▓▒░  "),
+        );
+        pb.set_message("Processing files...");
+        Some(pb)
+    } else {
+        None
+    };
+
+    let chunk_size = config.chunk_size.max(1);
+    let mut chunked_writer =
+        ChunkedIndexWriter::with_index_path(root.clone(), index_path.clone(), config)?;
+    let rejected_files = Mutex::new(Vec::<(PathBuf, u64)>::new());
+    let error_count = AtomicUsize::new(0);
+
+    for (chunk_idx, chunk) in entries.chunks(chunk_size).enumerate() {
+        // Blob reads are serialized on the cat-file pipe; extraction runs
+        // in parallel
+        let processed_files: Vec<ProcessedFile> = chunk
+            .par_iter()
+            .filter_map(|rel_path| {
+                if let Some(ref pb) = progress_bar {
+                    pb.inc(1);
+                }
+                let Some(content) = blobs.read(rel_path) else {
+                    error_count.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                };
+                let result = process_file_content(rel_path.clone(), &content, mtime, store_content);
+                if result.is_none() {
+                    rejected_files
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .push((rel_path.clone(), mtime));
+                }
+                result
+            })
+            .collect();
+
+        chunked_writer.write_chunk((chunk_idx + 1) as SegmentId, processed_files)?;
+    }
+
+    chunked_writer.set_rejected_files(
+        rejected_files
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    );
+    chunked_writer.finalize()?;
+
+    let mut meta: IndexMeta = serde_json::from_reader(
+        File::open(index_path.join("meta.json")).context("Failed to open meta.json")?,
+    )?;
+    meta.git_rev = Some(commit);
+    crate::index::writer::write_meta_atomic(&index_path, &meta)?;
+
+    if let Some(pb) = progress_bar {
+        pb.finish_with_message(format!("Processed {} files", meta.doc_count));
+    }
+    if !silent {
+        println!("Index stored at: {}", index_path.display());
+    }
+    let errors = error_count.load(Ordering::Relaxed);
+    if errors > 0 && !silent {
+        eprintln!("({} files could not be read)", errors);
+    }
+
+    Ok(())
+}
+
+/// Whether the index at `index_path` keeps a content store (false when
+/// there is no index yet)
+pub fn stores_content(index_path: &Path) -> bool {
+    File::open(index_path.join("meta.json"))
+        .ok()
+        .and_then(|f| serde_json::from_reader::<_, IndexMeta>(f).ok())
+        .is_some_and(|meta| meta.content_store)
+}
+
+/// Store the work tree's git state in meta.json now that the index reflects
+/// it. `before` must be captured before any file was read: combined with a
+/// snapshot taken now, files edited mid-pass end up in the dirty set and get
+/// rechecked by the next update.
+fn record_git_state(root: &Path, before: Option<GitState>) -> Result<()> {
+    let state = before
+        .zip(git::snapshot(root))
+        .and_then(|(before, after)| git::combine(before, after));
+
+    let index_path = get_index_dir(root)?;
+    let mut meta: IndexMeta = serde_json::from_reader(
+        File::open(index_path.join("meta.json")).context("Failed to open meta.json")?,
+    )?;
+    if meta.git_state != state {
+        meta.git_state = state;
+        crate::index::writer::write_meta_atomic(&index_path, &meta)?;
+    }
     Ok(())
 }
 
@@ -553,6 +805,9 @@ struct IndexDiff {
     new_files: Vec<(PathBuf, PathBuf)>, // (full_path, rel_path)
     /// Modified files (mtime changed)
     modified_files: Vec<(PathBuf, PathBuf, u32)>, // (full_path, rel_path, old_doc_id)
+    /// Files whose mtime changed but content hash did not: only their
+    /// mtime is refreshed (see [`skip_unchanged_content`])
+    touched_files: Vec<(PathBuf, u64)>, // (rel_path, current mtime)
     /// Deleted files (relative paths, to mark as tombstones)
     deleted_files: Vec<PathBuf>,
     /// Previously rejected files still present with unchanged mtime
@@ -562,6 +817,13 @@ struct IndexDiff {
     indexed_count: usize,
 }
 
+/// An indexed file as the change scan sees it
+struct IndexedFile {
+    doc_id: u32,
+    mtime: u64,
+    content_hash: u64,
+}
+
 /// Incrementally update the index (smart mode)
 /// Returns Ok(true) if incremental update was performed, Ok(false) if full rebuild was needed
 pub fn update_index(root_path: &Path) -> Result<bool> {
@@ -583,25 +845,45 @@ pub fn update_index(root_path: &Path) -> Result<bool> {
     // Open existing index to get file list
     let reader = IndexReader::open(&root)?;
 
-    // Build map of indexed files: rel_path -> (doc_id, mtime)
-    let mut indexed_files: HashMap<PathBuf, (u32, u64)> = HashMap::new();
+    // Build map of indexed files by relative path
+    let mut indexed_files: HashMap<PathBuf, IndexedFile> = HashMap::new();
     for doc_id in reader.valid_doc_ids().iter() {
         if let Some(doc) = reader.get_document(doc_id)
             && let Some(path) = reader.get_path(doc)
         {
-            indexed_files.insert(path.clone(), (doc_id, doc.mtime));
+            indexed_files.insert(
+                path.clone(),
+                IndexedFile {
+                    doc_id,
+                    mtime: doc.mtime,
+                    content_hash: doc.content_hash,
+                },
+            );
         }
     }
 
     // Previously rejected files (binary sniff etc.) with their mtimes
     let rejected: HashMap<PathBuf, u64> = meta.rejected_files.iter().cloned().collect();
 
-    // Compute diff with filesystem
-    let diff = compute_index_diff(&root, &indexed_files, &rejected)?;
+    // In a git work tree only the paths git reports as changed since the
+    // last update need a stat; otherwise walk the whole tree
+    let git_before = git::snapshot(&root);
+    let git_diff = meta
+        .git_state
+        .as_ref()
+        .zip(git_before.as_ref())
+        .and_then(|(since, now)| git::changed_paths(&root, since, now))
+        .map(|paths| compute_index_diff_for_paths(&root, &paths, &indexed_files, &rejected));
+    let diff = match git_diff {
+        Some(diff) => diff,
+        None => compute_index_diff(&root, &indexed_files, &rejected)?,
+    };
+    let diff = skip_unchanged_content(diff, &indexed_files);
 
     let total_changes = diff.new_files.len() + diff.modified_files.len() + diff.deleted_files.len();
 
-    if total_changes == 0 {
+    if total_changes == 0 && diff.touched_files.is_empty() {
+        record_git_state(&root, git_before)?;
         println!("Index is up to date, no changes detected.");
         return Ok(true);
     }
@@ -611,14 +893,22 @@ pub fn update_index(root_path: &Path) -> Result<bool> {
         .checked_div(diff.indexed_count)
         .unwrap_or(100);
 
-    println!(
-        "Detected {} changes: {} new, {} modified, {} deleted ({:.1}% of index)",
-        total_changes,
-        diff.new_files.len(),
-        diff.modified_files.len(),
-        diff.deleted_files.len(),
-        change_percent as f64
-    );
+    if total_changes > 0 {
+        println!(
+            "Detected {} changes: {} new, {} modified, {} deleted ({:.1}% of index)",
+            total_changes,
+            diff.new_files.len(),
+            diff.modified_files.len(),
+            diff.deleted_files.len(),
+            change_percent as f64
+        );
+    }
+    if !diff.touched_files.is_empty() {
+        println!(
+            "{} touched files have unchanged content, refreshing their mtimes",
+            diff.touched_files.len()
+        );
+    }
 
     // If too many changes, do full rebuild
     if change_percent > INCREMENTAL_THRESHOLD_PERCENT {
@@ -633,145 +923,258 @@ pub fn update_index(root_path: &Path) -> Result<bool> {
     // Perform incremental update
     println!("Performing incremental update...");
     perform_incremental_update(&root, &meta, diff)?;
+    record_git_state(&root, git_before)?;
 
     Ok(true)
 }
 
-/// Compute the difference between indexed files and filesystem
-fn compute_index_diff(
+/// Diff the index against an explicit set of candidate paths (from git)
+/// instead of walking the tree. A candidate the walk would skip (hidden,
+/// ignored by any ignore file, in a skipped directory, or with a binary
+/// extension) counts as absent, so both update paths index the same files.
+/// A directory candidate (a submodule or nested repository, which git
+/// reports whole) is walked, and a vanished one stands for the indexed
+/// files under it.
+fn compute_index_diff_for_paths(
     root: &Path,
-    indexed_files: &HashMap<PathBuf, (u32, u64)>,
+    candidates: &[PathBuf],
+    indexed_files: &HashMap<PathBuf, IndexedFile>,
     rejected: &HashMap<PathBuf, u64>,
-) -> Result<IndexDiff> {
-    let config = IndexConfig::default();
-    let max_file_size = config.max_file_size;
+) -> IndexDiff {
+    let max_file_size = IndexConfig::default().max_file_size;
+    let candidates = expand_subtrees(root, candidates, indexed_files, max_file_size);
+    let candidate_set: HashSet<&Path> = candidates.iter().map(|p| p.as_path()).collect();
 
-    // Walk the filesystem in parallel: the per-file metadata() stat dominates
-    // the scan on large trees, so it runs on the walker threads
-    let scanned: Vec<(PathBuf, PathBuf, u64)> = {
-        let entries: Arc<Mutex<Vec<(PathBuf, PathBuf, u64)>>> =
-            Arc::new(Mutex::new(Vec::with_capacity(indexed_files.len())));
-
-        struct ScanVisitor {
-            root: PathBuf,
-            max_file_size: u64,
-            shared: Arc<Mutex<Vec<(PathBuf, PathBuf, u64)>>>,
-            // Batch into a thread-local vec; take the shared lock once per
-            // walker thread instead of once per file
-            local: Vec<(PathBuf, PathBuf, u64)>,
+    let mut new_files = Vec::new();
+    let mut modified_files = Vec::new();
+    let mut deleted_files = Vec::new();
+    let mut rejected_unchanged = Vec::new();
+    let mut filter = WalkFilter::new(root);
+
+    for rel_path in &candidates {
+        let full_path = root.join(rel_path);
+        // symlink_metadata: the walker does not follow symlinks either
+        let metadata = fs::symlink_metadata(&full_path).ok();
+        if metadata.as_ref().is_some_and(|m| m.is_dir()) {
+            continue;
         }
 
-        impl ScanVisitor {
-            fn flush(&mut self) {
-                if !self.local.is_empty() {
-                    let mut entries = self
-                        .shared
-                        .lock()
-                        .unwrap_or_else(|poisoned| poisoned.into_inner());
-                    entries.append(&mut self.local);
+        let walked = metadata.is_some()
+            && !rel_path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(is_known_binary_ext)
+            && filter.walks(rel_path);
+
+        let current_mtime = metadata
+            .filter(|m| walked && m.is_file() && m.len() > 0 && m.len() <= max_file_size)
+            .map(|m| {
+                m.modified()
+                    .map(|t| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs())
+                    .unwrap_or(0)
+            });
+
+        match (current_mtime, indexed_files.get(rel_path)) {
+            (None, Some(_)) => deleted_files.push(rel_path.clone()),
+            (None, None) => {}
+            (Some(mtime), Some(indexed)) => {
+                if mtime != indexed.mtime {
+                    modified_files.push((full_path, rel_path.clone(), indexed.doc_id));
                 }
             }
-        }
-
-        impl ignore::ParallelVisitor for ScanVisitor {
-            fn visit(
-                &mut self,
-                result: Result<ignore::DirEntry, ignore::Error>,
-            ) -> ignore::WalkState {
-                if let Ok(entry) = result
-                    // file_type() comes from the directory entry — no extra
-                    // stat(2) per file like path().is_file()
-                    && entry.file_type().is_some_and(|t| t.is_file())
-                    && let Ok(rel_path) = entry.path().strip_prefix(&self.root)
-                    // Known-binary extensions are never indexed; skipping them
-                    // here keeps them from showing up as eternally-"new" files
-                    // that every incremental update re-reads and rejects
-                    && !rel_path
-                        .extension()
-                        .and_then(|e| e.to_str())
-                        .is_some_and(is_known_binary_ext)
-                    && let Ok(metadata) = entry.metadata()
-                    && metadata.len() <= self.max_file_size
-                    && metadata.len() > 0
-                {
-                    let mtime = metadata
-                        .modified()
-                        .map(|t| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs())
-                        .unwrap_or(0);
-                    let rel_path = rel_path.to_path_buf();
-                    self.local.push((entry.into_path(), rel_path, mtime));
+            (Some(mtime), None) => {
+                if rejected.get(rel_path) == Some(&mtime) {
+                    rejected_unchanged.push((rel_path.clone(), mtime));
+                } else {
+                    new_files.push((full_path, rel_path.clone()));
                 }
-                ignore::WalkState::Continue
             }
         }
+    }
 
-        impl Drop for ScanVisitor {
-            fn drop(&mut self) {
-                self.flush();
-            }
+    // Rejected files git didn't report are unchanged
+    rejected_unchanged.extend(
+        rejected
+            .iter()
+            .filter(|(path, _)| !candidate_set.contains(path.as_path()))
+            .map(|(path, mtime)| (path.clone(), *mtime)),
+    );
+
+    IndexDiff {
+        new_files,
+        modified_files,
+        touched_files: Vec::new(),
+        deleted_files,
+        rejected_unchanged,
+        indexed_count: indexed_files.len(),
+    }
+}
+
+/// `candidates` plus, for each directory among them, the files the walk
+/// finds under it and the indexed files under it; for each vanished path
+/// that is not an indexed file, the indexed files under it. Sorted and
+/// deduplicated.
+fn expand_subtrees(
+    root: &Path,
+    candidates: &[PathBuf],
+    indexed_files: &HashMap<PathBuf, IndexedFile>,
+    max_file_size: u64,
+) -> Vec<PathBuf> {
+    let mut expanded = candidates.to_vec();
+    // Sorted only when needed: component-wise order keeps a directory's
+    // files together, right after it
+    let mut indexed: Option<Vec<&Path>> = None;
+    for rel_path in candidates {
+        let metadata = fs::symlink_metadata(root.join(rel_path)).ok();
+        let is_dir = metadata.as_ref().is_some_and(|m| m.is_dir());
+        if !is_dir && (metadata.is_some() || indexed_files.contains_key(rel_path)) {
+            continue;
+        }
+        let indexed = indexed.get_or_insert_with(|| {
+            let mut paths: Vec<&Path> = indexed_files.keys().map(|p| p.as_path()).collect();
+            paths.sort_unstable();
+            paths
+        });
+        let start = indexed.partition_point(|p| *p < rel_path.as_path());
+        expanded.extend(
+            indexed[start..]
+                .iter()
+                .take_while(|p| p.starts_with(rel_path))
+                .map(|p| p.to_path_buf()),
+        );
+        if is_dir {
+            expanded.extend(
+                scan_tree(root, &root.join(rel_path), max_file_size, 0)
+                    .into_iter()
+                    .map(|(_, rel_path, _)| rel_path),
+            );
         }
+    }
+    expanded.sort();
+    expanded.dedup();
+    expanded
+}
 
-        struct ScanBuilder {
-            root: PathBuf,
-            max_file_size: u64,
-            shared: Arc<Mutex<Vec<(PathBuf, PathBuf, u64)>>>,
+/// Walk the tree under `start` (`root` or a directory in it) in parallel,
+/// returning (full path, path relative to `root`, mtime) of the files the
+/// index would take, with room for `expected` of them. The per-file
+/// metadata() stat dominates the scan on large trees, so it runs on the
+/// walker threads.
+fn scan_tree(
+    root: &Path,
+    start: &Path,
+    max_file_size: u64,
+    expected: usize,
+) -> Vec<(PathBuf, PathBuf, u64)> {
+    let entries: Arc<Mutex<Vec<(PathBuf, PathBuf, u64)>>> =
+        Arc::new(Mutex::new(Vec::with_capacity(expected)));
+
+    struct ScanVisitor {
+        root: PathBuf,
+        max_file_size: u64,
+        shared: Arc<Mutex<Vec<(PathBuf, PathBuf, u64)>>>,
+        // Batch into a thread-local vec; take the shared lock once per
+        // walker thread instead of once per file
+        local: Vec<(PathBuf, PathBuf, u64)>,
+    }
+
+    impl ScanVisitor {
+        fn flush(&mut self) {
+            if !self.local.is_empty() {
+                let mut entries = self
+                    .shared
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                entries.append(&mut self.local);
+            }
         }
+    }
 
-        impl<'s> ignore::ParallelVisitorBuilder<'s> for ScanBuilder {
-            fn build(&mut self) -> Box<dyn ignore::ParallelVisitor + 's> {
-                Box::new(ScanVisitor {
-                    root: self.root.clone(),
-                    max_file_size: self.max_file_size,
-                    shared: Arc::clone(&self.shared),
-                    local: Vec::with_capacity(1024),
-                })
+    impl ignore::ParallelVisitor for ScanVisitor {
+        fn visit(&mut self, result: Result<ignore::DirEntry, ignore::Error>) -> ignore::WalkState {
+            if let Ok(entry) = result
+                // file_type() comes from the directory entry — no extra
+                // stat(2) per file like path().is_file()
+                && entry.file_type().is_some_and(|t| t.is_file())
+                && let Ok(rel_path) = entry.path().strip_prefix(&self.root)
+                // Known-binary extensions are never indexed; skipping them
+                // here keeps them from showing up as eternally-"new" files
+                // that every incremental update re-reads and rejects
+                && !rel_path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(is_known_binary_ext)
+                && let Ok(metadata) = entry.metadata()
+                && metadata.len() <= self.max_file_size
+                && metadata.len() > 0
+            {
+                let mtime = metadata
+                    .modified()
+                    .map(|t| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs())
+                    .unwrap_or(0);
+                let rel_path = rel_path.to_path_buf();
+                self.local.push((entry.into_path(), rel_path, mtime));
             }
+            ignore::WalkState::Continue
         }
+    }
 
-        let mut builder = ScanBuilder {
-            root: root.to_path_buf(),
-            max_file_size,
-            shared: Arc::clone(&entries),
-        };
+    impl Drop for ScanVisitor {
+        fn drop(&mut self) {
+            self.flush();
+        }
+    }
+
+    struct ScanBuilder {
+        root: PathBuf,
+        max_file_size: u64,
+        shared: Arc<Mutex<Vec<(PathBuf, PathBuf, u64)>>>,
+    }
 
-        WalkBuilder::new(root)
-            .hidden(true)
-            .git_ignore(true)
-            .git_global(true)
-            .git_exclude(true)
-            .filter_entry(|entry| {
-                let name = entry.file_name().to_string_lossy();
-                !matches!(
-                    name.as_ref(),
-                    ".git"
-                        | "node_modules"
-                        | "target"
-                        | ".codesearch"
-                        | "__pycache__"
-                        | ".venv"
-                        | "venv"
-                )
+    impl<'s> ignore::ParallelVisitorBuilder<'s> for ScanBuilder {
+        fn build(&mut self) -> Box<dyn ignore::ParallelVisitor + 's> {
+            Box::new(ScanVisitor {
+                root: self.root.clone(),
+                max_file_size: self.max_file_size,
+                shared: Arc::clone(&self.shared),
+                local: Vec::with_capacity(1024),
             })
-            .build_parallel()
-            .visit(&mut builder);
+        }
+    }
 
-        drop(builder);
-        Arc::try_unwrap(entries).unwrap().into_inner().unwrap()
+    let mut builder = ScanBuilder {
+        root: root.to_path_buf(),
+        max_file_size,
+        shared: Arc::clone(&entries),
     };
 
+    walker(start).build_parallel().visit(&mut builder);
+
+    drop(builder);
+    Arc::try_unwrap(entries).unwrap().into_inner().unwrap()
+}
+
+/// Compute the difference between indexed files and filesystem
+fn compute_index_diff(
+    root: &Path,
+    indexed_files: &HashMap<PathBuf, IndexedFile>,
+    rejected: &HashMap<PathBuf, u64>,
+) -> Result<IndexDiff> {
+    let config = IndexConfig::default();
+    let scanned = scan_tree(root, root, config.max_file_size, indexed_files.len());
+
     let mut new_files = Vec::new();
     let mut modified_files = Vec::new();
     let mut rejected_unchanged = Vec::new();
-    let mut seen_paths: std::collections::HashSet<&Path> =
-        std::collections::HashSet::with_capacity(scanned.len());
+    let mut seen_paths: HashSet<&Path> = HashSet::with_capacity(scanned.len());
 
     for (full_path, rel_path, current_mtime) in &scanned {
         seen_paths.insert(rel_path.as_path());
 
-        if let Some(&(doc_id, indexed_mtime)) = indexed_files.get(rel_path) {
+        if let Some(indexed) = indexed_files.get(rel_path) {
             // File exists in index - check if modified
-            if *current_mtime != indexed_mtime {
-                modified_files.push((full_path.clone(), rel_path.clone(), doc_id));
+            if *current_mtime != indexed.mtime {
+                modified_files.push((full_path.clone(), rel_path.clone(), indexed.doc_id));
             }
         } else if rejected.get(rel_path) == Some(current_mtime) {
             // Previously rejected (binary sniff etc.) and unchanged since:
@@ -793,18 +1196,58 @@ fn compute_index_diff(
     Ok(IndexDiff {
         new_files,
         modified_files,
+        touched_files: Vec::new(),
         deleted_files,
         rejected_unchanged,
         indexed_count: indexed_files.len(),
     })
 }
 
+/// Move files whose mtime changed but whose content still hashes to the
+/// indexed value (a `touch`, or a checkout away and back) from
+/// `modified_files` to `touched_files`, so they are not re-extracted and
+/// tombstoned. Only mtime-changed files are read, in parallel.
+fn skip_unchanged_content(
+    mut diff: IndexDiff,
+    indexed_files: &HashMap<PathBuf, IndexedFile>,
+) -> IndexDiff {
+    let unchanged: Vec<Option<u64>> = diff
+        .modified_files
+        .par_iter()
+        .map(|(full_path, rel_path, _)| {
+            let indexed_hash = indexed_files.get(rel_path)?.content_hash;
+            if indexed_hash == 0 {
+                return None;
+            }
+            let metadata = fs::metadata(full_path).ok()?;
+            let content = fs::read(full_path).ok()?;
+            (content_hash(&content) == indexed_hash).then(|| {
+                metadata
+                    .modified()
+                    .map(|t| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs())
+                    .unwrap_or(0)
+            })
+        })
+        .collect();
+
+    let mut modified_files = Vec::with_capacity(diff.modified_files.len());
+    for (file, mtime) in diff.modified_files.into_iter().zip(unchanged) {
+        match mtime {
+            Some(mtime) => diff.touched_files.push((file.1, mtime)),
+            None => modified_files.push(file),
+        }
+    }
+    diff.modified_files = modified_files;
+    diff
+}
+
 /// Read and process a single file for an incremental update.
 /// Returns None for binary, empty, oversized or unreadable files.
 fn process_file_for_update(
     full_path: &Path,
     rel_path: &Path,
     max_file_size: u64,
+    store_content: bool,
 ) -> Option<ProcessedFile> {
     let ext = rel_path.extension().and_then(|e| e.to_str()).unwrap_or("");
     if is_known_binary_ext(ext) {
@@ -824,7 +1267,7 @@ fn process_file_for_update(
         .unwrap_or(0);
 
     let content = fs::read(full_path).ok()?;
-    process_file_content(rel_path.to_path_buf(), &content, mtime)
+    process_file_content(rel_path.to_path_buf(), &content, mtime, store_content)
 }
 
 /// Perform incremental update by writing the diff as a delta segment:
@@ -857,6 +1300,9 @@ fn perform_incremental_update(root: &Path, meta: &IndexMeta, diff: IndexDiff) ->
     for (_, rel_path, _) in &diff.modified_files {
         writer.mark_tombstone(rel_path);
     }
+    for (rel_path, mtime) in &diff.touched_files {
+        writer.refresh_mtime(rel_path, *mtime);
+    }
 
     // Index new and modified files (in parallel - extraction is the
     // expensive part; add_file itself is cheap)
@@ -870,7 +1316,7 @@ fn perform_incremental_update(root: &Path, meta: &IndexMeta, diff: IndexDiff) ->
     let outcomes: Vec<Result<ProcessedFile, (PathBuf, u64)>> = to_index
         .par_iter()
         .map(|(full, rel)| {
-            match process_file_for_update(full, rel, config.max_file_size) {
+            match process_file_for_update(full, rel, config.max_file_size, meta.content_store) {
                 Some(p) => Ok(p),
                 None => {
                     // Rejected (binary sniff etc.): remember it with its
@@ -911,13 +1357,19 @@ fn perform_incremental_update(root: &Path, meta: &IndexMeta, diff: IndexDiff) ->
     }
 
     // Commits segment -> docs.bin -> paths.bin -> meta.json atomically
+    let tombstones = diff.deleted_files.len() + diff.modified_files.len();
     writer.finalize(&mut meta)?;
-    println!(
-        "Wrote delta segment seg_{:04}: {} files indexed, {} tombstones",
-        next_segment_id,
-        added_count,
-        diff.deleted_files.len() + diff.modified_files.len()
-    );
+    if added_count + tombstones > 0 {
+        println!(
+            "Wrote delta segment seg_{:04}: {} files indexed, {} tombstones",
+            next_segment_id, added_count, tombstones
+        );
+    } else {
+        println!(
+            "Refreshed mtimes of {} unchanged files.",
+            diff.touched_files.len()
+        );
+    }
 
     // Compact when fragmentation builds up (same policy as the daemon)
     let tombstone_ratio = if meta.doc_count > 0 {
@@ -943,14 +1395,24 @@ fn perform_incremental_update(root: &Path, meta: &IndexMeta, diff: IndexDiff) ->
 }
 
 /// Build index, detecting codebase root from current directory
-/// Uses incremental update by default, force=true for full rebuild
-pub fn build_index_auto(start_path: &Path, force: bool, chunk_size: Option<usize>) -> Result<()> {
+/// Uses incremental update by default, force=true for full rebuild.
+/// `store_content` turns the content store on or off (None keeps it as is).
+pub fn build_index_auto(
+    start_path: &Path,
+    force: bool,
+    chunk_size: Option<usize>,
+    store_content: Option<bool>,
+) -> Result<()> {
     let root = find_codebase_root(start_path)?;
     println!("Detected codebase root: {}", root.display());
 
-    if force || chunk_size.is_some() {
+    // Existing segments can't gain or drop a store incrementally
+    let index_path = get_index_dir(&root)?;
+    let store_changed = store_content.is_some_and(|store| store != stores_content(&index_path));
+
+    if force || chunk_size.is_some() || store_changed {
         // Force full rebuild (also when chunk_size is specified, since incremental doesn't support it)
-        build_index_with_chunk_size(&root, true, chunk_size)
+        build_index_with_config(&root, true, false, chunk_size, store_content)
     } else {
         // Try incremental update first
         update_index(&root)?;
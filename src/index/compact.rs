@@ -5,6 +5,8 @@
 //! rebuild because it only reads/merges existing index data, avoiding expensive
 //! source file I/O.
 
+use crate::index::content_store::{CompressedContent, ContentStore, write_content_store};
+use crate::index::minhash::{MinHashStore, Signature, write_minhashes};
 use crate::index::reader::{read_documents, read_paths};
 use crate::index::segment_io;
 use crate::index::types::*;
@@ -61,6 +63,14 @@ pub fn merge_segments(root_path: &Path) -> Result<()> {
         anyhow::bail!("No index found. Run 'fxi index' first.");
     }
 
+    merge_index_segments(&index_path)
+}
+
+/// Merge the segments of the index stored at `index_path` (see
+/// [`merge_segments`]; also used for git history indexes)
+pub fn merge_index_segments(index_path: &Path) -> Result<()> {
+    let index_path = index_path.to_path_buf();
+
     // Read current metadata
     let meta_path = index_path.join("meta.json");
     let meta_file = File::open(&meta_path).context("Failed to open meta.json")?;
@@ -112,8 +122,23 @@ pub fn merge_segments(root_path: &Path) -> Result<()> {
         }
     );
 
-    // Step 3: Compute stop-grams from merged frequencies
+    // Stored snapshots are copied as compressed blocks, read now because
+    // the merged segment may overwrite an old segment's directory
+    let stored = if meta.content_store {
+        merge_content_stores(&index_path, &segment_ids, &remapping)?
+    } else {
+        Vec::new()
+    };
+    let minhashes = merge_minhashes(&index_path, &segment_ids, &remapping)?;
+
+    // Step 3: Compute stop-grams from merged frequencies, keeping their
+    // frequencies for `fxi terms --grams` since the dictionary drops them
     let stop_grams = compute_stop_grams(&trigram_postings, remapping.valid_docs.len(), 512);
+    let mut stop_gram_doc_freqs: Vec<(Trigram, u32)> = stop_grams
+        .iter()
+        .map(|t| (*t, trigram_postings[t].len() as u32))
+        .collect();
+    stop_gram_doc_freqs.sort_unstable();
     eprintln!("  Computed {} stop-grams", stop_grams.len());
 
     // Step 4: Write merged segment atomically
@@ -138,6 +163,10 @@ pub fn merge_segments(root_path: &Path) -> Result<()> {
     )?;
     segment_io::write_line_maps(&new_segment_path, &line_maps)?;
     segment_io::build_and_write_bloom(&new_segment_path, trigram_postings.keys().copied(), 10000)?;
+    write_minhashes(&new_segment_path, minhashes.iter().map(|(id, s)| (*id, s)))?;
+    if !stored.is_empty() {
+        write_content_store(&new_segment_path, stored.iter().map(|(id, c)| (*id, c)))?;
+    }
     eprintln!("  Wrote merged segment to seg_{:04}", new_segment_id);
 
     // Step 5: Write global files atomically
@@ -154,13 +183,14 @@ pub fn merge_segments(root_path: &Path) -> Result<()> {
         .as_secs();
 
     let new_meta = IndexMeta {
-        version: meta.version,
+        version: INDEX_VERSION,
         root_path: meta.root_path,
         doc_count: remapping.valid_docs.len() as u32,
         segment_count: 1,
         base_segment: Some(new_segment_id),
         delta_segments: Vec::new(),
         stop_grams: stop_grams.iter().copied().collect(),
+        stop_gram_doc_freqs,
         created_at: meta.created_at,
         updated_at: now,
         tombstone_count: 0,
@@ -170,6 +200,10 @@ pub fn merge_segments(root_path: &Path) -> Result<()> {
         // Compaction merges segments; the rejected-file scan cache is
         // unaffected and must survive the meta rewrite
         rejected_files: meta.rejected_files,
+        git_state: meta.git_state,
+        git_rev: meta.git_rev,
+        content_store: meta.content_store,
+        history_head: meta.history_head,
     };
     write_meta_atomic(&index_path, &new_meta)?;
     eprintln!("  Updated meta.json");
@@ -316,6 +350,54 @@ fn merge_all_segments(
     ))
 }
 
+/// Collect the stored snapshots of all valid docs under their new ids
+fn merge_content_stores(
+    index_path: &Path,
+    segment_ids: &[SegmentId],
+    remapping: &DocIdRemapping,
+) -> Result<Vec<(DocId, CompressedContent)>> {
+    let mut stored = Vec::new();
+    for &seg_id in segment_ids {
+        let segment_path = index_path
+            .join("segments")
+            .join(format!("seg_{:04}", seg_id));
+        let Some(store) = ContentStore::open(&segment_path)? else {
+            continue;
+        };
+        for old_id in store.doc_ids() {
+            if let Some(new_id) = remapping.remap(old_id)
+                && let Some(content) = store.get_compressed(old_id)
+            {
+                stored.push((new_id, content));
+            }
+        }
+    }
+    Ok(stored)
+}
+
+/// Collect the MinHash signatures of all valid docs under their new ids
+fn merge_minhashes(
+    index_path: &Path,
+    segment_ids: &[SegmentId],
+    remapping: &DocIdRemapping,
+) -> Result<Vec<(DocId, Signature)>> {
+    let mut minhashes = Vec::new();
+    for &seg_id in segment_ids {
+        let segment_path = index_path
+            .join("segments")
+            .join(format!("seg_{:04}", seg_id));
+        let Some(store) = MinHashStore::open(&segment_path)? else {
+            continue;
+        };
+        minhashes.extend(
+            store
+                .iter()
+                .filter_map(|(old_id, signature)| Some((remapping.remap(old_id)?, *signature))),
+        );
+    }
+    Ok(minhashes)
+}
+
 /// Merge trigram postings from a single segment.
 fn merge_trigram_segment(
     segment_path: &Path,
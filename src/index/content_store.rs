@@ -0,0 +1,188 @@
+//! Optional per-segment content store.
+//!
+//! With `fxi index --store-content`, every segment keeps a zstd-compressed
+//! snapshot of its documents next to the postings. Queries then verify and
+//! extract context from the snapshot instead of re-reading the work tree,
+//! so results always agree with the index (no races with edits in flight)
+//! and the index directory is self-contained.
+//!
+//! Files per segment:
+//! - `content.zst`: one zstd frame per document, concatenated
+//! - `content.dict`: entry count (u32), then per document, sorted by doc id:
+//!   doc_id (u32), offset (u64), compressed length (u32), raw length (u32)
+
+use crate::index::types::DocId;
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// zstd level: fast to write, still ~4x on source code
+const COMPRESSION_LEVEL: i32 = 3;
+
+const DICT_ENTRY_SIZE: usize = 20;
+
+/// A document compressed for the store (done during parallel extraction)
+#[derive(Debug, Clone)]
+pub struct CompressedContent {
+    pub raw_len: u32,
+    pub data: Vec<u8>,
+}
+
+/// Compress one document's content
+pub fn compress(content: &[u8]) -> Option<CompressedContent> {
+    let data = zstd::bulk::compress(content, COMPRESSION_LEVEL).ok()?;
+    Some(CompressedContent {
+        raw_len: content.len() as u32,
+        data,
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StoreEntry {
+    doc_id: DocId,
+    offset: u64,
+    compressed_len: u32,
+    raw_len: u32,
+}
+
+/// Write a segment's content store. `docs` may be in any order.
+pub fn write_content_store<'a>(
+    segment_path: &Path,
+    docs: impl IntoIterator<Item = (DocId, &'a CompressedContent)>,
+) -> Result<()> {
+    let mut docs: Vec<_> = docs.into_iter().collect();
+    docs.sort_unstable_by_key(|(doc_id, _)| *doc_id);
+
+    let mut data_file = BufWriter::new(File::create(segment_path.join("content.zst"))?);
+    let mut dict_file = BufWriter::new(File::create(segment_path.join("content.dict"))?);
+    dict_file.write_all(&(docs.len() as u32).to_le_bytes())?;
+
+    let mut offset: u64 = 0;
+    for (doc_id, content) in docs {
+        dict_file.write_all(&doc_id.to_le_bytes())?;
+        dict_file.write_all(&offset.to_le_bytes())?;
+        dict_file.write_all(&(content.data.len() as u32).to_le_bytes())?;
+        dict_file.write_all(&content.raw_len.to_le_bytes())?;
+        data_file.write_all(&content.data)?;
+        offset += content.data.len() as u64;
+    }
+
+    data_file.flush()?;
+    dict_file.flush()?;
+    Ok(())
+}
+
+/// Read side of a segment's content store
+pub struct ContentStore {
+    entries: Vec<StoreEntry>,
+    data: Option<Mmap>,
+}
+
+impl ContentStore {
+    /// Open the store of a segment, or None if it was written without one
+    pub fn open(segment_path: &Path) -> Result<Option<Self>> {
+        let dict_path = segment_path.join("content.dict");
+        if !dict_path.exists() {
+            return Ok(None);
+        }
+
+        let mut dict = Vec::new();
+        BufReader::new(File::open(&dict_path)?).read_to_end(&mut dict)?;
+        let count = u32::from_le_bytes(
+            dict.get(..4)
+                .context("Truncated content.dict")?
+                .try_into()?,
+        ) as usize;
+        if dict.len() < 4 + count * DICT_ENTRY_SIZE {
+            anyhow::bail!("Truncated content.dict");
+        }
+        let entries = dict[4..4 + count * DICT_ENTRY_SIZE]
+            .chunks_exact(DICT_ENTRY_SIZE)
+            .map(|e| StoreEntry {
+                doc_id: u32::from_le_bytes(e[0..4].try_into().unwrap()),
+                offset: u64::from_le_bytes(e[4..12].try_into().unwrap()),
+                compressed_len: u32::from_le_bytes(e[12..16].try_into().unwrap()),
+                raw_len: u32::from_le_bytes(e[16..20].try_into().unwrap()),
+            })
+            .collect();
+
+        // An empty file cannot be mapped
+        let data_file = File::open(segment_path.join("content.zst"))?;
+        let data = if data_file.metadata()?.len() > 0 {
+            Some(unsafe { Mmap::map(&data_file)? })
+        } else {
+            None
+        };
+
+        Ok(Some(Self { entries, data }))
+    }
+
+    fn entry(&self, doc_id: DocId) -> Option<&StoreEntry> {
+        self.entries
+            .binary_search_by_key(&doc_id, |e| e.doc_id)
+            .ok()
+            .map(|i| &self.entries[i])
+    }
+
+    fn compressed(&self, entry: &StoreEntry) -> Option<&[u8]> {
+        let start = entry.offset as usize;
+        self.data
+            .as_ref()?
+            .get(start..start + entry.compressed_len as usize)
+    }
+
+    /// Decompress a document's stored content
+    pub fn get(&self, doc_id: DocId) -> Option<Vec<u8>> {
+        let entry = self.entry(doc_id)?;
+        zstd::bulk::decompress(self.compressed(entry)?, entry.raw_len as usize).ok()
+    }
+
+    /// A document's compressed block as stored, for copying into a merged
+    /// segment without recompressing
+    pub fn get_compressed(&self, doc_id: DocId) -> Option<CompressedContent> {
+        let entry = self.entry(doc_id)?;
+        Some(CompressedContent {
+            raw_len: entry.raw_len,
+            data: self.compressed(entry)?.to_vec(),
+        })
+    }
+
+    /// Doc ids with stored content
+    pub fn doc_ids(&self) -> impl Iterator<Item = DocId> + '_ {
+        self.entries.iter().map(|e| e.doc_id)
+    }
+
+    /// Total (raw, compressed) bytes held
+    pub fn sizes(&self) -> (u64, u64) {
+        self.entries.iter().fold((0, 0), |(raw, compressed), e| {
+            (raw + e.raw_len as u64, compressed + e.compressed_len as u64)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_store_roundtrip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(ContentStore::open(dir.path()).unwrap().is_none());
+
+        let a = compress(b"fn a() {}\n".repeat(100).as_slice()).unwrap();
+        let b = compress(b"").unwrap();
+        write_content_store(dir.path(), [(7, &a), (3, &b)]).unwrap();
+
+        let store = ContentStore::open(dir.path()).unwrap().unwrap();
+        assert_eq!(store.get(7).unwrap(), b"fn a() {}\n".repeat(100));
+        assert_eq!(store.get(3).unwrap(), b"");
+        assert!(store.get(5).is_none());
+        assert_eq!(store.get_compressed(7).unwrap().data, a.data);
+
+        let (raw, compressed) = store.sizes();
+        assert_eq!(raw, 1000);
+        assert!(compressed < raw);
+    }
+}
@@ -0,0 +1,1076 @@
+//! Git-assisted change detection for incremental updates.
+//!
+//! Walking and stat'ing every file dominates `fxi index` on large trees. When
+//! the root is a git work tree, git already knows what changed: the commits
+//! between the indexed HEAD and the current one, plus whatever `git status`
+//! reports as modified or untracked. Only those paths need a stat; every
+//! other file is clean at a HEAD the index has already seen.
+//!
+//! The superproject's status says nothing about the files inside its
+//! submodules, which the walk does index. Submodule directories are
+//! therefore always reported dirty, for the caller to walk, and the
+//! `changed:`/`modified:` filters ask each submodule's own git.
+//!
+//! All git access goes through the local `git` binary. Any failure (no git,
+//! no commits yet, an indexed HEAD that was garbage-collected) returns `None`
+//! and the caller falls back to the full walk.
+//!
+//! The same binary serves revision indexes (`fxi index --rev`): the tree of a
+//! commit is listed with `git ls-tree` and blobs are streamed from a single
+//! long-running `git cat-file --batch` process. The history index
+//! (`fxi log`) reads the zero-context patches of every commit from a single
+//! `git log -p` stream.
+
+use crate::index::types::GitState;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+
+/// Capture HEAD and the dirty paths of the work tree at `root`.
+/// Returns None unless `root` is the top level of a git work tree.
+pub fn snapshot(root: &Path) -> Option<GitState> {
+    // .git is a directory in a normal clone and a file in worktrees
+    if !root.join(".git").exists() {
+        return None;
+    }
+
+    let head = run_git(root, &["rev-parse", "--verify", "HEAD"])?;
+    let head = String::from_utf8(head).ok()?.trim().to_string();
+
+    let status = run_git(
+        root,
+        &[
+            "status",
+            "--porcelain",
+            "-z",
+            "--untracked-files=all",
+            "--no-renames",
+            "--ignore-submodules",
+        ],
+    )?;
+
+    let mut dirty = parse_status(&status);
+    dirty.extend(submodule_paths(root));
+    dirty.sort();
+    dirty.dedup();
+    Some(GitState { head, dirty })
+}
+
+/// Paths of the submodules declared in `.gitmodules` at `root`, relative
+/// to it. Submodules nested in them are not listed.
+pub fn submodule_paths(root: &Path) -> Vec<PathBuf> {
+    if !root.join(".gitmodules").exists() {
+        return Vec::new();
+    }
+    // "submodule.<name>.path\n<path>" entries
+    run_git(
+        root,
+        &[
+            "config",
+            "--file",
+            ".gitmodules",
+            "-z",
+            "--get-regexp",
+            r"^submodule\..*\.path$",
+        ],
+    )
+    .map(|out| {
+        split_z(&out)
+            .filter_map(|entry| {
+                let newline = entry.iter().position(|&b| b == b'\n')?;
+                Some(git_path(&entry[newline + 1..]))
+            })
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Whether the submodule at `path` is checked out (has its own `.git`)
+fn is_checked_out(root: &Path, path: &Path) -> bool {
+    root.join(path).join(".git").exists()
+}
+
+/// Merge snapshots taken before and after an index pass. A file that
+/// changed while the pass read it shows up dirty in one of them, so the next
+/// update rechecks it. Returns None if HEAD moved in between: the indexed
+/// content then matches neither commit.
+pub fn combine(before: GitState, after: GitState) -> Option<GitState> {
+    if before.head != after.head {
+        return None;
+    }
+    let mut dirty = before.dirty;
+    dirty.extend(after.dirty);
+    dirty.sort();
+    dirty.dedup();
+    Some(GitState {
+        head: after.head,
+        dirty,
+    })
+}
+
+/// Paths that may differ from the index recorded at `since`: files changed
+/// by commits from `since.head` to `now.head`, plus files dirty at either
+/// point (a file dirty then but clean now was reverted, and the index holds
+/// the dirty content). A submodule shows up as its directory, which may be
+/// gone when the commits removed it.
+pub fn changed_paths(root: &Path, since: &GitState, now: &GitState) -> Option<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = since.dirty.iter().chain(&now.dirty).cloned().collect();
+
+    if since.head != now.head {
+        let diff = run_git(
+            root,
+            &[
+                "diff",
+                "--name-only",
+                "-z",
+                "--no-renames",
+                &since.head,
+                &now.head,
+            ],
+        )?;
+        paths.extend(split_z(&diff).map(git_path));
+    }
+
+    paths.sort();
+    paths.dedup();
+    Some(paths)
+}
+
+/// Resolve a revision (branch, tag, abbreviated SHA...) to a full commit SHA
+pub fn resolve_commit(root: &Path, rev: &str) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["rev-parse", "--verify", "--end-of-options"])
+        .arg(format!("{}^{{commit}}", rev))
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Unknown revision '{}' in {}: {}",
+            rev,
+            root.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Committer timestamp of `commit`, used as the mtime of its files
+pub fn commit_time(root: &Path, commit: &str) -> Option<u64> {
+    let out = run_git(root, &["show", "-s", "--format=%ct", commit])?;
+    String::from_utf8(out).ok()?.trim().parse().ok()
+}
+
+/// A regular file in a commit's tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// List the regular files of `commit` (symlinks and submodules are skipped,
+/// matching the work tree walk)
+pub fn ls_tree(root: &Path, commit: &str) -> Result<Vec<TreeEntry>> {
+    let out = run_git(
+        root,
+        &["ls-tree", "-r", "-z", "--long", "--full-tree", commit],
+    )
+    .with_context(|| format!("Failed to list the tree of {}", commit))?;
+    Ok(split_z(&out).filter_map(parse_tree_entry).collect())
+}
+
+/// Parse one `git ls-tree --long` entry: "<mode> <type> <sha> <size>\t<path>"
+fn parse_tree_entry(entry: &[u8]) -> Option<TreeEntry> {
+    let tab = entry.iter().position(|&b| b == b'\t')?;
+    let header = std::str::from_utf8(&entry[..tab]).ok()?;
+    let mut fields = header.split_ascii_whitespace();
+    let mode = fields.next()?;
+    let kind = fields.next()?;
+    let _sha = fields.next()?;
+    let size = fields.next()?.parse().ok()?;
+    if kind != "blob" || !(mode == "100644" || mode == "100755") {
+        return None;
+    }
+    Some(TreeEntry {
+        path: git_path(&entry[tab + 1..]),
+        size,
+    })
+}
+
+/// Reads file content from a commit through `git cat-file --batch`. The
+/// process is started on first use and shared; reads are serialized.
+pub struct BlobReader {
+    root: PathBuf,
+    commit: String,
+    process: Mutex<Option<CatFile>>,
+}
+
+impl BlobReader {
+    pub fn new(root: &Path, commit: &str) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            commit: commit.to_string(),
+            process: Mutex::new(None),
+        }
+    }
+
+    /// Read the blob at `rel_path` in the commit. Returns None if the path
+    /// does not exist there or git fails; a failed process is restarted on
+    /// the next read.
+    pub fn read(&self, rel_path: &Path) -> Option<Vec<u8>> {
+        let spec = format!("{}:{}", self.commit, tree_path(rel_path)?);
+        let mut process = self
+            .process
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if process.is_none() {
+            *process = CatFile::spawn(&self.root).ok();
+        }
+        match process.as_mut()?.read(&spec) {
+            Ok(blob) => blob,
+            Err(_) => {
+                *process = None;
+                None
+            }
+        }
+    }
+}
+
+/// A running `git cat-file --batch`
+struct CatFile {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl CatFile {
+    fn spawn(root: &Path) -> std::io::Result<Self> {
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or(std::io::ErrorKind::BrokenPipe)?;
+        let stdout = child.stdout.take().ok_or(std::io::ErrorKind::BrokenPipe)?;
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+
+    /// Request one object: the reply is "<sha> <type> <size>\n<content>\n",
+    /// or "<spec> missing\n"
+    fn read(&mut self, spec: &str) -> std::io::Result<Option<Vec<u8>>> {
+        writeln!(self.stdin, "{}", spec)?;
+        self.stdin.flush()?;
+
+        let mut header = String::new();
+        if self.stdout.read_line(&mut header)? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        let fields: Vec<&str> = header.split_ascii_whitespace().collect();
+        let [_, kind, size] = fields[..] else {
+            // "missing" or "ambiguous"
+            return Ok(None);
+        };
+        let size: usize = size
+            .parse()
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidData))?;
+
+        let mut content = vec![0u8; size + 1];
+        self.stdout.read_exact(&mut content)?;
+        content.truncate(size);
+        Ok((kind == "blob").then_some(content))
+    }
+}
+
+impl Drop for CatFile {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Render a relative path the way git names it in a tree. Newlines cannot be
+/// sent over the batch protocol.
+fn tree_path(rel_path: &Path) -> Option<String> {
+    let parts: Vec<&str> = rel_path
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect::<Option<_>>()?;
+    let path = parts.join("/");
+    (!path.is_empty() && !path.contains('\n')).then_some(path)
+}
+
+/// Work tree paths that differ from `rev`: everything changed since the
+/// merge base of `rev` and HEAD (committed, staged or not) plus untracked
+/// files. When `rev` is an ancestor of HEAD the base is `rev` itself; when
+/// it moved on (`origin/main` after a fetch), its own new commits are not
+/// counted as changes of this branch. Inside a submodule the changes are
+/// those since the commit the base records for it; every file of a
+/// submodule the base does not have is changed.
+pub fn changed_since(root: &Path, rev: &str) -> Result<Vec<PathBuf>> {
+    let commit = resolve_commit(root, rev)?;
+    let base = run_git(root, &["merge-base", &commit, "HEAD"])
+        .and_then(|out| String::from_utf8(out).ok())
+        .map(|out| out.trim().to_string())
+        .with_context(|| format!("No common history between {} and HEAD", rev))?;
+    let diff = run_git(
+        root,
+        &[
+            "diff",
+            "--name-only",
+            "-z",
+            "--no-renames",
+            "--ignore-submodules",
+            &base,
+        ],
+    )
+    .with_context(|| format!("Failed to diff {} against the work tree", rev))?;
+
+    let mut paths: Vec<PathBuf> = split_z(&diff).map(git_path).collect();
+    paths.extend(work_tree_status(root)?.untracked);
+
+    for module in submodule_paths(root) {
+        if !is_checked_out(root, &module) {
+            continue;
+        }
+        let module_root = root.join(&module);
+        let changed = match gitlink(root, &base, &module) {
+            Some(commit) => changed_since(&module_root, &commit)?,
+            None => run_git(&module_root, &["ls-files", "-z"])
+                .map(|out| split_z(&out).map(git_path).collect())
+                .unwrap_or_default(),
+        };
+        paths.extend(changed.into_iter().map(|path| module.join(path)));
+    }
+
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// The commit `tree` records for the submodule at `path`, if any
+fn gitlink(root: &Path, tree: &str, path: &Path) -> Option<String> {
+    let out = run_git(
+        root,
+        &[
+            "ls-tree",
+            "-z",
+            tree,
+            "--",
+            &path.to_string_lossy().replace('\\', "/"),
+        ],
+    )?;
+    // "160000 commit <sha>\t<path>"
+    let entry = split_z(&out).next()?;
+    let tab = entry.iter().position(|&b| b == b'\t')?;
+    let header = std::str::from_utf8(&entry[..tab]).ok()?;
+    let mut fields = header.split_ascii_whitespace();
+    let mode = fields.next()?;
+    let kind = fields.next()?;
+    let sha = fields.next()?;
+    (mode == "160000" && kind == "commit").then(|| sha.to_string())
+}
+
+/// Uncommitted state of a work tree, relative to its root
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkTreeStatus {
+    /// Tracked files with staged or unstaged changes
+    pub modified: Vec<PathBuf>,
+    /// Files git does not track (ignored files excluded)
+    pub untracked: Vec<PathBuf>,
+}
+
+/// Read the modified and untracked files of the work tree at `root`
+pub fn work_tree_status(root: &Path) -> Result<WorkTreeStatus> {
+    let status = run_git(
+        root,
+        &[
+            "status",
+            "--porcelain",
+            "-z",
+            "--untracked-files=all",
+            "--no-renames",
+            "--ignore-submodules",
+        ],
+    )
+    .with_context(|| format!("{} is not a git work tree", root.display()))?;
+
+    let mut result = WorkTreeStatus::default();
+    for entry in split_z(&status).filter(|entry| entry.len() > 3) {
+        let path = git_path(&entry[3..]);
+        if entry.starts_with(b"??") {
+            result.untracked.push(path);
+        } else {
+            result.modified.push(path);
+        }
+    }
+
+    // Submodule files, as their own git sees them
+    for module in submodule_paths(root) {
+        if !is_checked_out(root, &module) {
+            continue;
+        }
+        let status = work_tree_status(&root.join(&module))?;
+        let prefixed = |paths: Vec<PathBuf>| paths.into_iter().map(|path| module.join(path));
+        result.modified.extend(prefixed(status.modified));
+        result.untracked.extend(prefixed(status.untracked));
+    }
+    Ok(result)
+}
+
+/// Whether `ancestor` is reachable from `commit` (history was not
+/// rewritten between them)
+pub fn is_ancestor(root: &Path, ancestor: &str, commit: &str) -> bool {
+    run_git(root, &["merge-base", "--is-ancestor", ancestor, commit]).is_some()
+}
+
+/// A commit listed by `git log`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitInfo {
+    pub sha: String,
+    pub author: String,
+    /// Committer timestamp (seconds)
+    pub time: u64,
+    /// First line of the message
+    pub summary: String,
+}
+
+/// The changes one commit made to one file: its `@@` hunk headers and the
+/// added (`+`) and removed (`-`) lines, without context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    pub hunks: Vec<u8>,
+}
+
+/// Separates commit headers from patch lines in `git log` output. Patch
+/// lines always start with a diff marker, so this byte never begins one.
+const COMMIT_MARKER: u8 = 0x1e;
+
+/// Stream the patches of the commits in `range` ("HEAD", "old..HEAD"),
+/// newest first, calling `on_diff` for every file a commit changed.
+/// Merges, binary files and mode-only changes have no hunks and are not
+/// reported. Returns every listed commit.
+pub fn log_diffs(
+    root: &Path,
+    range: &str,
+    mut on_diff: impl FnMut(&CommitInfo, FileDiff) -> Result<()>,
+) -> Result<Vec<CommitInfo>> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "-c",
+            "core.quotepath=off",
+            "log",
+            "--no-color",
+            "--no-ext-diff",
+            "--no-textconv",
+            "--no-renames",
+            "-p",
+            "-U0",
+            "--format=%x1e%H%x00%an%x00%ct%x00%s",
+            "--end-of-options",
+            range,
+            "--",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to run git")?;
+    let stdout = child.stdout.take().context("Failed to read git log")?;
+
+    let mut parser = LogParser::default();
+    let result = (|| -> Result<()> {
+        let mut reader = BufReader::new(stdout);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            if let Some((commit, diff)) = parser.feed(&line) {
+                on_diff(&parser.commits[commit], diff)?;
+            }
+        }
+        if let Some((commit, diff)) = parser.finish() {
+            on_diff(&parser.commits[commit], diff)?;
+        }
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = child.kill();
+    }
+    let status = child.wait()?;
+    result?;
+    if !status.success() {
+        anyhow::bail!("git log {} failed in {}", range, root.display());
+    }
+    Ok(parser.commits)
+}
+
+/// Incremental parser for `git log -p -U0` output in the format used by
+/// [`log_diffs`]
+#[derive(Default)]
+struct LogParser {
+    commits: Vec<CommitInfo>,
+    file: Option<PendingDiff>,
+}
+
+/// A file diff being read: index of its commit, paths from the `---` and
+/// `+++` headers, and the hunks so far
+struct PendingDiff {
+    commit: usize,
+    old_path: Option<PathBuf>,
+    new_path: Option<PathBuf>,
+    in_header: bool,
+    hunks: Vec<u8>,
+}
+
+impl LogParser {
+    /// Consume one line; returns a file diff once it is complete
+    fn feed(&mut self, line: &[u8]) -> Option<(usize, FileDiff)> {
+        if let Some(header) = line.strip_prefix(&[COMMIT_MARKER]) {
+            let done = self.finish();
+            let mut fields = header.split(|&b| b == 0).map(String::from_utf8_lossy);
+            if let (Some(sha), Some(author), Some(time), summary) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            {
+                self.commits.push(CommitInfo {
+                    sha: sha.into_owned(),
+                    author: author.into_owned(),
+                    time: time.parse().unwrap_or(0),
+                    summary: summary.map(|s| s.into_owned()).unwrap_or_default(),
+                });
+            }
+            return done;
+        }
+
+        if line.starts_with(b"diff --git ") {
+            let done = self.finish();
+            if let Some(commit) = self.commits.len().checked_sub(1) {
+                self.file = Some(PendingDiff {
+                    commit,
+                    old_path: None,
+                    new_path: None,
+                    in_header: true,
+                    hunks: Vec::new(),
+                });
+            }
+            return done;
+        }
+
+        let file = self.file.as_mut()?;
+        if file.in_header {
+            // "--- a/x" / "+++ b/x", or /dev/null for an added or deleted
+            // file. Only trusted before the first hunk: a removed line
+            // "-- a/x" reads the same. Paths with spaces get a trailing tab.
+            if let Some(path) = line.strip_prefix(b"--- a/") {
+                file.old_path = Some(git_path(path.strip_suffix(b"\t").unwrap_or(path)));
+            } else if let Some(path) = line.strip_prefix(b"+++ b/") {
+                file.new_path = Some(git_path(path.strip_suffix(b"\t").unwrap_or(path)));
+            } else if line.starts_with(b"@@") {
+                file.in_header = false;
+                push_hunk_header(&mut file.hunks, line);
+            }
+            return None;
+        }
+
+        match line.first() {
+            Some(b'+' | b'-') => {
+                file.hunks.extend_from_slice(line);
+                file.hunks.push(b'\n');
+            }
+            Some(b'@') => push_hunk_header(&mut file.hunks, line),
+            // "\ No newline at end of file"
+            _ => {}
+        }
+        None
+    }
+
+    /// Complete the file diff being read, if it has any hunks
+    fn finish(&mut self) -> Option<(usize, FileDiff)> {
+        let file = self.file.take()?;
+        let path = file.new_path.or(file.old_path)?;
+        if file.hunks.is_empty() {
+            return None;
+        }
+        Some((
+            file.commit,
+            FileDiff {
+                path,
+                hunks: file.hunks,
+            },
+        ))
+    }
+}
+
+/// Keep "@@ -a,b +c,d @@" and drop the function context git appends, which
+/// is not part of the change
+fn push_hunk_header(hunks: &mut Vec<u8>, line: &[u8]) {
+    let end = line
+        .get(2..)
+        .and_then(|rest| memchr::memmem::find(rest, b"@@"))
+        .map_or(line.len(), |pos| pos + 4);
+    hunks.extend_from_slice(&line[..end]);
+    hunks.push(b'\n');
+}
+
+/// Run a git command in `root`, returning stdout on success
+fn run_git(root: &Path, args: &[&str]) -> Option<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+/// Parse `git status --porcelain -z` entries ("XY path") into paths
+fn parse_status(output: &[u8]) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = split_z(output)
+        .filter(|entry| entry.len() > 3)
+        .map(|entry| git_path(&entry[3..]))
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+fn split_z(output: &[u8]) -> impl Iterator<Item = &[u8]> {
+    output.split(|&b| b == 0).filter(|s| !s.is_empty())
+}
+
+/// Convert a git path (always `/`-separated) into a native relative path
+fn git_path(raw: &[u8]) -> PathBuf {
+    String::from_utf8_lossy(raw)
+        .trim_end_matches('/')
+        .split('/')
+        .collect()
+}
+
+/// Run git in a test repository, with a fixed identity and no commit
+/// signing, and assert that it succeeded
+#[cfg(test)]
+pub(crate) fn git(root: &Path, args: &[&str]) {
+    let ok = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "-c",
+            "user.name=t",
+            "-c",
+            "user.email=t@t",
+            "-c",
+            "commit.gpgsign=false",
+        ])
+        .args(args)
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    assert!(ok, "git {:?} failed", args);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse_status() {
+        let out = b" M src/a.rs\0?? new file.txt\0D  gone.rs\0?? dir/\0";
+        assert_eq!(
+            parse_status(out),
+            vec![
+                PathBuf::from("dir"),
+                PathBuf::from("gone.rs"),
+                PathBuf::from("new file.txt"),
+                ["src", "a.rs"].iter().collect::<PathBuf>(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_tree_entry() {
+        let entry = parse_tree_entry(b"100644 blob 0123abcd     42\tsrc/a b.rs").unwrap();
+        assert_eq!(entry.path, ["src", "a b.rs"].iter().collect::<PathBuf>());
+        assert_eq!(entry.size, 42);
+        assert!(parse_tree_entry(b"120000 blob 0123abcd      5\tlink").is_none());
+        assert!(parse_tree_entry(b"160000 commit 0123abcd       -\tsub").is_none());
+    }
+
+    #[test]
+    fn test_combine_requires_same_head() {
+        let state = |head: &str, dirty: &[&str]| GitState {
+            head: head.to_string(),
+            dirty: dirty.iter().map(PathBuf::from).collect(),
+        };
+        let merged = combine(state("a", &["x"]), state("a", &["y", "x"])).unwrap();
+        assert_eq!(merged.dirty, vec![PathBuf::from("x"), PathBuf::from("y")]);
+        assert!(combine(state("a", &[]), state("b", &[])).is_none());
+    }
+
+    #[test]
+    fn test_snapshot_and_changed_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        assert!(snapshot(root).is_none(), "not a git repo");
+
+        git(root, &["init", "-q"]);
+        fs::write(root.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("b.rs"), "fn b() {}\n").unwrap();
+        assert!(snapshot(root).is_none(), "no commits yet");
+
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "init"]);
+        let clean = snapshot(root).unwrap();
+        assert!(clean.dirty.is_empty());
+
+        // Uncommitted edit and a new file
+        fs::write(root.join("a.rs"), "fn a2() {}\n").unwrap();
+        fs::write(root.join("c.rs"), "fn c() {}\n").unwrap();
+        let dirty = snapshot(root).unwrap();
+        assert_eq!(dirty.head, clean.head);
+        assert_eq!(
+            changed_paths(root, &clean, &dirty).unwrap(),
+            vec![PathBuf::from("a.rs"), PathBuf::from("c.rs")]
+        );
+
+        // Commit the new file and revert the edit: c.rs comes from the
+        // commit range, a.rs from the previous dirty set
+        git(root, &["checkout", "-q", "a.rs"]);
+        git(root, &["add", "c.rs"]);
+        git(root, &["commit", "-q", "-m", "c"]);
+        let now = snapshot(root).unwrap();
+        assert_ne!(now.head, dirty.head);
+        assert_eq!(
+            changed_paths(root, &dirty, &now).unwrap(),
+            vec![PathBuf::from("a.rs"), PathBuf::from("c.rs")]
+        );
+
+        // An unknown HEAD (e.g. garbage-collected) forces the fallback
+        let bogus = GitState {
+            head: "0".repeat(40),
+            dirty: vec![],
+        };
+        assert!(changed_paths(root, &bogus, &now).is_none());
+    }
+
+    #[test]
+    fn test_read_tree_at_revision() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        git(root, &["init", "-q"]);
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src").join("a.rs"), "fn old() {}\n").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "one"]);
+        let first = resolve_commit(root, "HEAD").unwrap();
+
+        fs::write(root.join("src").join("a.rs"), "fn new() {}\n").unwrap();
+        fs::write(root.join("b.rs"), "fn b() {}\n").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "two"]);
+
+        assert_eq!(resolve_commit(root, "HEAD~1").unwrap(), first);
+        assert!(resolve_commit(root, "no-such-branch").is_err());
+        assert!(commit_time(root, &first).unwrap() > 0);
+
+        let a_rs: PathBuf = ["src", "a.rs"].iter().collect();
+        assert_eq!(
+            ls_tree(root, &first).unwrap(),
+            vec![TreeEntry {
+                path: a_rs.clone(),
+                size: 12
+            }]
+        );
+
+        // Reads see the commit, not the work tree, and survive misses
+        let blobs = BlobReader::new(root, &first);
+        assert_eq!(blobs.read(&a_rs).unwrap(), b"fn old() {}\n");
+        assert!(blobs.read(Path::new("b.rs")).is_none());
+        assert_eq!(blobs.read(&a_rs).unwrap(), b"fn old() {}\n");
+    }
+
+    #[test]
+    fn test_parse_log() {
+        let out = b"\x1eaaaa\0Ann\x001700000000\0Add a\n\
+            \n\
+            diff --git a/a b.rs b/a b.rs\n\
+            new file mode 100644\n\
+            --- /dev/null\n\
+            +++ b/a b.rs\t\n\
+            @@ -0,0 +1,2 @@\n\
+            +fn a() {}\n\
+            +-- a/not-a-header\n\
+            diff --git a/img.png b/img.png\n\
+            Binary files /dev/null and b/img.png differ\n\
+            \x1ebbbb\0Bob\x001700000100\0Drop a\n\
+            \n\
+            diff --git a/a b.rs b/a b.rs\n\
+            deleted file mode 100644\n\
+            --- a/a b.rs\t\n\
+            +++ /dev/null\n\
+            @@ -1,2 +0,0 @@ fn context()\n\
+            -fn a() {}\n\
+            \\ No newline at end of file\n";
+
+        let mut parser = LogParser::default();
+        let mut diffs = Vec::new();
+        for line in out.split(|&b| b == b'\n') {
+            diffs.extend(parser.feed(line));
+        }
+        diffs.extend(parser.finish());
+
+        assert_eq!(parser.commits.len(), 2);
+        assert_eq!(parser.commits[1].author, "Bob");
+        assert_eq!(parser.commits[1].time, 1_700_000_100);
+        assert_eq!(parser.commits[1].summary, "Drop a");
+
+        let a = PathBuf::from("a b.rs");
+        assert_eq!(
+            diffs,
+            vec![
+                (
+                    0,
+                    FileDiff {
+                        path: a.clone(),
+                        hunks: b"@@ -0,0 +1,2 @@\n+fn a() {}\n+-- a/not-a-header\n".to_vec(),
+                    }
+                ),
+                (
+                    1,
+                    FileDiff {
+                        path: a,
+                        hunks: b"@@ -1,2 +0,0 @@\n-fn a() {}\n".to_vec(),
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_log_diffs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        git(root, &["init", "-q"]);
+        fs::write(root.join("a.rs"), "fn one() {}\n").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "one"]);
+        let first = resolve_commit(root, "HEAD").unwrap();
+
+        fs::write(root.join("a.rs"), "fn one() {}\nfn two() {}\n").unwrap();
+        git(root, &["commit", "-q", "-am", "two"]);
+        let second = resolve_commit(root, "HEAD").unwrap();
+        assert!(is_ancestor(root, &first, &second));
+        assert!(!is_ancestor(root, &second, &first));
+
+        let mut diffs = Vec::new();
+        let commits = log_diffs(root, "HEAD", |commit, diff| {
+            diffs.push((commit.summary.clone(), diff));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].sha, second);
+        assert_eq!(diffs[0].0, "two");
+        assert_eq!(diffs[0].1.hunks, b"@@ -1,0 +2 @@\n+fn two() {}\n");
+
+        // A range lists only the newer commits
+        let commits = log_diffs(root, &format!("{}..HEAD", first), |_, _| Ok(())).unwrap();
+        assert_eq!(commits.len(), 1);
+    }
+
+    #[test]
+    fn test_changed_since_and_status() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        git(root, &["init", "-q", "-b", "main"]);
+        fs::write(root.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("b.rs"), "fn b() {}\n").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "base"]);
+
+        // A branch commit, then main moves on: its commit is not a change
+        // of this branch
+        git(root, &["checkout", "-q", "-b", "topic"]);
+        fs::write(root.join("c.rs"), "fn c() {}\n").unwrap();
+        git(root, &["add", "c.rs"]);
+        git(root, &["commit", "-q", "-m", "c"]);
+        git(root, &["checkout", "-q", "main"]);
+        fs::write(root.join("b.rs"), "fn b2() {}\n").unwrap();
+        git(root, &["commit", "-q", "-am", "b on main"]);
+        git(root, &["checkout", "-q", "topic"]);
+
+        // An unstaged edit and an untracked file
+        fs::write(root.join("a.rs"), "fn a2() {}\n").unwrap();
+        fs::write(root.join("new.rs"), "fn n() {}\n").unwrap();
+
+        assert_eq!(
+            changed_since(root, "main").unwrap(),
+            vec![
+                PathBuf::from("a.rs"),
+                PathBuf::from("c.rs"),
+                PathBuf::from("new.rs")
+            ]
+        );
+        assert!(changed_since(root, "no-such-branch").is_err());
+
+        let status = work_tree_status(root).unwrap();
+        assert_eq!(status.modified, vec![PathBuf::from("a.rs")]);
+        assert_eq!(status.untracked, vec![PathBuf::from("new.rs")]);
+    }
+
+    /// A repository at `root`/lib with one commit, added to the one at
+    /// `root` as a submodule
+    fn repo_with_submodule(root: &Path) {
+        let upstream = root.join("upstream");
+        fs::create_dir(&upstream).unwrap();
+        git(&upstream, &["init", "-q"]);
+        fs::write(upstream.join("a.rs"), "fn a() {}\n").unwrap();
+        git(&upstream, &["add", "."]);
+        git(&upstream, &["commit", "-q", "-m", "lib"]);
+
+        let work = root.join("work");
+        fs::create_dir(&work).unwrap();
+        git(&work, &["init", "-q"]);
+        fs::write(work.join("main.rs"), "fn main() {}\n").unwrap();
+        git(
+            &work,
+            &[
+                "-c",
+                "protocol.file.allow=always",
+                "submodule",
+                "add",
+                "-q",
+                &upstream.to_string_lossy(),
+                "lib",
+            ],
+        );
+        git(&work, &["add", "."]);
+        git(&work, &["commit", "-q", "-m", "init"]);
+    }
+
+    #[test]
+    fn test_submodule_changes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        repo_with_submodule(dir.path());
+        let root = &dir.path().join("work");
+        let base = resolve_commit(root, "HEAD").unwrap();
+        assert_eq!(submodule_paths(root), vec![PathBuf::from("lib")]);
+
+        // The superproject's status cannot vouch for the submodule
+        let clean = snapshot(root).unwrap();
+        assert_eq!(clean.dirty, vec![PathBuf::from("lib")]);
+
+        fs::write(root.join("lib/a.rs"), "fn a2() {}\n").unwrap();
+        fs::write(root.join("lib/b.rs"), "fn b() {}\n").unwrap();
+        let status = work_tree_status(root).unwrap();
+        assert_eq!(status.modified, vec![PathBuf::from("lib/a.rs")]);
+        assert_eq!(status.untracked, vec![PathBuf::from("lib/b.rs")]);
+        assert_eq!(
+            changed_since(root, &base).unwrap(),
+            vec![PathBuf::from("lib/a.rs"), PathBuf::from("lib/b.rs")]
+        );
+    }
+
+    #[test]
+    fn test_update_index_sees_submodule_edit() {
+        use crate::index::build::{build_index, update_index};
+        use crate::index::reader::IndexReader;
+        use crate::query::{QueryExecutor, parse_query};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        repo_with_submodule(dir.path());
+        let root = &dir.path().join("work").canonicalize().unwrap();
+        build_index(root, true).unwrap();
+        assert!(IndexReader::open(root).unwrap().meta.git_state.is_some());
+
+        // A later mtime, even within the second the index was built in
+        let file = fs::File::options()
+            .append(true)
+            .open(root.join("lib/a.rs"))
+            .unwrap();
+        writeln!(&file, "fn submodule_edit() {{}}").unwrap();
+        file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(5))
+            .unwrap();
+        update_index(root).unwrap();
+
+        let reader = IndexReader::open(root).unwrap();
+        let matches = QueryExecutor::new(&reader)
+            .execute(&parse_query("submodule_edit"))
+            .unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, Path::new("lib/a.rs"));
+        crate::utils::remove_index(root).unwrap();
+    }
+
+    #[test]
+    fn test_update_index_applies_ignore_files() {
+        use crate::index::build::{build_index, update_index};
+        use crate::index::reader::IndexReader;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = &dir.path().canonicalize().unwrap();
+        git(root, &["init", "-q"]);
+        for i in 0..5 {
+            fs::write(root.join(format!("{}.rs", i)), "fn f() {}\n").unwrap();
+        }
+        fs::write(root.join(".ignore"), "secret.txt\n").unwrap();
+        fs::write(root.join(".gitignore"), "gen.rs\n").unwrap();
+        fs::write(root.join("gen.rs"), "fn generated() {}\n").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["add", "-f", "gen.rs"]);
+        git(root, &["commit", "-q", "-m", "init"]);
+        build_index(root, true).unwrap();
+
+        // git reports all three, but only new.rs is walked
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+        for (name, text) in [
+            ("secret.txt", "password\n"),
+            ("gen.rs", "fn regenerated() {}\n"),
+            ("new.rs", "fn new() {}\n"),
+        ] {
+            fs::write(root.join(name), text).unwrap();
+            let file = fs::File::options()
+                .append(true)
+                .open(root.join(name))
+                .unwrap();
+            file.set_modified(later).unwrap();
+        }
+        assert!(
+            update_index(root).unwrap(),
+            "expected an incremental update"
+        );
+
+        let reader = IndexReader::open(root).unwrap();
+        let mut paths: Vec<PathBuf> = reader
+            .documents()
+            .iter()
+            .filter(|d| d.is_valid())
+            .filter_map(|d| reader.get_path(d).cloned())
+            .collect();
+        paths.sort();
+        let expected: Vec<PathBuf> = ["0.rs", "1.rs", "2.rs", "3.rs", "4.rs", "new.rs"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(paths, expected);
+        crate::utils::remove_index(root).unwrap();
+    }
+}
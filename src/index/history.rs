@@ -0,0 +1,334 @@
+//! Git history index (`fxi log`).
+//!
+//! `git log -S` rereads every blob of every commit on each query. The
+//! history index instead records what each commit changed: one document per
+//! commit and file, holding the zero-context hunks of its diff (`@@` headers,
+//! added `+` and removed `-` lines). Those documents go through the regular
+//! trigram/token pipeline into an index beside the work tree one, so a
+//! history search is an ordinary indexed query.
+//!
+//! Documents are named `<commit sha>/<file path>` and always kept in the
+//! content store, since there is no work tree to read them back from. Commit
+//! metadata lives in `commits.json`. The meta records the newest indexed
+//! commit; later runs index only the commits after it (one delta segment),
+//! or rebuild when history was rewritten underneath it.
+
+use crate::index::build::{ProcessedFile, is_indexed_tree_path, process_file_content};
+use crate::index::git::{self, CommitInfo, FileDiff};
+use crate::index::types::{IndexConfig, IndexMeta, SegmentId};
+use crate::index::writer::{ChunkedIndexWriter, DeltaSegmentWriter, write_meta_atomic};
+use crate::utils::get_history_index_dir;
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+const COMMITS_FILE: &str = "commits.json";
+
+/// A file diff waiting to be tokenized: document path, hunks, commit time
+type PendingDoc = (PathBuf, Vec<u8>, u64);
+
+/// A changed line found by `fxi log`
+#[derive(Debug, Clone)]
+pub struct LogMatch {
+    pub commit: CommitInfo,
+    /// File the commit changed
+    pub path: PathBuf,
+    /// Line in the file after the commit (added) or before it (removed);
+    /// 0 if unknown
+    pub line_number: u32,
+    /// The diff line, starting with `+` or `-`
+    pub line: String,
+    pub match_start: usize,
+    pub match_end: usize,
+}
+
+/// Build the history index of the git repository at `root_path`, or bring
+/// it up to date with HEAD. Returns how many commits were indexed.
+pub fn update_history_index(root_path: &Path, force: bool, silent: bool) -> Result<usize> {
+    let root = root_path.canonicalize().context("Invalid path")?;
+    let head = git::resolve_commit(&root, "HEAD")?;
+    let index_path = get_history_index_dir(&root)?;
+
+    if index_path.exists() {
+        let indexed = read_meta(&index_path)
+            .ok()
+            .and_then(|meta| meta.history_head.clone().map(|h| (meta, h)));
+        if !force && let Some((meta, indexed)) = indexed {
+            if indexed == head {
+                return Ok(0);
+            }
+            if git::is_ancestor(&root, &indexed, &head) {
+                return catch_up(&root, &index_path, meta, &indexed, &head, silent);
+            }
+            if !silent {
+                println!("History was rewritten since the last index, rebuilding");
+            }
+        }
+        fs::remove_dir_all(&index_path).context("Failed to remove existing index")?;
+    }
+
+    build_full(&root, &index_path, &head, silent)
+}
+
+/// Index every commit reachable from `head`
+fn build_full(root: &Path, index_path: &Path, head: &str, silent: bool) -> Result<usize> {
+    if !silent {
+        println!("Indexing history: {}", root.display());
+    }
+
+    let config = IndexConfig {
+        store_content: true,
+        ..IndexConfig::default()
+    };
+    let chunk_size = config.chunk_size.max(1);
+    let max_file_size = config.max_file_size;
+    let mut writer =
+        ChunkedIndexWriter::with_index_path(root.to_path_buf(), index_path.to_path_buf(), config)?;
+
+    let mut batch = Vec::new();
+    let mut segment_id: SegmentId = 0;
+    let mut docs = 0;
+    let commits = git::log_diffs(root, head, |commit, diff| {
+        batch.extend(pending_doc(commit, diff, max_file_size));
+        if batch.len() >= chunk_size {
+            let processed = process_batch(std::mem::take(&mut batch));
+            docs += processed.len();
+            segment_id += 1;
+            writer.write_chunk(segment_id, processed)?;
+        }
+        Ok(())
+    })?;
+    if !batch.is_empty() {
+        let processed = process_batch(batch);
+        docs += processed.len();
+        writer.write_chunk(segment_id + 1, processed)?;
+    }
+    writer.finalize()?;
+
+    // The commit list first: meta names the head only once it is complete
+    write_commits(index_path, &commits)?;
+    let mut meta = read_meta(index_path)?;
+    meta.history_head = Some(head.to_string());
+    write_meta_atomic(index_path, &meta)?;
+
+    if !silent {
+        println!("Indexed {} commits ({} file changes)", commits.len(), docs);
+        println!("Index stored at: {}", index_path.display());
+    }
+    Ok(commits.len())
+}
+
+/// Index the commits after `indexed` up to `head` into one delta segment
+fn catch_up(
+    root: &Path,
+    index_path: &Path,
+    mut meta: IndexMeta,
+    indexed: &str,
+    head: &str,
+    silent: bool,
+) -> Result<usize> {
+    let config = IndexConfig::default();
+    let next_segment_id = meta
+        .delta_segments
+        .iter()
+        .max()
+        .copied()
+        .unwrap_or(meta.base_segment.unwrap_or(0))
+        + 1;
+    let mut writer = DeltaSegmentWriter::with_index_path(
+        root.to_path_buf(),
+        index_path.to_path_buf(),
+        next_segment_id,
+    )?;
+
+    let mut batch = Vec::new();
+    let new_commits = git::log_diffs(root, &format!("{}..{}", indexed, head), |commit, diff| {
+        batch.extend(pending_doc(commit, diff, config.max_file_size));
+        if batch.len() >= config.chunk_size.max(1) {
+            process_batch(std::mem::take(&mut batch))
+                .into_iter()
+                .for_each(|processed| writer.add_file(processed));
+        }
+        Ok(())
+    })?;
+    process_batch(batch)
+        .into_iter()
+        .for_each(|processed| writer.add_file(processed));
+
+    let mut commits = load_commits(index_path)?;
+    commits.extend(new_commits.iter().cloned());
+    write_commits(index_path, &commits)?;
+
+    meta.history_head = Some(head.to_string());
+    if writer.has_changes() {
+        writer.finalize(&mut meta)?;
+    } else {
+        // Only merges or binary changes: nothing to add but the new head
+        write_meta_atomic(index_path, &meta)?;
+    }
+
+    let new_deltas = meta
+        .delta_segments
+        .len()
+        .saturating_sub(meta.delta_baseline);
+    if new_deltas >= crate::server::watcher::DEFAULT_MERGE_SEGMENT_THRESHOLD {
+        crate::index::compact::merge_index_segments(index_path)?;
+    }
+
+    if !silent {
+        println!("Indexed {} new commit(s)", new_commits.len());
+    }
+    Ok(new_commits.len())
+}
+
+/// A file diff as a document, unless the walk would skip the file or the
+/// diff is too large
+fn pending_doc(commit: &CommitInfo, diff: FileDiff, max_size: u64) -> Option<PendingDoc> {
+    if !is_indexed_tree_path(&diff.path) || diff.hunks.len() as u64 > max_size {
+        return None;
+    }
+    Some((doc_path(&commit.sha, &diff.path), diff.hunks, commit.time))
+}
+
+fn process_batch(batch: Vec<PendingDoc>) -> Vec<ProcessedFile> {
+    batch
+        .into_par_iter()
+        .filter_map(|(path, hunks, time)| process_file_content(path, &hunks, time, true))
+        .collect()
+}
+
+/// Document path of a file's diff in a commit
+fn doc_path(sha: &str, file: &Path) -> PathBuf {
+    Path::new(sha).join(file)
+}
+
+/// Split a document path (relative to the root) into commit SHA and file
+pub fn split_doc_path(path: &Path) -> Option<(String, PathBuf)> {
+    let mut components = path.components();
+    let sha = components.next()?.as_os_str().to_str()?.to_string();
+    Some((sha, components.as_path().to_path_buf()))
+}
+
+/// Line number in the file of line `line_idx` (0-based) of a diff document:
+/// the new-side number for an added line, the old-side one for a removed
+/// line. None for hunk headers.
+pub fn file_line_number(hunks: &str, line_idx: usize) -> Option<u32> {
+    let lines: Vec<&str> = hunks.lines().collect();
+    let target = *lines.get(line_idx)?;
+    let removed = match target.as_bytes().first()? {
+        b'-' => true,
+        b'+' => false,
+        _ => return None,
+    };
+
+    // Back to this hunk's header, counting lines on the same side
+    let mut before = 0;
+    for line in lines[..line_idx].iter().rev() {
+        if line.starts_with("@@") {
+            let (old_start, new_start) = parse_hunk_header(line)?;
+            let start = if removed { old_start } else { new_start };
+            return Some(start + before);
+        }
+        if line.starts_with('-') == removed {
+            before += 1;
+        }
+    }
+    None
+}
+
+/// Start lines of "@@ -a[,b] +c[,d] @@"
+fn parse_hunk_header(line: &str) -> Option<(u32, u32)> {
+    let mut fields = line.split_ascii_whitespace().skip(1);
+    let start = |field: &str| field.split(',').next()?.parse::<u32>().ok();
+    let old = start(fields.next()?.strip_prefix('-')?)?;
+    let new = start(fields.next()?.strip_prefix('+')?)?;
+    Some((old, new))
+}
+
+/// Commits known to the history index at `index_path`
+pub fn load_commits(index_path: &Path) -> Result<Vec<CommitInfo>> {
+    let file = File::open(index_path.join(COMMITS_FILE)).context("Failed to open commits.json")?;
+    Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+}
+
+fn write_commits(index_path: &Path, commits: &[CommitInfo]) -> Result<()> {
+    let tmp_path = index_path.join("commits.json.tmp");
+    let file = File::create(&tmp_path)?;
+    serde_json::to_writer(std::io::BufWriter::new(file), commits)?;
+    fs::rename(&tmp_path, index_path.join(COMMITS_FILE))?;
+    Ok(())
+}
+
+fn read_meta(index_path: &Path) -> Result<IndexMeta> {
+    let file = File::open(index_path.join("meta.json")).context("Failed to open meta.json")?;
+    Ok(serde_json::from_reader(file)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::git::git;
+    use crate::index::reader::IndexReader;
+
+    #[test]
+    fn test_history_index_catch_up() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        git(&root, &["init", "-q"]);
+        fs::write(root.join("a.rs"), "fn alpha() {}\n").unwrap();
+        git(&root, &["add", "."]);
+        git(&root, &["commit", "-q", "-m", "one"]);
+
+        assert_eq!(update_history_index(&root, false, true).unwrap(), 1);
+        assert_eq!(update_history_index(&root, false, true).unwrap(), 0);
+
+        // New commits land in a delta segment
+        fs::write(root.join("a.rs"), "fn beta() {}\n").unwrap();
+        git(&root, &["commit", "-q", "-am", "two"]);
+        assert_eq!(update_history_index(&root, false, true).unwrap(), 1);
+
+        let reader = IndexReader::open_history(&root).unwrap();
+        assert_eq!(reader.meta.delta_segments.len(), 1);
+        assert_eq!(reader.documents().len(), 2);
+        let index_path = get_history_index_dir(&root).unwrap();
+        assert_eq!(load_commits(&index_path).unwrap().len(), 2);
+        let doc = reader.documents().last().unwrap();
+        let hunks = reader.stored_content(doc.doc_id).unwrap();
+        assert_eq!(hunks, "@@ -1 +1 @@\n-fn alpha() {}\n+fn beta() {}\n");
+        drop(reader);
+
+        // Rewritten history is indexed from scratch
+        fs::write(root.join("a.rs"), "fn gamma() {}\n").unwrap();
+        git(&root, &["commit", "-q", "--amend", "-am", "two again"]);
+        assert_eq!(update_history_index(&root, false, true).unwrap(), 2);
+        let reader = IndexReader::open_history(&root).unwrap();
+        assert!(reader.meta.delta_segments.is_empty());
+
+        crate::utils::remove_rev_indexes(&root).unwrap();
+    }
+
+    #[test]
+    fn test_file_line_number() {
+        let hunks = "@@ -3,2 +3 @@\n-a\n-b\n+c\n@@ -10,0 +10,2 @@\n+d\n+e\n";
+        assert_eq!(file_line_number(hunks, 0), None);
+        assert_eq!(file_line_number(hunks, 1), Some(3));
+        assert_eq!(file_line_number(hunks, 2), Some(4));
+        assert_eq!(file_line_number(hunks, 3), Some(3));
+        assert_eq!(file_line_number(hunks, 5), Some(10));
+        assert_eq!(file_line_number(hunks, 6), Some(11));
+        assert_eq!(file_line_number(hunks, 7), None);
+    }
+
+    #[test]
+    fn test_split_doc_path() {
+        let path = doc_path("abc123", &["src", "a.rs"].iter().collect::<PathBuf>());
+        assert_eq!(
+            split_doc_path(&path),
+            Some((
+                "abc123".to_string(),
+                ["src", "a.rs"].iter().collect::<PathBuf>()
+            ))
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! MinHash signatures of each document's trigram set, for similar-file
+//! search (`like:` and `fxi similar`).
+//!
+//! A signature keeps the minimum of [`SIGNATURE_LEN`] independent hash
+//! functions over the trigram set. The fraction of positions on which two
+//! signatures agree estimates the Jaccard similarity of the two sets, so
+//! similar files are found by comparing signatures alone, without reading
+//! any content. Only the top 16 bits of each minimum are kept (b-bit
+//! MinHash): unrelated minima then agree by accident once in 65536, which
+//! is far below the estimator's own error.
+//!
+//! Files per segment:
+//! - `minhash.bin`: entry count (u32), then per document, sorted by doc id:
+//!   doc_id (u32), [`SIGNATURE_LEN`] minima (u16 each)
+
+use crate::index::types::DocId;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Hash functions per signature: ~0.06 standard error on the estimate
+pub const SIGNATURE_LEN: usize = 64;
+
+/// Estimated similarity at or above which `like:` and `fxi similar`
+/// report a file by default
+pub const DEFAULT_THRESHOLD: f32 = 0.5;
+
+/// Files with fewer distinct trigrams get no signature: a handful of
+/// trigrams looks similar to almost anything
+const MIN_TRIGRAMS: usize = 16;
+
+const ENTRY_SIZE: usize = 4 + SIGNATURE_LEN * 2;
+
+pub type Signature = [u16; SIGNATURE_LEN];
+
+/// splitmix64 step, usable in const context
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+/// Multiply-add hash family `(a * x + b) >> 48`, one (a, b) per position.
+/// Fixed at compile time: signatures are stored on disk.
+const HASH_PARAMS: [(u64, u64); SIGNATURE_LEN] = {
+    let mut params = [(0u64, 0u64); SIGNATURE_LEN];
+    let mut state = 0x6678_695F_6D69_6E68; // "fxi_minh"
+    let mut i = 0;
+    while i < SIGNATURE_LEN {
+        let (s, a) = splitmix64(state);
+        let (s, b) = splitmix64(s);
+        state = s;
+        params[i] = (a | 1, b);
+        i += 1;
+    }
+    params
+};
+
+/// Signature of a distinct trigram set, or None when the set is too small
+/// to say anything about similarity
+pub fn signature(trigrams: &[u32]) -> Option<Signature> {
+    if trigrams.len() < MIN_TRIGRAMS {
+        return None;
+    }
+
+    let mut mins = [u64::MAX; SIGNATURE_LEN];
+    for &trigram in trigrams {
+        // Spread the 24 trigram bits over the whole word first
+        let x = splitmix64(trigram as u64).1;
+        for (min, &(a, b)) in mins.iter_mut().zip(&HASH_PARAMS) {
+            *min = (*min).min(a.wrapping_mul(x).wrapping_add(b));
+        }
+    }
+    Some(mins.map(|m| (m >> 48) as u16))
+}
+
+/// Estimated Jaccard similarity of the trigram sets behind two signatures
+pub fn similarity(a: &Signature, b: &Signature) -> f32 {
+    let agree = a.iter().zip(b).filter(|(x, y)| x == y).count();
+    agree as f32 / SIGNATURE_LEN as f32
+}
+
+/// Write a segment's signatures. `docs` may be in any order.
+pub fn write_minhashes<'a>(
+    segment_path: &Path,
+    docs: impl IntoIterator<Item = (DocId, &'a Signature)>,
+) -> Result<()> {
+    let mut docs: Vec<_> = docs.into_iter().collect();
+    docs.sort_unstable_by_key(|(doc_id, _)| *doc_id);
+
+    let mut file = BufWriter::new(File::create(segment_path.join("minhash.bin"))?);
+    file.write_all(&(docs.len() as u32).to_le_bytes())?;
+    for (doc_id, signature) in docs {
+        file.write_all(&doc_id.to_le_bytes())?;
+        for min in signature {
+            file.write_all(&min.to_le_bytes())?;
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Read side of a segment's signatures (small enough to load whole)
+pub struct MinHashStore {
+    entries: Vec<(DocId, Signature)>,
+}
+
+impl MinHashStore {
+    /// Open the signatures of a segment, or None if it predates them
+    pub fn open(segment_path: &Path) -> Result<Option<Self>> {
+        let path = segment_path.join("minhash.bin");
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let mut data = Vec::new();
+        BufReader::new(File::open(&path)?).read_to_end(&mut data)?;
+        let count = u32::from_le_bytes(data.get(..4).context("Truncated minhash.bin")?.try_into()?)
+            as usize;
+        if data.len() < 4 + count * ENTRY_SIZE {
+            anyhow::bail!("Truncated minhash.bin");
+        }
+        let entries = data[4..4 + count * ENTRY_SIZE]
+            .chunks_exact(ENTRY_SIZE)
+            .map(|e| {
+                let doc_id = u32::from_le_bytes(e[0..4].try_into().unwrap());
+                let mut signature = [0u16; SIGNATURE_LEN];
+                for (min, bytes) in signature.iter_mut().zip(e[4..].chunks_exact(2)) {
+                    *min = u16::from_le_bytes([bytes[0], bytes[1]]);
+                }
+                (doc_id, signature)
+            })
+            .collect();
+
+        Ok(Some(Self { entries }))
+    }
+
+    /// A document's signature
+    pub fn get(&self, doc_id: DocId) -> Option<&Signature> {
+        self.entries
+            .binary_search_by_key(&doc_id, |(id, _)| *id)
+            .ok()
+            .map(|i| &self.entries[i].1)
+    }
+
+    /// All (doc id, signature) entries
+    pub fn iter(&self) -> impl Iterator<Item = (DocId, &Signature)> + '_ {
+        self.entries.iter().map(|(id, sig)| (*id, sig))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::extract_trigrams;
+
+    fn sig(text: &str) -> Signature {
+        signature(&extract_trigrams(text.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn test_similarity_estimates() {
+        let base: String = (0..40)
+            .map(|i| format!("let value_{i} = compute_{i}(input);\n"))
+            .collect();
+        // Same file with a few lines changed
+        let edited = base
+            .replace("compute_3(", "other_3(")
+            .replace("value_17", "renamed");
+        let unrelated: String = (0..40)
+            .map(|i| format!("<li class=\"item-{i}\">Entry {i}</li>\n"))
+            .collect();
+
+        assert_eq!(similarity(&sig(&base), &sig(&base)), 1.0);
+        assert!(similarity(&sig(&base), &sig(&edited)) > 0.8);
+        assert!(similarity(&sig(&base), &sig(&unrelated)) < 0.2);
+        assert!(signature(&extract_trigrams(b"tiny")).is_none());
+    }
+
+    #[test]
+    fn test_minhash_store_roundtrip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(MinHashStore::open(dir.path()).unwrap().is_none());
+
+        let a = sig("fn first_function() { do_something(); }");
+        let b = sig("struct SecondThing { field: Vec<String> }");
+        write_minhashes(dir.path(), [(9, &a), (2, &b)]).unwrap();
+
+        let store = MinHashStore::open(dir.path()).unwrap().unwrap();
+        assert_eq!(store.get(9), Some(&a));
+        assert_eq!(store.get(2), Some(&b));
+        assert!(store.get(5).is_none());
+        assert_eq!(store.iter().map(|(id, _)| id).collect::<Vec<_>>(), [2, 9]);
+    }
+}
@@ -7,6 +7,11 @@
 //! - [`writer`] - Streaming index writing
 //! - [`types`] - Data structures (Document, Trigram, etc.)
 //! - [`compact`] - Segment compaction
+//! - [`git`] - Git-assisted change detection for incremental updates
+//! - [`content_store`] - Optional compressed document snapshots
+//! - [`minhash`] - Per-document MinHash signatures for similar-file search
+//! - [`path_grams`] - Trigram index over paths for path and filename filters
+//! - [`history`] - Git history index of per-commit diffs (`fxi log`)
 //! - [`stats`] - Index statistics
 //!
 //! ## Index Structure
@@ -18,13 +23,16 @@
 //! ├── meta.json           # Index metadata
 //! ├── docs.bin            # Document table (mmap'd)
 //! ├── paths.bin           # Path store
+//! ├── path_grams.bin      # Path trigram index
 //! └── segments/
 //!     └── seg_0001/
 //!         ├── grams.dict     # Trigram dictionary
 //!         ├── grams.postings # Trigram postings
 //!         ├── tokens.dict    # Token dictionary
 //!         ├── tokens.postings# Token postings
-//!         └── bloom.bin      # Bloom filter
+//!         ├── bloom.bin      # Bloom filter
+//!         ├── minhash.bin    # MinHash signatures (similar-file search)
+//!         └── content.*      # Compressed snapshots (--store-content)
 //! ```
 //!
 //! ## Usage
@@ -45,6 +53,11 @@
 
 pub mod build;
 pub mod compact;
+pub mod content_store;
+pub mod git;
+pub mod history;
+pub mod minhash;
+pub mod path_grams;
 pub mod reader;
 pub mod segment_io;
 pub mod stats;
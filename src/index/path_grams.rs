@@ -0,0 +1,268 @@
+//! Trigram index over relative paths, for `file:`, `path:`, `-g`, `-t`
+//! and filename-term queries.
+//!
+//! Path filters used to be checked by matching every document's path. This
+//! index maps each trigram of the ASCII-lowercased path to the path ids
+//! containing it, so a filter first narrows to the paths containing its
+//! literal fragments (`src/`, `.rs`, `config`) and only those are matched
+//! exactly. Lowercasing keeps it usable for the case-insensitive filters;
+//! the case-sensitive ones just verify a few more candidates.
+//!
+//! Written next to `paths.bin` whenever that is rewritten:
+//! - `path_grams.bin`: fingerprint of the path table (u64), trigram count
+//!   (u32), then per
+//!   trigram, ascending: trigram (u32), postings offset (u32), postings
+//!   length (u32); then the postings, delta-encoded path ids
+
+use crate::index::types::{PathId, Trigram};
+use crate::utils::hash::xxh64;
+use crate::utils::{delta_decode_bitmap, delta_encode, extract_trigrams};
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+use roaring::RoaringBitmap;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const HEADER_SIZE: usize = 12;
+const ENTRY_SIZE: usize = 12;
+
+/// Bytes of a path as indexed: ASCII-lowercased, so that a fragment taken
+/// from a lowercased query is a substring exactly when it was before
+fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().as_bytes().to_ascii_lowercase()
+}
+
+/// Identifies a path table, so the index is never used with a table it
+/// was not built from (paths.bin is replaced after it, and compaction
+/// renumbers paths)
+fn fingerprint(paths: &[PathBuf]) -> u64 {
+    paths.iter().fold(paths.len() as u64, |h, p| {
+        xxh64(p.to_string_lossy().as_bytes(), h)
+    })
+}
+
+/// Write `path_grams.bin` for a path table (atomically, via a temp file)
+pub fn write_path_grams(index_path: &Path, paths: &[PathBuf]) -> Result<()> {
+    let mut postings: BTreeMap<Trigram, Vec<PathId>> = BTreeMap::new();
+    for (path_id, path) in paths.iter().enumerate() {
+        for trigram in extract_trigrams(&path_bytes(path)) {
+            // Path ids ascend, so each list stays sorted
+            postings.entry(trigram).or_default().push(path_id as PathId);
+        }
+    }
+
+    let mut data = Vec::new();
+    let mut entries = Vec::with_capacity(postings.len());
+    for (trigram, ids) in &postings {
+        let offset = data.len();
+        delta_encode(ids, &mut data);
+        entries.push((*trigram, offset as u32, (data.len() - offset) as u32));
+    }
+
+    let final_path = index_path.join("path_grams.bin");
+    let tmp_path = index_path.join("path_grams.bin.tmp");
+    {
+        let mut file = BufWriter::with_capacity(65536, File::create(&tmp_path)?);
+        file.write_all(&fingerprint(paths).to_le_bytes())?;
+        file.write_all(&(entries.len() as u32).to_le_bytes())?;
+        for (trigram, offset, length) in entries {
+            file.write_all(&trigram.to_le_bytes())?;
+            file.write_all(&offset.to_le_bytes())?;
+            file.write_all(&length.to_le_bytes())?;
+        }
+        file.write_all(&data)?;
+        file.flush()?;
+    }
+    fs::rename(&tmp_path, &final_path)?;
+    Ok(())
+}
+
+/// Read side of `path_grams.bin`
+pub struct PathGramIndex {
+    data: Mmap,
+    entry_count: usize,
+}
+
+impl PathGramIndex {
+    /// Open the path trigram index, or None if the index predates it or it
+    /// was built from a different path table than `paths`
+    pub fn open(index_path: &Path, paths: &[PathBuf]) -> Result<Option<Self>> {
+        let path = index_path.join("path_grams.bin");
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let data = unsafe { Mmap::map(&File::open(&path)?)? };
+        let header = data
+            .get(..HEADER_SIZE)
+            .context("Truncated path_grams.bin")?;
+        let indexed_paths = u64::from_le_bytes(header[0..8].try_into()?);
+        let entry_count = u32::from_le_bytes(header[8..12].try_into()?) as usize;
+        if data.len() < HEADER_SIZE + entry_count * ENTRY_SIZE {
+            anyhow::bail!("Truncated path_grams.bin");
+        }
+        if indexed_paths != fingerprint(paths) {
+            return Ok(None);
+        }
+
+        Ok(Some(Self { data, entry_count }))
+    }
+
+    fn entry(&self, i: usize) -> (Trigram, usize, usize) {
+        let start = HEADER_SIZE + i * ENTRY_SIZE;
+        let u32_at = |at: usize| {
+            u32::from_le_bytes(self.data[start + at..start + at + 4].try_into().unwrap())
+        };
+        (u32_at(0), u32_at(4) as usize, u32_at(8) as usize)
+    }
+
+    fn postings(&self, trigram: Trigram) -> RoaringBitmap {
+        let mut lo = 0;
+        let mut hi = self.entry_count;
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let (t, offset, length) = self.entry(mid);
+            match t.cmp(&trigram) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => {
+                    let base = HEADER_SIZE + self.entry_count * ENTRY_SIZE + offset;
+                    return self
+                        .data
+                        .get(base..base + length)
+                        .map(delta_decode_bitmap)
+                        .unwrap_or_default();
+                }
+            }
+        }
+        RoaringBitmap::new()
+    }
+
+    /// Ids of the paths that contain every fragment (as returned by
+    /// [`literal_fragments`]), or None when no fragment is long enough to
+    /// narrow anything
+    pub fn paths_containing(&self, fragments: &[String]) -> Option<RoaringBitmap> {
+        let mut trigrams: Vec<Trigram> = fragments
+            .iter()
+            .flat_map(|f| extract_trigrams(f.as_bytes()))
+            .collect();
+        if trigrams.is_empty() {
+            return None;
+        }
+        trigrams.sort_unstable();
+        trigrams.dedup();
+
+        let mut lists: Vec<RoaringBitmap> = trigrams.iter().map(|&t| self.postings(t)).collect();
+        lists.sort_by_key(|l| l.len());
+        let mut result = lists.swap_remove(0);
+        for list in &lists {
+            if result.is_empty() {
+                break;
+            }
+            result &= list;
+        }
+        Some(result)
+    }
+}
+
+/// The literal runs of a glob or search term that any matching path must
+/// contain: the text between glob metacharacters, lowercased, keeping only
+/// ASCII runs of at least three bytes (non-ASCII case folding can change
+/// lengths, so those are left to the exact match)
+pub fn literal_fragments(pattern: &str) -> Vec<String> {
+    let mut fragments = Vec::new();
+    let mut current = String::new();
+    // Inside `[...]` and `{a,b}` nothing is required
+    let mut in_class = false;
+    let mut alternation_depth = 0;
+    for (i, c) in pattern.char_indices() {
+        let literal = match c {
+            '[' => {
+                in_class = true;
+                false
+            }
+            ']' if in_class => {
+                in_class = false;
+                false
+            }
+            _ if in_class => false,
+            '{' => {
+                alternation_depth += 1;
+                false
+            }
+            '}' if alternation_depth > 0 => {
+                alternation_depth -= 1;
+                false
+            }
+            _ if alternation_depth > 0 => false,
+            '*' | '?' | '\\' => false,
+            // `**/` also matches no directory at all
+            '/' if pattern[..i].ends_with("**") => false,
+            _ => c.is_ascii(),
+        };
+        if literal {
+            current.push(c.to_ascii_lowercase());
+        } else if !current.is_empty() {
+            fragments.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        fragments.push(current);
+    }
+    fragments.retain(|f| f.len() >= 3);
+    fragments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_fragments() {
+        assert_eq!(literal_fragments("src/**/*.rs"), ["src/", ".rs"]);
+        assert_eq!(literal_fragments("Config"), ["config"]);
+        assert_eq!(literal_fragments("**/build.rs"), ["build.rs"]);
+        assert_eq!(literal_fragments("*.[ch]pp"), Vec::<String>::new());
+        assert_eq!(
+            literal_fragments("test_[a-z]*_util.go"),
+            ["test_", "_util.go"]
+        );
+        assert_eq!(literal_fragments("lib.{rs,c}"), ["lib."]);
+        assert_eq!(literal_fragments("{src,lib}/mod.rs"), ["/mod.rs"]);
+        assert_eq!(literal_fragments("día_config"), ["a_config"]);
+    }
+
+    #[test]
+    fn test_path_grams_roundtrip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let paths: Vec<PathBuf> = ["src/main.rs", "src/Config.rs", "docs/config.md", "build.rs"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert!(PathGramIndex::open(dir.path(), &paths).unwrap().is_none());
+        write_path_grams(dir.path(), &paths).unwrap();
+
+        // Not used for a table it was not built from
+        let mut renumbered = paths.clone();
+        renumbered.swap(0, 1);
+        assert!(
+            PathGramIndex::open(dir.path(), &renumbered)
+                .unwrap()
+                .is_none()
+        );
+
+        let index = PathGramIndex::open(dir.path(), &paths).unwrap().unwrap();
+        let ids = |fragments: &[&str]| {
+            let fragments: Vec<String> = fragments.iter().map(|f| f.to_string()).collect();
+            index
+                .paths_containing(&fragments)
+                .map(|b| b.iter().collect::<Vec<_>>())
+        };
+        assert_eq!(ids(&["config"]), Some(vec![1, 2]));
+        assert_eq!(ids(&["src/", ".rs"]), Some(vec![0, 1]));
+        assert_eq!(ids(&["missing"]), Some(vec![]));
+        assert_eq!(ids(&["rs"]), None);
+    }
+}
@@ -1,6 +1,13 @@
+use crate::index::content_store::ContentStore;
+use crate::index::git::{self, BlobReader};
+use crate::index::minhash::{self, MinHashStore, Signature};
+use crate::index::path_grams::PathGramIndex;
 use crate::index::types::*;
+use crate::query::levenshtein::{self, LevenshteinAutomaton};
+use crate::query::proximity::{self, Occurrence, Within};
 use crate::utils::{
-    BloomFilter, delta_decode, delta_decode_bitmap, delta_decode_intersect, get_index_dir,
+    BloomFilter, delta_decode, delta_decode_bitmap, delta_decode_intersect, get_history_index_dir,
+    get_index_dir, get_rev_index_dir,
 };
 use ahash::AHashSet;
 use anyhow::{Context, Result};
@@ -8,7 +15,7 @@ use lru::LruCache;
 use memmap2::Mmap;
 use rayon::prelude::*;
 use roaring::RoaringBitmap;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::num::NonZeroUsize;
@@ -20,7 +27,6 @@ struct TrigramDictEntry {
     trigram: Trigram,
     offset: u64,
     length: u32,
-    #[allow(dead_code)]
     doc_freq: u32,
 }
 
@@ -36,6 +42,22 @@ impl TrigramDict {
             .ok()
             .map(|i| &self.entries[i])
     }
+
+    /// Entries whose bytes start with `prefix` (at most three bytes): a
+    /// contiguous run, since trigrams pack their bytes big-endian
+    fn with_prefix(&self, prefix: &[u8]) -> &[TrigramDictEntry] {
+        if prefix.len() > 3 {
+            return &[];
+        }
+        let pad = |fill: u8| {
+            let mut bytes = [fill; 3];
+            bytes[..prefix.len()].copy_from_slice(prefix);
+            bytes_to_trigram(bytes[0], bytes[1], bytes[2])
+        };
+        let start = self.entries.partition_point(|e| e.trigram < pad(0));
+        let end = self.entries.partition_point(|e| e.trigram <= pad(0xFF));
+        &self.entries[start..end]
+    }
 }
 
 /// Token dictionary entry
@@ -43,7 +65,6 @@ struct TokenDictEntry {
     token: String,
     offset: u64,
     length: u32,
-    #[allow(dead_code)]
     doc_freq: u32,
     /// Offset into tokens.positions file (0 if no positions)
     pos_offset: u64,
@@ -63,11 +84,43 @@ impl TokenDict {
             .ok()
             .map(|i| &self.entries[i])
     }
+
+    /// Entries starting with `prefix` (a contiguous run: entries are sorted)
+    fn with_prefix(&self, prefix: &str) -> &[TokenDictEntry] {
+        let start = self.entries.partition_point(|e| e.token.as_str() < prefix);
+        let len = self.entries[start..]
+            .iter()
+            .take_while(|e| e.token.starts_with(prefix))
+            .count();
+        &self.entries[start..start + len]
+    }
+
+    /// Entries accepted by a Levenshtein automaton, with their edit distance
+    fn fuzzy(&self, automaton: &LevenshteinAutomaton) -> Vec<(&TokenDictEntry, u32)> {
+        levenshtein::matching_sorted(automaton, self.entries.len(), |i| &self.entries[i].token)
+            .into_iter()
+            .map(|(i, distance)| (&self.entries[i], distance))
+            .collect()
+    }
+}
+
+/// Distinct tokens with document frequencies summed over segments, sorted
+/// by token
+fn sum_token_freqs<'a>(entries: impl Iterator<Item = &'a TokenDictEntry>) -> Vec<(String, u32)> {
+    let mut freqs: HashMap<&str, u32> = HashMap::new();
+    for entry in entries {
+        *freqs.entry(entry.token.as_str()).or_default() += entry.doc_freq;
+    }
+    let mut tokens: Vec<(String, u32)> = freqs
+        .into_iter()
+        .map(|(token, freq)| (token.to_string(), freq))
+        .collect();
+    tokens.sort_unstable();
+    tokens
 }
 
 /// Reader for a single segment
 struct SegmentReader {
-    #[allow(dead_code)]
     segment_id: SegmentId,
     trigram_dict: TrigramDict,
     trigram_postings: Mmap,
@@ -81,6 +134,10 @@ struct SegmentReader {
     segment_path: PathBuf,
     /// Bloom filter for fast trigram pre-filtering (optional for backwards compat)
     bloom_filter: Option<BloomFilter>,
+    /// Compressed document snapshots (only with --store-content)
+    content_store: Option<ContentStore>,
+    /// MinHash signatures (absent in segments written before them)
+    minhashes: Option<MinHashStore>,
 }
 
 impl SegmentReader {
@@ -152,6 +209,9 @@ impl SegmentReader {
         // Load bloom filter if it exists (optional for backwards compat)
         let bloom_filter = read_bloom_filter(segment_path).ok();
 
+        let content_store = ContentStore::open(segment_path)?;
+        let minhashes = MinHashStore::open(segment_path)?;
+
         Ok(Self {
             segment_id,
             trigram_dict,
@@ -162,6 +222,8 @@ impl SegmentReader {
             line_maps: OnceLock::new(),
             segment_path: segment_path.to_path_buf(),
             bloom_filter,
+            content_store,
+            minhashes,
         })
     }
 
@@ -232,6 +294,20 @@ impl SegmentReader {
         result
     }
 
+    /// Union the postings of every dictionary token within the automaton's
+    /// edit distance
+    fn get_fuzzy_token_docs(&self, automaton: &LevenshteinAutomaton) -> RoaringBitmap {
+        let mut result = RoaringBitmap::new();
+        for (entry, _) in self.token_dict.fuzzy(automaton) {
+            let start = entry.offset as usize;
+            let end = start + entry.length as usize;
+            if end <= self.token_postings.len() {
+                result |= delta_decode_bitmap(&self.token_postings[start..end]);
+            }
+        }
+        result
+    }
+
     /// Get position postings for a token: Vec<(doc_id, positions)>.
     /// When `filter` is provided, only candidate docs are decoded — other
     /// docs' position data is skipped byte-wise, and decoding stops once doc
@@ -259,6 +335,78 @@ impl SegmentReader {
         })
     }
 
+    /// Occurrences of a phrase in each document, sorted by doc id, from the
+    /// position postings (None if a token has none in this segment)
+    fn phrase_occurrences(
+        &self,
+        phrase: &[(String, u32)],
+        filter: Option<&RoaringBitmap>,
+    ) -> Option<Vec<(DocId, Vec<Occurrence>)>> {
+        let postings: Vec<Vec<(u32, Vec<u32>)>> = phrase
+            .iter()
+            .map(|(token, _)| self.get_token_positions(token, filter))
+            .collect::<Option<_>>()?;
+        let tokens: Vec<&str> = phrase.iter().map(|(t, _)| t.as_str()).collect();
+
+        let mut result = Vec::with_capacity(postings[0].len());
+        for (doc_id, starts) in &postings[0] {
+            let doc_positions: Option<Vec<&[u32]>> = postings
+                .iter()
+                .map(|p| {
+                    p.binary_search_by_key(doc_id, |&(d, _)| d)
+                        .ok()
+                        .map(|i| p[i].1.as_slice())
+                })
+                .collect();
+            let Some(doc_positions) = doc_positions else {
+                continue;
+            };
+            let word_at = |pos: u32, token: &str| {
+                tokens
+                    .iter()
+                    .zip(&doc_positions)
+                    .any(|(t, positions)| *t == token && positions.binary_search(&pos).is_ok())
+            };
+            let occs = proximity::phrase_occurrences(phrase, starts.iter().copied(), word_at);
+            if !occs.is_empty() {
+                result.push((*doc_id, occs));
+            }
+        }
+        Some(result)
+    }
+
+    /// Documents of this segment satisfying a proximity (see
+    /// [`IndexReader::resolve_proximity_positional`])
+    fn resolve_proximity(
+        &self,
+        terms: &[Vec<(String, u32)>],
+        within: Within,
+        ordered: bool,
+        filter: Option<&RoaringBitmap>,
+    ) -> Option<RoaringBitmap> {
+        let per_term: Vec<Vec<(DocId, Vec<Occurrence>)>> = terms
+            .iter()
+            .map(|phrase| self.phrase_occurrences(phrase, filter))
+            .collect::<Option<_>>()?;
+
+        let mut result = RoaringBitmap::new();
+        for (doc_id, first) in &per_term[0] {
+            let occurrences: Option<Vec<&[Occurrence]>> = std::iter::once(Some(first.as_slice()))
+                .chain(per_term[1..].iter().map(|docs| {
+                    docs.binary_search_by_key(doc_id, |(d, _)| *d)
+                        .ok()
+                        .map(|i| docs[i].1.as_slice())
+                }))
+                .collect();
+            if let Some(occurrences) = occurrences
+                && !proximity::anchors(&occurrences, within, ordered).is_empty()
+            {
+                result.insert(*doc_id);
+            }
+        }
+        Some(result)
+    }
+
     /// Get line map for a document in this segment (lazy loads on first access)
     fn get_line_map(&self, doc_id: DocId) -> Option<&Vec<u32>> {
         let line_maps = self
@@ -312,10 +460,31 @@ impl std::ops::Deref for FileContent {
     }
 }
 
+/// Where document content is read from when verifying matches
+pub enum ContentSource {
+    /// Files under the root on disk
+    WorkTree,
+    /// Blobs of the commit a revision index was built from
+    GitRev(BlobReader),
+    /// Only the segments' content store (git history indexes, whose
+    /// documents exist nowhere else)
+    Stored,
+}
+
+/// Where cached file content came from. A document's stored snapshot and
+/// the content source's copy of its path are kept apart, so one never
+/// answers a read of the other.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    /// A document's snapshot from the content store
+    Stored(DocId),
+    /// A path read from the content source (work tree or revision blobs)
+    Source(PathBuf),
+}
+
 /// Memory-mapped index reader for fast queries
 pub struct IndexReader {
     root_path: PathBuf,
-    #[allow(dead_code)]
     index_path: PathBuf,
     pub meta: IndexMeta,
     /// Documents stored as Vec for iteration, with a HashMap index for O(1) lookup
@@ -327,10 +496,17 @@ pub struct IndexReader {
     /// O(1) stop-gram lookup (converted from Vec on load)
     stop_grams: AHashSet<Trigram>,
     /// LRU cache for file contents (speeds up repeated queries on same files)
-    file_cache: Mutex<LruCache<PathBuf, Arc<str>>>,
+    file_cache: Mutex<LruCache<CacheKey, Arc<str>>>,
     /// Lazily-built bitmap of valid doc IDs. Safe to cache: documents are
     /// immutable after open (index updates swap in a whole new reader).
     valid_docs_cache: OnceLock<RoaringBitmap>,
+    /// Path trigram index, loaded on the first path-filtered query (None
+    /// for indexes built before it)
+    path_grams: OnceLock<Option<PathGramIndex>>,
+    /// (path id, doc id) of valid documents, sorted, for turning path ids
+    /// from the path trigram index into documents
+    valid_docs_by_path: OnceLock<Vec<(PathId, DocId)>>,
+    content_source: ContentSource,
 }
 
 impl IndexReader {
@@ -343,6 +519,48 @@ impl IndexReader {
             anyhow::bail!("No index found. Run 'fxi index' first.");
         }
 
+        Self::open_at(root_path, index_path, ContentSource::WorkTree)
+    }
+
+    /// Open the index of a git revision built by `fxi index --rev`.
+    /// Content is read from the commit's blobs, not the work tree.
+    pub fn open_rev(root_path: &Path, rev: &str) -> Result<Self> {
+        let root_path = root_path.canonicalize()?;
+        let commit = git::resolve_commit(&root_path, rev)?;
+        let index_path = get_rev_index_dir(&root_path, &commit)?;
+
+        if !index_path.exists() {
+            anyhow::bail!(
+                "No index for revision {} ({}). Run 'fxi index --rev {}' first.",
+                rev,
+                &commit[..commit.len().min(12)],
+                rev
+            );
+        }
+
+        let blobs = BlobReader::new(&root_path, &commit);
+        Self::open_at(root_path, index_path, ContentSource::GitRev(blobs))
+    }
+
+    /// Open the git history index built by `fxi log` / `fxi index
+    /// --history`. Its documents are commit diffs read from the content
+    /// store.
+    pub fn open_history(root_path: &Path) -> Result<Self> {
+        let root_path = root_path.canonicalize()?;
+        let index_path = get_history_index_dir(&root_path)?;
+
+        if !index_path.exists() {
+            anyhow::bail!("No history index found. Run 'fxi index --history' first.");
+        }
+
+        Self::open_at(root_path, index_path, ContentSource::Stored)
+    }
+
+    fn open_at(
+        root_path: PathBuf,
+        index_path: PathBuf,
+        content_source: ContentSource,
+    ) -> Result<Self> {
         // Cleanup stale .tmp files from interrupted operations (crash safety)
         cleanup_tmp_files(&index_path);
 
@@ -426,6 +644,9 @@ impl IndexReader {
             stop_grams,
             file_cache,
             valid_docs_cache: OnceLock::new(),
+            path_grams: OnceLock::new(),
+            valid_docs_by_path: OnceLock::new(),
+            content_source,
         })
     }
 
@@ -509,6 +730,35 @@ impl IndexReader {
         }
     }
 
+    /// Distinct indexed tokens starting with `prefix` (case-insensitive),
+    /// with their document frequency. Frequencies come from the token
+    /// dictionaries, summed over segments, so a file updated in a delta
+    /// segment counts until the next compaction.
+    pub fn tokens_with_prefix(&self, prefix: &str) -> Vec<(String, u32)> {
+        let prefix = prefix.to_lowercase();
+        sum_token_freqs(
+            self.segments
+                .iter()
+                .flat_map(|s| s.token_dict.with_prefix(&prefix)),
+        )
+    }
+
+    /// Distinct indexed trigrams whose bytes start with `prefix` (at most
+    /// three bytes, case-sensitive), with their document frequency summed
+    /// over segments (approximate, as for [`Self::tokens_with_prefix`]),
+    /// sorted by trigram
+    pub fn trigrams_with_prefix(&self, prefix: &[u8]) -> Vec<(Trigram, u32)> {
+        let mut freqs: BTreeMap<Trigram, u32> = BTreeMap::new();
+        for entry in self
+            .segments
+            .iter()
+            .flat_map(|s| s.trigram_dict.with_prefix(prefix))
+        {
+            *freqs.entry(entry.trigram).or_default() += entry.doc_freq;
+        }
+        freqs.into_iter().collect()
+    }
+
     /// Get documents whose token dictionary has any token containing `needle`
     /// as a substring (queries all segments in parallel). Used as a recall
     /// fallback when trigram narrowing is unavailable (stop-grams).
@@ -530,6 +780,68 @@ impl IndexReader {
         }
     }
 
+    /// Get documents containing any token within `max_edits` edits of `term`
+    /// (queries all segments in parallel)
+    pub fn get_fuzzy_token_docs(&self, term: &str, max_edits: u32) -> RoaringBitmap {
+        let automaton = LevenshteinAutomaton::new(term, max_edits);
+        self.segments
+            .par_iter()
+            .map(|segment| segment.get_fuzzy_token_docs(&automaton))
+            .reduce(RoaringBitmap::new, |mut a, b| {
+                a |= b;
+                a
+            })
+    }
+
+    /// Distinct dictionary tokens within `max_edits` edits of `term`, with
+    /// their document frequency (approximate, as for
+    /// [`Self::tokens_with_prefix`])
+    pub fn fuzzy_tokens(&self, term: &str, max_edits: u32) -> Vec<(String, u32)> {
+        let automaton = LevenshteinAutomaton::new(term, max_edits);
+        sum_token_freqs(
+            self.segments
+                .iter()
+                .flat_map(|s| s.token_dict.fuzzy(&automaton))
+                .map(|(entry, _)| entry),
+        )
+    }
+
+    /// Document frequency of a token (case-insensitive) summed over
+    /// segments, as recorded in the token dictionaries
+    pub fn token_doc_freq(&self, token: &str) -> u32 {
+        let token_lower = token.to_lowercase();
+        self.segments
+            .iter()
+            .filter_map(|s| s.token_dict.lookup(&token_lower))
+            .map(|e| e.doc_freq)
+            .sum()
+    }
+
+    /// Document frequency of a trigram summed over segments
+    pub fn trigram_doc_freq(&self, trigram: Trigram) -> u32 {
+        self.segments
+            .iter()
+            .map(|s| s.get_trigram_doc_freq(trigram))
+            .sum()
+    }
+
+    /// Number of loaded segments (base plus deltas)
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Number of segments whose bloom filter rules out containing all of
+    /// `trigrams` (skipped by [`Self::get_trigram_docs_with_bloom`])
+    pub fn bloom_rejections(&self, trigrams: &[Trigram]) -> usize {
+        if trigrams.is_empty() {
+            return 0;
+        }
+        self.segments
+            .iter()
+            .filter(|s| !s.might_contain_trigrams(trigrams))
+            .count()
+    }
+
     /// Get line offsets for a document (searches all segments)
     #[allow(dead_code)]
     pub fn get_line_map(&self, doc_id: DocId) -> Option<&Vec<u32>> {
@@ -684,6 +996,47 @@ impl IndexReader {
         Some(result)
     }
 
+    /// Resolve ordered or word-distance proximity from position postings:
+    /// documents where every term (a phrase of (token, offset) pairs, as
+    /// from `tokenize_query_with_positions`) occurs within reach of the
+    /// first, in order when `ordered`. Line distances cannot be checked
+    /// from word positions, so [`Within::Lines`] is treated as
+    /// [`Within::Anywhere`] and the result only narrows. Only `candidates`
+    /// are decoded when given. Returns None if any segment lacks position
+    /// data.
+    pub fn resolve_proximity_positional(
+        &self,
+        terms: &[Vec<(String, u32)>],
+        within: Within,
+        ordered: bool,
+        candidates: Option<&RoaringBitmap>,
+    ) -> Option<RoaringBitmap> {
+        if terms.len() < 2 || terms.iter().any(|t| t.is_empty()) {
+            return None;
+        }
+        if self.segments.iter().any(|s| s.token_positions.is_none()) {
+            return None;
+        }
+        let within = match within {
+            Within::Lines(_) => Within::Anywhere,
+            w => w,
+        };
+
+        Some(
+            self.segments
+                .par_iter()
+                .map(|segment| {
+                    segment
+                        .resolve_proximity(terms, within, ordered, candidates)
+                        .unwrap_or_default()
+                })
+                .reduce(RoaringBitmap::new, |mut a, b| {
+                    a |= b;
+                    a
+                }),
+        )
+    }
+
     /// Resolve a phrase within one segment (see resolve_phrase_positional).
     fn resolve_phrase_in_segment(
         &self,
@@ -775,11 +1128,133 @@ impl IndexReader {
         })
     }
 
+    /// Valid documents whose ASCII-lowercased path contains every fragment
+    /// (see [`crate::index::path_grams::literal_fragments`]), or None when
+    /// the index has no path trigram index or no fragment is long enough
+    /// to narrow. A superset: callers still match the paths exactly.
+    pub fn docs_with_path_fragments(&self, fragments: &[String]) -> Option<RoaringBitmap> {
+        let path_grams = self
+            .path_grams
+            .get_or_init(|| {
+                PathGramIndex::open(&self.index_path, &self.paths).unwrap_or_else(|e| {
+                    eprintln!("Warning: ignoring path trigram index: {}", e);
+                    None
+                })
+            })
+            .as_ref()?;
+        let path_ids = path_grams.paths_containing(fragments)?;
+
+        let by_path = self.valid_docs_by_path.get_or_init(|| {
+            let mut by_path: Vec<(PathId, DocId)> = self
+                .documents
+                .iter()
+                .filter(|d| d.is_valid())
+                .map(|d| (d.path_id, d.doc_id))
+                .collect();
+            by_path.sort_unstable();
+            by_path
+        });
+        let mut docs = RoaringBitmap::new();
+        for path_id in path_ids {
+            let start = by_path.partition_point(|&(p, _)| p < path_id);
+            docs.extend(
+                by_path[start..]
+                    .iter()
+                    .take_while(|&&(p, _)| p == path_id)
+                    .map(|&(_, doc_id)| doc_id),
+            );
+        }
+        Some(docs)
+    }
+
     /// Get the root path
     pub fn root_path(&self) -> &Path {
         &self.root_path
     }
 
+    /// Where document content comes from
+    pub fn content_source(&self) -> &ContentSource {
+        &self.content_source
+    }
+
+    /// A document's snapshot from its segment's content store, if the index
+    /// keeps one
+    pub fn stored_content(&self, doc_id: DocId) -> Option<String> {
+        let doc = self.get_document(doc_id)?;
+        let store = self
+            .segments
+            .iter()
+            .find(|s| s.segment_id == doc.segment_id)?
+            .content_store
+            .as_ref()?;
+        String::from_utf8(store.get(doc_id)?).ok()
+    }
+
+    /// A document's MinHash signature (None for files too small to have
+    /// one, or segments written before signatures)
+    pub fn minhash(&self, doc_id: DocId) -> Option<&Signature> {
+        let doc = self.get_document(doc_id)?;
+        self.segments
+            .iter()
+            .find(|s| s.segment_id == doc.segment_id)?
+            .minhashes
+            .as_ref()?
+            .get(doc_id)
+    }
+
+    /// Whether any segment carries MinHash signatures
+    pub fn has_minhashes(&self) -> bool {
+        self.segments.iter().any(|s| s.minhashes.is_some())
+    }
+
+    /// Valid documents whose estimated similarity to `signature` is at
+    /// least `threshold`, most similar first
+    pub fn similar_documents(&self, signature: &Signature, threshold: f32) -> Vec<(DocId, f32)> {
+        let valid = self.valid_doc_ids();
+        let mut similar: Vec<(DocId, f32)> = self
+            .segments
+            .iter()
+            .filter_map(|s| s.minhashes.as_ref())
+            .flat_map(|store| store.iter())
+            .filter(|(doc_id, _)| valid.contains(*doc_id))
+            .map(|(doc_id, other)| (doc_id, minhash::similarity(signature, other)))
+            .filter(|&(_, score)| score >= threshold)
+            .collect();
+        similar.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        similar
+    }
+
+    /// The valid document indexed under a relative path
+    pub fn find_document(&self, rel_path: &Path) -> Option<&Document> {
+        self.documents
+            .iter()
+            .filter(|doc| doc.is_valid())
+            .find(|doc| self.get_path(doc).is_some_and(|p| p == rel_path))
+    }
+
+    /// Total (raw, compressed) bytes held by the content stores, or None
+    /// when no segment has one
+    pub fn content_store_sizes(&self) -> Option<(u64, u64)> {
+        self.segments
+            .iter()
+            .filter_map(|s| s.content_store.as_ref())
+            .map(|store| store.sizes())
+            .reduce(|(raw, compressed), (r, c)| (raw + r, compressed + c))
+    }
+
+    /// Read a document's content with LRU caching, preferring the stored
+    /// snapshot over the content source
+    pub fn read_doc_cached(&self, doc_id: DocId, path: &Path) -> Option<FileContent> {
+        let key = CacheKey::Stored(doc_id);
+        if let Some(content) = self.cache_get(&key) {
+            return Some(content);
+        }
+        match self.stored_content(doc_id) {
+            Some(content) => Some(self.cache_content(key, content)),
+            None => self.read_file_cached(path),
+        }
+    }
+
     /// Read file content with LRU caching.
     /// This speeds up repeated queries that access the same files.
     /// The cache stores Arc<str>, so a hit is a refcount bump rather than a
@@ -788,25 +1263,41 @@ impl IndexReader {
     /// Returns None if the file cannot be read.
     pub fn read_file_cached(&self, path: &Path) -> Option<FileContent> {
         // Check cache first
-        {
-            let mut cache = self.file_cache.lock().ok()?;
-            if let Some(content) = cache.get(path) {
-                return Some(FileContent::Cached(Arc::clone(content)));
-            }
+        let key = CacheKey::Source(path.to_path_buf());
+        if let Some(content) = self.cache_get(&key) {
+            return Some(content);
         }
 
-        // Read from disk
-        let content = std::fs::read_to_string(path).ok()?;
+        // Read from disk, or from the indexed commit
+        let content = match &self.content_source {
+            ContentSource::WorkTree => std::fs::read_to_string(path).ok()?,
+            ContentSource::GitRev(blobs) => {
+                let rel_path = path.strip_prefix(&self.root_path).ok()?;
+                String::from_utf8(blobs.read(rel_path)?).ok()?
+            }
+            ContentSource::Stored => return None,
+        };
+        Some(self.cache_content(key, content))
+    }
 
+    fn cache_get(&self, key: &CacheKey) -> Option<FileContent> {
+        let mut cache = self.file_cache.lock().ok()?;
+        cache
+            .get(key)
+            .map(|content| FileContent::Cached(Arc::clone(content)))
+    }
+
+    /// Put freshly read content in the LRU cache if it is small enough
+    fn cache_content(&self, key: CacheKey, content: String) -> FileContent {
         // Only cache if file is small enough
         if content.len() <= MAX_CACHEABLE_FILE_SIZE {
             let content: Arc<str> = content.into();
             if let Ok(mut cache) = self.file_cache.lock() {
-                cache.put(path.to_path_buf(), Arc::clone(&content));
+                cache.put(key, Arc::clone(&content));
             }
-            Some(FileContent::Cached(content))
+            FileContent::Cached(content)
         } else {
-            Some(FileContent::Owned(content))
+            FileContent::Owned(content)
         }
     }
 
@@ -828,54 +1319,45 @@ impl IndexReader {
     }
 }
 
-/// Read documents from docs.bin
+/// Read documents from docs.bin. The entry size tells the format apart:
+/// version 1 entries (no content hash) load with a hash of 0.
 pub fn read_documents(index_path: &Path) -> Result<Vec<Document>> {
     let docs_path = index_path.join("docs.bin");
-    let mut file = BufReader::new(File::open(&docs_path)?);
-
-    let mut buf4 = [0u8; 4];
-    let mut buf8 = [0u8; 8];
-    let mut buf2 = [0u8; 2];
-
-    // Read count
-    file.read_exact(&mut buf4)?;
-    let count = u32::from_le_bytes(buf4) as usize;
-
-    let mut documents = Vec::with_capacity(count);
-
-    for _ in 0..count {
-        file.read_exact(&mut buf4)?;
-        let doc_id = u32::from_le_bytes(buf4);
-
-        file.read_exact(&mut buf4)?;
-        let path_id = u32::from_le_bytes(buf4);
-
-        file.read_exact(&mut buf8)?;
-        let size = u64::from_le_bytes(buf8);
-
-        file.read_exact(&mut buf8)?;
-        let mtime = u64::from_le_bytes(buf8);
-
-        file.read_exact(&mut buf2)?;
-        let lang_val = u16::from_le_bytes(buf2);
-        let language = Language::try_from(lang_val).unwrap_or(Language::Unknown);
-
-        file.read_exact(&mut buf2)?;
-        let flags = DocFlags(u16::from_le_bytes(buf2));
-
-        file.read_exact(&mut buf2)?;
-        let segment_id = u16::from_le_bytes(buf2);
-
-        documents.push(Document {
-            doc_id,
-            path_id,
-            size,
-            mtime,
-            language,
-            flags,
-            segment_id,
-        });
-    }
+    let mut data = Vec::new();
+    BufReader::new(File::open(&docs_path)?).read_to_end(&mut data)?;
+
+    let count =
+        u32::from_le_bytes(data.get(..4).context("Truncated docs.bin")?.try_into()?) as usize;
+    let body = &data[4..];
+    let entry_size = match body.len().checked_div(count) {
+        None => return Ok(Vec::new()),
+        Some(size) if size * count != body.len() => anyhow::bail!("Truncated docs.bin"),
+        Some(size @ (Document::SIZE | Document::SIZE_V1)) => size,
+        Some(size) => anyhow::bail!("Unsupported docs.bin entry size {}", size),
+    };
+
+    let documents = body
+        .chunks_exact(entry_size)
+        .map(|e| {
+            let u16_at = |i: usize| u16::from_le_bytes(e[i..i + 2].try_into().unwrap());
+            let u32_at = |i: usize| u32::from_le_bytes(e[i..i + 4].try_into().unwrap());
+            let u64_at = |i: usize| u64::from_le_bytes(e[i..i + 8].try_into().unwrap());
+            Document {
+                doc_id: u32_at(0),
+                path_id: u32_at(4),
+                size: u64_at(8),
+                mtime: u64_at(16),
+                language: Language::try_from(u16_at(24)).unwrap_or(Language::Unknown),
+                flags: DocFlags(u16_at(26)),
+                segment_id: u16_at(28),
+                content_hash: if entry_size == Document::SIZE {
+                    u64_at(30)
+                } else {
+                    0
+                },
+            }
+        })
+        .collect();
 
     Ok(documents)
 }
@@ -1117,7 +1599,12 @@ fn read_bloom_filter(segment_path: &Path) -> Result<BloomFilter> {
 /// merge or delta segment write operation.
 fn cleanup_tmp_files(index_path: &Path) {
     // Clean up .tmp files in the index directory
-    let tmp_patterns = ["docs.bin.tmp", "paths.bin.tmp", "meta.json.tmp"];
+    let tmp_patterns = [
+        "docs.bin.tmp",
+        "paths.bin.tmp",
+        "path_grams.bin.tmp",
+        "meta.json.tmp",
+    ];
 
     for pattern in &tmp_patterns {
         let tmp_path = index_path.join(pattern);
@@ -1317,6 +1804,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_doc_cached_keeps_snapshot_apart() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root_path = temp_dir.path().canonicalize().unwrap();
+        let file = root_path.join("test.rs");
+        fs::write(&file, "fn indexed() {}\n").unwrap();
+        crate::index::build::build_index_with_config(&root_path, false, true, None, Some(true))
+            .unwrap();
+        fs::write(&file, "fn edited() {}\n").unwrap();
+
+        let reader = IndexReader::open(&root_path).expect("Failed to open index");
+        let doc_id = reader.find_document(Path::new("test.rs")).unwrap().doc_id;
+        // Either order: a cached read of one kind never answers the other
+        assert!(reader.read_file_cached(&file).unwrap().contains("edited"));
+        assert!(
+            reader
+                .read_doc_cached(doc_id, &file)
+                .unwrap()
+                .contains("indexed")
+        );
+        reader.clear_file_cache();
+        assert!(
+            reader
+                .read_doc_cached(doc_id, &file)
+                .unwrap()
+                .contains("indexed")
+        );
+        assert!(reader.read_file_cached(&file).unwrap().contains("edited"));
+        crate::utils::remove_index(&root_path).unwrap();
+    }
+
     #[test]
     fn test_path_traversal_protection() {
         let (_temp_dir, root_path) = create_test_index();
@@ -1,7 +1,11 @@
 use crate::index::reader::IndexReader;
+use crate::index::types::{Trigram, trigram_to_bytes};
 use crate::utils::{find_codebase_root, get_index_dir, list_indexed_codebases};
 use anyhow::Result;
-use std::path::Path;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 /// Display index statistics
 pub fn show_stats(root_path: &Path) -> Result<()> {
@@ -43,11 +47,33 @@ pub fn show_stats(root_path: &Path) -> Result<()> {
     }
 
     // Index size
-    if let Ok(size) = dir_size(&index_path) {
+    let index_size = dir_size(&index_path).ok();
+    if let Some(size) = index_size {
         println!();
         println!("Index size:       {}", format_size(size));
     }
 
+    // Content store overhead (--store-content)
+    if let Some((raw, compressed)) = reader.content_store_sizes() {
+        let ratio = if compressed > 0 {
+            raw as f64 / compressed as f64
+        } else {
+            0.0
+        };
+        println!(
+            "Content store:    {} ({} raw, {:.1}x compression)",
+            format_size(compressed),
+            format_size(raw),
+            ratio
+        );
+        if let Some(size) = index_size.filter(|&s| s > 0) {
+            println!(
+                "Store overhead:   {:.1}% of index size",
+                compressed as f64 * 100.0 / size as f64
+            );
+        }
+    }
+
     // Timestamps
     println!();
     println!(
@@ -62,6 +88,204 @@ pub fn show_stats(root_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Indexed files whose contents are identical
+pub struct DuplicateGroup {
+    /// Size of each copy in bytes
+    pub size: u64,
+    /// Paths relative to the root, sorted
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// Bytes taken by every copy after the first
+    pub fn wasted_bytes(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// Group live documents by their stored content hash, most wasted bytes
+/// first. Reads nothing but the document table; files smaller than
+/// `min_size` (and empty files) are ignored, as are documents indexed
+/// before hashes were recorded.
+pub fn duplicate_groups(reader: &IndexReader, min_size: u64) -> Vec<DuplicateGroup> {
+    let mut by_hash: HashMap<(u64, u64), Vec<PathBuf>> = HashMap::new();
+    for doc_id in reader.valid_doc_ids() {
+        if let Some(doc) = reader.get_document(doc_id)
+            && doc.content_hash != 0
+            && doc.size >= min_size.max(1)
+            && let Some(path) = reader.get_path(doc)
+        {
+            by_hash
+                .entry((doc.content_hash, doc.size))
+                .or_default()
+                .push(path.clone());
+        }
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|((_, size), mut paths)| {
+            paths.sort();
+            DuplicateGroup { size, paths }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.wasted_bytes()
+            .cmp(&a.wasted_bytes())
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    groups
+}
+
+/// List duplicate files and the bytes they waste (`fxi dupes`)
+pub fn show_duplicates(root_path: &Path, min_size: u64) -> Result<()> {
+    let root = find_codebase_root(root_path)?;
+    let reader = IndexReader::open(&root)?;
+
+    if reader
+        .valid_doc_ids()
+        .iter()
+        .filter_map(|id| reader.get_document(id))
+        .all(|doc| doc.content_hash == 0)
+        && !reader.valid_doc_ids().is_empty()
+    {
+        println!("This index has no content hashes; rebuild it with `fxi index --force`.");
+        return Ok(());
+    }
+
+    let groups = duplicate_groups(&reader, min_size);
+    if groups.is_empty() {
+        println!("No duplicate files found.");
+        return Ok(());
+    }
+
+    for group in &groups {
+        println!(
+            "{} x {} ({} wasted)",
+            format_size(group.size),
+            group.paths.len(),
+            format_size(group.wasted_bytes())
+        );
+        for path in &group.paths {
+            println!("  {}", path.display());
+        }
+        println!();
+    }
+
+    let redundant: usize = groups.iter().map(|g| g.paths.len() - 1).sum();
+    let wasted: u64 = groups.iter().map(DuplicateGroup::wasted_bytes).sum();
+    println!(
+        "{} duplicate groups, {} redundant files, {} wasted",
+        groups.len(),
+        redundant,
+        format_size(wasted)
+    );
+
+    Ok(())
+}
+
+/// A dictionary token or trigram with its document frequency
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TermStat {
+    /// The token, or the trigram's bytes with non-printable ones escaped
+    pub term: String,
+    /// Documents containing it, summed over segments (files replaced in a
+    /// delta segment count twice until the next compaction)
+    pub doc_freq: u32,
+    /// Trigram too common to narrow with, skipped by the planner (always
+    /// false for tokens)
+    pub stop_gram: bool,
+}
+
+/// Printable form of a trigram: its bytes, ASCII-escaped (`\t`, `\xe2`)
+pub fn trigram_text(trigram: Trigram) -> String {
+    trigram_to_bytes(trigram)
+        .iter()
+        .flat_map(|&b| std::ascii::escape_default(b))
+        .map(char::from)
+        .collect()
+}
+
+/// Tokens (or trigrams, with `grams`) starting with `prefix`, most frequent
+/// first, at most `top` of them (0 = all). Token prefixes are
+/// case-insensitive; trigram prefixes are raw bytes, at most three.
+/// Stop-grams are listed even once compaction dropped them from the
+/// dictionary, counted as of that compaction plus any later segments.
+pub fn term_stats(reader: &IndexReader, prefix: &str, grams: bool, top: usize) -> Vec<TermStat> {
+    let mut stats: Vec<TermStat> = if grams {
+        let mut freqs: BTreeMap<Trigram, u32> = reader
+            .trigrams_with_prefix(prefix.as_bytes())
+            .into_iter()
+            .collect();
+        let compacted: HashMap<Trigram, u32> =
+            reader.meta.stop_gram_doc_freqs.iter().copied().collect();
+        for &trigram in &reader.meta.stop_grams {
+            if trigram_to_bytes(trigram).starts_with(prefix.as_bytes()) {
+                *freqs.entry(trigram).or_default() += compacted.get(&trigram).copied().unwrap_or(0);
+            }
+        }
+        freqs
+            .into_iter()
+            .map(|(trigram, doc_freq)| TermStat {
+                term: trigram_text(trigram),
+                doc_freq,
+                stop_gram: reader.is_stop_gram(trigram),
+            })
+            .collect()
+    } else {
+        reader
+            .tokens_with_prefix(prefix)
+            .into_iter()
+            .map(|(term, doc_freq)| TermStat {
+                term,
+                doc_freq,
+                stop_gram: false,
+            })
+            .collect()
+    };
+    stats.sort_by(|a, b| {
+        b.doc_freq
+            .cmp(&a.doc_freq)
+            .then_with(|| a.term.cmp(&b.term))
+    });
+    if top > 0 {
+        stats.truncate(top);
+    }
+    stats
+}
+
+/// Print dictionary terms with their document frequencies (`fxi terms`),
+/// as aligned text or one JSON object per line
+pub fn show_terms(
+    root_path: &Path,
+    prefix: &str,
+    top: usize,
+    grams: bool,
+    json: bool,
+) -> Result<()> {
+    if grams && prefix.len() > 3 {
+        anyhow::bail!("a trigram prefix is at most 3 bytes, got {:?}", prefix);
+    }
+    let root = find_codebase_root(root_path)?;
+    let reader = IndexReader::open(&root)?;
+
+    let stats = term_stats(&reader, prefix, grams, top);
+    let mut stdout = std::io::stdout().lock();
+    for stat in &stats {
+        if json {
+            serde_json::to_writer(&mut stdout, stat)?;
+            writeln!(stdout)?;
+        } else if stat.stop_gram {
+            writeln!(stdout, "{:>9}  {}  (stop-gram)", stat.doc_freq, stat.term)?;
+        } else {
+            writeln!(stdout, "{:>9}  {}", stat.doc_freq, stat.term)?;
+        }
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
 /// List all indexed codebases
 pub fn list_indexes() -> Result<()> {
     let codebases = list_indexed_codebases()?;
@@ -78,7 +302,18 @@ pub fn list_indexes() -> Result<()> {
     for codebase in codebases {
         let exists = codebase.root_path.exists();
         let status = if exists { "" } else { " [missing]" };
-        println!("  {}{}", codebase.root_path.display(), status);
+        let rev = codebase
+            .rev
+            .as_deref()
+            .map(|c| format!(" @ {}", &c[..c.len().min(12)]))
+            .unwrap_or_else(|| {
+                if codebase.history {
+                    " (history)".to_string()
+                } else {
+                    String::new()
+                }
+            });
+        println!("  {}{}{}", codebase.root_path.display(), rev, status);
         println!("    Index: {}", codebase.index_dir.display());
         println!();
     }
@@ -121,7 +356,7 @@ fn format_size(bytes: u64) -> String {
 }
 
 /// Format unix timestamp as human-readable date string
-fn format_timestamp(ts: u64) -> String {
+pub fn format_timestamp(ts: u64) -> String {
     // Convert to a basic readable format without external crate
     let secs = ts;
     let days = secs / 86400;
@@ -180,3 +415,111 @@ fn format_timestamp(ts: u64) -> String {
         year, month, day, hours, minutes, seconds
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::build::build_index;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_duplicate_groups() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        let big = "fn shared() {}\n".repeat(20);
+        std::fs::create_dir(root.join("vendor")).unwrap();
+        std::fs::write(root.join("a.rs"), &big).unwrap();
+        std::fs::write(root.join("vendor/a.rs"), &big).unwrap();
+        std::fs::write(root.join("vendor/b.rs"), &big).unwrap();
+        std::fs::write(root.join("small1.rs"), "x\n").unwrap();
+        std::fs::write(root.join("small2.rs"), "x\n").unwrap();
+        std::fs::write(root.join("unique.rs"), "fn unique() {}\n").unwrap();
+        build_index(root, true).unwrap();
+
+        let reader = IndexReader::open(root).unwrap();
+        let groups = duplicate_groups(&reader, 0);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups[0].paths,
+            vec![
+                PathBuf::from("a.rs"),
+                PathBuf::from("vendor/a.rs"),
+                PathBuf::from("vendor/b.rs")
+            ]
+        );
+        assert_eq!(groups[0].wasted_bytes(), 2 * big.len() as u64);
+        assert_eq!(groups[1].paths.len(), 2);
+
+        // --min-size drops the small pair
+        assert_eq!(duplicate_groups(&reader, 100).len(), 1);
+
+        drop(reader);
+        crate::utils::remove_index(root).unwrap();
+    }
+
+    #[test]
+    fn test_term_stats() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("a.rs"), "fn parse_query() {}\n").unwrap();
+        std::fs::write(root.join("b.rs"), "fn parse() {}\nfn parser() {}\n").unwrap();
+        build_index(root, true).unwrap();
+        let reader = IndexReader::open(root).unwrap();
+
+        let terms = |prefix, grams, top| -> Vec<(String, u32)> {
+            term_stats(&reader, prefix, grams, top)
+                .into_iter()
+                .map(|s| (s.term, s.doc_freq))
+                .collect()
+        };
+        assert_eq!(
+            terms("PAR", false, 0),
+            [("parse".to_string(), 2), ("parser".to_string(), 1)]
+        );
+        assert_eq!(terms("par", false, 1).len(), 1);
+        assert_eq!(terms("fn ", true, 0), [("fn ".to_string(), 2)]);
+        assert_eq!(terms("ars", true, 0), [("ars".to_string(), 2)]);
+        assert!(terms("zzz", false, 0).is_empty());
+        assert_eq!(
+            trigram_text(crate::index::types::bytes_to_trigram(b'a', b'\t', 0xe2)),
+            "a\\t\\xe2"
+        );
+
+        drop(reader);
+        crate::utils::remove_index(root).unwrap();
+    }
+
+    #[test]
+    fn test_term_stats_lists_compacted_stop_grams() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        for i in 0..9 {
+            std::fs::write(
+                root.join(format!("{}.rs", i)),
+                format!("fn f{}() {{}}\n", i),
+            )
+            .unwrap();
+        }
+        build_index(root, true).unwrap();
+        // A delta segment, so there is something to merge
+        std::fs::write(root.join("9.rs"), "fn f9() {}\n").unwrap();
+        crate::index::build::update_index(root).unwrap();
+        crate::index::compact::merge_segments(root).unwrap();
+
+        // "fn " is in every file: a stop-gram, gone from the merged
+        // dictionary but still listed
+        let reader = IndexReader::open(root).unwrap();
+        assert_eq!(reader.meta.delta_segments.len(), 0);
+        let fn_gram = crate::index::types::bytes_to_trigram(b'f', b'n', b' ');
+        assert!(reader.is_stop_gram(fn_gram));
+        assert!(reader.trigrams_with_prefix(b"fn ").is_empty());
+        let stats = term_stats(&reader, "fn", true, 0);
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].term, "fn ");
+        assert_eq!(stats[0].doc_freq, 10);
+        assert!(stats[0].stop_gram);
+
+        drop(reader);
+        crate::utils::remove_index(root).unwrap();
+    }
+}
@@ -197,18 +197,28 @@ pub struct Document {
     pub language: Language,
     pub flags: DocFlags,
     pub segment_id: SegmentId,
+    /// [`crate::utils::hash::content_hash`] of the indexed content, so
+    /// updates can skip files whose mtime changed but content did not.
+    /// 0 for documents indexed before format version 2.
+    pub content_hash: u64,
 }
 
 impl Document {
     /// Size of a document entry in bytes (fixed-size for mmap)
-    #[allow(dead_code)]
-    pub const SIZE: usize = 4 + 4 + 8 + 8 + 2 + 2 + 2; // 30 bytes
+    pub const SIZE: usize = 4 + 4 + 8 + 8 + 2 + 2 + 2 + 8; // 38 bytes
+
+    /// Entry size in format version 1, before content hashes
+    pub const SIZE_V1: usize = 30;
 
     pub fn is_valid(&self) -> bool {
         !self.flags.is_stale() && !self.flags.is_tombstone()
     }
 }
 
+/// On-disk format version written to `meta.json`. Version 2 added the
+/// per-document content hash; version 1 indexes still load.
+pub const INDEX_VERSION: u32 = 2;
+
 /// Index metadata stored in meta.json
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexMeta {
@@ -219,6 +229,10 @@ pub struct IndexMeta {
     pub base_segment: Option<u16>,
     pub delta_segments: Vec<u16>,
     pub stop_grams: Vec<Trigram>,
+    /// Document frequency of each stop-gram as of the last compaction,
+    /// which leaves stop-grams out of the merged trigram dictionary
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_gram_doc_freqs: Vec<(Trigram, u32)>,
     pub created_at: u64,
     pub updated_at: u64,
     /// Number of tombstoned documents (for fragmentation tracking)
@@ -242,18 +256,47 @@ pub struct IndexMeta {
     /// re-rejecting them on every update.
     #[serde(default)]
     pub rejected_files: Vec<(PathBuf, u64)>,
+    /// Git state when the index was last brought up to date, so the next
+    /// update can ask git what changed instead of walking the tree. None
+    /// outside git work trees (and for indexes built before this existed).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_state: Option<GitState>,
+    /// Commit this index was built from when it indexes a git revision
+    /// (`fxi index --rev`) rather than the work tree. Content is then read
+    /// from blobs instead of the filesystem.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_rev: Option<String>,
+    /// Whether segments keep compressed document snapshots
+    /// (`fxi index --store-content`) that queries read instead of the
+    /// work tree. Delta writes and compaction preserve the setting.
+    #[serde(default)]
+    pub content_store: bool,
+    /// Newest commit covered by a git history index (`fxi log`). Each
+    /// document there holds the lines one commit added or removed in one
+    /// file; the next catch-up indexes the commits after this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_head: Option<String>,
+}
+
+/// HEAD commit and dirty (modified, staged or untracked) paths of a git
+/// work tree, relative to its root
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitState {
+    pub head: String,
+    pub dirty: Vec<PathBuf>,
 }
 
 impl Default for IndexMeta {
     fn default() -> Self {
         Self {
-            version: 1,
+            version: INDEX_VERSION,
             root_path: PathBuf::new(),
             doc_count: 0,
             segment_count: 0,
             base_segment: None,
             delta_segments: Vec::new(),
             stop_grams: Vec::new(),
+            stop_gram_doc_freqs: Vec::new(),
             created_at: 0,
             updated_at: 0,
             tombstone_count: 0,
@@ -261,6 +304,10 @@ impl Default for IndexMeta {
             delta_baseline: 0,
             has_positions: false,
             rejected_files: Vec::new(),
+            git_state: None,
+            git_rev: None,
+            content_store: false,
+            history_head: None,
         }
     }
 }
@@ -288,6 +335,8 @@ pub struct SearchMatch {
     pub path: PathBuf,
     pub line_number: u32,
     pub score: f32,
+    /// Language the file was indexed as
+    pub language: Language,
 }
 
 /// Configuration for the indexer
@@ -302,6 +351,8 @@ pub struct IndexConfig {
     pub scoring_weights: ScoringWeights,
     /// Number of files per segment chunk (for memory-bounded indexing)
     pub chunk_size: usize,
+    /// Keep a compressed snapshot of every document in its segment
+    pub store_content: bool,
 }
 
 impl Default for IndexConfig {
@@ -323,6 +374,7 @@ impl Default for IndexConfig {
             // queries in parallel -- measured on Chromium, ~100-250 segments
             // answer queries FASTER than one merged segment
             chunk_size: 2000,
+            store_content: false,
         }
     }
 }
@@ -335,7 +387,6 @@ pub fn bytes_to_trigram(b0: u8, b1: u8, b2: u8) -> Trigram {
 
 /// Convert trigram back to bytes
 #[inline]
-#[allow(dead_code)]
 pub fn trigram_to_bytes(t: Trigram) -> [u8; 3] {
     [
         ((t >> 16) & 0xFF) as u8,
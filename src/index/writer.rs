@@ -1,4 +1,6 @@
 use crate::index::build::ProcessedFile;
+use crate::index::content_store::{CompressedContent, write_content_store};
+use crate::index::minhash::{Signature, write_minhashes};
 use crate::index::types::*;
 #[allow(unused_imports)]
 use crate::utils::{
@@ -24,6 +26,8 @@ struct AssignedFile {
     line_offsets: Vec<u32>,
     /// (index into `tokens`, word_position)
     token_positions: Vec<(u32, u32)>,
+    content: Option<CompressedContent>,
+    minhash: Option<Signature>,
 }
 
 /// Data needed to write a segment to disk (sent to background thread)
@@ -81,7 +85,16 @@ impl ChunkedIndexWriter {
     pub fn new(root_path: &Path, config: IndexConfig) -> Result<Self> {
         let root_path = root_path.canonicalize()?;
         let index_path = get_index_dir(&root_path)?;
+        Self::with_index_path(root_path, index_path, config)
+    }
 
+    /// Create a writer for an index stored outside the root's default
+    /// location (git revision indexes)
+    pub fn with_index_path(
+        root_path: PathBuf,
+        index_path: PathBuf,
+        config: IndexConfig,
+    ) -> Result<Self> {
         // Create index directory structure
         fs::create_dir_all(&index_path)?;
         let segments_path = index_path.join("segments");
@@ -193,6 +206,7 @@ impl ChunkedIndexWriter {
                 language: processed.language,
                 flags: processed.flags,
                 segment_id,
+                content_hash: processed.content_hash,
             };
             self.all_documents.push(doc);
 
@@ -203,6 +217,8 @@ impl ChunkedIndexWriter {
                 tokens: processed.tokens,
                 line_offsets: processed.line_offsets,
                 token_positions: processed.token_positions,
+                content: processed.content,
+                minhash: processed.minhash,
             });
         }
 
@@ -244,11 +260,20 @@ impl ChunkedIndexWriter {
 
         let mut token_ids: ahash::AHashMap<String, u32> =
             ahash::AHashMap::with_capacity(file_count * 32);
+        let mut stored: Vec<(DocId, CompressedContent)> = Vec::new();
+        let mut minhashes: Vec<(DocId, Signature)> = Vec::with_capacity(file_count);
 
         // Process each file - just append to flat vectors
         for file in job.files {
             let doc_id = file.doc_id;
 
+            if let Some(content) = file.content {
+                stored.push((doc_id, content));
+            }
+            if let Some(signature) = file.minhash {
+                minhashes.push((doc_id, signature));
+            }
+
             // Add trigram pairs
             for trigram in file.trigrams {
                 trigram_pairs.push((trigram, doc_id));
@@ -364,11 +389,23 @@ impl ChunkedIndexWriter {
                 s.spawn(|| Self::write_line_maps_flat(&job.segment_path, &line_maps));
             let bloom_handle =
                 s.spawn(|| Self::write_bloom_filter(&job.segment_path, &bloom_filter));
+            let minhash_handle = s.spawn(|| {
+                write_minhashes(&job.segment_path, minhashes.iter().map(|(id, s)| (*id, s)))
+            });
+            let content_handle = (!stored.is_empty()).then(|| {
+                s.spawn(|| {
+                    write_content_store(&job.segment_path, stored.iter().map(|(id, c)| (*id, c)))
+                })
+            });
 
             trigram_handle.join().unwrap()?;
             token_handle.join().unwrap()?;
             linemap_handle.join().unwrap()?;
             bloom_handle.join().unwrap()?;
+            minhash_handle.join().unwrap()?;
+            if let Some(handle) = content_handle {
+                handle.join().unwrap()?;
+            }
             Ok::<(), anyhow::Error>(())
         })?;
 
@@ -749,24 +786,17 @@ impl ChunkedIndexWriter {
         file.write_all(&(self.all_documents.len() as u32).to_le_bytes())?;
 
         for doc in &self.all_documents {
-            // One write per record instead of seven
-            let mut rec = [0u8; 30];
-            rec[0..4].copy_from_slice(&doc.doc_id.to_le_bytes());
-            rec[4..8].copy_from_slice(&doc.path_id.to_le_bytes());
-            rec[8..16].copy_from_slice(&doc.size.to_le_bytes());
-            rec[16..24].copy_from_slice(&doc.mtime.to_le_bytes());
-            rec[24..26].copy_from_slice(&(doc.language as u16).to_le_bytes());
-            rec[26..28].copy_from_slice(&doc.flags.0.to_le_bytes());
-            rec[28..30].copy_from_slice(&doc.segment_id.to_le_bytes());
-            file.write_all(&rec)?;
+            file.write_all(&encode_document(doc))?;
         }
 
         file.flush()?;
         Ok(())
     }
 
-    /// Write path store
+    /// Write path store and its trigram index
     fn write_paths(&self) -> Result<()> {
+        crate::index::path_grams::write_path_grams(&self.index_path, &self.all_paths)?;
+
         let paths_path = self.index_path.join("paths.bin");
         let mut file = BufWriter::with_capacity(65536, File::create(&paths_path)?);
 
@@ -807,13 +837,15 @@ impl ChunkedIndexWriter {
         let delta_baseline = delta_segments.len();
 
         let meta = IndexMeta {
-            version: 1,
+            version: INDEX_VERSION,
             root_path: self.root_path.clone(),
             doc_count: self.all_documents.len() as u32,
             segment_count: self.segment_ids.len() as u16,
             base_segment,
             delta_segments,
             stop_grams: stop_grams.iter().copied().collect(),
+            // Every segment still lists its stop-grams
+            stop_gram_doc_freqs: Vec::new(),
             created_at: now,
             updated_at: now,
             tombstone_count: 0, // Fresh index has no tombstones
@@ -821,6 +853,11 @@ impl ChunkedIndexWriter {
             delta_baseline,
             has_positions: true,
             rejected_files: self.rejected_files.clone(),
+            // Recorded by the build once the index is committed
+            git_state: None,
+            git_rev: None,
+            content_store: self.config.store_content,
+            history_head: None,
         };
 
         let meta_path = self.index_path.join("meta.json");
@@ -870,9 +907,14 @@ pub struct DeltaSegmentWriter {
     /// Token -> doc_id -> positions (for positional phrase queries)
     token_position_postings: BTreeMap<String, BTreeMap<DocId, Vec<u32>>>,
     line_maps: HashMap<DocId, Vec<u32>>,
+    /// Compressed snapshots, when the index keeps a content store
+    stored: Vec<(DocId, CompressedContent)>,
+    minhashes: Vec<(DocId, Signature)>,
 
     // Docs to mark as tombstones
     tombstone_doc_ids: Vec<DocId>,
+    /// Existing docs whose mtime was refreshed in place (content unchanged)
+    refreshed_count: usize,
 }
 
 impl DeltaSegmentWriter {
@@ -881,7 +923,16 @@ impl DeltaSegmentWriter {
     pub fn new(root_path: &Path, segment_id: SegmentId) -> Result<Self> {
         let root_path = root_path.canonicalize()?;
         let index_path = get_index_dir(&root_path)?;
+        Self::with_index_path(root_path, index_path, segment_id)
+    }
 
+    /// Create a delta writer for an index stored outside the root's default
+    /// location (git history indexes)
+    pub fn with_index_path(
+        root_path: PathBuf,
+        index_path: PathBuf,
+        segment_id: SegmentId,
+    ) -> Result<Self> {
         // Load existing documents and paths
         let existing_documents = crate::index::reader::read_documents(&index_path)?;
         let existing_paths = crate::index::reader::read_paths(&index_path)?;
@@ -916,25 +967,51 @@ impl DeltaSegmentWriter {
             token_postings: BTreeMap::new(),
             token_position_postings: BTreeMap::new(),
             line_maps: HashMap::new(),
+            stored: Vec::new(),
+            minhashes: Vec::new(),
             tombstone_doc_ids: Vec::new(),
+            refreshed_count: 0,
         })
     }
 
     /// Mark a document as a tombstone by its relative path.
     /// The document will be marked as deleted in docs.bin but its segment data remains.
     pub fn mark_tombstone(&mut self, rel_path: &Path) {
-        // Find the path_id for this path
-        if let Some(&path_id) = self.path_to_id.get(rel_path) {
-            // Find the doc_id for this path_id (most recent non-tombstone)
-            for doc in self.existing_documents.iter().rev() {
-                if doc.path_id == path_id && doc.is_valid() {
-                    self.tombstone_doc_ids.push(doc.doc_id);
-                    break;
-                }
-            }
+        if let Some(idx) = self.live_document(rel_path) {
+            self.tombstone_doc_ids
+                .push(self.existing_documents[idx].doc_id);
         }
     }
 
+    /// Index into `existing_documents` of a path's live document (the most
+    /// recent non-tombstone), if indexed
+    fn live_document(&self, rel_path: &Path) -> Option<usize> {
+        let &path_id = self.path_to_id.get(rel_path)?;
+        self.existing_documents
+            .iter()
+            .rposition(|doc| doc.path_id == path_id && doc.is_valid())
+    }
+
+    /// Content hash recorded for a path's live document (None if the path
+    /// is not indexed or was indexed before hashes were recorded)
+    pub fn indexed_content_hash(&self, rel_path: &Path) -> Option<u64> {
+        let hash = self.existing_documents[self.live_document(rel_path)?].content_hash;
+        (hash != 0).then_some(hash)
+    }
+
+    /// Update a document's mtime in place for a file whose content is
+    /// unchanged (touched, or checked out back and forth), instead of
+    /// tombstoning and re-indexing it. Returns false if the path is not
+    /// indexed.
+    pub fn refresh_mtime(&mut self, rel_path: &Path, mtime: u64) -> bool {
+        let Some(idx) = self.live_document(rel_path) else {
+            return false;
+        };
+        self.existing_documents[idx].mtime = mtime;
+        self.refreshed_count += 1;
+        true
+    }
+
     /// Get or create a path ID for the given relative path
     fn get_or_create_path_id(&mut self, rel_path: &Path) -> PathId {
         if let Some(&path_id) = self.path_to_id.get(rel_path) {
@@ -965,6 +1042,7 @@ impl DeltaSegmentWriter {
             language: processed.language,
             flags: processed.flags,
             segment_id: self.segment_id,
+            content_hash: processed.content_hash,
         };
         self.new_documents.push(doc);
 
@@ -996,10 +1074,23 @@ impl DeltaSegmentWriter {
 
         // Store line map
         self.line_maps.insert(doc_id, processed.line_offsets);
+
+        if let Some(content) = processed.content {
+            self.stored.push((doc_id, content));
+        }
+        if let Some(signature) = processed.minhash {
+            self.minhashes.push((doc_id, signature));
+        }
     }
 
     /// Check if there are any changes to write
     pub fn has_changes(&self) -> bool {
+        self.has_segment_changes() || self.refreshed_count > 0
+    }
+
+    /// Whether the update adds or removes documents (rather than only
+    /// refreshing mtimes, which needs no new segment)
+    fn has_segment_changes(&self) -> bool {
         !self.new_documents.is_empty() || !self.tombstone_doc_ids.is_empty()
     }
 
@@ -1010,6 +1101,7 @@ impl DeltaSegmentWriter {
         if !self.has_changes() {
             return Ok(());
         }
+        let adds_segment = self.has_segment_changes();
 
         // Create segment directory if we have new documents
         if !self.new_documents.is_empty() {
@@ -1050,9 +1142,12 @@ impl DeltaSegmentWriter {
         // Update paths.bin atomically
         write_paths_atomic(&self.index_path, &all_paths)?;
 
-        // Update meta
+        // Update meta (docs.bin is now in the current format)
+        meta.version = INDEX_VERSION;
         meta.doc_count = all_documents.len() as u32;
-        meta.delta_segments.push(self.segment_id);
+        if adds_segment {
+            meta.delta_segments.push(self.segment_id);
+        }
         meta.segment_count = 1 + meta.delta_segments.len() as u16;
         meta.updated_at = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -1072,7 +1167,8 @@ impl DeltaSegmentWriter {
         Ok(())
     }
 
-    /// Write segment files (trigrams, tokens, line maps, bloom filter)
+    /// Write segment files (trigrams, tokens, line maps, bloom filter,
+    /// MinHash signatures, content store)
     fn write_segment_files(&self, segment_path: &Path) -> Result<()> {
         use crate::index::segment_io;
 
@@ -1090,6 +1186,10 @@ impl DeltaSegmentWriter {
             self.trigram_postings.keys().copied(),
             1000,
         )?;
+        write_minhashes(segment_path, self.minhashes.iter().map(|(id, s)| (*id, s)))?;
+        if !self.stored.is_empty() {
+            write_content_store(segment_path, self.stored.iter().map(|(id, c)| (*id, c)))?;
+        }
 
         Ok(())
     }
@@ -1099,6 +1199,20 @@ impl DeltaSegmentWriter {
 // Atomic Write Helpers
 // =============================================================================
 
+/// Encode one docs.bin entry (current format; see [`Document::SIZE`])
+fn encode_document(doc: &Document) -> [u8; Document::SIZE] {
+    let mut rec = [0u8; Document::SIZE];
+    rec[0..4].copy_from_slice(&doc.doc_id.to_le_bytes());
+    rec[4..8].copy_from_slice(&doc.path_id.to_le_bytes());
+    rec[8..16].copy_from_slice(&doc.size.to_le_bytes());
+    rec[16..24].copy_from_slice(&doc.mtime.to_le_bytes());
+    rec[24..26].copy_from_slice(&(doc.language as u16).to_le_bytes());
+    rec[26..28].copy_from_slice(&doc.flags.0.to_le_bytes());
+    rec[28..30].copy_from_slice(&doc.segment_id.to_le_bytes());
+    rec[30..38].copy_from_slice(&doc.content_hash.to_le_bytes());
+    rec
+}
+
 /// Write documents to docs.bin atomically using temp file + rename
 pub fn write_documents_atomic(index_path: &Path, documents: &[Document]) -> Result<()> {
     let docs_path = index_path.join("docs.bin");
@@ -1111,13 +1225,7 @@ pub fn write_documents_atomic(index_path: &Path, documents: &[Document]) -> Resu
         file.write_all(&(documents.len() as u32).to_le_bytes())?;
 
         for doc in documents {
-            file.write_all(&doc.doc_id.to_le_bytes())?;
-            file.write_all(&doc.path_id.to_le_bytes())?;
-            file.write_all(&doc.size.to_le_bytes())?;
-            file.write_all(&doc.mtime.to_le_bytes())?;
-            file.write_all(&(doc.language as u16).to_le_bytes())?;
-            file.write_all(&doc.flags.0.to_le_bytes())?;
-            file.write_all(&doc.segment_id.to_le_bytes())?;
+            file.write_all(&encode_document(doc))?;
         }
 
         file.flush()?;
@@ -1128,8 +1236,12 @@ pub fn write_documents_atomic(index_path: &Path, documents: &[Document]) -> Resu
     Ok(())
 }
 
-/// Write paths to paths.bin atomically using temp file + rename
+/// Write paths to paths.bin atomically using temp file + rename. The path
+/// trigram index is replaced first; until paths.bin follows, readers see
+/// that it was built from other paths and ignore it.
 pub fn write_paths_atomic(index_path: &Path, paths: &[PathBuf]) -> Result<()> {
+    crate::index::path_grams::write_path_grams(index_path, paths)?;
+
     let paths_path = index_path.join("paths.bin");
     let tmp_path = index_path.join("paths.bin.tmp");
 
@@ -1206,10 +1318,13 @@ mod tests {
             size: content.len() as u64,
             language: Language::Rust,
             flags: DocFlags::new(),
+            content_hash: crate::utils::hash::content_hash(content.as_bytes()),
+            minhash: crate::index::minhash::signature(&trigrams),
             trigrams,
             tokens,
             line_offsets,
             token_positions,
+            content: None,
         }
     }
 
@@ -1240,6 +1355,7 @@ mod tests {
         assert!(index_path.join("meta.json").exists());
         assert!(index_path.join("docs.bin").exists());
         assert!(index_path.join("paths.bin").exists());
+        assert!(index_path.join("path_grams.bin").exists());
         assert!(index_path.join("segments").join("seg_0001").exists());
     }
 
@@ -1393,4 +1509,104 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_documents_format_versions() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let doc = Document {
+            doc_id: 7,
+            path_id: 3,
+            size: 120,
+            mtime: 1_700_000_000,
+            language: Language::Rust,
+            flags: DocFlags::new(),
+            segment_id: 2,
+            content_hash: 0xDEAD_BEEF_0000_0001,
+        };
+        write_documents_atomic(dir, std::slice::from_ref(&doc)).unwrap();
+        let read = crate::index::reader::read_documents(dir).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].content_hash, doc.content_hash);
+        assert_eq!(read[0].mtime, doc.mtime);
+
+        // Version 1 entries lack the hash and load with 0
+        let mut v1 = 2u32.to_le_bytes().to_vec();
+        for _ in 0..2 {
+            v1.extend_from_slice(&encode_document(&doc)[..Document::SIZE_V1]);
+        }
+        fs::write(dir.join("docs.bin"), &v1).unwrap();
+        let read = crate::index::reader::read_documents(dir).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].content_hash, 0);
+        assert_eq!(read[1].segment_id, 2);
+
+        v1.pop();
+        fs::write(dir.join("docs.bin"), &v1).unwrap();
+        assert!(crate::index::reader::read_documents(dir).is_err());
+    }
+
+    #[test]
+    fn test_update_skips_touched_unchanged_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        fs::write(root.join("a.rs"), "fn alpha() {}\n").unwrap();
+        fs::write(root.join("b.rs"), "fn beta() {}\n").unwrap();
+        // Enough files that one edit stays under the full-rebuild threshold
+        for i in 0..8 {
+            fs::write(
+                root.join(format!("pad{i}.rs")),
+                format!("fn pad{i}() {{}}\n"),
+            )
+            .unwrap();
+        }
+        crate::index::build::build_index(&root, true).unwrap();
+
+        let index_path = get_index_dir(&root).unwrap();
+        let read_meta = || -> IndexMeta {
+            serde_json::from_reader(File::open(index_path.join("meta.json")).unwrap()).unwrap()
+        };
+        assert_eq!(read_meta().version, INDEX_VERSION);
+        let deltas_before = read_meta().delta_segments.len();
+
+        // Touch a.rs (same content, new mtime) and really change b.rs
+        let later = SystemTime::now() + Duration::from_secs(3600);
+        File::options()
+            .write(true)
+            .open(root.join("a.rs"))
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let writer = DeltaSegmentWriter::new(&root, 99).unwrap();
+        assert_eq!(
+            writer.indexed_content_hash(Path::new("a.rs")),
+            Some(crate::utils::hash::content_hash(b"fn alpha() {}\n"))
+        );
+        assert_eq!(writer.indexed_content_hash(Path::new("missing.rs")), None);
+        drop(writer);
+
+        // Touch only: no new segment, mtime refreshed in place
+        crate::index::build::update_index(&root).unwrap();
+        let meta = read_meta();
+        assert_eq!(meta.delta_segments.len(), deltas_before);
+        assert_eq!(meta.tombstone_count, 0);
+        let docs = crate::index::reader::read_documents(&index_path).unwrap();
+        let later_secs = later.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert_eq!(docs.iter().filter(|d| d.mtime == later_secs).count(), 1);
+
+        // A real edit still goes through a delta segment
+        fs::write(root.join("b.rs"), "fn beta() { changed() }\n").unwrap();
+        File::options()
+            .write(true)
+            .open(root.join("b.rs"))
+            .unwrap()
+            .set_modified(later + Duration::from_secs(60))
+            .unwrap();
+        crate::index::build::update_index(&root).unwrap();
+        let meta = read_meta();
+        assert_eq!(meta.delta_segments.len(), deltas_before + 1);
+        assert_eq!(meta.tombstone_count, 1);
+
+        crate::utils::remove_index(&root).unwrap();
+    }
 }
@@ -13,6 +13,7 @@
 //! - [`server`] - Persistent daemon for instant searches
 //! - [`tui`] - Interactive terminal UI
 //! - [`output`] - Result formatting (ripgrep-compatible)
+//! - [`replace`] - Search and replace (`--replace`)
 //! - [`utils`] - Utility functions (trigrams, encoding, bloom filters)
 //!
 //! ## Quick Start
@@ -48,6 +49,7 @@
 pub mod index;
 pub mod output;
 pub mod query;
+pub mod replace;
 pub mod server;
 pub mod tui;
 pub mod utils;
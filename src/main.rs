@@ -1,6 +1,7 @@
 mod index;
 mod output;
 mod query;
+mod replace;
 mod server;
 mod tui;
 mod utils;
@@ -20,6 +21,20 @@ pub enum ColorChoice {
     Auto,
 }
 
+/// How `fxi count` groups match counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum CountBy {
+    /// Per file
+    #[default]
+    File,
+    /// Per directory (not including subdirectories)
+    Dir,
+    /// Per file extension
+    Ext,
+    /// Per language
+    Lang,
+}
+
 #[derive(Parser)]
 #[command(name = "fxi")]
 #[command(about = "Terminal-first, ultra-fast code search engine")]
@@ -38,6 +53,19 @@ struct Cli {
     #[arg(short, long, default_value = ".")]
     path: PathBuf,
 
+    /// Search every indexed codebase (see `fxi list`) instead of --path
+    #[arg(long, conflicts_with = "roots")]
+    all: bool,
+
+    /// Search these codebases instead of --path (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    roots: Vec<PathBuf>,
+
+    /// Search a git revision (branch, tag, commit) indexed with
+    /// `fxi index --rev` instead of the work tree
+    #[arg(long, conflicts_with_all = ["all", "roots"])]
+    rev: Option<String>,
+
     /// Lines of context after match (-A)
     #[arg(short = 'A', long, default_value = "0")]
     after_context: u32,
@@ -62,6 +90,14 @@ struct Cli {
     #[arg(short = 'w', long)]
     word_regexp: bool,
 
+    /// Treat patterns as literal strings, not queries (-F)
+    #[arg(short = 'F', long)]
+    fixed_strings: bool,
+
+    /// Interpret the pattern as an fxi query (the default; overrides -F)
+    #[arg(long, overrides_with = "fixed_strings")]
+    query_syntax: bool,
+
     /// Maximum number of results (-m), 0 for unlimited
     #[arg(short = 'm', long, default_value = "0")]
     max_count: usize,
@@ -74,6 +110,61 @@ struct Cli {
     #[arg(short = 'c', long)]
     count: bool,
 
+    /// Include or exclude files matching a glob (-g, can be repeated).
+    /// Prefix with ! to exclude, e.g. -g '!*.min.js'
+    #[arg(short = 'g', long = "glob", action = clap::ArgAction::Append)]
+    globs: Vec<String>,
+
+    /// Only search files of this type (-t, can be repeated)
+    #[arg(short = 't', long = "type", action = clap::ArgAction::Append)]
+    types: Vec<String>,
+
+    /// Do not search files of this type (-T, can be repeated)
+    #[arg(short = 'T', long = "type-not", action = clap::ArgAction::Append)]
+    types_not: Vec<String>,
+
+    /// Only search files changed since a git revision (committed on this
+    /// branch, uncommitted or untracked), e.g. --changed-since origin/main
+    #[arg(long, value_name = "REV")]
+    changed_since: Option<String>,
+
+    /// Label matches with the signature of their enclosing function or
+    /// class (like `git grep -p`)
+    #[arg(long)]
+    function_context: bool,
+
+    /// Report files with identical content once, listing the other copies
+    /// as "also in"
+    #[arg(long)]
+    dedupe: bool,
+
+    /// Print the query plan to stderr after the results: each narrowing
+    /// step with its trigrams, stop-grams, bloom rejections, posting sizes
+    /// and remaining candidates, then files verified and matched, and the
+    /// time spent in each phase. Bypasses the daemon's result cache.
+    #[arg(long, conflicts_with_all = ["all", "roots"])]
+    explain: bool,
+
+    /// Replace every match with this text and print the result as a diff
+    /// (-r). `$1`, `${name}` and `$0` insert capture groups, `$$` a `$`.
+    /// Nothing is written without --apply.
+    #[arg(
+        short = 'r',
+        long,
+        value_name = "TEXT",
+        conflicts_with_all = ["all", "roots", "rev", "files_with_matches", "count", "dedupe"]
+    )]
+    replace: Option<String>,
+
+    /// Write the --replace edits to the files instead of previewing them.
+    /// A file that changed since the search is skipped.
+    #[arg(long, requires = "replace")]
+    apply: bool,
+
+    /// Show all supported file types and their globs
+    #[arg(long)]
+    type_list: bool,
+
     /// When to use colors: always, never, auto
     #[arg(long, default_value = "auto", value_enum)]
     color: ColorChoice,
@@ -94,6 +185,23 @@ enum Commands {
         /// Files per chunk (0 = all in one chunk)
         #[arg(long)]
         chunk_size: Option<usize>,
+
+        /// Index a git revision (branch, tag, commit) without checking it out
+        #[arg(long, conflicts_with = "chunk_size")]
+        rev: Option<String>,
+
+        /// Keep a compressed snapshot of every file in the index and search
+        /// it instead of the work tree (kept by later updates)
+        #[arg(long, overrides_with = "no_store_content")]
+        store_content: bool,
+
+        /// Stop keeping file snapshots (see --store-content)
+        #[arg(long)]
+        no_store_content: bool,
+
+        /// Build or catch up the git history index searched by `fxi log`
+        #[arg(long, conflicts_with_all = ["rev", "chunk_size", "store_content", "no_store_content"])]
+        history: bool,
     },
     /// Interactive search TUI
     Search {
@@ -107,12 +215,163 @@ enum Commands {
         #[arg(default_value = ".")]
         path: PathBuf,
     },
+    /// List files with identical content and the space they waste,
+    /// computed from the index
+    Dupes {
+        /// Path to index
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Ignore files smaller than this many bytes
+        #[arg(long, default_value_t = 1)]
+        min_size: u64,
+    },
     /// Compact delta segments
     Compact {
         /// Path to index
         #[arg(default_value = ".")]
         path: PathBuf,
     },
+    /// Search git history for commits that added or removed matching lines
+    /// (needs `fxi index --history`; new commits are indexed first)
+    Log {
+        /// Search pattern (same syntax as content search)
+        pattern: String,
+
+        /// Path inside the repository
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Case insensitive search
+        #[arg(short = 'i', long)]
+        ignore_case: bool,
+
+        /// Treat the pattern as a literal string
+        #[arg(short = 'F', long)]
+        fixed_strings: bool,
+
+        /// Show at most NUM commits, newest first (0 = all)
+        #[arg(short = 'n', long = "max-count", default_value = "20")]
+        max_count: usize,
+
+        /// When to use colors: always, never, auto
+        #[arg(long, default_value = "auto", value_enum)]
+        color: ColorChoice,
+    },
+    /// Find indexed files similar to a file (estimated from MinHash
+    /// signatures of their trigram sets; the file need not be indexed)
+    Similar {
+        /// File to compare against
+        file: PathBuf,
+
+        /// Minimum estimated similarity, from 0 to 1
+        #[arg(long, default_value_t = index::minhash::DEFAULT_THRESHOLD)]
+        threshold: f32,
+
+        /// Show at most NUM files (0 = all)
+        #[arg(short = 'n', long = "max-count", default_value = "20")]
+        max_count: usize,
+
+        /// When to use colors: always, never, auto
+        #[arg(long, default_value = "auto", value_enum)]
+        color: ColorChoice,
+    },
+    /// List indexed files without walking the tree, optionally ranked by
+    /// a fuzzy pattern (like fzf)
+    Files {
+        /// Path inside the codebase
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Rank paths by fuzzy match against this pattern
+        #[arg(short = 'f', long, value_name = "PATTERN")]
+        fuzzy: Option<String>,
+
+        /// Include or exclude files matching this glob (-g, can be repeated)
+        #[arg(short = 'g', long = "glob", action = clap::ArgAction::Append)]
+        globs: Vec<String>,
+
+        /// Only list files of this type (-t, can be repeated)
+        #[arg(short = 't', long = "type", action = clap::ArgAction::Append)]
+        types: Vec<String>,
+
+        /// Do not list files of this type (-T, can be repeated)
+        #[arg(short = 'T', long = "type-not", action = clap::ArgAction::Append)]
+        types_not: Vec<String>,
+
+        /// Show at most NUM files (0 = all)
+        #[arg(short = 'n', long = "max-count", default_value = "0")]
+        max_count: usize,
+
+        /// When to use colors: always, never, auto
+        #[arg(long, default_value = "auto", value_enum)]
+        color: ColorChoice,
+    },
+    /// Complete a token prefix from the index, most common tokens first
+    Complete {
+        /// Token prefix (identifiers are indexed split on `_` and camelCase)
+        prefix: String,
+
+        /// Path inside the codebase
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Show at most NUM tokens (0 = all)
+        #[arg(short = 'n', long = "max-count", default_value = "20")]
+        max_count: usize,
+    },
+    /// List dictionary tokens (or trigrams) with their document frequency
+    Terms {
+        /// Path inside the codebase
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Only terms starting with this prefix (at most 3 bytes with --grams)
+        #[arg(long, default_value = "")]
+        prefix: String,
+
+        /// Show only the N most frequent terms (0 = all)
+        #[arg(long, value_name = "N", default_value = "0")]
+        top: usize,
+
+        /// List trigrams instead of tokens, marking stop-grams
+        #[arg(long)]
+        grams: bool,
+
+        /// Print one JSON object per term
+        #[arg(long)]
+        json: bool,
+    },
+    /// Count matching lines per file, directory, extension or language,
+    /// largest first
+    Count {
+        /// Search pattern (same syntax as content search, `count:` included)
+        pattern: String,
+
+        /// Path inside the codebase
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Group counts by
+        #[arg(long, value_enum, default_value = "file")]
+        by: CountBy,
+
+        /// Case insensitive search
+        #[arg(short = 'i', long)]
+        ignore_case: bool,
+
+        /// Treat the pattern as a literal string
+        #[arg(short = 'F', long)]
+        fixed_strings: bool,
+
+        /// Show at most NUM rows (0 = all)
+        #[arg(short = 'n', long = "max-count", default_value = "0")]
+        max_count: usize,
+
+        /// Print one JSON object per row
+        #[arg(long)]
+        json: bool,
+    },
     /// List all indexed codebases
     List,
     /// Remove an index
@@ -159,15 +418,28 @@ enum DaemonAction {
 struct GrepOptions {
     patterns: Vec<String>,
     path: PathBuf,
+    all: bool,
+    roots: Vec<PathBuf>,
+    rev: Option<String>,
     after_context: u32,
     before_context: u32,
     context: Option<u32>,
     ignore_case: bool,
     invert_match: bool,
     word_regexp: bool,
+    fixed_strings: bool,
     max_count: usize,
     files_with_matches: bool,
     count: bool,
+    globs: Vec<String>,
+    types: Vec<String>,
+    types_not: Vec<String>,
+    changed_since: Option<String>,
+    function_context: bool,
+    dedupe: bool,
+    explain: bool,
+    replace: Option<String>,
+    apply: bool,
     color: ColorChoice,
 }
 
@@ -181,15 +453,28 @@ impl GrepOptions {
         Self {
             patterns,
             path: cli.path.clone(),
+            all: cli.all,
+            roots: cli.roots.clone(),
+            rev: cli.rev.clone(),
             after_context: cli.after_context,
             before_context: cli.before_context,
             context: cli.context,
             ignore_case: cli.ignore_case,
             invert_match: cli.invert_match,
             word_regexp: cli.word_regexp,
+            fixed_strings: cli.fixed_strings && !cli.query_syntax,
             max_count: cli.max_count,
             files_with_matches: cli.files_with_matches,
             count: cli.count,
+            globs: cli.globs.clone(),
+            types: cli.types.clone(),
+            types_not: cli.types_not.clone(),
+            changed_since: cli.changed_since.clone(),
+            function_context: cli.function_context,
+            dedupe: cli.dedupe,
+            explain: cli.explain,
+            replace: cli.replace.clone(),
+            apply: cli.apply,
             color: cli.color,
         }
     }
@@ -199,16 +484,54 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
+        Some(Commands::Index {
+            path,
+            force,
+            history: true,
+            ..
+        }) => {
+            let root = utils::find_codebase_root(&path)?;
+            let _lock = utils::IndexLock::acquire_at(&utils::get_history_index_dir(&root)?)?;
+            let indexed = index::history::update_history_index(&root, force, false)?;
+            if indexed == 0 {
+                println!("History index is up to date");
+            }
+        }
+        Some(Commands::Index {
+            path,
+            force,
+            chunk_size: _,
+            rev: Some(rev),
+            store_content,
+            ..
+        }) => {
+            // Revision indexes live beside the work tree index and never
+            // change, so neither the daemon nor the write lock is involved
+            let root = utils::find_codebase_root(&path)?;
+            index::build::build_rev_index(&root, &rev, force, false, store_content)?;
+        }
         Some(Commands::Index {
             path,
             force,
             chunk_size,
+            rev: None,
+            store_content,
+            no_store_content,
+            history: false,
         }) => {
+            let store_content = if store_content {
+                Some(true)
+            } else if no_store_content {
+                Some(false)
+            } else {
+                None
+            };
+
             // When the daemon watches this root it owns index freshness:
             // it reconciled at watch start and applies file events as they
             // happen, so a scan here would only race its delta writes.
             // --force still rebuilds locally.
-            if !force && chunk_size.is_none() {
+            if !force && chunk_size.is_none() && store_content.is_none() {
                 if let Some(mut client) = server::IndexClient::connect() {
                     let root = utils::find_codebase_root(&path)?;
                     if let Ok((true, pending)) = client.watch_status(Some(&root)) {
@@ -233,7 +556,7 @@ fn main() -> Result<()> {
             // a daemon flush or another fxi index on the same root.
             let root = utils::find_codebase_root(&path)?;
             let _lock = utils::IndexLock::acquire(&root)?;
-            index::build::build_index_auto(&path, force, chunk_size)?;
+            index::build::build_index_auto(&path, force, chunk_size, store_content)?;
         }
         Some(Commands::Search { path }) => {
             tui::run(path, None)?;
@@ -241,22 +564,127 @@ fn main() -> Result<()> {
         Some(Commands::Stats { path }) => {
             index::stats::show_stats(&path)?;
         }
+        Some(Commands::Dupes { path, min_size }) => {
+            index::stats::show_duplicates(&path, min_size)?;
+        }
         Some(Commands::Compact { path }) => {
             let root = utils::find_codebase_root(&path)?;
             let _lock = utils::IndexLock::acquire(&root)?;
             index::compact::compact_segments(&path)?;
         }
+        Some(Commands::Log {
+            pattern,
+            path,
+            ignore_case,
+            fixed_strings,
+            max_count,
+            color,
+        }) => {
+            use std::io::IsTerminal;
+            let matches = do_log_search(&pattern, &path, ignore_case, fixed_strings, max_count)?;
+            let color = match color {
+                ColorChoice::Always => true,
+                ColorChoice::Never => false,
+                ColorChoice::Auto => std::io::stdout().is_terminal(),
+            };
+            output::print_log_matches(&matches, color)?;
+        }
+        Some(Commands::Similar {
+            file,
+            threshold,
+            max_count,
+            color,
+        }) => {
+            use std::io::IsTerminal;
+            let similar = do_similar_search(&file, threshold, max_count)?;
+            let color = match color {
+                ColorChoice::Always => true,
+                ColorChoice::Never => false,
+                ColorChoice::Auto => std::io::stdout().is_terminal(),
+            };
+            output::print_similar_files(&similar, color)?;
+        }
+        Some(Commands::Files {
+            path,
+            fuzzy,
+            globs,
+            types,
+            types_not,
+            max_count,
+            color,
+        }) => {
+            use std::io::IsTerminal;
+            let options = server::protocol::FilesOptions {
+                globs,
+                types,
+                types_not,
+                fuzzy,
+            };
+            let files = do_list_files(&path, max_count, options)?;
+            let color = match color {
+                ColorChoice::Always => true,
+                ColorChoice::Never => false,
+                ColorChoice::Auto => std::io::stdout().is_terminal(),
+            };
+            output::print_file_list(&files, color)?;
+        }
+        Some(Commands::Complete {
+            prefix,
+            path,
+            max_count,
+        }) => {
+            let completions = do_complete(&prefix, &path, max_count)?;
+            output::print_completions(&completions)?;
+        }
+        Some(Commands::Terms {
+            path,
+            prefix,
+            top,
+            grams,
+            json,
+        }) => {
+            index::stats::show_terms(&path, &prefix, top, grams, json)?;
+        }
+        Some(Commands::Count {
+            pattern,
+            path,
+            by,
+            ignore_case,
+            fixed_strings,
+            max_count,
+            json,
+        }) => {
+            let options = server::protocol::ContentSearchOptions {
+                case_insensitive: ignore_case,
+                fixed_strings,
+                ..Default::default()
+            };
+            let root = utils::find_codebase_root(&path)?;
+            let response = do_content_search(&pattern, &root, 0, &options)?;
+            let mut rows = group_match_counts(&response.matches, by);
+            if max_count > 0 {
+                rows.truncate(max_count);
+            }
+            output::print_count_table(&rows, by == CountBy::File, json)?;
+        }
         Some(Commands::List) => {
             index::stats::list_indexes()?;
         }
         Some(Commands::Remove { path }) => {
             let root = utils::find_codebase_root(&path)?;
             utils::remove_index(&root)?;
+            let revs = utils::remove_rev_indexes(&root)?;
             println!("Removed index for: {}", root.display());
+            if revs > 0 {
+                println!("Removed {} revision/history index(es)", revs);
+            }
         }
         Some(Commands::Daemon { action }) => {
             handle_daemon_command(action)?;
         }
+        None if cli.type_list => {
+            output::print_type_list()?;
+        }
         None => {
             let opts = GrepOptions::from_cli(&cli);
 
@@ -430,11 +858,19 @@ fn handle_grep_command(opts: GrepOptions) -> Result<()> {
         );
     }
 
-    // Find codebase root
-    let root = utils::find_codebase_root(&opts.path)?;
+    // Reject unknown -t/-T names up front, before touching the index
+    query::filetypes::check_names(opts.types.iter().chain(&opts.types_not))
+        .map_err(anyhow::Error::msg)?;
 
-    // Build combined pattern for multiple -e flags (OR them together)
-    let combined_pattern = build_pattern(&opts.patterns, opts.word_regexp);
+    // Build combined pattern for multiple -e flags (OR them together).
+    // -F searches the raw strings, one per line; with -w the word boundaries
+    // need a regex, which build_pattern escapes anyway.
+    let fixed_strings = opts.fixed_strings && !opts.word_regexp;
+    let combined_pattern = if fixed_strings {
+        opts.patterns.join("\n")
+    } else {
+        build_pattern(&opts.patterns, opts.word_regexp)
+    };
 
     // Resolve context flags (-C overrides -A and -B)
     let (ctx_before, ctx_after) = if let Some(c) = opts.context {
@@ -448,41 +884,110 @@ fn handle_grep_command(opts: GrepOptions) -> Result<()> {
         context_after: ctx_after,
         case_insensitive: opts.ignore_case,
         files_only: opts.files_with_matches, // Optimize for -l mode
+        globs: opts.globs.clone(),
+        types: opts.types.clone(),
+        types_not: opts.types_not.clone(),
+        fixed_strings,
+        changed_since: opts.changed_since.clone(),
+        function_context: opts.function_context,
+        dedupe: opts.dedupe,
+        explain: opts.explain,
+        facets: false,
     };
 
-    // Try to use daemon for warm search
-    let matches = if let Some(mut client) = server::IndexClient::connect() {
-        match client.content_search(
-            &combined_pattern,
-            Some(&root),
-            opts.max_count,
-            search_options,
-        ) {
-            Ok(response) => response.matches,
-            Err(e) => {
-                eprintln!("Daemon search failed, falling back to direct search: {}", e);
-                do_direct_content_search(
-                    &combined_pattern,
-                    &root,
-                    opts.max_count,
-                    ctx_before,
-                    ctx_after,
-                    opts.ignore_case,
-                    opts.files_with_matches,
-                )?
-            }
+    // Reject a query without a single span to replace before searching
+    let replacer = match opts.replace {
+        Some(ref replacement) => {
+            let parsed = search_options
+                .parse_pattern(&combined_pattern)
+                .map_err(anyhow::Error::msg)?;
+            let plan = query::QueryPlan::from_query(&parsed);
+            Some(replace::Replacer::new(
+                plan.verification.as_ref(),
+                replacement,
+            )?)
+        }
+        None => None,
+    };
+
+    // --all / --roots fan out across codebases; otherwise search the one
+    // containing --path
+    let federated_roots: Vec<PathBuf> = if opts.all {
+        let roots: Vec<PathBuf> = utils::list_indexed_codebases()?
+            .into_iter()
+            .filter(|c| c.is_work_tree())
+            .map(|c| c.root_path)
+            .filter(|p| p.exists())
+            .collect();
+        if roots.is_empty() {
+            anyhow::bail!("No indexed codebases found; run `fxi index` first");
         }
+        roots
     } else {
-        // Fall back to direct search without daemon
-        do_direct_content_search(
+        let mut roots = Vec::with_capacity(opts.roots.len());
+        for path in &opts.roots {
+            match utils::find_codebase_root(path) {
+                Ok(root) => roots.push(root),
+                Err(e) => eprintln!("fxi: skipped {}: {:#}", path.display(), e),
+            }
+        }
+        if roots.is_empty() && !opts.roots.is_empty() {
+            anyhow::bail!("None of the --roots is an indexed codebase");
+        }
+        roots
+    };
+
+    let (matches, suggestions, explain) = if let Some(ref rev) = opts.rev {
+        // The daemon only serves work tree indexes
+        let root = utils::find_codebase_root(&opts.path)?;
+        let response = do_direct_content_search(
             &combined_pattern,
             &root,
+            Some(rev),
             opts.max_count,
-            ctx_before,
-            ctx_after,
-            opts.ignore_case,
-            opts.files_with_matches,
-        )?
+            &search_options,
+        )?;
+        (response.matches, response.suggestions, response.explain)
+    } else if !federated_roots.is_empty() {
+        if let Some(mut client) = server::IndexClient::connect() {
+            match client.content_search_roots(
+                &combined_pattern,
+                &federated_roots,
+                opts.max_count,
+                search_options.clone(),
+            ) {
+                Ok(response) => {
+                    print_root_warnings(&response.warnings);
+                    (response.matches, Vec::new(), None)
+                }
+                Err(e) => {
+                    eprintln!("Daemon search failed, falling back to direct search: {}", e);
+                    let (matches, warnings) = do_direct_federated_search(
+                        &combined_pattern,
+                        &federated_roots,
+                        opts.max_count,
+                        &search_options,
+                    )?;
+                    print_root_warnings(&warnings);
+                    (matches, Vec::new(), None)
+                }
+            }
+        } else {
+            let (matches, warnings) = do_direct_federated_search(
+                &combined_pattern,
+                &federated_roots,
+                opts.max_count,
+                &search_options,
+            )?;
+            print_root_warnings(&warnings);
+            (matches, Vec::new(), None)
+        }
+    } else {
+        // Find codebase root
+        let root = utils::find_codebase_root(&opts.path)?;
+        let response =
+            do_content_search(&combined_pattern, &root, opts.max_count, &search_options)?;
+        (response.matches, response.suggestions, response.explain)
     };
 
     // Output results
@@ -499,7 +1004,10 @@ fn handle_grep_command(opts: GrepOptions) -> Result<()> {
         .len()
         > 1;
 
-    if opts.files_with_matches {
+    if let Some(ref replacer) = replacer {
+        let root = utils::find_codebase_root(&opts.path)?;
+        replace_matches(replacer, &root, &matches, opts.apply, color)?;
+    } else if opts.files_with_matches {
         output::print_files_only(&matches, color)?;
     } else if opts.count {
         output::print_match_counts(&matches, color)?;
@@ -507,9 +1015,341 @@ fn handle_grep_command(opts: GrepOptions) -> Result<()> {
         output::print_content_matches(&matches, color, use_heading)?;
     }
 
+    // Hints go to stderr and only to a person, so scripts see what rg prints
+    if std::io::stderr().is_terminal() {
+        for suggestion in &suggestions {
+            eprintln!("fxi: {}", suggestion.message());
+        }
+    }
+
+    // Asked for explicitly, so printed whether or not stderr is a terminal
+    if let Some(explain) = explain {
+        eprint!("{}", explain.render());
+    }
+
+    Ok(())
+}
+
+/// Preview the replacement of the reported matches as a diff, or write it
+/// (`--apply`) and print how many lines and files changed
+fn replace_matches(
+    replacer: &replace::Replacer,
+    root: &Path,
+    matches: &[server::protocol::ContentMatch],
+    apply: bool,
+    color: bool,
+) -> Result<()> {
+    use std::io::IsTerminal;
+
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    let (edits, skipped) = replacer.plan(root, matches);
+    for (path, reason) in &skipped {
+        eprintln!("fxi: skipped {}: {}", path.display(), reason);
+    }
+
+    if !apply {
+        output::print_replace_diff(&edits, color)?;
+        if std::io::stderr().is_terminal() && !edits.is_empty() {
+            let lines = edits.iter().map(|e| e.lines.len()).sum();
+            eprintln!(
+                "fxi: {} in {} would change; rerun with --apply to write them",
+                plural(lines, "line"),
+                plural(edits.len(), "file")
+            );
+        }
+        return Ok(());
+    }
+
+    // The daemon's watcher sees the renames and reindexes the files
+    let (mut files, mut lines) = (0, 0);
+    for edit in &edits {
+        match edit.apply(root) {
+            Ok(()) => {
+                files += 1;
+                lines += edit.lines.len();
+            }
+            Err(e) => eprintln!("fxi: skipped {}: {:#}", edit.path.display(), e),
+        }
+    }
+    println!(
+        "Replaced {} in {}",
+        plural(lines, "line"),
+        plural(files, "file")
+    );
     Ok(())
 }
 
+/// Search one codebase's work tree index, through the daemon when it runs
+/// (warm) and directly otherwise
+fn do_content_search(
+    pattern: &str,
+    root: &Path,
+    limit: usize,
+    options: &server::protocol::ContentSearchOptions,
+) -> Result<server::protocol::ContentSearchResponse> {
+    // Try to use daemon for warm search
+    if let Some(mut client) = server::IndexClient::connect() {
+        match client.content_search(pattern, Some(root), limit, options.clone()) {
+            Ok(response) => return Ok(response),
+            Err(e) => {
+                eprintln!("Daemon search failed, falling back to direct search: {}", e);
+            }
+        }
+    }
+    // Fall back to direct search without daemon
+    do_direct_content_search(pattern, root, None, limit, options)
+}
+
+/// Matching lines and files per group, most lines first (ties by name).
+/// Files without an extension group under `(none)`, root files under `.`.
+fn group_match_counts(
+    matches: &[server::protocol::ContentMatch],
+    by: CountBy,
+) -> Vec<output::CountRow> {
+    use std::collections::{HashMap, HashSet};
+
+    let mut groups: HashMap<String, (usize, HashSet<&Path>)> = HashMap::new();
+    for m in matches {
+        let key = match by {
+            CountBy::File => m.path.display().to_string(),
+            CountBy::Dir => match m.path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.display().to_string(),
+                _ => ".".to_string(),
+            },
+            CountBy::Ext => m.path.extension().map_or_else(
+                || "(none)".to_string(),
+                |e| e.to_string_lossy().into_owned(),
+            ),
+            CountBy::Lang => format!("{:?}", m.language),
+        };
+        let group = groups.entry(key).or_default();
+        group.0 += 1;
+        group.1.insert(&m.path);
+    }
+
+    let mut rows: Vec<output::CountRow> = groups
+        .into_iter()
+        .map(|(key, (lines, files))| output::CountRow {
+            key,
+            lines,
+            files: files.len(),
+        })
+        .collect();
+    rows.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.key.cmp(&b.key)));
+    rows
+}
+
+/// Search the git history index: matching added/removed lines grouped by
+/// commit, newest first, at most `max_commits` commits (0 = all). Commits
+/// made since the last run are indexed first.
+fn do_log_search(
+    pattern: &str,
+    path: &Path,
+    ignore_case: bool,
+    fixed_strings: bool,
+    max_commits: usize,
+) -> Result<Vec<index::history::LogMatch>> {
+    use crate::index::history;
+    use crate::index::reader::IndexReader;
+    use crate::query::QueryExecutor;
+    use std::collections::HashMap;
+
+    let root = utils::find_codebase_root(path)?;
+    let index_path = utils::get_history_index_dir(&root)?;
+    if !index_path.exists() {
+        anyhow::bail!("No history index found. Run 'fxi index --history' first.");
+    }
+    {
+        let _lock = utils::IndexLock::acquire_at(&index_path)?;
+        let indexed = history::update_history_index(&root, false, true)?;
+        if indexed > 0 {
+            eprintln!("fxi: indexed {} new commit(s)", indexed);
+        }
+    }
+
+    let reader = IndexReader::open_history(&root)?;
+    let commits: HashMap<String, index::git::CommitInfo> = history::load_commits(&index_path)?
+        .into_iter()
+        .map(|c| (c.sha.clone(), c))
+        .collect();
+
+    let mut query = if fixed_strings {
+        query::parse_fixed_strings(pattern)
+    } else {
+        query::parse_query(pattern)
+    };
+    query.options.case_insensitive = ignore_case;
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let matches = QueryExecutor::new(&reader).execute_with_content(&query, 0, 0)?;
+
+    // Diff documents by path, to map match lines back to file lines
+    let doc_ids: HashMap<&Path, index::types::DocId> = reader
+        .documents()
+        .iter()
+        .filter(|doc| doc.is_valid())
+        .filter_map(|doc| Some((reader.get_path(doc)?.as_path(), doc.doc_id)))
+        .collect();
+
+    let mut results: Vec<history::LogMatch> = Vec::new();
+    for m in matches {
+        // Hunk headers are indexed alongside the lines but are not changes
+        if !m.line_content.starts_with(['+', '-']) {
+            continue;
+        }
+        let Some((sha, file)) = history::split_doc_path(&m.path) else {
+            continue;
+        };
+        let Some(commit) = commits.get(&sha) else {
+            continue;
+        };
+        let line_number = doc_ids
+            .get(m.path.as_path())
+            .and_then(|&doc_id| reader.read_doc_cached(doc_id, &root.join(&m.path)))
+            .and_then(|hunks| {
+                history::file_line_number(&hunks, m.line_number.saturating_sub(1) as usize)
+            })
+            .unwrap_or(0);
+        results.push(history::LogMatch {
+            commit: commit.clone(),
+            path: file,
+            line_number,
+            line: m.line_content,
+            match_start: m.match_start,
+            match_end: m.match_end,
+        });
+    }
+
+    // Newest commit first, then by file and line
+    results.sort_by(|a, b| {
+        b.commit
+            .time
+            .cmp(&a.commit.time)
+            .then_with(|| a.commit.sha.cmp(&b.commit.sha))
+            .then_with(|| a.path.cmp(&b.path))
+            .then_with(|| a.line_number.cmp(&b.line_number))
+    });
+    if max_commits > 0 {
+        let mut seen = 0;
+        let mut last: Option<String> = None;
+        results.retain(|m| {
+            if last.as_deref() != Some(m.commit.sha.as_str()) {
+                last = Some(m.commit.sha.clone());
+                seen += 1;
+            }
+            seen <= max_commits
+        });
+    }
+    Ok(results)
+}
+
+/// Token completions for `fxi complete`, from the daemon when it is
+/// running, else straight from the index
+fn do_complete(prefix: &str, path: &Path, limit: usize) -> Result<Vec<query::suggest::Completion>> {
+    use crate::index::reader::IndexReader;
+
+    let root = utils::find_codebase_root(path)?;
+    if let Some(mut client) = server::IndexClient::connect() {
+        match client.complete(Some(&root), prefix, limit) {
+            Ok(response) => return Ok(response.completions),
+            Err(e) => eprintln!(
+                "Daemon completion failed, falling back to direct lookup: {}",
+                e
+            ),
+        }
+    }
+
+    let reader = IndexReader::open(&root)?;
+    warn_if_stale(&reader, &root);
+    Ok(query::suggest::complete(&reader, prefix, limit))
+}
+
+/// Indexed paths for `fxi files`, from the daemon when it is running,
+/// else straight from the index
+fn do_list_files(
+    path: &Path,
+    limit: usize,
+    options: server::protocol::FilesOptions,
+) -> Result<Vec<server::protocol::FileEntry>> {
+    use crate::index::reader::IndexReader;
+    use crate::query::QueryExecutor;
+
+    let root = utils::find_codebase_root(path)?;
+    if let Some(mut client) = server::IndexClient::connect() {
+        match client.files(Some(&root), limit, options.clone()) {
+            Ok(response) => return Ok(response.files),
+            Err(e) => eprintln!(
+                "Daemon listing failed, falling back to direct listing: {}",
+                e
+            ),
+        }
+    }
+
+    let reader = IndexReader::open(&root)?;
+    warn_if_stale(&reader, &root);
+    let query = options.to_query().map_err(anyhow::Error::msg)?;
+    let (files, _) =
+        QueryExecutor::new(&reader).list_files(&query, options.fuzzy.as_deref(), limit)?;
+    Ok(files
+        .into_iter()
+        .map(|f| server::protocol::FileEntry {
+            path: f.path,
+            positions: f.positions,
+        })
+        .collect())
+}
+
+/// Indexed files whose trigram sets are estimated to be at least
+/// `threshold` similar to `file`'s, most similar first. An indexed file
+/// uses its stored signature and is left out of its own results; any other
+/// file is read and hashed on the spot.
+fn do_similar_search(file: &Path, threshold: f32, max_count: usize) -> Result<Vec<(PathBuf, f32)>> {
+    use crate::index::minhash;
+    use crate::index::reader::IndexReader;
+
+    let root = utils::find_codebase_root(file)?;
+    let reader = IndexReader::open(&root)?;
+    warn_if_stale(&reader, &root);
+    if !reader.has_minhashes() {
+        anyhow::bail!(
+            "This index has no similarity signatures; rebuild it with `fxi index --force`."
+        );
+    }
+
+    let full_path = file.canonicalize()?;
+    let indexed = full_path
+        .strip_prefix(&root)
+        .ok()
+        .and_then(|rel| reader.find_document(rel));
+    let (source, signature) = match indexed.and_then(|doc| Some((doc, reader.minhash(doc.doc_id)?)))
+    {
+        Some((doc, signature)) => (Some(doc.doc_id), *signature),
+        None => {
+            let content = std::fs::read(&full_path)?;
+            let signature = minhash::signature(&utils::extract_trigrams(&content))
+                .ok_or_else(|| anyhow::anyhow!("{} is too small to compare", file.display()))?;
+            (indexed.map(|doc| doc.doc_id), signature)
+        }
+    };
+
+    let similar = reader
+        .similar_documents(&signature, threshold)
+        .into_iter()
+        .filter(|&(doc_id, _)| Some(doc_id) != source)
+        .filter_map(|(doc_id, score)| {
+            let doc = reader.get_document(doc_id)?;
+            Some((reader.get_path(doc)?.clone(), score))
+        })
+        .take(if max_count == 0 {
+            usize::MAX
+        } else {
+            max_count
+        })
+        .collect();
+    Ok(similar)
+}
+
 /// Searching without a daemon means results reflect the index as of its
 /// last update; surface that when the index looks old instead of silently
 /// missing recent changes. Tunable via FXI_STALE_WARN_SECS (0 disables).
@@ -556,7 +1396,8 @@ fn build_pattern(patterns: &[String], word_regexp: bool) -> String {
     let escaped: Vec<String> = patterns
         .iter()
         .map(|p| {
-            let escaped = regex::escape(p);
+            // `/` would end the re:/.../ literal early
+            let escaped = regex::escape(p).replace('/', r"\/");
             if word_regexp {
                 format!(r"\b{}\b", escaped)
             } else {
@@ -580,40 +1421,122 @@ fn build_pattern(patterns: &[String], word_regexp: bool) -> String {
     }
 }
 
-/// Direct content search without daemon
+/// Report the roots a federated search left out
+fn print_root_warnings(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("fxi: skipped {}", warning);
+    }
+}
+
+/// Direct content search over several codebases in parallel, merged the
+/// same way the daemon merges a federated search. Roots that fail are left
+/// out and returned as warnings, unless all do.
+fn do_direct_federated_search(
+    pattern: &str,
+    roots: &[PathBuf],
+    limit: usize,
+    options: &server::protocol::ContentSearchOptions,
+) -> Result<(Vec<server::protocol::ContentMatch>, Vec<String>)> {
+    use rayon::prelude::*;
+
+    let results: Vec<_> = roots
+        .par_iter()
+        .map(|root| {
+            (
+                root,
+                do_direct_content_search(pattern, root, None, limit, options),
+            )
+        })
+        .collect();
+
+    let mut per_root = Vec::with_capacity(results.len());
+    let mut warnings = Vec::new();
+    for (root, result) in results {
+        match result {
+            Ok(response) => per_root.push((root.clone(), response.matches)),
+            Err(e) => warnings.push(format!("{}: {:#}", root.display(), e)),
+        }
+    }
+    if per_root.is_empty() {
+        anyhow::bail!("{}", warnings.join("; "));
+    }
+    Ok((
+        server::protocol::merge_root_matches(per_root, limit),
+        warnings,
+    ))
+}
+
+/// Direct content search without daemon, over the work tree index or the
+/// index of a git revision, answered the way the daemon would: with "did
+/// you mean" suggestions when nothing matched, the annotated plan when
+/// `options.explain` is set and facets when `options.facets` is
 fn do_direct_content_search(
     pattern: &str,
     root: &Path,
+    rev: Option<&str>,
     limit: usize,
-    context_before: u32,
-    context_after: u32,
-    case_insensitive: bool,
-    files_only: bool,
-) -> Result<Vec<server::protocol::ContentMatch>> {
+    options: &server::protocol::ContentSearchOptions,
+) -> Result<server::protocol::ContentSearchResponse> {
     use crate::index::reader::IndexReader;
-    use crate::query::{QueryExecutor, parse_query};
+    use crate::query::QueryExecutor;
+    use server::protocol::ContentSearchResponse;
+
+    let start = std::time::Instant::now();
+    let response = |matches: Vec<server::protocol::ContentMatch>, suggestions, explain, facets| {
+        let files: std::collections::HashSet<&Path> =
+            matches.iter().map(|m| m.path.as_path()).collect();
+        ContentSearchResponse {
+            files_with_matches: files.len(),
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+            resolved_root: Some(root.to_path_buf()),
+            matches,
+            suggestions,
+            explain,
+            facets,
+            warnings: Vec::new(),
+        }
+    };
 
-    // Load index
-    let reader = IndexReader::open(root)?;
-    warn_if_stale(&reader, root);
+    // Load index. A revision index cannot go stale.
+    let reader = match rev {
+        Some(rev) => IndexReader::open_rev(root, rev)?,
+        None => {
+            let reader = IndexReader::open(root)?;
+            warn_if_stale(&reader, root);
+            reader
+        }
+    };
 
     // Case-insensitivity is applied at the plan level: the planner narrows
     // through the lowercased token index and verifiers ignore case
-    let mut parsed = parse_query(pattern);
-    parsed.options.case_insensitive = case_insensitive;
+    let parsed = options.parse_pattern(pattern).map_err(anyhow::Error::msg)?;
     if parsed.is_empty() {
-        return Ok(Vec::new());
+        return Ok(response(Vec::new(), Vec::new(), None, None));
     }
 
-    let executor = QueryExecutor::new(&reader);
+    let mut executor = QueryExecutor::new(&reader);
+    if options.explain {
+        executor = executor.with_explain();
+    }
+    if options.facets {
+        executor = executor.with_facets();
+    }
+    let suggestions = |found: bool| {
+        if found {
+            Vec::new()
+        } else {
+            query::suggest::suggest(&reader, &parsed)
+        }
+    };
 
     // -l: files-only path stops scanning each file at its first match and
     // skips per-line match extraction entirely (same as the daemon path)
-    if files_only {
+    if options.files_only {
         let matching_files = executor.execute_files_only(&parsed, limit)?;
-        return Ok(matching_files
+        let suggestions = suggestions(!matching_files.is_empty());
+        let matches = matching_files
             .into_iter()
-            .map(|path| server::protocol::ContentMatch {
+            .map(|(path, duplicates)| server::protocol::ContentMatch {
                 path,
                 line_number: 1,
                 line_content: String::new(),
@@ -621,11 +1544,21 @@ fn do_direct_content_search(
                 match_end: 0,
                 context_before: vec![],
                 context_after: vec![],
+                enclosing_scope: None,
+                duplicates,
+                language: index::types::Language::Unknown,
             })
-            .collect());
+            .collect();
+        return Ok(response(
+            matches,
+            suggestions,
+            executor.take_explain().map(Box::new),
+            None,
+        ));
     }
 
-    let matches = executor.execute_with_content(&parsed, context_before, context_after)?;
+    let matches =
+        executor.execute_with_content(&parsed, options.context_before, options.context_after)?;
 
     // Convert to protocol type and apply limit (0 = unlimited)
     let iter = matches.into_iter();
@@ -643,8 +1576,124 @@ fn do_direct_content_search(
             match_end: m.match_end,
             context_before: m.context_before,
             context_after: m.context_after,
+            enclosing_scope: m.enclosing_scope,
+            duplicates: m.duplicates,
+            language: m.language,
         })
         .collect();
 
-    Ok(result)
+    let suggestions = suggestions(!result.is_empty());
+    Ok(response(
+        result,
+        suggestions,
+        executor.take_explain().map(Box::new),
+        executor.take_facets().map(Box::new),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use index::types::Language;
+    use server::protocol::ContentMatch;
+
+    fn content_match(path: &str, language: Language) -> ContentMatch {
+        ContentMatch {
+            path: PathBuf::from(path),
+            line_number: 1,
+            line_content: String::new(),
+            match_start: 0,
+            match_end: 0,
+            context_before: vec![],
+            context_after: vec![],
+            enclosing_scope: None,
+            duplicates: vec![],
+            language,
+        }
+    }
+
+    fn counts(matches: &[ContentMatch], by: CountBy) -> Vec<(String, usize, usize)> {
+        group_match_counts(matches, by)
+            .into_iter()
+            .map(|row| (row.key, row.lines, row.files))
+            .collect()
+    }
+
+    fn sample() -> Vec<ContentMatch> {
+        vec![
+            content_match("src/a.rs", Language::Rust),
+            content_match("src/a.rs", Language::Rust),
+            content_match("src/b.rs", Language::Rust),
+            content_match("build", Language::Shell),
+            content_match("Makefile", Language::Unknown),
+            content_match("src/c.h", Language::Cpp),
+        ]
+    }
+
+    #[test]
+    fn test_group_match_counts_by_file() {
+        assert_eq!(
+            counts(&sample(), CountBy::File),
+            vec![
+                ("src/a.rs".to_string(), 2, 1),
+                ("Makefile".to_string(), 1, 1),
+                ("build".to_string(), 1, 1),
+                ("src/b.rs".to_string(), 1, 1),
+                ("src/c.h".to_string(), 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_match_counts_by_dir_and_ext() {
+        assert_eq!(
+            counts(&sample(), CountBy::Dir),
+            vec![("src".to_string(), 4, 3), (".".to_string(), 2, 2)]
+        );
+        assert_eq!(
+            counts(&sample(), CountBy::Ext),
+            vec![
+                ("rs".to_string(), 3, 2),
+                ("(none)".to_string(), 2, 2),
+                ("h".to_string(), 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_match_counts_by_lang_uses_indexed_language() {
+        // A shebang script and a C++ header are grouped by what the index
+        // detected, not by their (missing or ambiguous) extension
+        assert_eq!(
+            counts(&sample(), CountBy::Lang),
+            vec![
+                ("Rust".to_string(), 3, 2),
+                ("Cpp".to_string(), 1, 1),
+                ("Shell".to_string(), 1, 1),
+                ("Unknown".to_string(), 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_direct_federated_search_skips_failed_root() {
+        let indexed = tempfile::TempDir::new().unwrap();
+        std::fs::write(indexed.path().join("lib.rs"), "fn federated_marker() {}\n").unwrap();
+        let root = indexed.path().canonicalize().unwrap();
+        index::build::build_index(&root, false).unwrap();
+        let unindexed = tempfile::TempDir::new().unwrap();
+        let bad = unindexed.path().canonicalize().unwrap();
+        let options = server::protocol::ContentSearchOptions::default();
+
+        let roots = [root.clone(), bad.clone()];
+        let (matches, warnings) =
+            do_direct_federated_search("federated_marker", &roots, 0, &options).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, root.join("lib.rs"));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with(&bad.display().to_string()));
+
+        assert!(do_direct_federated_search("federated_marker", &[bad], 0, &options).is_err());
+        utils::remove_index(&root).unwrap();
+    }
 }
@@ -9,6 +9,7 @@
 //! - **Heading mode** (`--heading`): Group results by file
 //! - **Files only** (`-l`): Print only matching file names
 //! - **Count** (`-c`): Print match count per file
+//! - **Replace preview** (`--replace`): Unified diff of the rewritten lines
 //!
 //! ## Color Scheme
 //!
@@ -16,6 +17,8 @@
 //! - **Green**: Line numbers
 //! - **Red (bold)**: Match highlights
 //! - **Cyan**: Context separators
+//! - **Yellow**: Commit SHAs (`fxi log`)
+//! - **Red / Green**: Removed and added lines (`--replace`)
 //!
 //! ## Example Output
 //!
@@ -26,8 +29,27 @@
 //! --
 //! 100:   search_index("another query");
 //! ```
+//!
+//! With `--function-context`, a `=` line names the enclosing function:
+//!
+//! ```text
+//! src/main.rs=38=fn run(args: &Args) -> Result<()> {
+//! src/main.rs:42:    let result = search_index("query");
+//! ```
+//!
+//! With `--dedupe`, a file standing in for identical copies names them:
+//!
+//! ```text
+//! vendor/a/lib.rs (also in: vendor/b/lib.rs, vendor/c/lib.rs)
+//! 12:pub fn parse() {}
+//! ```
 
-use crate::server::protocol::ContentMatch;
+use crate::index::history::LogMatch;
+use crate::index::stats::format_timestamp;
+use crate::query::suggest::Completion;
+use crate::replace::FileEdit;
+use crate::server::protocol::{ContentMatch, FileEntry};
+use serde::Serialize;
 use std::io::{self, Write};
 use termcolor::{BufferedStandardStream, Color, ColorChoice, ColorSpec, WriteColor};
 
@@ -38,6 +60,9 @@ struct Colors {
     line_num: ColorSpec,
     separator: ColorSpec,
     highlight: ColorSpec,
+    commit: ColorSpec,
+    removed: ColorSpec,
+    added: ColorSpec,
 }
 
 impl Colors {
@@ -52,12 +77,21 @@ impl Colors {
         separator.set_fg(Some(Color::Cyan));
         let mut highlight = ColorSpec::new();
         highlight.set_fg(Some(Color::Red)).set_bold(true);
+        let mut commit = ColorSpec::new();
+        commit.set_fg(Some(Color::Yellow));
+        let mut removed = ColorSpec::new();
+        removed.set_fg(Some(Color::Red));
+        let mut added = ColorSpec::new();
+        added.set_fg(Some(Color::Green));
         Self {
             path,
             path_heading,
             line_num,
             separator,
             highlight,
+            commit,
+            removed,
+            added,
         }
     }
 }
@@ -91,6 +125,7 @@ pub fn print_content_matches(
     let colors = Colors::new();
     let mut current_file: Option<&std::path::Path> = None;
     let mut last_line_num: Option<u32> = None;
+    let mut last_scope: Option<u32> = None;
 
     for m in matches {
         let is_new_file = current_file.map(|p| p != m.path).unwrap_or(true);
@@ -104,12 +139,23 @@ pub fn print_content_matches(
             if heading {
                 // Print filename header
                 stdout.set_color(&colors.path_heading)?;
-                writeln!(stdout, "{}", m.path.display())?;
+                write!(stdout, "{}", m.path.display())?;
+                stdout.reset()?;
+                write_also_in(&mut stdout, &colors, &m.duplicates)?;
+                writeln!(stdout)?;
+            } else if !m.duplicates.is_empty() {
+                // Without headings the copies get a line of their own
+                // ahead of the file's first match
+                stdout.set_color(&colors.path)?;
+                write!(stdout, "{}", m.path.display())?;
                 stdout.reset()?;
+                write_also_in(&mut stdout, &colors, &m.duplicates)?;
+                writeln!(stdout)?;
             }
 
             current_file = Some(&m.path);
             last_line_num = None;
+            last_scope = None;
         }
 
         // Print context separator if there's a gap
@@ -128,9 +174,41 @@ pub fn print_content_matches(
             }
         }
 
+        // Print the enclosing function (--function-context) as a `=` line,
+        // like `git grep -p`, unless it is already on screen
+        if let Some((scope_line, signature)) = &m.enclosing_scope
+            && last_scope != Some(*scope_line)
+        {
+            let first_shown = m
+                .context_before
+                .first()
+                .map(|(n, _)| *n)
+                .unwrap_or(m.line_number);
+            if *scope_line < first_shown && last_line_num.is_none_or(|last| *scope_line > last) {
+                print_context_line(
+                    &mut stdout,
+                    &colors,
+                    &m.path,
+                    *scope_line,
+                    signature,
+                    heading,
+                    '=',
+                )?;
+            }
+            last_scope = Some(*scope_line);
+        }
+
         // Print context before
         for (line_num, content) in &m.context_before {
-            print_context_line(&mut stdout, &colors, &m.path, *line_num, content, heading)?;
+            print_context_line(
+                &mut stdout,
+                &colors,
+                &m.path,
+                *line_num,
+                content,
+                heading,
+                '-',
+            )?;
         }
 
         // Print the match line
@@ -147,7 +225,15 @@ pub fn print_content_matches(
 
         // Print context after
         for (line_num, content) in &m.context_after {
-            print_context_line(&mut stdout, &colors, &m.path, *line_num, content, heading)?;
+            print_context_line(
+                &mut stdout,
+                &colors,
+                &m.path,
+                *line_num,
+                content,
+                heading,
+                '-',
+            )?;
         }
 
         // Track last line for gap detection
@@ -162,7 +248,7 @@ pub fn print_content_matches(
     stdout.flush()
 }
 
-/// Print a context line (non-matching)
+/// Print a non-matching line: context (`-`) or an enclosing scope (`=`)
 fn print_context_line(
     stdout: &mut BufferedStandardStream,
     colors: &Colors,
@@ -170,20 +256,21 @@ fn print_context_line(
     line_num: u32,
     content: &str,
     heading: bool,
+    sep: char,
 ) -> io::Result<()> {
     if !heading {
         // Print path prefix when not using heading mode
         stdout.set_color(&colors.path)?;
         write!(stdout, "{}", path.display())?;
         stdout.reset()?;
-        write!(stdout, "-")?;
+        write!(stdout, "{}", sep)?;
     }
 
     // Print line number
     stdout.set_color(&colors.line_num)?;
     write!(stdout, "{}", line_num)?;
     stdout.reset()?;
-    write!(stdout, "-")?;
+    write!(stdout, "{}", sep)?;
 
     // Print content
     writeln!(stdout, "{}", content)?;
@@ -244,6 +331,163 @@ fn print_match_line(
     Ok(())
 }
 
+/// Print `fxi log` results: a header per commit (short SHA, date, author,
+/// summary) followed by its matching added and removed lines
+pub fn print_log_matches(matches: &[LogMatch], color: bool) -> io::Result<()> {
+    let mut stdout = buffered_stdout(color);
+    let colors = Colors::new();
+    let mut last_sha: Option<&str> = None;
+
+    for m in matches {
+        let commit = &m.commit;
+        if last_sha != Some(commit.sha.as_str()) {
+            if last_sha.is_some() {
+                writeln!(stdout)?;
+            }
+            stdout.set_color(&colors.commit)?;
+            write!(stdout, "{}", &commit.sha[..commit.sha.len().min(12)])?;
+            stdout.reset()?;
+            let date = format_timestamp(commit.time);
+            writeln!(
+                stdout,
+                " {} {}  {}",
+                &date[..10],
+                commit.author,
+                commit.summary
+            )?;
+            last_sha = Some(&commit.sha);
+        }
+        print_match_line(
+            &mut stdout,
+            &colors,
+            &m.path,
+            m.line_number,
+            &m.line,
+            m.match_start,
+            m.match_end,
+            false,
+        )?;
+    }
+
+    stdout.flush()
+}
+
+/// Print a `--replace` preview as a unified diff without context lines:
+/// one hunk per run of consecutive rewritten lines
+pub fn print_replace_diff(edits: &[FileEdit], color: bool) -> io::Result<()> {
+    let mut stdout = buffered_stdout(color);
+    let colors = Colors::new();
+
+    for edit in edits {
+        stdout.set_color(&colors.path_heading)?;
+        writeln!(stdout, "--- a/{}", edit.path.display())?;
+        writeln!(stdout, "+++ b/{}", edit.path.display())?;
+        stdout.reset()?;
+
+        // A replacement may insert line breaks, shifting later lines
+        let mut shift: i64 = 0;
+        for hunk in edit
+            .lines
+            .chunk_by(|a, b| b.line_number == a.line_number + 1)
+        {
+            let start = hunk[0].line_number as i64;
+            let added: i64 = hunk.iter().map(|l| l.new.split('\n').count() as i64).sum();
+            stdout.set_color(&colors.separator)?;
+            writeln!(
+                stdout,
+                "@@ -{},{} +{},{} @@",
+                start,
+                hunk.len(),
+                start + shift,
+                added
+            )?;
+            stdout.reset()?;
+            for line in hunk {
+                stdout.set_color(&colors.removed)?;
+                writeln!(stdout, "-{}", line.old)?;
+            }
+            for line in hunk.iter().flat_map(|l| l.new.split('\n')) {
+                stdout.set_color(&colors.added)?;
+                writeln!(stdout, "+{}", line)?;
+            }
+            stdout.reset()?;
+            shift += added - hunk.len() as i64;
+        }
+    }
+
+    stdout.flush()
+}
+
+/// Append ` (also in: a, b)` for the identical copies collapsed into a
+/// file by `--dedupe`; nothing when there are none
+fn write_also_in(
+    stdout: &mut BufferedStandardStream,
+    colors: &Colors,
+    duplicates: &[std::path::PathBuf],
+) -> io::Result<()> {
+    if duplicates.is_empty() {
+        return Ok(());
+    }
+    let paths: Vec<String> = duplicates.iter().map(|p| p.display().to_string()).collect();
+    stdout.set_color(&colors.separator)?;
+    write!(stdout, " (also in: {})", paths.join(", "))?;
+    stdout.reset()
+}
+
+/// Print `fxi files` results, highlighting fuzzy-matched characters
+pub fn print_file_list(files: &[FileEntry], color: bool) -> io::Result<()> {
+    let mut stdout = buffered_stdout(color);
+    let colors = Colors::new();
+
+    for file in files {
+        let path = file.path.display().to_string();
+        if file.positions.is_empty() || !color {
+            writeln!(stdout, "{}", path)?;
+            continue;
+        }
+        let mut positions = file.positions.iter().peekable();
+        for (i, c) in path.chars().enumerate() {
+            if positions.next_if_eq(&&i).is_some() {
+                stdout.set_color(&colors.highlight)?;
+                write!(stdout, "{}", c)?;
+                stdout.reset()?;
+            } else {
+                write!(stdout, "{}", c)?;
+            }
+        }
+        writeln!(stdout)?;
+    }
+
+    stdout.flush()
+}
+
+/// Print `fxi similar` results: estimated similarity, then the path
+pub fn print_similar_files(files: &[(std::path::PathBuf, f32)], color: bool) -> io::Result<()> {
+    let mut stdout = buffered_stdout(color);
+    let colors = Colors::new();
+
+    for (path, score) in files {
+        stdout.set_color(&colors.line_num)?;
+        write!(stdout, "{:>3.0}%", score * 100.0)?;
+        stdout.reset()?;
+        write!(stdout, "  ")?;
+        stdout.set_color(&colors.path)?;
+        writeln!(stdout, "{}", path.display())?;
+        stdout.reset()?;
+    }
+
+    stdout.flush()
+}
+
+/// Print token completions (`fxi complete`): document count, then token
+pub fn print_completions(completions: &[Completion]) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    for c in completions {
+        writeln!(stdout, "{:>7}  {}", c.doc_freq, c.token)?;
+    }
+    stdout.flush()
+}
+
 /// Print only filenames (for -l flag)
 pub fn print_files_only(matches: &[ContentMatch], color: bool) -> io::Result<()> {
     let mut stdout = buffered_stdout(color);
@@ -254,8 +498,10 @@ pub fn print_files_only(matches: &[ContentMatch], color: bool) -> io::Result<()>
     for m in matches {
         if seen_files.insert(m.path.as_path()) {
             stdout.set_color(&colors.path)?;
-            writeln!(stdout, "{}", m.path.display())?;
+            write!(stdout, "{}", m.path.display())?;
             stdout.reset()?;
+            write_also_in(&mut stdout, &colors, &m.duplicates)?;
+            writeln!(stdout)?;
         }
     }
 
@@ -267,25 +513,68 @@ pub fn print_match_counts(matches: &[ContentMatch], color: bool) -> io::Result<(
     let mut stdout = buffered_stdout(color);
     let colors = Colors::new();
 
-    let mut counts: std::collections::HashMap<&std::path::Path, usize> =
+    let mut counts: std::collections::HashMap<&std::path::Path, (usize, &[std::path::PathBuf])> =
         std::collections::HashMap::new();
 
     for m in matches {
-        *counts.entry(&m.path).or_insert(0) += 1;
+        counts.entry(&m.path).or_insert((0, &m.duplicates)).0 += 1;
     }
 
     let mut sorted: Vec<_> = counts.into_iter().collect();
     sorted.sort_by(|a, b| a.0.cmp(b.0));
 
-    for (path, count) in sorted {
+    for (path, (count, duplicates)) in sorted {
         stdout.set_color(&colors.path)?;
         write!(stdout, "{}", path.display())?;
         stdout.reset()?;
         write!(stdout, ":")?;
         stdout.set_color(&colors.line_num)?;
-        writeln!(stdout, "{}", count)?;
+        write!(stdout, "{}", count)?;
         stdout.reset()?;
+        write_also_in(&mut stdout, &colors, duplicates)?;
+        writeln!(stdout)?;
+    }
+
+    stdout.flush()
+}
+
+/// One row of `fxi count`: a file, directory, extension or language
+#[derive(Debug, Clone, Serialize)]
+pub struct CountRow {
+    pub key: String,
+    /// Matching lines
+    pub lines: usize,
+    /// Files with a match
+    pub files: usize,
+}
+
+/// Print `fxi count` rows: matching lines, then the group (and its file
+/// count unless grouping by file), or one JSON object per row
+pub fn print_count_table(rows: &[CountRow], by_file: bool, json: bool) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    for row in rows {
+        if json {
+            serde_json::to_writer(&mut stdout, row)?;
+            writeln!(stdout)?;
+        } else if by_file {
+            writeln!(stdout, "{:>9}  {}", row.lines, row.key)?;
+        } else {
+            let files = if row.files == 1 { "file" } else { "files" };
+            writeln!(
+                stdout,
+                "{:>9}  {}  ({} {})",
+                row.lines, row.key, row.files, files
+            )?;
+        }
     }
+    stdout.flush()
+}
 
+/// Print the file type table (for --type-list), in ripgrep's format
+pub fn print_type_list() -> io::Result<()> {
+    let mut stdout = buffered_stdout(false);
+    for file_type in crate::query::filetypes::FILE_TYPES {
+        writeln!(stdout, "{}: {}", file_type.name, file_type.globs.join(", "))?;
+    }
     stdout.flush()
 }
@@ -1,10 +1,23 @@
-use crate::index::reader::{FileContent, IndexReader};
+use crate::index::build::build_line_map;
+use crate::index::git;
+use crate::index::path_grams::literal_fragments;
+use crate::index::reader::{ContentSource, FileContent, IndexReader};
 use crate::index::types::{DocId, Language, SearchMatch};
-use crate::query::parser::{Query, SortOrder};
+use crate::query::explain::{
+    PhaseTimings, QueryExplain, StepExplain, describe_verification, millis,
+};
+use crate::query::facets::Facets;
+use crate::query::fuzzy::{FuzzyPattern, rank_paths};
+use crate::query::levenshtein::LevenshteinAutomaton;
+use crate::query::lexer;
+use crate::query::parser::{FieldFilter, Query, SortOrder};
 use crate::query::planner::{FilterStep, PlanStep, QueryPlan, VerificationStep};
+use crate::query::proximity::{ContentWords, Within};
+use crate::query::scope::{ScopeTable, line_map_matches};
 use crate::query::scorer::{ScoreContext, Scorer, ScoringWeights};
+use crate::utils::token_spans;
 use anyhow::Result;
-use globset::Glob;
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
 use memmap2::Mmap;
 use rayon::prelude::*;
 use regex::Regex;
@@ -13,7 +26,8 @@ use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, OnceLock, RwLock};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Instant;
 
 /// Context lines before/after a match: Vec<(line_number, line_content)>
 type ContextLines = Vec<(u32, String)>;
@@ -24,19 +38,196 @@ type FileMatch = (u32, String, usize, usize);
 /// Collected file matches with metadata: (doc_id, full_path, rel_path, mtime, matches)
 type FileMatchResult = (DocId, PathBuf, PathBuf, u64, Vec<FileMatch>);
 
-/// Precompiled filename filter matcher to avoid per-document recompilation.
-enum FilenameMatcher {
-    Exact(String),
-    Glob(globset::GlobMatcher),
+/// Precompiled include/exclude matcher for a glob-valued filter field
+/// (`path:`, `file:`, `-g`, `-t`), built once per query instead of per
+/// document.
+struct GlobFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    /// Literal fragments of each include glob: a matching path contains
+    /// all fragments of at least one. None when an include value matches
+    /// everything.
+    include_fragments: Option<Vec<Vec<String>>>,
 }
 
-impl FilenameMatcher {
+impl GlobFilter {
+    /// Compile path globs. Globs are anchored to the whole relative path
+    /// with `*` not crossing `/`; a trailing `/` (`-path:vendor/`) selects
+    /// everything under that directory.
+    fn for_paths(field: &FieldFilter) -> Option<Self> {
+        Self::build(field, |value, _| {
+            let pattern = match value.strip_suffix('/') {
+                Some(dir) if !dir.is_empty() => format!("{}/**", dir),
+                _ => value.to_string(),
+            };
+            Self::separator_glob(&pattern).into_iter().collect()
+        })
+    }
+
+    /// Compile filename patterns (matched case-insensitively against the
+    /// lowercased file name). Patterns without glob metacharacters match
+    /// the name exactly.
+    fn for_filenames(field: &FieldFilter) -> Option<Self> {
+        Self::build(field, |value, _| {
+            let lower = value.to_lowercase();
+            let glob = if lower.contains('*') || lower.contains('?') || lower.contains('[') {
+                Glob::new(&lower).or_else(|_| Glob::new(&globset::escape(&lower)))
+            } else {
+                Glob::new(&globset::escape(&lower))
+            };
+            glob.into_iter().collect()
+        })
+    }
+
+    /// Compile ripgrep `-g` globs with gitignore semantics, matched against
+    /// the relative path: a glob without `/` matches at any depth, one with
+    /// `/` is anchored to the root. Excluded globs also exclude everything
+    /// below a matching directory (`-g '!vendor'`).
+    fn for_ripgrep_globs(field: &FieldFilter) -> Option<Self> {
+        Self::build(field, |value, exclude| {
+            let anchored = value.trim_start_matches('/');
+            let base = if value.trim_end_matches('/').contains('/') {
+                anchored.trim_end_matches('/').to_string()
+            } else {
+                format!("**/{}", anchored.trim_end_matches('/'))
+            };
+            let mut patterns = vec![base.clone()];
+            if exclude {
+                patterns.push(format!("{}/**", base));
+            }
+            patterns
+                .iter()
+                .filter_map(|p| Self::separator_glob(p))
+                .collect()
+        })
+    }
+
+    /// Compile file type names (see [`crate::query::filetypes`]) into their
+    /// globs, matched against the file name
+    fn for_file_types(field: &FieldFilter) -> Option<Self> {
+        Self::build(field, |name, _| {
+            crate::query::filetypes::lookup(name)
+                .map(|t| t.globs)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|g| Glob::new(g).ok())
+                .collect()
+        })
+    }
+
+    fn separator_glob(pattern: &str) -> Option<Glob> {
+        GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .ok()
+    }
+
+    /// `compile` maps one field value (and whether it is an exclusion) to
+    /// the globs it stands for
+    fn build(field: &FieldFilter, compile: impl Fn(&str, bool) -> Vec<Glob>) -> Option<Self> {
+        if field.is_empty() {
+            return None;
+        }
+
+        // An include value that compiles to nothing (invalid glob, unknown
+        // type) matches everything -- the long-standing behaviour for a bad
+        // `path:`; an exclude value that compiles to nothing is dropped
+        // rather than excluding everything
+        let mut include_fragments = (!field.include.is_empty()).then(Vec::new);
+        let include = (!field.include.is_empty()).then(|| {
+            let mut builder = GlobSetBuilder::new();
+            for value in &field.include {
+                let globs = compile(value, false);
+                if globs.is_empty() {
+                    builder.add(Glob::new("**").unwrap());
+                    include_fragments = None;
+                }
+                for glob in globs {
+                    if let Some(alternatives) = include_fragments.as_mut() {
+                        alternatives.push(literal_fragments(glob.glob()));
+                    }
+                    builder.add(glob);
+                }
+            }
+            builder.build().unwrap_or_else(|_| GlobSet::empty())
+        });
+        let exclude = (!field.exclude.is_empty()).then(|| {
+            let mut builder = GlobSetBuilder::new();
+            for glob in field.exclude.iter().flat_map(|v| compile(v, true)) {
+                builder.add(glob);
+            }
+            builder.build().unwrap_or_else(|_| GlobSet::empty())
+        });
+
+        Some(Self {
+            include,
+            exclude,
+            include_fragments,
+        })
+    }
+
+    /// Superset of the documents this filter can match, from the path
+    /// trigram index
+    fn path_candidates(&self, reader: &IndexReader) -> Option<RoaringBitmap> {
+        docs_with_any_fragments(reader, self.include_fragments.as_deref()?)
+    }
+
     #[inline]
-    fn is_match(&self, filename_lower: &str) -> bool {
-        match self {
-            Self::Exact(exact) => filename_lower == exact,
-            Self::Glob(glob) => glob.is_match(filename_lower),
+    fn is_match(&self, candidate: &Path) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|set| set.is_match(candidate))
+            && !self
+                .exclude
+                .as_ref()
+                .is_some_and(|set| set.is_match(candidate))
+    }
+}
+
+/// Documents whose path contains all fragments of at least one of the
+/// alternatives, or None if any alternative cannot narrow (too short, or
+/// no path trigram index)
+fn docs_with_any_fragments(
+    reader: &IndexReader,
+    alternatives: &[Vec<String>],
+) -> Option<RoaringBitmap> {
+    let mut docs = RoaringBitmap::new();
+    for fragments in alternatives {
+        docs |= reader.docs_with_path_fragments(fragments)?;
+    }
+    Some(docs)
+}
+
+/// Include/exclude sets of relative paths for a filter field whose values
+/// each stand for a set of files (`changed:REV`, `is:modified`)
+struct PathSetFilter {
+    include: Option<HashSet<PathBuf>>,
+    exclude: HashSet<PathBuf>,
+}
+
+impl PathSetFilter {
+    fn resolve(
+        field: &FieldFilter,
+        mut paths: impl FnMut(&str) -> Result<Vec<PathBuf>>,
+    ) -> Result<Self> {
+        let include = if field.include.is_empty() {
+            None
+        } else {
+            let mut set = HashSet::new();
+            for value in &field.include {
+                set.extend(paths(value)?);
+            }
+            Some(set)
+        };
+        let mut exclude = HashSet::new();
+        for value in &field.exclude {
+            exclude.extend(paths(value)?);
         }
+        Ok(Self { include, exclude })
+    }
+
+    fn allows(&self, path: &Path) -> bool {
+        self.include.as_ref().is_none_or(|set| set.contains(path)) && !self.exclude.contains(path)
     }
 }
 
@@ -166,12 +357,34 @@ pub struct ContentMatchResult {
     pub match_end: usize,
     pub context_before: Vec<(u32, String)>,
     pub context_after: Vec<(u32, String)>,
+    /// Enclosing function/class: (line number, signature), with
+    /// `--function-context`
+    pub enclosing_scope: Option<(u32, String)>,
+    /// Files with identical content collapsed into this one, with
+    /// `--dedupe`
+    pub duplicates: Vec<PathBuf>,
+    /// Language the file was indexed as
+    pub language: Language,
+}
+
+/// An indexed path listed by [`QueryExecutor::list_files`]
+#[derive(Debug, Clone)]
+pub struct ListedFile {
+    pub path: PathBuf,
+    /// Character indices matched by the fuzzy pattern (empty without one)
+    pub positions: Vec<usize>,
 }
 
 /// Query executor
 pub struct QueryExecutor<'a> {
     reader: &'a IndexReader,
     scorer: Scorer,
+    /// Annotated plan of the last query, when explaining (`--explain`)
+    explain: Option<Mutex<QueryExplain>>,
+    /// Candidate files read for verification by the last query
+    files_verified: AtomicUsize,
+    /// Facets of the last query's verified files, when requested
+    facets: Option<Mutex<Facets>>,
 }
 
 impl<'a> QueryExecutor<'a> {
@@ -179,6 +392,9 @@ impl<'a> QueryExecutor<'a> {
         Self {
             reader,
             scorer: Scorer::with_defaults(),
+            explain: None,
+            files_verified: AtomicUsize::new(0),
+            facets: None,
         }
     }
 
@@ -188,22 +404,122 @@ impl<'a> QueryExecutor<'a> {
         Self {
             reader,
             scorer: Scorer::new(weights),
+            explain: None,
+            files_verified: AtomicUsize::new(0),
+            facets: None,
+        }
+    }
+
+    /// Record an explanation of each query run: the plan steps with their
+    /// index statistics and candidate counts, files verified and matched,
+    /// and per-phase timings (see [`Self::take_explain`])
+    pub fn with_explain(mut self) -> Self {
+        self.explain = Some(Mutex::new(QueryExplain::default()));
+        self
+    }
+
+    /// The explanation of the last query run, if explaining
+    pub fn take_explain(&self) -> Option<QueryExplain> {
+        let explain = self.explain.as_ref()?;
+        explain.lock().ok().map(|mut e| std::mem::take(&mut *e))
+    }
+
+    /// Group each query's verified files by directory, extension and
+    /// language (see [`Self::take_facets`]). [`Self::execute`] then verifies
+    /// every candidate instead of stopping near the result limit.
+    pub fn with_facets(mut self) -> Self {
+        self.facets = Some(Mutex::new(Facets::default()));
+        self
+    }
+
+    /// The facets of the last query run, if requested (not computed by
+    /// [`Self::execute_files_only`], which stops at each file's first match)
+    pub fn take_facets(&self) -> Option<Facets> {
+        let facets = self.facets.as_ref()?;
+        facets.lock().ok().map(|mut f| std::mem::take(&mut *f))
+    }
+
+    /// Record the facets of verified files, when requested
+    fn record_facets(&self, verified: &[FileMatchResult]) {
+        let Some(Ok(mut facets)) = self.facets.as_ref().map(|f| f.lock()) else {
+            return;
+        };
+        *facets =
+            Facets::from_files(verified.iter().filter_map(|(doc_id, _, path, _, matches)| {
+                let language = self.reader.get_document(*doc_id)?.language;
+                // A file-only query reports each file once
+                Some((path.as_path(), language, matches.len().max(1)))
+            }));
+    }
+
+    /// Narrowing phase of a top-level query: [`Self::execute_plan`],
+    /// recording the annotated steps when explaining
+    fn narrow(&self, plan: &QueryPlan) -> Result<RoaringBitmap> {
+        self.files_verified.store(0, Ordering::Relaxed);
+        let Some(explain) = &self.explain else {
+            return self.execute_plan(plan);
+        };
+        let mut steps = Vec::new();
+        let candidates = self.execute_plan_traced(plan, Some(&mut steps))?;
+        if let Ok(mut explain) = explain.lock() {
+            explain.steps = steps;
+        }
+        Ok(candidates)
+    }
+
+    /// Complete the explanation of a query run, when explaining
+    fn finish_explain(
+        &self,
+        plan: &QueryPlan,
+        candidates: u64,
+        files_matched: usize,
+        timings: PhaseTimings,
+    ) {
+        let Some(Ok(mut explain)) = self.explain.as_ref().map(|e| e.lock()) else {
+            return;
+        };
+        explain.verification = plan.verification.as_ref().map(describe_verification);
+        explain.total_docs = self.reader.valid_doc_ids().len();
+        explain.segments = self.reader.segment_count();
+        explain.candidates = candidates;
+        explain.files_verified = self.files_verified.load(Ordering::Relaxed) as u64;
+        explain.files_matched = files_matched as u64;
+        explain.timings = timings;
+    }
+
+    /// Uncached read for parallel scans: the stored snapshot when the index
+    /// keeps one, else mmap from the work tree, or the reader's blob path
+    /// when the index was built from a git revision
+    fn read_doc_parallel(&self, doc_id: DocId, path: &Path) -> Option<FileContent> {
+        if let Some(content) = self.reader.stored_content(doc_id) {
+            return Some(FileContent::Owned(content));
+        }
+        match self.reader.content_source() {
+            ContentSource::WorkTree => read_file_mmap(path),
+            ContentSource::GitRev(_) => self.reader.read_file_cached(path),
+            ContentSource::Stored => None,
         }
     }
 
     /// Execute a query and return matches
     pub fn execute(&self, query: &Query) -> Result<Vec<SearchMatch>> {
+        let start = Instant::now();
         let plan = QueryPlan::from_query(query);
-        let candidates = self.execute_plan(&plan)?;
+        let planned = Instant::now();
+        let candidates = self.narrow(&plan)?;
+        let narrowed = Instant::now();
 
         let limit = query.options.limit;
-        // Collect 1.5x limit for better ranking (early termination)
-        let target = if limit > 0 {
+        // Collect 1.5x limit for better ranking (early termination), unless
+        // facets must see every verified file
+        let target = if limit > 0 && self.facets.is_none() {
             Some(limit + (limit / 2))
         } else {
             None
         };
         let all_matches = self.find_verified_matches(&candidates, &plan, target)?;
+        let verified = Instant::now();
+        self.record_facets(&all_matches);
 
         // Build results with scoring
         let verification = plan.verification.as_ref();
@@ -211,6 +527,10 @@ impl<'a> QueryExecutor<'a> {
             .map(Self::extract_search_terms)
             .unwrap_or_default();
         let boost = verification.map(Self::extract_boost).unwrap_or(1.0);
+        let mut fuzzy_terms = Vec::new();
+        if let Some(v) = verification {
+            Self::collect_fuzzy_terms(v, &mut fuzzy_terms);
+        }
 
         let estimated_total = all_matches.len() * 2;
         let mut results = Vec::with_capacity(estimated_total.min(if limit > 0 {
@@ -220,6 +540,10 @@ impl<'a> QueryExecutor<'a> {
         }));
 
         for (doc_id, _full_path, path, mtime, file_matches) in &all_matches {
+            let language = self
+                .reader
+                .get_document(*doc_id)
+                .map_or(Language::Unknown, |doc| doc.language);
             if file_matches.is_empty() {
                 // File-only query (no verification) — emit one match per file
                 results.push(SearchMatch {
@@ -227,6 +551,7 @@ impl<'a> QueryExecutor<'a> {
                     path: path.clone(),
                     line_number: 1,
                     score: 1.0,
+                    language,
                 });
                 continue;
             }
@@ -238,6 +563,7 @@ impl<'a> QueryExecutor<'a> {
                 depth: Scorer::path_depth(path),
                 mtime: *mtime,
                 boost,
+                edit_distance: Self::fuzzy_distance(&fuzzy_terms, file_matches),
             };
             let score = self.scorer.calculate_score(&score_ctx);
 
@@ -247,6 +573,7 @@ impl<'a> QueryExecutor<'a> {
                     path: path.clone(),
                     line_number: *line_num,
                     score,
+                    language,
                 });
             }
         }
@@ -277,6 +604,12 @@ impl<'a> QueryExecutor<'a> {
             results.truncate(limit);
         }
 
+        self.finish_explain(
+            &plan,
+            candidates.len(),
+            all_matches.len(),
+            PhaseTimings::from_marks(start, planned, narrowed, verified, Instant::now()),
+        );
         Ok(results)
     }
 
@@ -287,14 +620,30 @@ impl<'a> QueryExecutor<'a> {
         context_before: u32,
         context_after: u32,
     ) -> Result<Vec<ContentMatchResult>> {
+        let start = Instant::now();
         let plan = QueryPlan::from_query(query);
-        let candidates = self.execute_plan(&plan)?;
+        let planned = Instant::now();
+        let mut candidates = self.narrow(&plan)?;
+        let mut duplicates = if query.options.dedupe {
+            self.collapse_duplicates(&mut candidates)
+        } else {
+            HashMap::new()
+        };
+        let narrowed = Instant::now();
 
         let verified = self.find_verified_matches(&candidates, &plan, None)?;
+        let verified_at = Instant::now();
+        let files_matched = verified.len();
+        self.record_facets(&verified);
 
         let mut all_results = Vec::new();
 
-        for (_doc_id, full_path, rel_path, _mtime, file_matches) in verified {
+        for (doc_id, full_path, rel_path, _mtime, file_matches) in verified {
+            let file_duplicates = duplicates.remove(&doc_id).unwrap_or_default();
+            let language = self
+                .reader
+                .get_document(doc_id)
+                .map_or(Language::Unknown, |doc| doc.language);
             if file_matches.is_empty() {
                 // File-only query (no verification) — emit one match per file
                 all_results.push(ContentMatchResult {
@@ -305,24 +654,32 @@ impl<'a> QueryExecutor<'a> {
                     match_end: 0,
                     context_before: vec![],
                     context_after: vec![],
+                    enclosing_scope: None,
+                    duplicates: file_duplicates,
+                    language,
                 });
                 continue;
             }
 
-            // Re-read the file ONLY when context lines were requested; the
-            // match lines themselves were captured during verification, so
-            // without -A/-B/-C this read would be pure waste (~0.9s over a
-            // broad-phrase result set on Chromium)
-            let content = if context_before > 0 || context_after > 0 {
+            // Re-read the file ONLY when context lines or scopes were
+            // requested; the match lines themselves were captured during
+            // verification, so without -A/-B/-C this read would be pure
+            // waste (~0.9s over a broad-phrase result set on Chromium)
+            let function_context = query.options.function_context;
+            let content = if context_before > 0 || context_after > 0 || function_context {
                 self.reader
-                    .read_file_cached(&full_path)
-                    .or_else(|| read_file_mmap(&full_path))
+                    .read_doc_cached(doc_id, &full_path)
+                    .or_else(|| self.read_doc_parallel(doc_id, &full_path))
             } else {
                 None
             };
 
             // Split into lines once per file, not once per match
             let lines: Option<Vec<&str>> = content.as_ref().map(|c| c.lines().collect());
+            let scopes = match &content {
+                Some(content) if function_context => self.scope_table(doc_id, content),
+                _ => None,
+            };
 
             for (line_num, line_content, start, end) in file_matches {
                 let (ctx_before, ctx_after) = match &lines {
@@ -343,6 +700,9 @@ impl<'a> QueryExecutor<'a> {
                     match_end: end,
                     context_before: ctx_before,
                     context_after: ctx_after,
+                    enclosing_scope: scopes.as_ref().and_then(|t| t.enclosing(line_num)),
+                    duplicates: file_duplicates.clone(),
+                    language,
                 });
             }
         }
@@ -353,6 +713,12 @@ impl<'a> QueryExecutor<'a> {
             other => other,
         });
 
+        self.finish_explain(
+            &plan,
+            candidates.len(),
+            files_matched,
+            PhaseTimings::from_marks(start, planned, narrowed, verified_at, Instant::now()),
+        );
         Ok(all_results)
     }
 
@@ -361,20 +727,35 @@ impl<'a> QueryExecutor<'a> {
     /// 1. Stops scanning each file after finding the first match
     /// 2. Skips context extraction
     /// 3. Returns minimal data per file
-    pub fn execute_files_only(&self, query: &Query, file_limit: usize) -> Result<Vec<PathBuf>> {
+    ///
+    /// Each path comes with the identical files collapsed into it (empty
+    /// unless `--dedupe`).
+    pub fn execute_files_only(
+        &self,
+        query: &Query,
+        file_limit: usize,
+    ) -> Result<Vec<(PathBuf, Vec<PathBuf>)>> {
+        let start = Instant::now();
         let plan = QueryPlan::from_query(query);
-        let candidates = self.execute_plan(&plan)?;
+        let planned = Instant::now();
+        let mut candidates = self.narrow(&plan)?;
+        let mut duplicates = if query.options.dedupe {
+            self.collapse_duplicates(&mut candidates)
+        } else {
+            HashMap::new()
+        };
+        let narrowed = Instant::now();
 
         let verification = match &plan.verification {
             Some(v) => v,
             None => {
                 // No content verification - just return file paths up to limit
-                let paths: Vec<PathBuf> = candidates
+                let paths: Vec<(PathBuf, Vec<PathBuf>)> = candidates
                     .iter()
                     .filter_map(|doc_id| {
-                        self.reader
-                            .get_document(doc_id)
-                            .and_then(|doc| self.reader.get_path(doc).cloned())
+                        let doc = self.reader.get_document(doc_id)?;
+                        let path = self.reader.get_path(doc)?.clone();
+                        Some((path, duplicates.remove(&doc_id).unwrap_or_default()))
                     })
                     .take(if file_limit == 0 {
                         usize::MAX
@@ -382,18 +763,25 @@ impl<'a> QueryExecutor<'a> {
                         file_limit
                     })
                     .collect();
+                let done = Instant::now();
+                self.finish_explain(
+                    &plan,
+                    candidates.len(),
+                    paths.len(),
+                    PhaseTimings::from_marks(start, planned, narrowed, done, done),
+                );
                 return Ok(paths);
             }
         };
 
         // Collect candidate doc_ids with their paths for processing
-        let candidate_infos: Vec<(DocId, PathBuf, PathBuf)> = candidates
+        let candidate_infos: Vec<(DocId, PathBuf, PathBuf, Language)> = candidates
             .iter()
             .filter_map(|doc_id| {
                 self.reader.get_document(doc_id).and_then(|doc| {
                     self.reader.get_full_path(doc).map(|full_path| {
                         let rel_path = self.reader.get_path(doc).cloned().unwrap_or_default();
-                        (doc_id, full_path, rel_path)
+                        (doc_id, full_path, rel_path, doc.language)
                     })
                 })
             })
@@ -407,25 +795,36 @@ impl<'a> QueryExecutor<'a> {
         };
 
         // For files-only, we use parallel processing with early termination
-        // Each file only needs to find ONE match to be included
+        // Each file only needs to find ONE match to be included, unless a
+        // count: predicate needs them all
         let match_count = AtomicUsize::new(0);
+        let count = Self::extract_count_filter(&plan.steps);
+        let is_match = |content: &str, language: Language| {
+            if count == (None, None) {
+                Self::has_match(content, verification, language)
+            } else {
+                let matches = Self::verify_content_static(content, verification, 0, language);
+                !matches.is_empty() && Self::count_allowed(&matches, count)
+            }
+        };
 
-        let matching_files: Vec<PathBuf> = if !should_use_parallel(candidate_count) {
+        let matching_files: Vec<(DocId, PathBuf)> = if !should_use_parallel(candidate_count) {
             // Sequential for small result sets
             let mut results = Vec::new();
-            for (_doc_id, full_path, rel_path) in candidate_infos {
+            for (doc_id, full_path, rel_path, language) in candidate_infos {
                 if results.len() >= effective_limit {
                     break;
                 }
 
-                let content = match self.reader.read_file_cached(&full_path) {
+                let content = match self.reader.read_doc_cached(doc_id, &full_path) {
                     Some(c) => c,
                     None => continue,
                 };
+                self.files_verified.fetch_add(1, Ordering::Relaxed);
 
                 // Check if file has ANY match (fast path)
-                if Self::has_match(&content, verification) {
-                    results.push(rel_path);
+                if is_match(&content, language) {
+                    results.push((doc_id, rel_path));
                 }
             }
             results
@@ -433,39 +832,135 @@ impl<'a> QueryExecutor<'a> {
             // Parallel processing with early termination and memory-mapped I/O
             candidate_infos
                 .into_par_iter()
-                .filter_map(|(_doc_id, full_path, rel_path)| {
+                .filter_map(|(doc_id, full_path, rel_path, language)| {
                     // Early termination check
                     if match_count.load(Ordering::Relaxed) >= effective_limit {
                         return None;
                     }
 
                     // Read file content using mmap for large files
-                    let content = read_file_mmap(&full_path)?;
+                    let content = self.read_doc_parallel(doc_id, &full_path)?;
+                    self.files_verified.fetch_add(1, Ordering::Relaxed);
 
                     // Check if file has ANY match
-                    if Self::has_match(&content, verification) {
+                    if is_match(&content, language) {
                         match_count.fetch_add(1, Ordering::Relaxed);
-                        Some(rel_path)
+                        Some((doc_id, rel_path))
                     } else {
                         None
                     }
                 })
                 .collect()
         };
+        let verified = Instant::now();
 
         // Sort by path for consistent output
-        let mut sorted = matching_files;
+        let mut sorted: Vec<(PathBuf, Vec<PathBuf>)> = matching_files
+            .into_iter()
+            .map(|(doc_id, path)| (path, duplicates.remove(&doc_id).unwrap_or_default()))
+            .collect();
         sorted.sort();
         if sorted.len() > effective_limit {
             sorted.truncate(effective_limit);
         }
 
+        self.finish_explain(
+            &plan,
+            candidates.len(),
+            sorted.len(),
+            PhaseTimings::from_marks(start, planned, narrowed, verified, Instant::now()),
+        );
         Ok(sorted)
     }
 
+    /// Keep one candidate per distinct (content hash, size) -- the one with
+    /// the lexicographically first path -- and return the paths of the
+    /// dropped copies keyed by the doc they were collapsed into (`--dedupe`).
+    /// Same key as `fxi dupes` ([`crate::index::stats::duplicate_groups`]).
+    /// Documents without a recorded hash are never collapsed.
+    fn collapse_duplicates(&self, candidates: &mut RoaringBitmap) -> HashMap<DocId, Vec<PathBuf>> {
+        let mut groups: HashMap<(u64, u64), Vec<(&Path, DocId)>> = HashMap::new();
+        for doc_id in candidates.iter() {
+            if let Some(doc) = self.reader.get_document(doc_id)
+                && doc.content_hash != 0
+                && let Some(path) = self.reader.get_path(doc)
+            {
+                groups
+                    .entry((doc.content_hash, doc.size))
+                    .or_default()
+                    .push((path.as_path(), doc_id));
+            }
+        }
+
+        let mut duplicates = HashMap::new();
+        for mut group in groups.into_values().filter(|g| g.len() > 1) {
+            group.sort_unstable();
+            let kept = group[0].1;
+            for &(_, doc_id) in &group[1..] {
+                candidates.remove(doc_id);
+            }
+            duplicates.insert(
+                kept,
+                group[1..]
+                    .iter()
+                    .map(|(path, _)| path.to_path_buf())
+                    .collect(),
+            );
+        }
+        duplicates
+    }
+
+    /// List indexed paths passing the query's filters straight from the
+    /// index (no file is read), sorted by path or, with a fuzzy pattern,
+    /// ranked by fuzzy score. Returns at most `limit` files (0 = all) and
+    /// the number that matched before the limit.
+    pub fn list_files(
+        &self,
+        query: &Query,
+        fuzzy: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<ListedFile>, usize)> {
+        let plan = QueryPlan::from_query(query);
+        let candidates = self.execute_plan(&plan)?;
+        let paths: Vec<&Path> = candidates
+            .iter()
+            .filter_map(|doc_id| {
+                let doc = self.reader.get_document(doc_id)?;
+                Some(self.reader.get_path(doc)?.as_path())
+            })
+            .collect();
+
+        let mut files: Vec<ListedFile> = match fuzzy.and_then(FuzzyPattern::new) {
+            Some(pattern) => rank_paths(&pattern, paths)
+                .into_iter()
+                .map(|(path, m)| ListedFile {
+                    path: path.to_path_buf(),
+                    positions: m.positions,
+                })
+                .collect(),
+            None => {
+                let mut paths = paths;
+                paths.sort_unstable();
+                paths
+                    .into_iter()
+                    .map(|path| ListedFile {
+                        path: path.to_path_buf(),
+                        positions: Vec::new(),
+                    })
+                    .collect()
+            }
+        };
+
+        let total = files.len();
+        if limit > 0 {
+            files.truncate(limit);
+        }
+        Ok((files, total))
+    }
+
     /// Fast check if content has ANY match (for files-only mode)
     /// Returns immediately on first match found
-    fn has_match(content: &str, verification: &VerificationStep) -> bool {
+    fn has_match(content: &str, verification: &VerificationStep, language: Language) -> bool {
         match verification {
             VerificationStep::Literal(text) => Self::has_literal_match(content, text),
             VerificationStep::BoostedLiteral { text, .. } => Self::has_literal_match(content, text),
@@ -489,14 +984,35 @@ impl<'a> QueryExecutor<'a> {
                     false
                 }
             }
-            VerificationStep::And(steps) => steps.iter().all(|step| Self::has_match(content, step)),
-            VerificationStep::Or(steps) => steps.iter().any(|step| Self::has_match(content, step)),
-            VerificationStep::Not(inner) => !Self::has_match(content, inner),
+            VerificationStep::And(steps) => steps
+                .iter()
+                .all(|step| Self::has_match(content, step, language)),
+            VerificationStep::Or(steps) => steps
+                .iter()
+                .any(|step| Self::has_match(content, step, language)),
+            VerificationStep::Not(inner) => !Self::has_match(content, inner, language),
+            VerificationStep::InRegion { regions, inner } => {
+                Self::has_match(&lexer::mask(language, content, *regions), inner, language)
+            }
             VerificationStep::Near { terms, distance } => {
                 // find_proximity_matches_static early-exits as soon as any
                 // term is missing, so no separate existence pre-check needed
                 !Self::find_proximity_matches_static(content, terms, *distance, 0).is_empty()
             }
+            VerificationStep::Proximity {
+                terms,
+                distance,
+                ordered,
+                words,
+            } => !ContentWords::new(content)
+                .matches(terms, Within::new(*distance, *words), *ordered)
+                .is_empty(),
+            VerificationStep::Fuzzy { term, max_edits } => {
+                let automaton = LevenshteinAutomaton::new(term, *max_edits);
+                content
+                    .lines()
+                    .any(|line| Self::first_fuzzy_token(line, &automaton).is_some())
+            }
         }
     }
 
@@ -530,6 +1046,18 @@ impl<'a> QueryExecutor<'a> {
         false
     }
 
+    /// Scope table for a document's content, slicing lines with the index's
+    /// line map while it still matches the content
+    fn scope_table(&self, doc_id: DocId, content: &str) -> Option<ScopeTable> {
+        let language = self.reader.get_document(doc_id)?.language;
+        match self.reader.get_line_map(doc_id) {
+            Some(map) if line_map_matches(content.as_bytes(), map) => {
+                ScopeTable::build(language, content, map)
+            }
+            _ => ScopeTable::build(language, content, &build_line_map(content.as_bytes())),
+        }
+    }
+
     /// Extract context lines around a match.
     ///
     /// The caller splits the file into lines once (`lines`); each match then
@@ -563,10 +1091,24 @@ impl<'a> QueryExecutor<'a> {
 
     /// Execute the narrowing phase using RoaringBitmap for efficient set operations
     fn execute_plan(&self, plan: &QueryPlan) -> Result<RoaringBitmap> {
+        self.execute_plan_traced(plan, None)
+    }
+
+    /// [`Self::execute_plan`], appending an annotated entry per executed
+    /// step to `trace` when given (`--explain`)
+    fn execute_plan_traced(
+        &self,
+        plan: &QueryPlan,
+        mut trace: Option<&mut Vec<StepExplain>>,
+    ) -> Result<RoaringBitmap> {
         let mut candidates: Option<RoaringBitmap> = None;
         let mut exclude_plans: Vec<&QueryPlan> = Vec::new();
 
         for step in &plan.steps {
+            let step_start = Instant::now();
+            // Explanations of this step's sub-plans, when tracing
+            let mut branches = trace.is_some().then(Vec::new);
+
             match step {
                 PlanStep::TrigramIntersect(trigrams) => {
                     // Filter out stop-grams
@@ -651,7 +1193,7 @@ impl<'a> QueryExecutor<'a> {
                 PlanStep::Union(sub_plans) => {
                     let mut union = RoaringBitmap::new();
                     for sub_plan in sub_plans {
-                        let sub_candidates = self.execute_plan(sub_plan)?;
+                        let sub_candidates = self.execute_sub_plan(sub_plan, branches.as_mut())?;
                         union |= sub_candidates;
                     }
 
@@ -664,7 +1206,7 @@ impl<'a> QueryExecutor<'a> {
                 PlanStep::Intersect(sub_plans) => {
                     let mut intersection: Option<RoaringBitmap> = None;
                     for sub_plan in sub_plans {
-                        let sub_candidates = self.execute_plan(sub_plan)?;
+                        let sub_candidates = self.execute_sub_plan(sub_plan, branches.as_mut())?;
                         intersection = Some(match intersection {
                             Some(existing) => existing & sub_candidates,
                             None => sub_candidates,
@@ -701,6 +1243,36 @@ impl<'a> QueryExecutor<'a> {
                     // If None (no positional data), skip — fall back to content verification
                 }
 
+                PlanStep::PositionalProximity {
+                    terms,
+                    distance,
+                    ordered,
+                    words,
+                } => {
+                    // Order and word distance resolve from positions alone;
+                    // line distance is left to content verification
+                    if let Some(docs) = self.reader.resolve_proximity_positional(
+                        terms,
+                        Within::new(*distance, *words),
+                        *ordered,
+                        candidates.as_ref(),
+                    ) {
+                        candidates = Some(match candidates {
+                            Some(existing) => existing & docs,
+                            None => docs,
+                        });
+                    }
+                }
+
+                PlanStep::FuzzyTokens { term, max_edits } => {
+                    let docs = self.reader.get_fuzzy_token_docs(term, *max_edits);
+
+                    candidates = Some(match candidates {
+                        Some(existing) => existing & docs,
+                        None => docs,
+                    });
+                }
+
                 PlanStep::Filter(filter) => {
                     // Apply document filters
                     let filtered = self.apply_filter(filter, candidates.as_ref())?;
@@ -708,6 +1280,20 @@ impl<'a> QueryExecutor<'a> {
                 }
             }
 
+            // Deferred exclusions are traced when they are resolved below
+            if let Some(trace) = trace.as_deref_mut()
+                && !matches!(step, PlanStep::Exclude(_))
+            {
+                let duration = step_start.elapsed();
+                let mut explain = StepExplain::for_step(step, self.reader);
+                explain.children = branches.unwrap_or_default();
+                explain.candidates = candidates
+                    .as_ref()
+                    .map_or_else(|| self.reader.valid_doc_ids().len(), |c| c.len());
+                explain.duration_ms = millis(duration);
+                trace.push(explain);
+            }
+
             // Once candidates are empty, later intersections/unions in this plan
             // cannot produce new matches, so exit early.
             if candidates.as_ref().is_some_and(|c| c.is_empty()) {
@@ -724,7 +1310,9 @@ impl<'a> QueryExecutor<'a> {
                 if cands.is_empty() {
                     break;
                 }
-                let excluded = self.execute_plan(sub_plan)?;
+                let step_start = Instant::now();
+                let mut branches = trace.is_some().then(Vec::new);
+                let excluded = self.execute_sub_plan(sub_plan, branches.as_mut())?;
 
                 if let Some(ref verification) = sub_plan.verification {
                     let to_check = excluded & &*cands;
@@ -734,6 +1322,14 @@ impl<'a> QueryExecutor<'a> {
                     // No verification available, fall back to trigram-only exclusion
                     *cands -= excluded;
                 }
+
+                if let Some(trace) = trace.as_deref_mut() {
+                    let mut explain = StepExplain::exclude(sub_plan.verification.as_ref());
+                    explain.children = branches.unwrap_or_default();
+                    explain.candidates = cands.len();
+                    explain.duration_ms = millis(step_start.elapsed());
+                    trace.push(explain);
+                }
             }
         }
 
@@ -741,6 +1337,21 @@ impl<'a> QueryExecutor<'a> {
         Ok(candidates.unwrap_or_else(|| self.reader.valid_doc_ids().clone()))
     }
 
+    /// Execute a sub-plan, adding its trace to `branches` when tracing
+    fn execute_sub_plan(
+        &self,
+        plan: &QueryPlan,
+        branches: Option<&mut Vec<StepExplain>>,
+    ) -> Result<RoaringBitmap> {
+        let Some(branches) = branches else {
+            return self.execute_plan(plan);
+        };
+        let mut steps = Vec::new();
+        let candidates = self.execute_plan_traced(plan, Some(&mut steps))?;
+        branches.push(StepExplain::branch(steps, candidates.len()));
+        Ok(candidates)
+    }
+
     /// Verify which of the candidate docs actually contain the excluded term.
     /// Uses early-exit matching (any match disqualifies) and goes parallel for
     /// larger sets.
@@ -754,8 +1365,8 @@ impl<'a> QueryExecutor<'a> {
             for doc_id in to_check.iter() {
                 if let Some(doc) = self.reader.get_document(doc_id)
                     && let Some(full_path) = self.reader.get_full_path(doc)
-                    && let Some(content) = self.reader.read_file_cached(&full_path)
-                    && Self::has_match(&content, verification)
+                    && let Some(content) = self.reader.read_doc_cached(doc_id, &full_path)
+                    && Self::has_match(&content, verification, doc.language)
                 {
                     confirmed.insert(doc_id);
                 }
@@ -766,12 +1377,15 @@ impl<'a> QueryExecutor<'a> {
             doc_ids
                 .into_par_iter()
                 .filter(|&doc_id| {
+                    let Some(doc) = self.reader.get_document(doc_id) else {
+                        return false;
+                    };
                     self.reader
-                        .get_document(doc_id)
-                        .and_then(|doc| self.reader.get_full_path(doc))
-                        .and_then(|full_path| read_file_mmap(&full_path))
-                        .map(|content| Self::has_match(&content, verification))
-                        .unwrap_or(false)
+                        .get_full_path(doc)
+                        .and_then(|full_path| self.read_doc_parallel(doc_id, &full_path))
+                        .is_some_and(|content| {
+                            Self::has_match(&content, verification, doc.language)
+                        })
                 })
                 .collect::<Vec<u32>>()
                 .into_iter()
@@ -785,29 +1399,55 @@ impl<'a> QueryExecutor<'a> {
         filter: &FilterStep,
         candidates: Option<&RoaringBitmap>,
     ) -> Result<RoaringBitmap> {
-        let path_matcher = filter.path_glob.as_ref().map(|g| {
-            globset::GlobBuilder::new(g)
-                .literal_separator(true)
-                .build()
-                .unwrap_or_else(|_| Glob::new("*").unwrap())
-                .compile_matcher()
-        });
-
-        let filename_matcher = filter.filename.as_ref().map(|pattern| {
-            let pattern_lower = pattern.to_lowercase();
-            if pattern.contains('*') || pattern.contains('?') || pattern.contains('[') {
-                match Glob::new(&pattern_lower) {
-                    Ok(glob) => FilenameMatcher::Glob(glob.compile_matcher()),
-                    Err(_) => FilenameMatcher::Exact(pattern_lower),
-                }
-            } else {
-                FilenameMatcher::Exact(pattern_lower)
-            }
-        });
-
-        let language_filter = filter.language.as_deref().map(parse_language);
-        let needs_path =
-            path_matcher.is_some() || filename_matcher.is_some() || filter.extension.is_some();
+        let path_matcher = GlobFilter::for_paths(&filter.path_glob);
+        let filename_matcher = GlobFilter::for_filenames(&filter.filename);
+        let glob_matcher = GlobFilter::for_ripgrep_globs(&filter.glob);
+        let type_matcher = GlobFilter::for_file_types(&filter.file_type);
+
+        // A misspelt name would silently select (or, negated, drop) every
+        // file of unknown language
+        let languages = |names: &[String]| -> Result<Vec<Language>> {
+            names
+                .iter()
+                .map(|l| {
+                    parse_language(l).ok_or_else(|| anyhow::anyhow!("Unknown language lang:{}", l))
+                })
+                .collect()
+        };
+        let include_langs = languages(&filter.language.include)?;
+        let exclude_langs = languages(&filter.language.exclude)?;
+        let git_docs = self.git_filter_docs(filter)?;
+        let like_docs = self.like_filter_docs(filter)?;
+        let needs_path = path_matcher.is_some()
+            || filename_matcher.is_some()
+            || glob_matcher.is_some()
+            || type_matcher.is_some()
+            || !filter.extension.is_empty();
+
+        // Narrow through the path trigram index, then match paths exactly
+        let extension_fragments: Option<Vec<Vec<String>>> = (!filter.extension.include.is_empty())
+            .then(|| {
+                filter
+                    .extension
+                    .include
+                    .iter()
+                    .map(|ext| literal_fragments(&format!(".{}", ext)))
+                    .collect()
+            });
+        let path_candidates = [
+            &path_matcher,
+            &filename_matcher,
+            &glob_matcher,
+            &type_matcher,
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|matcher| matcher.path_candidates(self.reader))
+        .chain(
+            extension_fragments
+                .and_then(|alternatives| docs_with_any_fragments(self.reader, &alternatives)),
+        )
+        .reduce(|a, b| a & b);
 
         let mut result = RoaringBitmap::new();
 
@@ -818,6 +1458,20 @@ impl<'a> QueryExecutor<'a> {
                     return;
                 }
 
+                // changed: / is: (resolved through git up front)
+                if let Some(ref allowed) = git_docs
+                    && !allowed.contains(doc_id)
+                {
+                    return;
+                }
+
+                // like: (resolved through the MinHash signatures up front)
+                if let Some(ref allowed) = like_docs
+                    && !allowed.contains(doc_id)
+                {
+                    return;
+                }
+
                 let path = if needs_path {
                     self.reader.get_path(doc)
                 } else {
@@ -831,6 +1485,23 @@ impl<'a> QueryExecutor<'a> {
                     return;
                 }
 
+                // -g globs
+                if let Some(ref matcher) = glob_matcher
+                    && !path.map(|p| matcher.is_match(p)).unwrap_or(false)
+                {
+                    return;
+                }
+
+                // -t/-T file types (case-sensitive, like ripgrep)
+                if let Some(ref matcher) = type_matcher
+                    && !path
+                        .and_then(|p| p.file_name())
+                        .map(|name| matcher.is_match(Path::new(name)))
+                        .unwrap_or(false)
+                {
+                    return;
+                }
+
                 // Filename filter (case-insensitive, exact match unless glob pattern)
                 if let Some(ref matcher) = filename_matcher {
                     let filename_lower = path
@@ -840,7 +1511,7 @@ impl<'a> QueryExecutor<'a> {
 
                     if !filename_lower
                         .as_deref()
-                        .map(|name| matcher.is_match(name))
+                        .map(|name| matcher.is_match(Path::new(name)))
                         .unwrap_or(false)
                     {
                         return;
@@ -848,22 +1519,27 @@ impl<'a> QueryExecutor<'a> {
                 }
 
                 // Extension filter
-                if let Some(ref ext) = filter.extension {
-                    if let Some(path) = path {
-                        let file_ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-                        if !file_ext.eq_ignore_ascii_case(ext) {
-                            return;
-                        }
-                    } else {
+                if !filter.extension.is_empty() {
+                    let Some(path) = path else {
+                        return;
+                    };
+                    let file_ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+                    let ext_eq = |ext: &String| file_ext.eq_ignore_ascii_case(ext);
+                    if !filter.extension.include.is_empty()
+                        && !filter.extension.include.iter().any(ext_eq)
+                    {
+                        return;
+                    }
+                    if filter.extension.exclude.iter().any(ext_eq) {
                         return;
                     }
                 }
 
                 // Language filter
-                if language_filter
-                    .map(|lang| doc.language != lang)
-                    .unwrap_or(false)
-                {
+                if !include_langs.is_empty() && !include_langs.contains(&doc.language) {
+                    return;
+                }
+                if exclude_langs.contains(&doc.language) {
                     return;
                 }
 
@@ -895,20 +1571,111 @@ impl<'a> QueryExecutor<'a> {
             }
         };
 
-        if let Some(cands) = candidates {
-            for doc_id in cands.iter() {
-                check_doc(doc_id);
+        let narrowed;
+        let to_check = match (candidates, path_candidates) {
+            (Some(cands), Some(paths)) => {
+                narrowed = cands & &paths;
+                &narrowed
             }
-        } else {
-            let valid_docs = self.reader.valid_doc_ids();
-            for doc_id in valid_docs.iter() {
-                check_doc(doc_id);
+            (Some(cands), None) => cands,
+            (None, Some(paths)) => {
+                narrowed = paths;
+                &narrowed
             }
+            (None, None) => self.reader.valid_doc_ids(),
+        };
+        for doc_id in to_check.iter() {
+            check_doc(doc_id);
         }
 
         Ok(result)
     }
 
+    /// Doc ids passing the git filters (`changed:`, `is:`), or None when the
+    /// query has none. Paths are resolved with git at query time, so they
+    /// follow the work tree even when the index is behind it.
+    fn git_filter_docs(&self, filter: &FilterStep) -> Result<Option<RoaringBitmap>> {
+        if filter.changed.is_empty() && filter.status.is_empty() {
+            return Ok(None);
+        }
+        let root = self.reader.root_path();
+
+        let changed = PathSetFilter::resolve(&filter.changed, |rev| git::changed_since(root, rev))?;
+        let status = if filter.status.is_empty() {
+            None
+        } else {
+            Some(git::work_tree_status(root)?)
+        };
+        let status = PathSetFilter::resolve(&filter.status, |value| {
+            let status = status.as_ref().expect("status read for is: values");
+            match value.to_lowercase().as_str() {
+                "modified" => Ok(status.modified.clone()),
+                "untracked" => Ok(status.untracked.clone()),
+                _ => anyhow::bail!(
+                    "Unknown filter is:{} (expected is:modified or is:untracked)",
+                    value
+                ),
+            }
+        })?;
+
+        let mut docs = RoaringBitmap::new();
+        for doc in self.reader.documents() {
+            if doc.is_valid()
+                && let Some(path) = self.reader.get_path(doc)
+                && changed.allows(path)
+                && status.allows(path)
+            {
+                docs.insert(doc.doc_id);
+            }
+        }
+        Ok(Some(docs))
+    }
+
+    /// Doc ids passing `like:`, or None when the query has none: files
+    /// similar to any included file (not counting that file itself) and to
+    /// none of the excluded ones (counting them)
+    fn like_filter_docs(&self, filter: &FilterStep) -> Result<Option<RoaringBitmap>> {
+        if filter.like.is_empty() {
+            return Ok(None);
+        }
+
+        let similar_to = |value: &str| -> Result<(DocId, RoaringBitmap)> {
+            let doc = self
+                .reader
+                .find_document(Path::new(value.trim_start_matches("./")))
+                .ok_or_else(|| anyhow::anyhow!("like:{}: file is not in the index", value))?;
+            let Some(signature) = self.reader.minhash(doc.doc_id) else {
+                // Too small to compare, or indexed before signatures
+                return Ok((doc.doc_id, RoaringBitmap::new()));
+            };
+            let docs = self
+                .reader
+                .similar_documents(signature, crate::index::minhash::DEFAULT_THRESHOLD)
+                .into_iter()
+                .map(|(doc_id, _)| doc_id)
+                .collect();
+            Ok((doc.doc_id, docs))
+        };
+
+        let mut docs = if filter.like.include.is_empty() {
+            self.reader.valid_doc_ids().clone()
+        } else {
+            let mut union = RoaringBitmap::new();
+            for value in &filter.like.include {
+                let (source, mut similar) = similar_to(value)?;
+                similar.remove(source);
+                union |= similar;
+            }
+            union
+        };
+        for value in &filter.like.exclude {
+            let (source, similar) = similar_to(value)?;
+            docs -= similar;
+            docs.remove(source);
+        }
+        Ok(Some(docs))
+    }
+
     /// Case-insensitive substring check for short ASCII haystacks (filenames)
     /// that avoids allocating a lowercased copy per call.
     #[inline]
@@ -952,9 +1719,16 @@ impl<'a> QueryExecutor<'a> {
         limit: usize,
     ) -> Result<Vec<SearchMatch>> {
         let mut matches = Vec::new();
-        let valid_docs = self.reader.valid_doc_ids();
+        let alternatives: Vec<Vec<String>> = search_terms_lower
+            .iter()
+            .map(|term| literal_fragments(term))
+            .collect();
+        let narrowed = docs_with_any_fragments(self.reader, &alternatives);
+        let candidates = narrowed
+            .as_ref()
+            .unwrap_or_else(|| self.reader.valid_doc_ids());
 
-        for doc_id in valid_docs.iter() {
+        for doc_id in candidates.iter() {
             if matches.len() >= limit {
                 break;
             }
@@ -968,6 +1742,7 @@ impl<'a> QueryExecutor<'a> {
                     path: path.clone(),
                     line_number: 1,
                     score: 2.0, // Boost filename matches
+                    language: doc.language,
                 });
             }
         }
@@ -1004,17 +1779,18 @@ impl<'a> QueryExecutor<'a> {
             }
         };
 
-        // Extract line filter from plan (if present)
+        // Extract line and count filters from plan (if present)
         let (line_start, line_end) = Self::extract_line_filter(&plan.steps);
+        let count = Self::extract_count_filter(&plan.steps);
 
         // Collect candidate doc_ids with their paths for processing
-        let candidate_infos: Vec<(DocId, PathBuf, PathBuf, u64)> = candidates
+        let candidate_infos: Vec<(DocId, PathBuf, PathBuf, u64, Language)> = candidates
             .iter()
             .filter_map(|doc_id| {
                 self.reader.get_document(doc_id).and_then(|doc| {
                     self.reader.get_full_path(doc).map(|full_path| {
                         let rel_path = self.reader.get_path(doc).cloned().unwrap_or_default();
-                        (doc_id, full_path, rel_path, doc.mtime)
+                        (doc_id, full_path, rel_path, doc.mtime, doc.language)
                     })
                 })
             })
@@ -1029,7 +1805,7 @@ impl<'a> QueryExecutor<'a> {
                 Vec::with_capacity(candidate_count.min(target_matches.unwrap_or(candidate_count)));
             let mut total_matches = 0;
 
-            for (doc_id, full_path, rel_path, mtime) in candidate_infos {
+            for (doc_id, full_path, rel_path, mtime, language) in candidate_infos {
                 // Early termination check
                 if let Some(target) = target_matches {
                     if total_matches >= target {
@@ -1037,12 +1813,14 @@ impl<'a> QueryExecutor<'a> {
                     }
                 }
 
-                let content = match self.reader.read_file_cached(&full_path) {
+                let content = match self.reader.read_doc_cached(doc_id, &full_path) {
                     Some(c) => c,
                     None => continue,
                 };
+                self.files_verified.fetch_add(1, Ordering::Relaxed);
 
-                let mut file_matches = Self::verify_content_static(&content, verification, doc_id);
+                let mut file_matches =
+                    Self::verify_content_static(&content, verification, doc_id, language);
 
                 // Apply line filter if specified
                 if line_start.is_some() || line_end.is_some() {
@@ -1053,7 +1831,7 @@ impl<'a> QueryExecutor<'a> {
                     });
                 }
 
-                if !file_matches.is_empty() {
+                if !file_matches.is_empty() && Self::count_allowed(&file_matches, count) {
                     total_matches += file_matches.len();
                     results.push((doc_id, full_path, rel_path, mtime, file_matches));
                 }
@@ -1066,7 +1844,7 @@ impl<'a> QueryExecutor<'a> {
             candidate_infos
                 .into_par_iter()
                 .with_min_len(4)
-                .filter_map(|(doc_id, full_path, rel_path, mtime)| {
+                .filter_map(|(doc_id, full_path, rel_path, mtime, language)| {
                     // Early termination check
                     if let Some(target) = target_matches {
                         if match_count.load(Ordering::Relaxed) >= target {
@@ -1074,10 +1852,11 @@ impl<'a> QueryExecutor<'a> {
                         }
                     }
 
-                    let content = read_file_mmap(&full_path)?;
+                    let content = self.read_doc_parallel(doc_id, &full_path)?;
+                    self.files_verified.fetch_add(1, Ordering::Relaxed);
 
                     let mut file_matches =
-                        Self::verify_content_static(&content, verification, doc_id);
+                        Self::verify_content_static(&content, verification, doc_id, language);
 
                     // Apply line filter if specified
                     if line_start.is_some() || line_end.is_some() {
@@ -1088,7 +1867,7 @@ impl<'a> QueryExecutor<'a> {
                         });
                     }
 
-                    if file_matches.is_empty() {
+                    if file_matches.is_empty() || !Self::count_allowed(&file_matches, count) {
                         None
                     } else {
                         match_count.fetch_add(file_matches.len(), Ordering::Relaxed);
@@ -1112,6 +1891,7 @@ impl<'a> QueryExecutor<'a> {
                     .map(Self::extract_boost)
                     .fold(1.0_f32, |a, b| a.max(b))
             }
+            VerificationStep::InRegion { inner, .. } => Self::extract_boost(inner),
             _ => 1.0,
         }
     }
@@ -1128,6 +1908,32 @@ impl<'a> QueryExecutor<'a> {
         (None, None)
     }
 
+    /// Extract the `count:` bounds from plan steps
+    fn extract_count_filter(steps: &[PlanStep]) -> (Option<u32>, Option<u32>) {
+        for step in steps {
+            if let PlanStep::Filter(filter) = step {
+                return (filter.count_min, filter.count_max);
+            }
+        }
+        (None, None)
+    }
+
+    /// Whether a file's matches satisfy a `count:` predicate. Counts
+    /// distinct lines: compound verifications can report a line twice.
+    fn count_allowed(
+        matches: &[(u32, String, usize, usize)],
+        (min, max): (Option<u32>, Option<u32>),
+    ) -> bool {
+        if min.is_none() && max.is_none() {
+            return true;
+        }
+        let mut lines: Vec<u32> = matches.iter().map(|m| m.0).collect();
+        lines.sort_unstable();
+        lines.dedup();
+        let n = lines.len() as u32;
+        min.is_none_or(|min| n >= min) && max.is_none_or(|max| n <= max)
+    }
+
     /// Extract search terms from verification step for filename matching
     fn extract_search_terms(verification: &VerificationStep) -> Vec<String> {
         let mut terms = Vec::new();
@@ -1177,6 +1983,9 @@ impl<'a> QueryExecutor<'a> {
             }
             VerificationStep::Near {
                 terms: near_terms, ..
+            }
+            | VerificationStep::Proximity {
+                terms: near_terms, ..
             } => {
                 // Include all proximity search terms
                 for term in near_terms {
@@ -1190,17 +1999,50 @@ impl<'a> QueryExecutor<'a> {
                     Self::collect_terms(step, terms);
                 }
             }
-            VerificationStep::Not(_) => {
-                // Don't include negated terms in filename matching
+            VerificationStep::InRegion { inner, .. } => Self::collect_terms(inner, terms),
+            VerificationStep::Not(_) | VerificationStep::Fuzzy { .. } => {
+                // Don't include negated terms in filename matching, nor
+                // fuzzy terms (they are likely misspelled)
             }
         }
     }
 
+    /// Automata for the fuzzy terms of a verification step, outside any NOT
+    fn collect_fuzzy_terms(verification: &VerificationStep, terms: &mut Vec<LevenshteinAutomaton>) {
+        match verification {
+            VerificationStep::Fuzzy { term, max_edits } => {
+                terms.push(LevenshteinAutomaton::new(term, *max_edits));
+            }
+            VerificationStep::And(steps) | VerificationStep::Or(steps) => {
+                for step in steps {
+                    Self::collect_fuzzy_terms(step, terms);
+                }
+            }
+            VerificationStep::InRegion { inner, .. } => Self::collect_fuzzy_terms(inner, terms),
+            _ => {}
+        }
+    }
+
+    /// Smallest edit distance between a fuzzy term and the text of a file's
+    /// matches, for ranking exact hits above typos (0 without fuzzy terms)
+    fn fuzzy_distance(terms: &[LevenshteinAutomaton], matches: &[FileMatch]) -> u32 {
+        if terms.is_empty() {
+            return 0;
+        }
+        matches
+            .iter()
+            .filter_map(|(_, line, start, end)| line.as_bytes().get(*start..*end))
+            .flat_map(|text| terms.iter().filter_map(|term| term.eval(text)))
+            .min()
+            .unwrap_or(0)
+    }
+
     /// Verify content against a verification step (static version for parallel processing)
     fn verify_content_static(
         content: &str,
         verification: &VerificationStep,
         doc_id: DocId,
+        language: Language,
     ) -> Vec<(u32, String, usize, usize)> {
         match verification {
             VerificationStep::Literal(text) => {
@@ -1226,12 +2068,23 @@ impl<'a> QueryExecutor<'a> {
             VerificationStep::Near { terms, distance } => {
                 Self::find_proximity_matches_static(content, terms, *distance, doc_id)
             }
+            VerificationStep::Proximity {
+                terms,
+                distance,
+                ordered,
+                words,
+            } => {
+                ContentWords::new(content).matches(terms, Within::new(*distance, *words), *ordered)
+            }
+            VerificationStep::Fuzzy { term, max_edits } => {
+                Self::find_fuzzy_matches_static(content, term, *max_edits)
+            }
             VerificationStep::And(steps) => {
                 // All must have at least one match
                 let mut all_matches: Option<Vec<(u32, String, usize, usize)>> = None;
 
                 for step in steps {
-                    let step_matches = Self::verify_content_static(content, step, doc_id);
+                    let step_matches = Self::verify_content_static(content, step, doc_id, language);
                     if step_matches.is_empty() {
                         return Vec::new();
                     }
@@ -1250,12 +2103,13 @@ impl<'a> QueryExecutor<'a> {
             VerificationStep::Or(steps) => {
                 let mut all_matches = Vec::new();
                 for step in steps {
-                    all_matches.extend(Self::verify_content_static(content, step, doc_id));
+                    all_matches
+                        .extend(Self::verify_content_static(content, step, doc_id, language));
                 }
                 all_matches
             }
             VerificationStep::Not(inner) => {
-                let inner_matches = Self::verify_content_static(content, inner, doc_id);
+                let inner_matches = Self::verify_content_static(content, inner, doc_id, language);
                 if inner_matches.is_empty() {
                     // Return a "match" indicating the file doesn't contain the pattern
                     vec![(1, content.lines().next().unwrap_or("").to_string(), 0, 0)]
@@ -1263,6 +2117,19 @@ impl<'a> QueryExecutor<'a> {
                     Vec::new()
                 }
             }
+            VerificationStep::InRegion { regions, inner } => {
+                // Matching against the masked copy keeps line numbers and
+                // offsets; the reported lines come from the real content
+                let masked = lexer::mask(language, content, *regions);
+                let mut matches = Self::verify_content_static(&masked, inner, doc_id, language);
+                let lines: Vec<&str> = content.lines().collect();
+                for m in &mut matches {
+                    if let Some(line) = (m.0 as usize).checked_sub(1).and_then(|i| lines.get(i)) {
+                        m.1 = line.to_string();
+                    }
+                }
+                matches
+            }
         }
     }
 
@@ -1477,6 +2344,36 @@ impl<'a> QueryExecutor<'a> {
         matches
     }
 
+    /// Find fuzzy matches: the first token per line within `max_edits` of
+    /// `term`, preferring the closest
+    fn find_fuzzy_matches_static(
+        content: &str,
+        term: &str,
+        max_edits: u32,
+    ) -> Vec<(u32, String, usize, usize)> {
+        let automaton = LevenshteinAutomaton::new(term, max_edits);
+        content
+            .lines()
+            .enumerate()
+            .filter_map(|(line_num, line)| {
+                let (start, end) = Self::first_fuzzy_token(line, &automaton)?;
+                Some(((line_num + 1) as u32, line.to_string(), start, end))
+            })
+            .collect()
+    }
+
+    /// Span of the closest token in a line accepted by the automaton (the
+    /// first of equally close ones)
+    fn first_fuzzy_token(line: &str, automaton: &LevenshteinAutomaton) -> Option<(usize, usize)> {
+        token_spans(line)
+            .into_iter()
+            .filter_map(|(start, end)| {
+                Some((automaton.eval(&line.as_bytes()[start..end])?, start, end))
+            })
+            .min_by_key(|&(distance, start, _)| (distance, start))
+            .map(|(_, start, end)| (start, end))
+    }
+
     /// Find regex matches (static)
     fn find_regex_matches_static(
         content: &str,
@@ -1523,8 +2420,8 @@ impl<'a> QueryExecutor<'a> {
 }
 
 /// Parse language string to enum
-fn parse_language(lang: &str) -> Language {
-    match lang.to_lowercase().as_str() {
+pub(crate) fn parse_language(lang: &str) -> Option<Language> {
+    Some(match lang.to_lowercase().as_str() {
         "rust" | "rs" => Language::Rust,
         "python" | "py" => Language::Python,
         "javascript" | "js" => Language::JavaScript,
@@ -1535,13 +2432,36 @@ fn parse_language(lang: &str) -> Language {
         "java" => Language::Java,
         "ruby" | "rb" => Language::Ruby,
         "shell" | "sh" | "bash" => Language::Shell,
-        _ => Language::Unknown,
-    }
+        "markdown" | "md" => Language::Markdown,
+        "json" => Language::Json,
+        "yaml" | "yml" => Language::Yaml,
+        "toml" => Language::Toml,
+        "html" => Language::Html,
+        "css" => Language::Css,
+        "sql" => Language::Sql,
+        "haskell" | "hs" => Language::Haskell,
+        "scala" => Language::Scala,
+        "kotlin" | "kt" => Language::Kotlin,
+        "swift" => Language::Swift,
+        "php" => Language::Php,
+        "csharp" | "c#" | "cs" => Language::CSharp,
+        "elixir" | "ex" => Language::Elixir,
+        "clojure" | "clj" => Language::Clojure,
+        "lua" => Language::Lua,
+        "perl" | "pl" => Language::Perl,
+        "r" => Language::R,
+        "zig" => Language::Zig,
+        "nim" => Language::Nim,
+        "ocaml" | "ml" => Language::Ocaml,
+        "unknown" => Language::Unknown,
+        _ => return None,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::index::git::git;
     use crate::index::reader::IndexReader;
     use crate::query::parser::parse_query;
     use std::fs;
@@ -1643,6 +2563,287 @@ def format_warning(msg: str) -> str:
         assert!(!results.is_empty(), "Should find exact phrase");
     }
 
+    #[test]
+    fn test_executor_fixed_strings() {
+        let (_temp_dir, _root_path, reader) = create_test_index();
+        let executor = QueryExecutor::new(&reader);
+
+        // Starts and ends mid-token, and is full of query operators
+        let query = crate::query::parse_fixed_strings("ntln!(\"Hello");
+        let results = executor.execute(&query).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].path.to_string_lossy().ends_with("main.rs"));
+
+        let query = crate::query::parse_fixed_strings("i32) -> i32\nf\"ERROR:");
+        let files: std::collections::HashSet<_> = executor
+            .execute(&query)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.path)
+            .collect();
+        assert_eq!(files.len(), 2, "each line is a separate pattern");
+
+        let mut query = crate::query::parse_fixed_strings("HELLO, WORLD");
+        assert!(executor.execute(&query).unwrap().is_empty());
+        query.options.case_insensitive = true;
+        assert_eq!(executor.execute(&query).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_executor_content_store() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+        fs::write(root.join("a.rs"), "fn indexed_fn() {\n    body();\n}\n").unwrap();
+        crate::index::build::build_index_with_config(root, false, true, None, Some(true)).unwrap();
+
+        // Queries see the snapshot, even once the file is gone
+        fs::remove_file(root.join("a.rs")).unwrap();
+        let reader = IndexReader::open(root).unwrap();
+        assert!(reader.meta.content_store);
+        let executor = QueryExecutor::new(&reader);
+        let matches = executor
+            .execute_with_content(&parse_query("indexed_fn"), 0, 1)
+            .unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            matches[0].context_after,
+            vec![(2, "    body();".to_string())]
+        );
+
+        let (raw, compressed) = reader.content_store_sizes().unwrap();
+        assert_eq!(raw, 32);
+        assert!(compressed > 0);
+    }
+
+    #[test]
+    fn test_executor_git_revision() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+        git(root, &["init", "-q"]);
+        fs::write(root.join("a.rs"), "fn committed_fn() {}\n").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "init"]);
+        git(root, &["tag", "v1"]);
+
+        // The work tree moves on; the revision index must not see it
+        fs::write(root.join("a.rs"), "fn worktree_fn() {}\n").unwrap();
+        crate::index::build::build_rev_index(root, "v1", false, true, false).unwrap();
+        let reader = IndexReader::open_rev(root, "v1").unwrap();
+        let executor = QueryExecutor::new(&reader);
+
+        let matches = executor
+            .execute_with_content(&parse_query("committed_fn"), 0, 0)
+            .unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line_content, "fn committed_fn() {}");
+        assert!(
+            executor
+                .execute(&parse_query("worktree_fn"))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(IndexReader::open_rev(root, "HEAD~5").is_err());
+
+        crate::utils::remove_rev_indexes(root).unwrap();
+    }
+
+    #[test]
+    fn test_executor_git_state_filters() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+        git(root, &["init", "-q"]);
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            fs::write(root.join(name), "fn shared_fn() {}\n").unwrap();
+        }
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "base"]);
+        git(root, &["tag", "base"]);
+
+        // c.rs committed after base, a.rs edited, d.rs untracked
+        fs::write(root.join("c.rs"), "fn shared_fn() { c() }\n").unwrap();
+        git(root, &["commit", "-q", "-am", "c"]);
+        fs::write(root.join("a.rs"), "fn shared_fn() { a() }\n").unwrap();
+        fs::write(root.join("d.rs"), "fn shared_fn() { d() }\n").unwrap();
+
+        crate::index::build::build_index(root, true).unwrap();
+        let reader = IndexReader::open(root).unwrap();
+        let executor = QueryExecutor::new(&reader);
+        let files = |query: &str| -> Vec<String> {
+            let mut files: Vec<String> = executor
+                .execute_with_content(&parse_query(query), 0, 0)
+                .unwrap()
+                .into_iter()
+                .map(|m| m.path.to_string_lossy().into_owned())
+                .collect();
+            files.sort();
+            files
+        };
+
+        assert_eq!(files("shared_fn changed:base"), ["a.rs", "c.rs", "d.rs"]);
+        assert_eq!(files("shared_fn is:modified"), ["a.rs"]);
+        assert_eq!(files("shared_fn is:untracked"), ["d.rs"]);
+        assert_eq!(
+            files("shared_fn changed:base -is:untracked"),
+            ["a.rs", "c.rs"]
+        );
+        assert!(
+            executor
+                .execute(&parse_query("shared_fn is:bogus"))
+                .is_err()
+        );
+        assert!(
+            executor
+                .execute(&parse_query("shared_fn changed:no-such-rev"))
+                .is_err()
+        );
+
+        crate::utils::remove_index(root).unwrap();
+    }
+
+    #[test]
+    fn test_executor_function_context() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+        fs::write(
+            root.join("lib.rs"),
+            "struct Cache;\n\nimpl Cache {\n    fn evict_oldest(&mut self) {\n        let marker_value = 1;\n    }\n}\n",
+        )
+        .unwrap();
+        fs::write(root.join("notes.md"), "# marker_value\n").unwrap();
+
+        crate::index::build::build_index(root, true).unwrap();
+        let reader = IndexReader::open(root).unwrap();
+        let executor = QueryExecutor::new(&reader);
+
+        let mut query = parse_query("marker_value");
+        let results = executor.execute_with_content(&query, 0, 0).unwrap();
+        assert!(results.iter().all(|m| m.enclosing_scope.is_none()));
+
+        query.options.function_context = true;
+        let results = executor.execute_with_content(&query, 0, 0).unwrap();
+        let scope = |path: &str| {
+            results
+                .iter()
+                .find(|m| m.path == Path::new(path))
+                .map(|m| m.enclosing_scope.clone())
+                .unwrap()
+        };
+        assert_eq!(
+            scope("lib.rs"),
+            Some((4, "fn evict_oldest(&mut self) {".to_string()))
+        );
+        assert_eq!(scope("notes.md"), None);
+
+        crate::utils::remove_index(root).unwrap();
+    }
+
+    #[test]
+    fn test_executor_dedupe() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+        let shared = "fn dedupe_marker() {}\n";
+        fs::create_dir(root.join("vendor")).unwrap();
+        fs::write(root.join("vendor/copy.rs"), shared).unwrap();
+        fs::write(root.join("lib.rs"), shared).unwrap();
+        fs::write(root.join("other.rs"), "// dedupe_marker\n").unwrap();
+
+        crate::index::build::build_index(root, true).unwrap();
+        let reader = IndexReader::open(root).unwrap();
+        let executor = QueryExecutor::new(&reader);
+
+        let mut query = parse_query("dedupe_marker");
+        let results = executor.execute_with_content(&query, 0, 0).unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|m| m.duplicates.is_empty()));
+
+        query.options.dedupe = true;
+        let results = executor.execute_with_content(&query, 0, 0).unwrap();
+        let got: Vec<_> = results
+            .iter()
+            .map(|m| (m.path.clone(), m.duplicates.clone()))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                (
+                    PathBuf::from("lib.rs"),
+                    vec![PathBuf::from("vendor/copy.rs")]
+                ),
+                (PathBuf::from("other.rs"), vec![]),
+            ]
+        );
+
+        let files = executor.execute_files_only(&query, 0).unwrap();
+        assert_eq!(files, got);
+
+        crate::utils::remove_index(root).unwrap();
+    }
+
+    #[test]
+    fn test_executor_like_filter() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+        let base: String = (0..40)
+            .map(|i| format!("let value_{i} = compute_{i}(input);\n"))
+            .collect();
+        fs::write(root.join("a.rs"), &base).unwrap();
+        fs::write(root.join("b.rs"), base.replace("compute_7(", "other_7(")).unwrap();
+        let unrelated: String = (0..40)
+            .map(|i| format!("<li class=\"item-{i}\">Entry {i}</li>\n"))
+            .collect();
+        fs::write(root.join("c.html"), unrelated).unwrap();
+
+        crate::index::build::build_index(root, true).unwrap();
+        let reader = IndexReader::open(root).unwrap();
+        let executor = QueryExecutor::new(&reader);
+        let files = |q: &str| {
+            let mut paths: Vec<String> = executor
+                .execute(&parse_query(q))
+                .unwrap()
+                .into_iter()
+                .map(|m| m.path.display().to_string())
+                .collect();
+            paths.sort();
+            paths.dedup();
+            paths
+        };
+
+        assert_eq!(files("like:a.rs"), ["b.rs"]);
+        assert_eq!(files("like:./b.rs input"), ["a.rs"]);
+        assert_eq!(files("-like:a.rs"), ["c.html"]);
+        assert!(executor.execute(&parse_query("like:missing.rs")).is_err());
+
+        crate::utils::remove_index(root).unwrap();
+    }
+
+    #[test]
+    fn test_executor_list_files() {
+        let (_temp_dir, _root_path, reader) = create_test_index();
+        let executor = QueryExecutor::new(&reader);
+        let paths = |files: Vec<ListedFile>| -> Vec<String> {
+            files
+                .into_iter()
+                .map(|f| f.path.display().to_string())
+                .collect()
+        };
+
+        let (files, total) = executor.list_files(&parse_query(""), None, 0).unwrap();
+        assert_eq!(total, 3);
+        assert!(files.iter().all(|f| f.positions.is_empty()));
+        assert_eq!(paths(files), ["lib.rs", "main.rs", "utils.py"]);
+
+        let mut query = parse_query("");
+        query.filters.add_file_types(&["rust".to_string()], &[]);
+        let (files, total) = executor.list_files(&query, None, 1).unwrap();
+        assert_eq!((paths(files), total), (vec!["lib.rs".to_string()], 2));
+
+        let (files, _) = executor
+            .list_files(&parse_query(""), Some("mn"), 0)
+            .unwrap();
+        assert_eq!(files[0].positions, vec![0, 3]);
+        assert_eq!(paths(files), ["main.rs"]);
+    }
+
     #[test]
     fn test_executor_no_results() {
         let (_temp_dir, _root_path, reader) = create_test_index();
@@ -1699,6 +2900,204 @@ def format_warning(msg: str) -> str:
         );
     }
 
+    #[test]
+    fn test_executor_count_filter() {
+        let (_temp_dir, root_path, reader) = create_test_index();
+        let executor = QueryExecutor::new(&reader);
+        let paths = |query: &str| {
+            let mut paths: Vec<PathBuf> = executor
+                .execute_with_content(&parse_query(query), 0, 0)
+                .unwrap()
+                .into_iter()
+                .map(|m| m.path)
+                .collect();
+            paths.dedup();
+            paths
+        };
+
+        // `fn` is on 2 lines of main.rs and 3 of lib.rs
+        assert_eq!(paths("count:>2 fn ext:rs"), [PathBuf::from("lib.rs")]);
+        assert_eq!(paths("count:<=2 fn ext:rs"), [PathBuf::from("main.rs")]);
+        assert_eq!(paths("count:2 fn ext:rs"), [PathBuf::from("main.rs")]);
+        assert!(paths("count:>3 fn").is_empty());
+        // Counted after the line filter
+        assert!(paths("count:>2 fn ext:rs line:1-10").is_empty());
+
+        // The files-only path counts too instead of stopping at one match
+        let files = executor
+            .execute_files_only(&parse_query("count:>=3 fn"), 0)
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, Path::new("lib.rs"));
+
+        crate::utils::remove_index(&root_path).unwrap();
+    }
+
+    #[test]
+    fn test_executor_region_scoping() {
+        let (_temp_dir, root_path, reader) = create_test_index();
+        let executor = QueryExecutor::new(&reader);
+        let lines = |query: &str| {
+            executor
+                .execute_with_content(&parse_query(query), 0, 0)
+                .unwrap()
+                .into_iter()
+                .map(|m| (m.path.to_string_lossy().into_owned(), m.line_number))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(lines("in:comment TODO"), [("main.rs".to_string(), 7)]);
+        assert!(lines("in:code TODO").is_empty());
+        assert_eq!(
+            lines("in:string msg"),
+            [("utils.py".to_string(), 2), ("utils.py".to_string(), 5)]
+        );
+        assert_eq!(
+            lines("-in:string msg"),
+            [("utils.py".to_string(), 1), ("utils.py".to_string(), 4)]
+        );
+
+        // Matched against the masked file, reported from the real one
+        let matches = executor
+            .execute_with_content(&parse_query("in:string world"), 0, 0)
+            .unwrap();
+        assert_eq!(matches[0].line_content, r#"    println!("Hello, world!");"#);
+        assert_eq!(matches[0].match_start, 21);
+
+        // An excluded term only counts where the query looks
+        assert!(lines("helper -TODO ext:rs").is_empty());
+        assert!(lines("helper -TODO in:code ext:rs").contains(&("main.rs".to_string(), 6)));
+
+        let files = executor
+            .execute_files_only(&parse_query("in:comment TODO"), 0)
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, Path::new("main.rs"));
+
+        crate::utils::remove_index(&root_path).unwrap();
+    }
+
+    #[test]
+    fn test_executor_extension_filter_list() {
+        let (_temp_dir, _root_path, reader) = create_test_index();
+        let executor = QueryExecutor::new(&reader);
+
+        let query = parse_query("ext:py,rs re:/fn|def/");
+        let results = executor.execute(&query).unwrap();
+
+        assert!(results.iter().any(|m| m.path.ends_with("utils.py")));
+        assert!(results.iter().any(|m| m.path.ends_with("main.rs")));
+    }
+
+    #[test]
+    fn test_executor_negated_extension_filter() {
+        let (_temp_dir, _root_path, reader) = create_test_index();
+        let executor = QueryExecutor::new(&reader);
+
+        let query = parse_query("-ext:rs re:/fn|def/");
+        let results = executor.execute(&query).unwrap();
+
+        assert!(!results.is_empty(), "Should still find utils.py");
+        for result in &results {
+            assert!(
+                !result.path.extension().map(|e| e == "rs").unwrap_or(false),
+                "-ext:rs should drop .rs files, got {:?}",
+                result.path
+            );
+        }
+    }
+
+    #[test]
+    fn test_executor_negated_language_filter() {
+        let (_temp_dir, _root_path, reader) = create_test_index();
+        let executor = QueryExecutor::new(&reader);
+
+        let query = parse_query("-lang:python re:/fn|def/");
+        let results = executor.execute(&query).unwrap();
+
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|m| !m.path.ends_with("utils.py")));
+
+        // A misspelt language is an error, not an empty (or negated: full)
+        // result set
+        for query in ["-lang:rsut fn", "lang:rsut fn"] {
+            let err = executor.execute(&parse_query(query)).unwrap_err();
+            assert_eq!(err.to_string(), "Unknown language lang:rsut");
+        }
+    }
+
+    #[test]
+    fn test_executor_path_include_and_exclude() {
+        let (_temp_dir, _root_path, reader) = create_test_index();
+        let executor = QueryExecutor::new(&reader);
+
+        let query = parse_query("path:*.rs -path:lib.rs fn");
+        let results = executor.execute(&query).unwrap();
+
+        assert!(results.iter().any(|m| m.path.ends_with("main.rs")));
+        assert!(results.iter().all(|m| !m.path.ends_with("lib.rs")));
+    }
+
+    #[test]
+    fn test_glob_filter_directory_exclude() {
+        let field = FieldFilter {
+            include: Vec::new(),
+            exclude: vec!["vendor/".to_string()],
+        };
+        let matcher = GlobFilter::for_paths(&field).unwrap();
+        assert!(!matcher.is_match(Path::new("vendor/lib/a.js")));
+        assert!(matcher.is_match(Path::new("src/vendor.rs")));
+        assert!(GlobFilter::for_paths(&FieldFilter::default()).is_none());
+    }
+
+    #[test]
+    fn test_glob_filter_ripgrep_semantics() {
+        let field = FieldFilter {
+            include: vec!["*.js".to_string()],
+            exclude: vec!["*.min.js".to_string(), "vendor".to_string()],
+        };
+        let matcher = GlobFilter::for_ripgrep_globs(&field).unwrap();
+        // Slash-free globs match at any depth
+        assert!(matcher.is_match(Path::new("app.js")));
+        assert!(matcher.is_match(Path::new("src/ui/app.js")));
+        assert!(!matcher.is_match(Path::new("src/app.min.js")));
+        // Excluded directory names prune everything below them
+        assert!(!matcher.is_match(Path::new("lib/vendor/jquery.js")));
+        assert!(!matcher.is_match(Path::new("src/app.ts")));
+
+        let anchored = FieldFilter {
+            include: vec!["/src/*.rs".to_string()],
+            exclude: Vec::new(),
+        };
+        let matcher = GlobFilter::for_ripgrep_globs(&anchored).unwrap();
+        assert!(matcher.is_match(Path::new("src/main.rs")));
+        assert!(!matcher.is_match(Path::new("lib/src/main.rs")));
+        assert!(!matcher.is_match(Path::new("src/a/main.rs")));
+    }
+
+    #[test]
+    fn test_executor_type_filter() {
+        let (_temp_dir, _root_path, reader) = create_test_index();
+        let executor = QueryExecutor::new(&reader);
+
+        let mut query = parse_query("re:/fn|def/");
+        query.filters.add_file_types(&["py".to_string()], &[]);
+        let results = executor.execute(&query).unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|m| m.path.ends_with("utils.py")));
+
+        let mut query = parse_query("re:/fn|def/");
+        query.filters.add_file_types(&[], &["rust".to_string()]);
+        query.filters.add_globs(&["!lib.*".to_string()]);
+        let results = executor.execute(&query).unwrap();
+        assert!(results.iter().any(|m| m.path.ends_with("utils.py")));
+        assert!(
+            results
+                .iter()
+                .all(|m| m.path.extension().is_none_or(|e| e != "rs"))
+        );
+    }
+
     #[test]
     fn test_executor_with_limit() {
         let (_temp_dir, _root_path, reader) = create_test_index();
@@ -1718,7 +3117,8 @@ def format_warning(msg: str) -> str:
         let content = "fn main() {\n    println!(\"hello\");\n}\n";
         let verification = VerificationStep::Literal("println".to_string());
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
 
         assert!(!matches.is_empty(), "Should find literal match");
         assert_eq!(matches[0].0, 2, "Match should be on line 2");
@@ -1732,7 +3132,8 @@ def format_warning(msg: str) -> str:
             case_insensitive: false,
         };
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
 
         assert!(!matches.is_empty(), "Should find phrase match");
     }
@@ -1742,7 +3143,8 @@ def format_warning(msg: str) -> str:
         let content = "fn main() {\n    let x = 42;\n    let y = 123;\n}\n";
         let verification = VerificationStep::Regex(r"\d+".to_string());
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
 
         assert!(matches.len() >= 2, "Should find at least 2 number matches");
     }
@@ -1755,7 +3157,8 @@ def format_warning(msg: str) -> str:
             VerificationStep::Literal("main".to_string()),
         ]);
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
 
         assert!(!matches.is_empty(), "Should find AND match");
     }
@@ -1768,7 +3171,8 @@ def format_warning(msg: str) -> str:
             VerificationStep::Literal("helper".to_string()),
         ]);
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
 
         assert!(!matches.is_empty(), "Should find OR match (helper)");
     }
@@ -1810,13 +3214,18 @@ def format_warning(msg: str) -> str:
 
     #[test]
     fn test_parse_language() {
-        assert_eq!(parse_language("rust"), Language::Rust);
-        assert_eq!(parse_language("rs"), Language::Rust);
-        assert_eq!(parse_language("PYTHON"), Language::Python);
-        assert_eq!(parse_language("Py"), Language::Python);
-        assert_eq!(parse_language("javascript"), Language::JavaScript);
-        assert_eq!(parse_language("JS"), Language::JavaScript);
-        assert_eq!(parse_language("unknown_lang"), Language::Unknown);
+        assert_eq!(parse_language("rust"), Some(Language::Rust));
+        assert_eq!(parse_language("rs"), Some(Language::Rust));
+        assert_eq!(parse_language("PYTHON"), Some(Language::Python));
+        assert_eq!(parse_language("Py"), Some(Language::Python));
+        assert_eq!(parse_language("javascript"), Some(Language::JavaScript));
+        assert_eq!(parse_language("JS"), Some(Language::JavaScript));
+        // Every language parses from its facet name
+        assert_eq!(parse_language("csharp"), Some(Language::CSharp));
+        assert_eq!(parse_language("kotlin"), Some(Language::Kotlin));
+        assert_eq!(parse_language("markdown"), Some(Language::Markdown));
+        assert_eq!(parse_language("unknown"), Some(Language::Unknown));
+        assert_eq!(parse_language("unknown_lang"), None);
     }
 
     #[test]
@@ -1940,7 +3349,8 @@ def format_warning(msg: str) -> str:
         let verification =
             VerificationStep::Not(Box::new(VerificationStep::Literal("println".to_string())));
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
         assert!(
             matches.is_empty(),
             "NOT should produce no matches when term is present"
@@ -1954,7 +3364,8 @@ def format_warning(msg: str) -> str:
         let verification =
             VerificationStep::Not(Box::new(VerificationStep::Literal("println".to_string())));
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
         assert!(
             !matches.is_empty(),
             "NOT should produce a match when term is absent"
@@ -1972,15 +3383,23 @@ def format_warning(msg: str) -> str:
             VerificationStep::Not(Box::new(VerificationStep::Literal("println".to_string()))),
         ]);
 
-        let matches_both =
-            QueryExecutor::verify_content_static(content_with_both, &verification, 1);
+        let matches_both = QueryExecutor::verify_content_static(
+            content_with_both,
+            &verification,
+            1,
+            Language::Unknown,
+        );
         assert!(
             matches_both.is_empty(),
             "Should NOT match when negated term is present"
         );
 
-        let matches_without =
-            QueryExecutor::verify_content_static(content_without, &verification, 1);
+        let matches_without = QueryExecutor::verify_content_static(
+            content_without,
+            &verification,
+            1,
+            Language::Unknown,
+        );
         assert!(
             !matches_without.is_empty(),
             "Should match when negated term is absent"
@@ -2053,7 +3472,8 @@ def format_warning(msg: str) -> str:
             distance: 2,
         };
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
         assert!(
             !matches.is_empty(),
             "Near should match when terms are within distance"
@@ -2069,7 +3489,8 @@ def format_warning(msg: str) -> str:
             distance: 2,
         };
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
         assert!(
             matches.is_empty(),
             "Near should NOT match when terms are beyond distance (9 lines apart, distance=2)"
@@ -2085,7 +3506,8 @@ def format_warning(msg: str) -> str:
             distance: 1,
         };
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
         assert!(
             !matches.is_empty(),
             "Near should match when terms are on the same line"
@@ -2101,7 +3523,8 @@ def format_warning(msg: str) -> str:
             distance: 100,
         };
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
         assert!(
             matches.is_empty(),
             "Near should NOT match when a term is missing"
@@ -2126,6 +3549,132 @@ def format_warning(msg: str) -> str:
         );
     }
 
+    #[test]
+    fn test_executor_fuzzy_search() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let root = temp_dir.path();
+        fs::write(root.join("exact.rs"), "fn receive() {}\n").unwrap();
+        fs::write(root.join("typo.rs"), "fn recieve_all() {}\n").unwrap();
+        fs::write(root.join("far.rs"), "fn recover() {}\n").unwrap();
+        crate::index::build::build_index(root, false).unwrap();
+        let reader = IndexReader::open(root).unwrap();
+        let executor = QueryExecutor::new(&reader);
+
+        // Exact hit first, then the transposed typo; `recover` is too far
+        let results = executor.execute(&parse_query("~receive")).unwrap();
+        let paths: Vec<_> = results.iter().map(|m| m.path.clone()).collect();
+        assert_eq!(paths, [PathBuf::from("exact.rs"), PathBuf::from("typo.rs")]);
+        assert!(results[0].score > results[1].score);
+
+        let matches = executor
+            .execute_with_content(&parse_query("~1:recieve"), 0, 0)
+            .unwrap();
+        let mut spans: Vec<_> = matches
+            .iter()
+            .map(|m| (m.path.clone(), &m.line_content[m.match_start..m.match_end]))
+            .collect();
+        spans.sort();
+        assert_eq!(
+            spans,
+            [
+                (PathBuf::from("exact.rs"), "receive"),
+                (PathBuf::from("typo.rs"), "recieve")
+            ]
+        );
+
+        let files = executor
+            .execute_files_only(&parse_query("~3:receive -recover"), 0)
+            .unwrap();
+        assert_eq!(files.len(), 2);
+
+        crate::utils::remove_index(root).unwrap();
+    }
+
+    #[test]
+    fn test_executor_ordered_proximity() {
+        let (_temp_dir, root_path, reader) = create_test_index();
+        let search = |query: &str| {
+            QueryExecutor::new(&reader)
+                .execute_with_content(&parse_query(query), 0, 0)
+                .unwrap()
+        };
+
+        // `pub fn` (words 10-11 of lib.rs) right before `multiply`
+        let matches = search(r#"wbefore:"pub fn",multiply,1"#);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, Path::new("lib.rs"));
+        assert_eq!(matches[0].line_number, 5);
+        assert!(search(r#"wbefore:multiply,"pub fn""#).is_empty());
+        assert_eq!(search(r#"wnear:multiply,"pub fn",1"#).len(), 1);
+
+        // Line distance is verified against content
+        assert_eq!(search("before:helper,todo,1").len(), 1);
+        assert!(search("before:todo,helper,1").is_empty());
+        assert!(search("before:main,todo,2").is_empty());
+
+        // Order is resolved from the position index, before any file is read
+        let executor = QueryExecutor::new(&reader).with_explain();
+        executor
+            .execute_with_content(&parse_query("before:todo,helper"), 0, 0)
+            .unwrap();
+        let explain = executor.take_explain().unwrap();
+        let step = explain.steps.last().unwrap();
+        assert_eq!(step.step, "proximity");
+        assert_eq!(step.candidates, 0);
+        assert_eq!(explain.files_verified, 0);
+
+        crate::utils::remove_index(&root_path).unwrap();
+    }
+
+    #[test]
+    fn test_executor_explain() {
+        let (_temp_dir, root_path, reader) = create_test_index();
+
+        // Not recorded unless asked for
+        let executor = QueryExecutor::new(&reader);
+        executor.execute(&parse_query("multiply")).unwrap();
+        assert!(executor.take_explain().is_none());
+
+        let executor = QueryExecutor::new(&reader).with_explain();
+        let matches = executor
+            .execute_with_content(&parse_query("multiply ext:rs -helper"), 0, 0)
+            .unwrap();
+        assert!(matches.iter().all(|m| m.path == Path::new("lib.rs")));
+        let explain = executor.take_explain().unwrap();
+
+        let steps: Vec<_> = explain.steps.iter().map(|s| s.step.as_str()).collect();
+        assert_eq!(steps, ["token|trigrams", "filter", "exclude"]);
+        let lookup = &explain.steps[0];
+        assert_eq!(lookup.detail, "multiply");
+        assert_eq!(lookup.tokens[0].term, "multiply");
+        assert_eq!(lookup.tokens[0].docs, 1);
+        assert!(!lookup.trigrams.is_empty() || !lookup.stop_grams.is_empty());
+        assert_eq!(lookup.candidates, 1);
+        assert_eq!(explain.steps[1].detail, "ext:rs");
+        assert_eq!(explain.steps[2].children.len(), 1);
+
+        assert_eq!(
+            explain.verification.as_deref(),
+            Some(r#"and(literal "multiply", not literal "helper")"#)
+        );
+        assert_eq!(explain.total_docs, reader.valid_doc_ids().len());
+        assert_eq!(explain.candidates, 1);
+        assert_eq!(explain.files_verified, 1);
+        assert_eq!(explain.files_matched, 1);
+        assert!(explain.timings.total_ms >= explain.timings.verify_ms);
+
+        let report = explain.render();
+        assert!(report.starts_with("plan:\n  token|trigrams multiply -> 1 candidate"));
+        assert!(report.contains("files: 3 indexed in 1 segment, 1 candidate, 1 verified"));
+
+        // Taken, so the next query starts a fresh explanation
+        assert!(executor.take_explain().unwrap().steps.is_empty());
+        executor.execute_files_only(&parse_query("fn"), 1).unwrap();
+        assert_eq!(executor.take_explain().unwrap().files_matched, 1);
+
+        crate::utils::remove_index(&root_path).unwrap();
+    }
+
     // ========================================================================
     // Size filter executor tests
     // ========================================================================
@@ -2337,7 +3886,8 @@ def format_warning(msg: str) -> str:
         let content = "let x = 42;\nlet y = hello;\nlet z = 99;\n";
         let verification = VerificationStep::Regex(r"\d+".to_string());
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
         assert_eq!(
             matches.len(),
             2,
@@ -2350,7 +3900,8 @@ def format_warning(msg: str) -> str:
         let content = "hello world\nfoo bar\n";
         let verification = VerificationStep::Regex(r"\d+".to_string());
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
         assert!(
             matches.is_empty(),
             "Regex \\d+ should not match text-only content"
@@ -2390,7 +3941,8 @@ def format_warning(msg: str) -> str:
             VerificationStep::Literal("nonexistent2".to_string()),
         ]);
 
-        let matches = QueryExecutor::verify_content_static(content, &verification, 1);
+        let matches =
+            QueryExecutor::verify_content_static(content, &verification, 1, Language::Unknown);
         assert!(
             matches.is_empty(),
             "OR with no matching terms should be empty"
@@ -2473,6 +4025,80 @@ def format_warning(msg: str) -> str:
         );
     }
 
+    #[test]
+    fn test_executor_path_filters_through_path_trigrams() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        for path in [
+            "src/Config.rs",
+            "src/main.rs",
+            "docs/config.md",
+            "tests/build_test.rs",
+            "build.rs",
+        ] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "let needle = 1;\n").unwrap();
+        }
+        crate::index::build::build_index(&root, false).unwrap();
+
+        let queries = [
+            "file:config.rs needle",
+            "path:src/*.rs needle",
+            "path:**/build.rs needle",
+            "ext:md needle",
+            "file:*_test* needle",
+            "-path:docs/ ext:rs needle",
+            "file:rs needle",
+        ];
+        let run = |reader: &IndexReader| -> Vec<Vec<String>> {
+            let executor = QueryExecutor::new(reader);
+            queries
+                .iter()
+                .map(|q| {
+                    let mut paths: Vec<String> = executor
+                        .execute(&parse_query(q))
+                        .unwrap()
+                        .into_iter()
+                        .map(|m| m.path.display().to_string())
+                        .collect();
+                    paths.sort();
+                    paths.dedup();
+                    paths
+                })
+                .collect()
+        };
+
+        let reader = IndexReader::open(&root).unwrap();
+        assert!(
+            reader
+                .docs_with_path_fragments(&["config".to_string()])
+                .is_some()
+        );
+        let narrowed = run(&reader);
+        assert_eq!(narrowed[0], ["src/Config.rs"]);
+        assert_eq!(narrowed[1], ["src/Config.rs", "src/main.rs"]);
+        assert_eq!(narrowed[2], ["build.rs"]);
+        assert_eq!(narrowed[3], ["docs/config.md"]);
+        assert_eq!(narrowed[4], ["tests/build_test.rs"]);
+        assert_eq!(narrowed[5].len(), 4);
+        assert!(narrowed[6].is_empty());
+
+        // Indexes built before the path trigram index scan every path and
+        // agree
+        let index_dir = crate::utils::get_index_dir(&root).unwrap();
+        fs::remove_file(index_dir.join("path_grams.bin")).unwrap();
+        let reader = IndexReader::open(&root).unwrap();
+        assert!(
+            reader
+                .docs_with_path_fragments(&["config".to_string()])
+                .is_none()
+        );
+        assert_eq!(run(&reader), narrowed);
+
+        crate::utils::remove_index(&root).unwrap();
+    }
+
     // ========================================================================
     // Edge case: empty results
     // ========================================================================
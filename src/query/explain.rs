@@ -0,0 +1,495 @@
+//! Query explain mode (`--explain`).
+//!
+//! With explaining turned on ([`QueryExecutor::with_explain`]) the executor
+//! records each narrowing step of the [`QueryPlan`] as it runs: the
+//! trigrams it looked up and the stop-grams it skipped, how many segments
+//! the bloom filters ruled out, the posting list sizes, and the candidate
+//! count left after the step. The verification and ranking phases add how
+//! many files were read and matched and the wall time of each phase, so a
+//! slow query shows whether it failed to narrow or spent its time reading.
+//!
+//! [`QueryExecutor::with_explain`]: crate::query::executor::QueryExecutor::with_explain
+//! [`QueryPlan`]: crate::query::planner::QueryPlan
+
+use crate::index::reader::IndexReader;
+use crate::index::stats::trigram_text;
+use crate::index::types::Trigram;
+use crate::query::parser::FieldFilter;
+use crate::query::planner::{FilterStep, PlanStep, VerificationStep};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+/// Most tokens listed for a step that expands to many (fuzzy); the rest
+/// are only counted in the step's detail
+const MAX_LISTED_TOKENS: usize = 32;
+
+/// An executed query, annotated
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueryExplain {
+    /// Narrowing steps in execution order
+    pub steps: Vec<StepExplain>,
+    /// Content verification run on the candidates, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<String>,
+    /// Indexed (valid) documents
+    pub total_docs: u64,
+    /// Segments in the index
+    pub segments: usize,
+    /// Documents left after narrowing
+    pub candidates: u64,
+    /// Candidate files read and verified (fewer than `candidates` when the
+    /// result limit stopped the scan early)
+    pub files_verified: u64,
+    /// Files with at least one match
+    pub files_matched: u64,
+    pub timings: PhaseTimings,
+}
+
+/// One narrowing step of the plan
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StepExplain {
+    /// Step kind: `trigrams`, `token`, `token|trigrams`, `phrase`, `fuzzy`,
+    /// `filter`, `union`, `intersect`, `exclude` or `branch`
+    pub step: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+    /// Trigrams looked up, rarest first (the order they are intersected
+    /// in), with their posting sizes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trigrams: Vec<Posting>,
+    /// Trigrams skipped as stop-grams (too common to narrow)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_grams: Vec<String>,
+    /// Segments whose bloom filter ruled out the trigrams
+    #[serde(default)]
+    pub bloom_rejected: usize,
+    /// Tokens looked up, with their posting sizes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<Posting>,
+    /// Candidate documents after this step
+    pub candidates: u64,
+    pub duration_ms: f64,
+    /// Sub-plans (`union`, `intersect`, `exclude`): one `branch` each
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<StepExplain>,
+}
+
+/// Size of one posting list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Posting {
+    pub term: String,
+    /// Documents in the list, summed over segments
+    pub docs: u32,
+}
+
+/// Wall time of each executor phase, in milliseconds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    /// Query to plan
+    pub plan_ms: f64,
+    /// Index lookups down to the candidate set
+    pub narrow_ms: f64,
+    /// Reading and verifying candidate files
+    pub verify_ms: f64,
+    /// Scoring, sorting and assembling results
+    pub rank_ms: f64,
+    pub total_ms: f64,
+}
+
+impl PhaseTimings {
+    /// Timings from the instants at which each phase ended
+    pub fn from_marks(
+        start: Instant,
+        planned: Instant,
+        narrowed: Instant,
+        verified: Instant,
+        done: Instant,
+    ) -> Self {
+        Self {
+            plan_ms: millis(planned - start),
+            narrow_ms: millis(narrowed - planned),
+            verify_ms: millis(verified - narrowed),
+            rank_ms: millis(done - verified),
+            total_ms: millis(done - start),
+        }
+    }
+}
+
+pub fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+impl StepExplain {
+    fn new(step: &str, detail: String) -> Self {
+        Self {
+            step: step.to_string(),
+            detail,
+            ..Self::default()
+        }
+    }
+
+    /// A sub-plan of a `union`, `intersect` or `exclude` step
+    pub fn branch(steps: Vec<StepExplain>, candidates: u64) -> Self {
+        Self {
+            step: "branch".to_string(),
+            candidates,
+            children: steps,
+            ..Self::default()
+        }
+    }
+
+    /// The deferred exclusion of a `-term` sub-plan
+    pub fn exclude(verification: Option<&VerificationStep>) -> Self {
+        Self::new(
+            "exclude",
+            verification.map(describe_verification).unwrap_or_default(),
+        )
+    }
+
+    /// Describe a plan step with the index statistics it will use: which
+    /// trigrams survive the stop-gram filter, how many segments their bloom
+    /// filters reject, and the posting sizes of every looked-up term.
+    /// Candidate counts, timings and children are filled in by the executor.
+    pub fn for_step(step: &PlanStep, reader: &IndexReader) -> Self {
+        match step {
+            PlanStep::TrigramIntersect(trigrams) => {
+                let mut explain = Self::new("trigrams", String::new());
+                explain.add_trigrams(trigrams, reader);
+                if explain.trigrams.is_empty() {
+                    explain.detail = "all stop-grams, no narrowing".to_string();
+                }
+                explain
+            }
+            PlanStep::TokenLookup(token) => {
+                let mut explain = Self::new("token", token.clone());
+                explain.add_token(token, reader);
+                explain
+            }
+            PlanStep::TokenOrTrigram {
+                token,
+                sub_tokens,
+                trigrams,
+            } => {
+                let mut explain = Self::new("token|trigrams", token.clone());
+                explain.add_token(token, reader);
+                explain.add_trigrams(trigrams, reader);
+                if explain.trigrams.is_empty() {
+                    explain.detail = format!("{token} (tokens containing it)");
+                    if sub_tokens.len() >= 2 {
+                        for sub in sub_tokens {
+                            explain.add_token(sub, reader);
+                        }
+                    }
+                }
+                explain
+            }
+            PlanStep::Union(sub_plans) => {
+                Self::new("union", format!("{} branches", sub_plans.len()))
+            }
+            PlanStep::Intersect(sub_plans) => {
+                Self::new("intersect", format!("{} branches", sub_plans.len()))
+            }
+            PlanStep::Exclude(sub_plan) => Self::exclude(sub_plan.verification.as_ref()),
+            PlanStep::PositionalPhrase(tokens) => {
+                let words: Vec<&str> = tokens.iter().map(|(t, _)| t.as_str()).collect();
+                let mut explain = Self::new("phrase", words.join(" "));
+                for token in words {
+                    explain.add_token(token, reader);
+                }
+                explain
+            }
+            PlanStep::PositionalProximity {
+                terms,
+                distance,
+                ordered,
+                words,
+            } => {
+                let phrases: Vec<String> = terms
+                    .iter()
+                    .map(|phrase| {
+                        let words: Vec<&str> = phrase.iter().map(|(t, _)| t.as_str()).collect();
+                        quoted(&words.join(" "))
+                    })
+                    .collect();
+                let reach = if *words {
+                    format!("within {}", plural(u64::from(*distance), "word"))
+                } else {
+                    // Line distance is checked when verifying
+                    "order only".to_string()
+                };
+                let mut explain = Self::new(
+                    "proximity",
+                    format!(
+                        "{} {}, {reach}",
+                        proximity_operator(*ordered, *words),
+                        phrases.join(",")
+                    ),
+                );
+                for (token, _) in terms.iter().flatten() {
+                    explain.add_token(token, reader);
+                }
+                explain
+            }
+            PlanStep::FuzzyTokens { term, max_edits } => {
+                let mut tokens = reader.fuzzy_tokens(term, *max_edits);
+                tokens.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                let mut explain = Self::new(
+                    "fuzzy",
+                    format!(
+                        "~{max_edits}:{term}, {}",
+                        plural(tokens.len() as u64, "token")
+                    ),
+                );
+                explain.tokens = tokens
+                    .into_iter()
+                    .take(MAX_LISTED_TOKENS)
+                    .map(|(term, docs)| Posting { term, docs })
+                    .collect();
+                explain
+            }
+            PlanStep::Filter(filter) => Self::new("filter", describe_filter(filter)),
+        }
+    }
+
+    fn add_token(&mut self, token: &str, reader: &IndexReader) {
+        self.tokens.push(Posting {
+            term: token.to_lowercase(),
+            docs: reader.token_doc_freq(token),
+        });
+    }
+
+    /// Split trigrams into looked-up and stop-grams, rarest looked-up first
+    /// (the order the reader intersects them in)
+    fn add_trigrams(&mut self, trigrams: &[Trigram], reader: &IndexReader) {
+        let mut used: Vec<(Trigram, u32)> = Vec::new();
+        for &t in trigrams {
+            if reader.is_stop_gram(t) {
+                self.stop_grams.push(trigram_text(t));
+            } else if !used.iter().any(|&(u, _)| u == t) {
+                used.push((t, reader.trigram_doc_freq(t)));
+            }
+        }
+        self.stop_grams.sort_unstable();
+        self.stop_grams.dedup();
+        used.sort_by_key(|&(_, freq)| freq);
+
+        let used_trigrams: Vec<Trigram> = used.iter().map(|&(t, _)| t).collect();
+        self.bloom_rejected = reader.bloom_rejections(&used_trigrams);
+        self.trigrams = used
+            .into_iter()
+            .map(|(t, docs)| Posting {
+                term: trigram_text(t),
+                docs,
+            })
+            .collect();
+    }
+}
+
+/// `literal "foo"`, `regex /a.b/`, `and(literal "a", not literal "b")`, ...
+pub fn describe_verification(verification: &VerificationStep) -> String {
+    match verification {
+        VerificationStep::Literal(text) => format!("literal {text:?}"),
+        VerificationStep::BoostedLiteral { text, boost } => {
+            format!("literal {text:?} (boost {boost})")
+        }
+        VerificationStep::Phrase {
+            text,
+            case_insensitive,
+        } => {
+            if *case_insensitive {
+                format!("phrase {text:?} (case-insensitive)")
+            } else {
+                format!("phrase {text:?}")
+            }
+        }
+        VerificationStep::Regex(pattern) => format!("regex /{pattern}/"),
+        VerificationStep::Near { terms, distance } => {
+            format!("near {} within {distance} lines", terms.join(","))
+        }
+        VerificationStep::Proximity {
+            terms,
+            distance,
+            ordered,
+            words,
+        } => {
+            let terms: Vec<String> = terms.iter().map(|t| quoted(t)).collect();
+            format!(
+                "{} {} within {}",
+                proximity_operator(*ordered, *words),
+                terms.join(","),
+                plural(u64::from(*distance), if *words { "word" } else { "line" })
+            )
+        }
+        VerificationStep::Fuzzy { term, max_edits } => format!("fuzzy ~{max_edits}:{term}"),
+        VerificationStep::And(steps) => describe_compound("and", steps),
+        VerificationStep::Or(steps) => describe_compound("or", steps),
+        VerificationStep::Not(step) => format!("not {}", describe_verification(step)),
+        VerificationStep::InRegion { regions, inner } => {
+            format!("{} in {regions}", describe_verification(inner))
+        }
+    }
+}
+
+/// The query operator of a whole-word proximity
+fn proximity_operator(ordered: bool, words: bool) -> &'static str {
+    match (ordered, words) {
+        (true, false) => "before",
+        (false, true) => "wnear",
+        (true, true) => "wbefore",
+        // Not produced by the parser: line-distance `near:` is substring based
+        (false, false) => "near",
+    }
+}
+
+fn describe_compound(op: &str, steps: &[VerificationStep]) -> String {
+    let parts: Vec<String> = steps.iter().map(describe_verification).collect();
+    format!("{op}({})", parts.join(", "))
+}
+
+/// The set fields of a filter step in query syntax: `ext:rs -path:vendor/`
+fn describe_filter(filter: &FilterStep) -> String {
+    let mut parts = Vec::new();
+    let fields = [
+        ("path", &filter.path_glob),
+        ("file", &filter.filename),
+        ("ext", &filter.extension),
+        ("lang", &filter.language),
+        ("glob", &filter.glob),
+        ("type", &filter.file_type),
+        ("changed", &filter.changed),
+        ("is", &filter.status),
+        ("like", &filter.like),
+    ];
+    for (name, field) in fields {
+        describe_field(&mut parts, name, field);
+    }
+    let ranges = [
+        ("size", filter.size_min, filter.size_max),
+        ("mtime", filter.mtime_min, filter.mtime_max),
+        (
+            "line",
+            filter.line_start.map(u64::from),
+            filter.line_end.map(u64::from),
+        ),
+        (
+            "count",
+            filter.count_min.map(u64::from),
+            filter.count_max.map(u64::from),
+        ),
+    ];
+    for (name, min, max) in ranges {
+        if min.is_some() || max.is_some() {
+            let bound = |b: Option<u64>| b.map(|b| b.to_string()).unwrap_or_default();
+            parts.push(format!("{name}:{}..{}", bound(min), bound(max)));
+        }
+    }
+    parts.join(" ")
+}
+
+fn describe_field(parts: &mut Vec<String>, name: &str, field: &FieldFilter) {
+    if !field.include.is_empty() {
+        parts.push(format!("{name}:{}", field.include.join(",")));
+    }
+    if !field.exclude.is_empty() {
+        parts.push(format!("-{name}:{}", field.exclude.join(",")));
+    }
+}
+
+impl QueryExplain {
+    /// Human-readable report, for stderr
+    pub fn render(&self) -> String {
+        let mut out = String::from("plan:\n");
+        if self.steps.is_empty() {
+            out.push_str("  (no narrowing: every document is a candidate)\n");
+        }
+        for step in &self.steps {
+            render_step(&mut out, step, 1);
+        }
+        if let Some(verification) = &self.verification {
+            let _ = writeln!(out, "verify: {verification}");
+        }
+        let _ = writeln!(
+            out,
+            "files: {} indexed in {}, {}, {} verified, {} matched",
+            self.total_docs,
+            plural(self.segments as u64, "segment"),
+            plural(self.candidates, "candidate"),
+            self.files_verified,
+            self.files_matched
+        );
+        let t = &self.timings;
+        let _ = writeln!(
+            out,
+            "time: plan {:.2}ms, narrow {:.2}ms, verify {:.2}ms, rank {:.2}ms, total {:.2}ms",
+            t.plan_ms, t.narrow_ms, t.verify_ms, t.rank_ms, t.total_ms
+        );
+        out
+    }
+}
+
+fn render_step(out: &mut String, step: &StepExplain, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let head = if step.detail.is_empty() {
+        step.step.clone()
+    } else {
+        format!("{} {}", step.step, step.detail)
+    };
+    let _ = writeln!(
+        out,
+        "{indent}{head} -> {} ({:.2}ms)",
+        plural(step.candidates, "candidate"),
+        step.duration_ms
+    );
+    if !step.trigrams.is_empty() {
+        let _ = writeln!(
+            out,
+            "{indent}    trigrams: {}",
+            render_postings(&step.trigrams)
+        );
+    }
+    if !step.stop_grams.is_empty() {
+        let stop_grams: Vec<String> = step.stop_grams.iter().map(|t| quoted(t)).collect();
+        let _ = writeln!(
+            out,
+            "{indent}    stop-grams skipped: {}",
+            stop_grams.join(" ")
+        );
+    }
+    if step.bloom_rejected > 0 {
+        let _ = writeln!(
+            out,
+            "{indent}    bloom filters rejected {}",
+            plural(step.bloom_rejected as u64, "segment")
+        );
+    }
+    if !step.tokens.is_empty() {
+        let _ = writeln!(out, "{indent}    tokens: {}", render_postings(&step.tokens));
+    }
+    for child in &step.children {
+        render_step(out, child, depth + 1);
+    }
+}
+
+/// `rec=40 " fo"=3`
+fn render_postings(postings: &[Posting]) -> String {
+    let parts: Vec<String> = postings
+        .iter()
+        .map(|p| format!("{}={}", quoted(&p.term), p.docs))
+        .collect();
+    parts.join(" ")
+}
+
+/// Trigrams may contain spaces; quote those so the list stays readable
+fn quoted(term: &str) -> String {
+    if term.contains(' ') {
+        format!("\"{term}\"")
+    } else {
+        term.to_string()
+    }
+}
+
+/// `1 candidate`, `2 candidates`
+fn plural(n: u64, word: &str) -> String {
+    format!("{n} {word}{}", if n == 1 { "" } else { "s" })
+}
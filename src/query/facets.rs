@@ -0,0 +1,186 @@
+//! Result facets: where the matches of a query concentrate.
+//!
+//! Verified files are grouped by parent directory, extension and
+//! [`Language`], each group carrying its match and file counts. Only the
+//! largest groups are kept, and each one can be turned back into a query
+//! filter (`path:src/query/*`, `ext:rs`, `lang:rust`) to narrow the search
+//! to it.
+
+use crate::index::types::Language;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Groups kept per facet kind
+pub const MAX_FACET_VALUES: usize = 10;
+
+/// What a facet groups by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FacetKind {
+    Dir,
+    Ext,
+    Lang,
+}
+
+impl FacetKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            FacetKind::Dir => "Directories",
+            FacetKind::Ext => "Extensions",
+            FacetKind::Lang => "Languages",
+        }
+    }
+
+    /// The query filter selecting a group's files, or None when the query
+    /// language cannot express it (whitespace in a path, no extension)
+    pub fn filter(self, value: &str) -> Option<String> {
+        if value.contains(char::is_whitespace) {
+            return None;
+        }
+        match self {
+            // `*` does not cross `/`, so subdirectories stay out
+            FacetKind::Dir if value == "." => Some("path:*".to_string()),
+            FacetKind::Dir => Some(format!("path:{}/*", globset::escape(value))),
+            FacetKind::Ext if value == NO_EXTENSION => None,
+            FacetKind::Ext => Some(format!("ext:{value}")),
+            FacetKind::Lang => Some(format!("lang:{value}")),
+        }
+    }
+}
+
+/// Group name of files without an extension
+const NO_EXTENSION: &str = "(none)";
+
+/// One group of matching files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Facet {
+    /// Directory (`.` for the root), extension or lowercase language name
+    pub value: String,
+    /// Matching lines in the group's files
+    pub matches: usize,
+    /// Files with a match
+    pub files: usize,
+}
+
+/// The largest groups of each kind, most matches first
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Facets {
+    pub dirs: Vec<Facet>,
+    pub exts: Vec<Facet>,
+    pub langs: Vec<Facet>,
+}
+
+impl Facets {
+    /// Facets of verified files, given as (relative path, language, match
+    /// count)
+    pub fn from_files<'a>(files: impl IntoIterator<Item = (&'a Path, Language, usize)>) -> Self {
+        let mut dirs: HashMap<String, (usize, usize)> = HashMap::new();
+        let mut exts: HashMap<String, (usize, usize)> = HashMap::new();
+        let mut langs: HashMap<String, (usize, usize)> = HashMap::new();
+        for (path, language, matches) in files {
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_string_lossy().into_owned(),
+                _ => ".".to_string(),
+            };
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_else(|| NO_EXTENSION.to_string());
+            let lang = format!("{:?}", language).to_lowercase();
+            for (groups, key) in [(&mut dirs, dir), (&mut exts, ext), (&mut langs, lang)] {
+                let group = groups.entry(key).or_default();
+                group.0 += matches;
+                group.1 += 1;
+            }
+        }
+        Self {
+            dirs: top_groups(dirs),
+            exts: top_groups(exts),
+            langs: top_groups(langs),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty() && self.exts.is_empty() && self.langs.is_empty()
+    }
+
+    /// Each kind with its groups, in display order
+    pub fn kinds(&self) -> [(FacetKind, &[Facet]); 3] {
+        [
+            (FacetKind::Dir, &self.dirs),
+            (FacetKind::Ext, &self.exts),
+            (FacetKind::Lang, &self.langs),
+        ]
+    }
+}
+
+/// The [`MAX_FACET_VALUES`] groups with the most matches (ties: more files,
+/// then by name)
+fn top_groups(groups: HashMap<String, (usize, usize)>) -> Vec<Facet> {
+    let mut facets: Vec<Facet> = groups
+        .into_iter()
+        .map(|(value, (matches, files))| Facet {
+            value,
+            matches,
+            files,
+        })
+        .collect();
+    facets.sort_by(|a, b| {
+        b.matches
+            .cmp(&a.matches)
+            .then_with(|| b.files.cmp(&a.files))
+            .then_with(|| a.value.cmp(&b.value))
+    });
+    facets.truncate(MAX_FACET_VALUES);
+    facets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_facets_from_files() {
+        let files = [
+            (Path::new("src/main.rs"), Language::Rust, 3),
+            (Path::new("src/lib.rs"), Language::Rust, 1),
+            (Path::new("src/query/mod.rs"), Language::Rust, 2),
+            (Path::new("build.py"), Language::Python, 5),
+            (Path::new("Makefile"), Language::Unknown, 1),
+        ];
+        let facets = Facets::from_files(files);
+
+        fn groups(facets: &[Facet]) -> Vec<(&str, usize, usize)> {
+            facets
+                .iter()
+                .map(|f| (f.value.as_str(), f.matches, f.files))
+                .collect()
+        }
+        assert_eq!(
+            groups(&facets.dirs),
+            [(".", 6, 2), ("src", 4, 2), ("src/query", 2, 1)]
+        );
+        assert_eq!(
+            groups(&facets.exts),
+            [("rs", 6, 3), ("py", 5, 1), ("(none)", 1, 1)]
+        );
+        assert_eq!(
+            groups(&facets.langs),
+            [("rust", 6, 3), ("python", 5, 1), ("unknown", 1, 1)]
+        );
+    }
+
+    #[test]
+    fn test_facet_filters() {
+        assert_eq!(
+            FacetKind::Dir.filter("src/query").unwrap(),
+            "path:src/query/*"
+        );
+        assert_eq!(FacetKind::Dir.filter(".").unwrap(), "path:*");
+        assert_eq!(FacetKind::Dir.filter("a[1]").unwrap(), "path:a[[]1[]]/*");
+        assert_eq!(FacetKind::Dir.filter("my docs"), None);
+        assert_eq!(FacetKind::Ext.filter("rs").unwrap(), "ext:rs");
+        assert_eq!(FacetKind::Ext.filter(NO_EXTENSION), None);
+        assert_eq!(FacetKind::Lang.filter("csharp").unwrap(), "lang:csharp");
+    }
+}
@@ -0,0 +1,351 @@
+//! File type table for ripgrep-style `-t/--type` and `-T/--type-not`.
+//!
+//! Type names and globs follow ripgrep's built-in table so scripts written
+//! for `rg -t rust` work unchanged. Types that correspond to a detected
+//! [`Language`] can also be selected by that language's name or alias
+//! (`-t python`, `-t rs`).
+
+use crate::index::types::Language;
+
+/// A named file type: the globs (matched against the file name) that select it
+#[derive(Debug)]
+pub struct FileType {
+    pub name: &'static str,
+    pub language: Option<Language>,
+    pub globs: &'static [&'static str],
+}
+
+/// Built-in file types, sorted by name
+pub static FILE_TYPES: &[FileType] = &[
+    FileType {
+        name: "c",
+        language: Some(Language::C),
+        globs: &["*.[chH]", "*.[chH].in", "*.cats"],
+    },
+    FileType {
+        name: "clojure",
+        language: Some(Language::Clojure),
+        globs: &["*.clj", "*.cljc", "*.cljs", "*.cljx", "*.edn"],
+    },
+    FileType {
+        name: "cmake",
+        language: None,
+        globs: &["*.cmake", "CMakeLists.txt"],
+    },
+    FileType {
+        name: "cpp",
+        language: Some(Language::Cpp),
+        globs: &[
+            "*.[ChH]",
+            "*.cc",
+            "*.[ch]pp",
+            "*.[ch]xx",
+            "*.hh",
+            "*.inl",
+            "*.[ChH].in",
+            "*.cc.in",
+            "*.[ch]pp.in",
+            "*.[ch]xx.in",
+            "*.hh.in",
+        ],
+    },
+    FileType {
+        name: "csharp",
+        language: Some(Language::CSharp),
+        globs: &["*.cs"],
+    },
+    FileType {
+        name: "css",
+        language: Some(Language::Css),
+        globs: &["*.css", "*.scss", "*.sass", "*.less"],
+    },
+    FileType {
+        name: "docker",
+        language: None,
+        globs: &["*Dockerfile*"],
+    },
+    FileType {
+        name: "elixir",
+        language: Some(Language::Elixir),
+        globs: &["*.ex", "*.eex", "*.exs", "*.heex", "*.leex", "*.livemd"],
+    },
+    FileType {
+        name: "go",
+        language: Some(Language::Go),
+        globs: &["*.go"],
+    },
+    FileType {
+        name: "haskell",
+        language: Some(Language::Haskell),
+        globs: &["*.hs", "*.lhs", "*.cpphs", "*.c2hs", "*.hsc"],
+    },
+    FileType {
+        name: "html",
+        language: Some(Language::Html),
+        globs: &["*.htm", "*.html", "*.ejs"],
+    },
+    FileType {
+        name: "java",
+        language: Some(Language::Java),
+        globs: &["*.java", "*.jsp", "*.jspx", "*.properties"],
+    },
+    FileType {
+        name: "js",
+        language: Some(Language::JavaScript),
+        globs: &["*.js", "*.jsx", "*.vue", "*.cjs", "*.mjs"],
+    },
+    FileType {
+        name: "json",
+        language: Some(Language::Json),
+        globs: &["*.json", "composer.lock", "*.sarif"],
+    },
+    FileType {
+        name: "kotlin",
+        language: Some(Language::Kotlin),
+        globs: &["*.kt", "*.kts"],
+    },
+    FileType {
+        name: "lua",
+        language: Some(Language::Lua),
+        globs: &["*.lua"],
+    },
+    FileType {
+        name: "make",
+        language: None,
+        globs: &[
+            "[Gg][Nn][Uu]makefile",
+            "[Mm]akefile",
+            "[Gg][Nn][Uu]makefile.am",
+            "[Gg][Nn][Uu]makefile.in",
+            "[Mm]akefile.am",
+            "[Mm]akefile.in",
+            "*.mk",
+            "*.mak",
+        ],
+    },
+    FileType {
+        name: "markdown",
+        language: Some(Language::Markdown),
+        globs: &[
+            "*.markdown",
+            "*.md",
+            "*.mdown",
+            "*.mdwn",
+            "*.mkd",
+            "*.mkdn",
+            "*.mdx",
+        ],
+    },
+    FileType {
+        name: "md",
+        language: None,
+        globs: &[
+            "*.markdown",
+            "*.md",
+            "*.mdown",
+            "*.mdwn",
+            "*.mkd",
+            "*.mkdn",
+            "*.mdx",
+        ],
+    },
+    FileType {
+        name: "nim",
+        language: Some(Language::Nim),
+        globs: &["*.nim", "*.nimf", "*.nimble", "*.nims"],
+    },
+    FileType {
+        name: "ocaml",
+        language: Some(Language::Ocaml),
+        globs: &["*.ml", "*.mli", "*.mll", "*.mly"],
+    },
+    FileType {
+        name: "perl",
+        language: Some(Language::Perl),
+        globs: &["*.perl", "*.pl", "*.PL", "*.plh", "*.plx", "*.pm", "*.t"],
+    },
+    FileType {
+        name: "php",
+        language: Some(Language::Php),
+        globs: &[
+            "*.php", "*.php3", "*.php4", "*.php5", "*.php7", "*.php8", "*.pht", "*.phtml",
+        ],
+    },
+    FileType {
+        name: "protobuf",
+        language: None,
+        globs: &["*.proto"],
+    },
+    FileType {
+        name: "py",
+        language: Some(Language::Python),
+        globs: &["*.py", "*.pyi"],
+    },
+    FileType {
+        name: "r",
+        language: Some(Language::R),
+        globs: &["*.R", "*.r", "*.Rmd", "*.Rnw"],
+    },
+    FileType {
+        name: "ruby",
+        language: Some(Language::Ruby),
+        globs: &[
+            "*.rb",
+            "Gemfile",
+            "*.gemspec",
+            ".irbrc",
+            "Rakefile",
+            "*.rake",
+            "config.ru",
+        ],
+    },
+    FileType {
+        name: "rust",
+        language: Some(Language::Rust),
+        globs: &["*.rs"],
+    },
+    FileType {
+        name: "scala",
+        language: Some(Language::Scala),
+        globs: &["*.scala", "*.sbt"],
+    },
+    FileType {
+        name: "sh",
+        language: Some(Language::Shell),
+        globs: &[
+            "*.bash",
+            ".bashrc",
+            ".bash_profile",
+            ".bash_logout",
+            "*.csh",
+            ".cshrc",
+            "*.ksh",
+            ".kshrc",
+            "*.sh",
+            "*.tcsh",
+            "*.zsh",
+            ".zshrc",
+            ".zshenv",
+            ".zprofile",
+            ".profile",
+        ],
+    },
+    FileType {
+        name: "sql",
+        language: Some(Language::Sql),
+        globs: &["*.sql", "*.psql"],
+    },
+    FileType {
+        name: "swift",
+        language: Some(Language::Swift),
+        globs: &["*.swift"],
+    },
+    FileType {
+        name: "toml",
+        language: Some(Language::Toml),
+        globs: &["*.toml", "Cargo.lock"],
+    },
+    FileType {
+        name: "ts",
+        language: Some(Language::TypeScript),
+        globs: &["*.ts", "*.tsx", "*.cts", "*.mts"],
+    },
+    FileType {
+        name: "txt",
+        language: None,
+        globs: &["*.txt"],
+    },
+    FileType {
+        name: "xml",
+        language: None,
+        globs: &[
+            "*.xml",
+            "*.xml.dist",
+            "*.dtd",
+            "*.xsl",
+            "*.xslt",
+            "*.xsd",
+            "*.xjb",
+            "*.rng",
+            "*.sch",
+            "*.xhtml",
+        ],
+    },
+    FileType {
+        name: "yaml",
+        language: Some(Language::Yaml),
+        globs: &["*.yaml", "*.yml"],
+    },
+    FileType {
+        name: "zig",
+        language: Some(Language::Zig),
+        globs: &["*.zig"],
+    },
+];
+
+/// Look up a file type by ripgrep name, falling back to language names and
+/// aliases (`python`, `rs`, `javascript`)
+pub fn lookup(name: &str) -> Option<&'static FileType> {
+    if let Some(t) = FILE_TYPES.iter().find(|t| t.name == name) {
+        return Some(t);
+    }
+
+    let lower = name.to_lowercase();
+    let lang = match crate::query::executor::parse_language(&lower) {
+        None | Some(Language::Unknown) => FILE_TYPES
+            .iter()
+            .filter_map(|t| t.language)
+            .find(|l| format!("{:?}", l).to_lowercase() == lower)?,
+        Some(lang) => lang,
+    };
+    FILE_TYPES.iter().find(|t| t.language == Some(lang))
+}
+
+/// Check that every name resolves to a file type, with ripgrep's error text
+pub fn check_names<'a>(names: impl IntoIterator<Item = &'a String>) -> Result<(), String> {
+    for name in names {
+        if lookup(name).is_none() {
+            return Err(format!("unrecognized file type: {}", name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_sorted_and_unique() {
+        for pair in FILE_TYPES.windows(2) {
+            assert!(pair[0].name < pair[1].name, "{} out of order", pair[1].name);
+        }
+    }
+
+    #[test]
+    fn test_lookup_ripgrep_names() {
+        assert_eq!(lookup("rust").unwrap().globs, &["*.rs"]);
+        assert_eq!(lookup("py").unwrap().language, Some(Language::Python));
+        assert!(lookup("make").unwrap().globs.contains(&"[Mm]akefile"));
+    }
+
+    #[test]
+    fn test_lookup_language_names() {
+        assert_eq!(lookup("python").unwrap().name, "py");
+        assert_eq!(lookup("rs").unwrap().name, "rust");
+        assert_eq!(lookup("JavaScript").unwrap().name, "js");
+        assert_eq!(lookup("csharp").unwrap().name, "csharp");
+        assert_eq!(lookup("shell").unwrap().name, "sh");
+    }
+
+    #[test]
+    fn test_check_names() {
+        let ok = vec!["rust".to_string(), "python".to_string()];
+        assert!(check_names(&ok).is_ok());
+        let bad = vec!["rust".to_string(), "nope".to_string()];
+        assert_eq!(
+            check_names(&bad).unwrap_err(),
+            "unrecognized file type: nope"
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! fzf-style fuzzy matching of paths (`fxi files --fuzzy`).
+//!
+//! A pattern matches a path when its characters appear in order. As in
+//! fzf's v1 algorithm, a forward scan finds where the first complete match
+//! ends and a backward scan from there picks the latest characters, which
+//! gives the shortest window and keeps runs together. Every matched
+//! character earns a base score, characters at word boundaries (after `/`, `_`, `-`, `.`,
+//! or a lower-to-upper case change) and runs of consecutive characters earn
+//! bonuses, and gaps cost a penalty. Matching is case-insensitive unless
+//! the pattern has an uppercase letter (smart case).
+
+use rayon::prelude::*;
+use std::path::Path;
+
+const SCORE_MATCH: i32 = 16;
+const PENALTY_GAP_START: i32 = -3;
+const PENALTY_GAP_EXTENSION: i32 = -1;
+const BONUS_SEPARATOR: i32 = 10;
+const BONUS_BOUNDARY: i32 = 8;
+const BONUS_CAMEL: i32 = 7;
+const BONUS_CONSECUTIVE: i32 = 4;
+/// The first pattern character's bonus counts this many times
+const BONUS_FIRST_MULTIPLIER: i32 = 2;
+/// Added when every matched character lies in the file name
+const BONUS_FILE_NAME: i32 = 12;
+
+/// A fuzzy match of a pattern against one path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i32,
+    /// Character (not byte) indices of the matched characters
+    pub positions: Vec<usize>,
+}
+
+/// A compiled pattern, reused across candidates
+pub struct FuzzyPattern {
+    chars: Vec<char>,
+    case_sensitive: bool,
+}
+
+impl FuzzyPattern {
+    /// Compile a pattern; whitespace is ignored. Returns None for an empty
+    /// pattern.
+    pub fn new(pattern: &str) -> Option<Self> {
+        let chars: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+        if chars.is_empty() {
+            return None;
+        }
+        let case_sensitive = chars.iter().any(|c| c.is_uppercase());
+        let chars = if case_sensitive {
+            chars
+        } else {
+            chars.iter().flat_map(|c| c.to_lowercase()).collect()
+        };
+        Some(Self {
+            chars,
+            case_sensitive,
+        })
+    }
+
+    fn eq(&self, pattern_char: char, candidate_char: char) -> bool {
+        if self.case_sensitive {
+            pattern_char == candidate_char
+        } else if candidate_char.is_ascii() {
+            pattern_char == candidate_char.to_ascii_lowercase()
+        } else {
+            candidate_char
+                .to_lowercase()
+                .eq(std::iter::once(pattern_char))
+        }
+    }
+
+    /// Score `candidate`, or None if the pattern's characters do not all
+    /// appear in it in order
+    pub fn score(&self, candidate: &str) -> Option<FuzzyMatch> {
+        // Cheap subsequence check before allocating anything: most
+        // candidates in a large tree fail here
+        let mut pending = self.chars.iter().peekable();
+        for c in candidate.chars() {
+            if pending.peek().is_some_and(|&&p| self.eq(p, c)) {
+                pending.next();
+            }
+        }
+        if pending.peek().is_some() {
+            return None;
+        }
+
+        let text: Vec<char> = candidate.chars().collect();
+
+        // Forward: where the first complete match ends
+        let mut p = 0;
+        let mut end = 0;
+        for (i, &c) in text.iter().enumerate() {
+            if self.eq(self.chars[p], c) {
+                p += 1;
+                if p == self.chars.len() {
+                    end = i;
+                    break;
+                }
+            }
+        }
+
+        // Backward from there: the latest occurrence of each character
+        let mut positions = vec![0; self.chars.len()];
+        let mut p = self.chars.len();
+        for i in (0..=end).rev() {
+            if self.eq(self.chars[p - 1], text[i]) {
+                p -= 1;
+                positions[p] = i;
+                if p == 0 {
+                    break;
+                }
+            }
+        }
+
+        // Score the window
+        let mut score = 0;
+        let mut p = 0;
+        let mut in_gap = false;
+        let mut consecutive = 0;
+        let mut first_bonus = 0;
+        for (i, &c) in text.iter().enumerate().take(end + 1).skip(positions[0]) {
+            if positions.get(p) == Some(&i) {
+                let mut bonus = boundary_bonus(i.checked_sub(1).map(|j| text[j]), c);
+                if consecutive > 0 {
+                    bonus = bonus.max(first_bonus).max(BONUS_CONSECUTIVE);
+                } else {
+                    first_bonus = bonus;
+                }
+                score += SCORE_MATCH
+                    + if p == 0 {
+                        bonus * BONUS_FIRST_MULTIPLIER
+                    } else {
+                        bonus
+                    };
+                consecutive += 1;
+                in_gap = false;
+                p += 1;
+            } else {
+                score += if in_gap {
+                    PENALTY_GAP_EXTENSION
+                } else {
+                    PENALTY_GAP_START
+                };
+                in_gap = true;
+                consecutive = 0;
+            }
+        }
+
+        let name_start = text.iter().rposition(|&c| c == '/').map_or(0, |i| i + 1);
+        if positions.first().is_some_and(|&first| first >= name_start) {
+            score += BONUS_FILE_NAME;
+        }
+
+        Some(FuzzyMatch { score, positions })
+    }
+}
+
+/// Bonus for matching `current` given the character before it
+fn boundary_bonus(prev: Option<char>, current: char) -> i32 {
+    match prev {
+        None | Some('/') => BONUS_SEPARATOR,
+        Some(p) if !p.is_alphanumeric() => BONUS_BOUNDARY,
+        Some(p) if p.is_lowercase() && current.is_uppercase() => BONUS_CAMEL,
+        Some(p) if !p.is_ascii_digit() && current.is_ascii_digit() => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+/// Rank paths against a pattern: best score first, then shorter paths,
+/// then alphabetically. Paths that do not match are dropped.
+pub fn rank_paths<'a>(pattern: &FuzzyPattern, paths: Vec<&'a Path>) -> Vec<(&'a Path, FuzzyMatch)> {
+    let mut ranked: Vec<(&Path, FuzzyMatch, usize)> = paths
+        .into_par_iter()
+        .filter_map(|path| {
+            let text = path.to_str()?;
+            let m = pattern.score(text)?;
+            Some((path, m, text.len()))
+        })
+        .collect();
+    ranked.par_sort_unstable_by(|a, b| {
+        b.1.score
+            .cmp(&a.1.score)
+            .then(a.2.cmp(&b.2))
+            .then(a.0.cmp(b.0))
+    });
+    ranked.into_iter().map(|(path, m, _)| (path, m)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(pattern: &str, candidate: &str) -> Option<FuzzyMatch> {
+        FuzzyPattern::new(pattern).unwrap().score(candidate)
+    }
+
+    #[test]
+    fn test_subsequence_required() {
+        assert!(score("main", "src/main.rs").is_some());
+        assert!(score("smr", "src/main.rs").is_some());
+        assert!(score("rsm", "src/main.rs").is_none());
+        assert!(FuzzyPattern::new("  ").is_none());
+    }
+
+    #[test]
+    fn test_positions_use_shortest_window() {
+        // The greedy first `m` is in "model/", but the shortest window for
+        // "main" is the file name
+        let m = score("main", "model/src/main.rs").unwrap();
+        assert_eq!(m.positions, vec![10, 11, 12, 13]);
+    }
+
+    #[test]
+    fn test_smart_case() {
+        assert!(score("readme", "README.md").is_some());
+        assert!(score("README", "readme.md").is_none());
+        assert!(score("Re", "src/Reader.rs").is_some());
+    }
+
+    #[test]
+    fn test_ranking() {
+        let paths = [
+            "src/domain/info.rs",
+            "examples/main_loop.rs",
+            "src/main.rs",
+            "tests/cli.rs",
+        ];
+        let pattern = FuzzyPattern::new("main").unwrap();
+        let ranked = rank_paths(&pattern, paths.iter().map(Path::new).collect());
+        let order: Vec<_> = ranked.iter().map(|(p, _)| p.to_str().unwrap()).collect();
+        assert_eq!(
+            order,
+            ["src/main.rs", "examples/main_loop.rs", "src/domain/info.rs"]
+        );
+
+        // Boundaries beat mid-word matches
+        assert!(
+            score("fb", "src/foo_bar.rs").unwrap().score
+                > score("fb", "src/xfxbx.rs").unwrap().score
+        );
+        // A match in the file name beats the same match in a directory
+        assert!(
+            score("cfg", "src/cfg.rs").unwrap().score > score("cfg", "cfg/src.rs").unwrap().score
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Levenshtein automaton for `~term` fuzzy search.
+//!
+//! A state is one row of the edit distance table between the query term and
+//! the bytes consumed so far, with cells capped at `max + 1`, plus the row
+//! and byte before it for transpositions. Stepping a byte computes the next
+//! row, so walking a sorted dictionary shares the rows of common prefixes
+//! between neighbouring tokens, and a state whose smallest cell exceeds
+//! `max` rules out every token that extends it (a transposition only
+//! reaches back past a row through a cell of that row that is no larger).
+//! Tokens are lowercased ASCII, so the automaton works on bytes.
+
+/// Largest edit distance a query may ask for: beyond this nearly every
+/// short token matches and the dictionary walk stops pruning
+pub const MAX_EDITS: u32 = 3;
+
+/// Edits allowed for `~term` without an explicit count, and for "did you
+/// mean" suggestions: one for terms up to five bytes, two for longer ones
+pub fn default_max_edits(term: &str) -> u32 {
+    if term.len() <= 5 { 1 } else { 2 }
+}
+
+/// Accepts the byte strings within `max` edits (insertions, deletions,
+/// substitutions and adjacent transpositions) of a term: the optimal string
+/// alignment distance
+#[derive(Debug, Clone)]
+pub struct LevenshteinAutomaton {
+    term: Vec<u8>,
+    max: u8,
+}
+
+/// Automaton state after some input: distances from the input to each
+/// prefix of the term, and from the input without its last byte, which is
+/// kept for transpositions
+#[derive(Debug, Clone)]
+pub struct State {
+    row: Vec<u8>,
+    before: Vec<u8>,
+    last: Option<u8>,
+}
+
+impl LevenshteinAutomaton {
+    pub fn new(term: &str, max_edits: u32) -> Self {
+        Self {
+            term: term.as_bytes().to_ascii_lowercase(),
+            max: max_edits.min(MAX_EDITS) as u8,
+        }
+    }
+
+    /// State before any input: `i` deletions reach the term's `i`-byte prefix
+    pub fn start(&self) -> State {
+        State {
+            row: (0..=self.term.len())
+                .map(|i| i.min(self.max as usize + 1) as u8)
+                .collect(),
+            before: Vec::new(),
+            last: None,
+        }
+    }
+
+    /// State after consuming one more byte
+    pub fn step(&self, state: &State, byte: u8) -> State {
+        let prev = &state.row;
+        let mut next = Vec::with_capacity(prev.len());
+        next.push((prev[0] + 1).min(self.max + 1));
+        for (i, &t) in self.term.iter().enumerate() {
+            let cost = u8::from(t != byte);
+            let mut d = (prev[i] + cost).min(prev[i + 1] + 1).min(next[i] + 1);
+            // The last two input bytes are the term's bytes i-1 and i swapped
+            if i > 0 && state.last == Some(t) && self.term[i - 1] == byte {
+                d = d.min(state.before[i - 1] + 1);
+            }
+            next.push(d.min(self.max + 1));
+        }
+        State {
+            row: next,
+            before: prev.clone(),
+            last: Some(byte),
+        }
+    }
+
+    /// Edit distance of the input so far, if it is accepted
+    pub fn distance(&self, state: &State) -> Option<u32> {
+        let d = *state.row.last()?;
+        (d <= self.max).then_some(d as u32)
+    }
+
+    /// Whether some continuation of the input can still be accepted
+    pub fn can_match(&self, state: &State) -> bool {
+        state.row.iter().any(|&d| d <= self.max)
+    }
+
+    /// Edit distance of a whole byte string (ASCII-lowercased first), if it
+    /// is accepted
+    pub fn eval(&self, input: &[u8]) -> Option<u32> {
+        let mut state = self.start();
+        for &b in input {
+            state = self.step(&state, b.to_ascii_lowercase());
+            if !self.can_match(&state) {
+                return None;
+            }
+        }
+        self.distance(&state)
+    }
+}
+
+/// Run the automaton over a sorted list of tokens, returning the index and
+/// edit distance of each accepted one. Rows are kept per prefix byte and
+/// reused for the prefix a token shares with the previous one; when a
+/// prefix cannot lead to a match, every following token that starts with it
+/// is skipped.
+pub fn matching_sorted<'a>(
+    automaton: &LevenshteinAutomaton,
+    len: usize,
+    token: impl Fn(usize) -> &'a str,
+) -> Vec<(usize, u32)> {
+    let mut matches = Vec::new();
+    // rows[i] is the state after the first i bytes of `prev`
+    let mut rows = vec![automaton.start()];
+    let mut prev: &[u8] = b"";
+    let mut i = 0;
+    while i < len {
+        let bytes = token(i).as_bytes();
+        let shared = prev
+            .iter()
+            .zip(bytes)
+            .take_while(|(a, b)| a == b)
+            .count()
+            .min(rows.len() - 1);
+        rows.truncate(shared + 1);
+        prev = bytes;
+
+        let mut dead_prefix = None;
+        for (depth, &b) in bytes.iter().enumerate().skip(shared) {
+            let next = automaton.step(&rows[depth], b);
+            if !automaton.can_match(&next) {
+                dead_prefix = Some(&bytes[..=depth]);
+                break;
+            }
+            rows.push(next);
+        }
+
+        match dead_prefix {
+            Some(prefix) => {
+                // Sorted, so the tokens sharing the prefix follow this one
+                i += 1;
+                while i < len && token(i).as_bytes().starts_with(prefix) {
+                    i += 1;
+                }
+            }
+            None => {
+                if let Some(d) = automaton.distance(&rows[bytes.len()]) {
+                    matches.push((i, d));
+                }
+                i += 1;
+            }
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval() {
+        let a = LevenshteinAutomaton::new("receive", 2);
+        assert_eq!(a.eval(b"receive"), Some(0));
+        assert_eq!(a.eval(b"Recieve"), Some(1)); // one transposition
+        assert_eq!(a.eval(b"erceive"), Some(1));
+        assert_eq!(a.eval(b"receiev"), Some(1));
+        assert_eq!(a.eval(b"ecreive"), Some(2));
+        assert_eq!(a.eval(b"receiver"), Some(1));
+        assert_eq!(a.eval(b"recv"), None);
+        assert_eq!(
+            LevenshteinAutomaton::new("kitten", 3).eval(b"sitting"),
+            Some(3)
+        );
+        assert_eq!(
+            LevenshteinAutomaton::new("kitten", 2).eval(b"sitting"),
+            None
+        );
+        assert_eq!(LevenshteinAutomaton::new("ab", 9).eval(b"abcdef"), None);
+        // Optimal string alignment: a transposed pair is not edited again
+        assert_eq!(LevenshteinAutomaton::new("ca", 3).eval(b"abc"), Some(3));
+        assert_eq!(
+            LevenshteinAutomaton::new("handler", 1).eval(b"handle"),
+            Some(1)
+        );
+        assert_eq!(LevenshteinAutomaton::new("ab", 2).eval(b""), Some(2));
+    }
+
+    #[test]
+    fn test_matching_sorted() {
+        let tokens = [
+            "handle", "handled", "handler", "handlers", "hanger", "zebra",
+        ];
+        let a = LevenshteinAutomaton::new("handler", 1);
+        let found = matching_sorted(&a, tokens.len(), |i| tokens[i]);
+        assert_eq!(found, [(0, 1), (1, 1), (2, 0), (3, 1)]);
+
+        // Same result as evaluating every token
+        for term in ["hand", "zebr", "handlrs", "hnadler", "x"] {
+            let a = LevenshteinAutomaton::new(term, 2);
+            let expected: Vec<_> = tokens
+                .iter()
+                .enumerate()
+                .filter_map(|(i, t)| Some((i, a.eval(t.as_bytes())?)))
+                .collect();
+            assert_eq!(matching_sorted(&a, tokens.len(), |i| tokens[i]), expected);
+        }
+    }
+}
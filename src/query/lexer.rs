@@ -0,0 +1,613 @@
+//! Code, comment and string literal classification for `in:` scoping.
+//!
+//! A single forward pass over a file splits it into byte ranges of code,
+//! comments and string literals, using each language's comment markers and
+//! quote rules. `in:code`, `in:comment` and `in:string` then verify a query
+//! against a [masked](mask) copy of the file in which every byte outside the
+//! wanted regions is blanked, so matches keep their line numbers and byte
+//! offsets.
+//!
+//! This is a lexer, not a parser: heredocs, regex literals, C++ raw strings
+//! and string interpolation are not understood, and languages without a
+//! lexer are treated as code throughout.
+
+use crate::index::types::Language;
+use crate::query::parser::FieldFilter;
+use std::fmt;
+
+/// What a byte of source belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Code,
+    Comment,
+    String,
+}
+
+impl Region {
+    const ALL: [Region; 3] = [Region::Code, Region::Comment, Region::String];
+
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "code" => Some(Region::Code),
+            "comment" | "comments" => Some(Region::Comment),
+            "string" | "strings" | "str" => Some(Region::String),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Region::Code => "code",
+            Region::Comment => "comment",
+            Region::String => "string",
+        }
+    }
+
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// The regions an `in:` filter lets matches come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionSet(u8);
+
+impl RegionSet {
+    const EVERYWHERE: u8 = 0b111;
+
+    /// Code alone, as for `in:code`
+    pub const CODE: Self = Self(Region::Code.bit());
+
+    /// Regions allowed by `in:` includes (all when there are none) minus
+    /// `-in:` excludes; None when that is every region. Unknown region
+    /// names are ignored.
+    pub fn from_filter(filter: &FieldFilter) -> Option<Self> {
+        let names = |values: &[String]| {
+            values
+                .iter()
+                .filter_map(|v| Region::parse(v))
+                .fold(0, |bits, r| bits | r.bit())
+        };
+        let include = match names(&filter.include) {
+            0 => Self::EVERYWHERE,
+            bits => bits,
+        };
+        let bits = include & !names(&filter.exclude);
+        (bits != Self::EVERYWHERE).then_some(Self(bits))
+    }
+
+    pub fn contains(self, region: Region) -> bool {
+        self.0 & region.bit() != 0
+    }
+}
+
+impl fmt::Display for RegionSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = Region::ALL
+            .into_iter()
+            .filter(|&r| self.contains(r))
+            .map(Region::name)
+            .collect();
+        if names.is_empty() {
+            f.write_str("nothing")
+        } else {
+            f.write_str(&names.join(","))
+        }
+    }
+}
+
+/// A string literal delimiter
+struct Quote {
+    open: &'static str,
+    close: &'static str,
+    /// Backslash escapes the next byte
+    escapes: bool,
+    /// May span lines; otherwise an unterminated literal ends at the newline
+    multiline: bool,
+}
+
+const fn quote(delimiter: &'static str, escapes: bool, multiline: bool) -> Quote {
+    Quote {
+        open: delimiter,
+        close: delimiter,
+        escapes,
+        multiline,
+    }
+}
+
+const DOUBLE: Quote = quote("\"", true, false);
+const DOUBLE_MULTILINE: Quote = quote("\"", true, true);
+const SINGLE: Quote = quote("'", true, false);
+const SINGLE_MULTILINE: Quote = quote("'", true, true);
+/// Shell and SQL: no escapes inside single quotes
+const SINGLE_RAW: Quote = quote("'", false, true);
+const TRIPLE_DOUBLE: Quote = quote("\"\"\"", true, true);
+const TRIPLE_SINGLE: Quote = quote("'''", true, true);
+const BACKTICK: Quote = quote("`", true, true);
+const BACKTICK_RAW: Quote = quote("`", false, true);
+
+/// Lexical rules of a language. Longer delimiters come first where one is
+/// a prefix of another (`"""` before `"`).
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// Block comments nest (Rust, Swift, Kotlin, Scala)
+    nested_comments: bool,
+    /// Line comments start only at the beginning of a word (`#` in shell,
+    /// where `$#` and `a#b` are not comments)
+    comment_at_word_start: bool,
+    quotes: &'static [Quote],
+    /// `'x'` and `'\n'` are character literals; any other `'` (a Rust
+    /// lifetime, a C++14 digit separator) is code
+    char_literals: bool,
+    /// Rust raw strings: `r"..."`, `r#"..."#`, `br"..."`
+    raw_strings: bool,
+}
+
+const C_FAMILY: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_comments: false,
+    comment_at_word_start: false,
+    quotes: &[DOUBLE],
+    char_literals: true,
+    raw_strings: false,
+};
+
+const HASH_COMMENTS: Syntax = Syntax {
+    line_comments: &["#"],
+    block_comment: None,
+    nested_comments: false,
+    comment_at_word_start: false,
+    quotes: &[DOUBLE_MULTILINE, SINGLE_MULTILINE],
+    char_literals: false,
+    raw_strings: false,
+};
+
+fn syntax(language: Language) -> Option<Syntax> {
+    Some(match language {
+        Language::C | Language::Cpp | Language::CSharp => C_FAMILY,
+        Language::Java => Syntax {
+            quotes: &[TRIPLE_DOUBLE, DOUBLE],
+            ..C_FAMILY
+        },
+        Language::Kotlin | Language::Scala | Language::Swift => Syntax {
+            nested_comments: true,
+            quotes: &[TRIPLE_DOUBLE, DOUBLE],
+            char_literals: language != Language::Swift,
+            ..C_FAMILY
+        },
+        Language::Rust => Syntax {
+            nested_comments: true,
+            quotes: &[DOUBLE_MULTILINE],
+            raw_strings: true,
+            ..C_FAMILY
+        },
+        Language::Go => Syntax {
+            quotes: &[DOUBLE, BACKTICK_RAW],
+            ..C_FAMILY
+        },
+        Language::JavaScript | Language::TypeScript => Syntax {
+            quotes: &[DOUBLE, SINGLE, BACKTICK],
+            char_literals: false,
+            ..C_FAMILY
+        },
+        Language::Zig => Syntax {
+            block_comment: None,
+            ..C_FAMILY
+        },
+        Language::Php => Syntax {
+            line_comments: &["//", "#"],
+            quotes: &[DOUBLE_MULTILINE, SINGLE_MULTILINE],
+            char_literals: false,
+            ..C_FAMILY
+        },
+        Language::Python => Syntax {
+            quotes: &[TRIPLE_DOUBLE, TRIPLE_SINGLE, DOUBLE, SINGLE],
+            ..HASH_COMMENTS
+        },
+        Language::Shell => Syntax {
+            comment_at_word_start: true,
+            quotes: &[DOUBLE_MULTILINE, SINGLE_RAW],
+            ..HASH_COMMENTS
+        },
+        Language::Ruby | Language::Perl | Language::R => HASH_COMMENTS,
+        Language::Nim => Syntax {
+            block_comment: Some(("#[", "]#")),
+            nested_comments: true,
+            quotes: &[TRIPLE_DOUBLE, DOUBLE],
+            char_literals: true,
+            ..HASH_COMMENTS
+        },
+        Language::Elixir => Syntax {
+            quotes: &[TRIPLE_DOUBLE, DOUBLE_MULTILINE, SINGLE_MULTILINE],
+            ..HASH_COMMENTS
+        },
+        Language::Lua => Syntax {
+            line_comments: &["--"],
+            block_comment: Some(("--[[", "]]")),
+            quotes: &[DOUBLE, SINGLE],
+            char_literals: false,
+            ..C_FAMILY
+        },
+        Language::Sql => Syntax {
+            line_comments: &["--"],
+            quotes: &[SINGLE_RAW, DOUBLE],
+            char_literals: false,
+            ..C_FAMILY
+        },
+        _ => return None,
+    })
+}
+
+/// Comment and string literal ranges of `content`, in order, as
+/// (start, end, region) byte ranges; everything between them is code.
+/// Every boundary falls on an ASCII delimiter or a newline.
+pub fn classify(language: Language, content: &str) -> Vec<(usize, usize, Region)> {
+    let Some(syntax) = syntax(language) else {
+        return Vec::new();
+    };
+    let bytes = content.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(end) = syntax.comment_end(bytes, i) {
+            spans.push((i, end, Region::Comment));
+            i = end;
+        } else if let Some(end) = syntax.string_end(bytes, i) {
+            spans.push((i, end, Region::String));
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    spans
+}
+
+/// `content` with every byte outside `regions` blanked to a space (line
+/// breaks are kept, so line numbers and byte offsets are unchanged)
+pub fn mask(language: Language, content: &str, regions: RegionSet) -> String {
+    let mut bytes = content.as_bytes().to_vec();
+    let mut blank = |start: usize, end: usize, region: Region| {
+        if !regions.contains(region) {
+            for b in &mut bytes[start..end] {
+                if *b != b'\n' && *b != b'\r' {
+                    *b = b' ';
+                }
+            }
+        }
+    };
+    let mut code_start = 0;
+    for (start, end, region) in classify(language, content) {
+        blank(code_start, start, Region::Code);
+        blank(start, end, region);
+        code_start = end;
+    }
+    blank(code_start, content.len(), Region::Code);
+    // Ranges start and end on ASCII bytes and are blanked whole, so no
+    // multi-byte character is ever cut
+    String::from_utf8(bytes).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+impl Syntax {
+    /// End of a comment starting at `i`
+    fn comment_end(&self, bytes: &[u8], i: usize) -> Option<usize> {
+        let rest = &bytes[i..];
+        if let Some((open, close)) = self.block_comment
+            && rest.starts_with(open.as_bytes())
+        {
+            return Some(self.block_comment_end(bytes, i + open.len(), open, close));
+        }
+        let word_start = !self.comment_at_word_start
+            || i == 0
+            || bytes[i - 1].is_ascii_whitespace()
+            || matches!(bytes[i - 1], b';' | b'(' | b'|' | b'&');
+        if word_start
+            && self
+                .line_comments
+                .iter()
+                .any(|marker| rest.starts_with(marker.as_bytes()))
+        {
+            return Some(line_end(bytes, i));
+        }
+        None
+    }
+
+    fn block_comment_end(&self, bytes: &[u8], mut i: usize, open: &str, close: &str) -> usize {
+        let mut depth = 1;
+        while i < bytes.len() {
+            let rest = &bytes[i..];
+            if rest.starts_with(close.as_bytes()) {
+                depth -= 1;
+                i += close.len();
+                if depth == 0 {
+                    return i;
+                }
+            } else if self.nested_comments && rest.starts_with(open.as_bytes()) {
+                depth += 1;
+                i += open.len();
+            } else {
+                i += 1;
+            }
+        }
+        bytes.len()
+    }
+
+    /// End of a string or character literal starting at `i`
+    fn string_end(&self, bytes: &[u8], i: usize) -> Option<usize> {
+        if self.raw_strings
+            && let Some(end) = raw_string_end(bytes, i)
+        {
+            return Some(end);
+        }
+        if self.char_literals && bytes[i] == b'\'' {
+            return char_literal_end(bytes, i);
+        }
+        let rest = &bytes[i..];
+        let quote = self
+            .quotes
+            .iter()
+            .find(|q| rest.starts_with(q.open.as_bytes()))?;
+        let mut j = i + quote.open.len();
+        while j < bytes.len() {
+            if quote.escapes && bytes[j] == b'\\' {
+                j += 2;
+            } else if bytes[j..].starts_with(quote.close.as_bytes()) {
+                return Some(j + quote.close.len());
+            } else if bytes[j] == b'\n' && !quote.multiline {
+                return Some(j);
+            } else {
+                j += 1;
+            }
+        }
+        Some(bytes.len())
+    }
+}
+
+/// Index of the newline ending the line at `i` (or the end of the input)
+fn line_end(bytes: &[u8], i: usize) -> usize {
+    memchr::memchr(b'\n', &bytes[i..]).map_or(bytes.len(), |n| i + n)
+}
+
+/// End of `'x'` or `'\n'` (`'\u{1F600}'`) at `i`, None for a lone `'`
+fn char_literal_end(bytes: &[u8], i: usize) -> Option<usize> {
+    let body = bytes.get(i + 1..)?;
+    let len = if body.first() == Some(&b'\\') {
+        // Escapes are short; stop at the first quote after the backslash
+        // and the escaped byte
+        let close = body.iter().skip(2).take(10).position(|&b| b == b'\'')?;
+        close + 2
+    } else {
+        // One character, up to four UTF-8 bytes
+        let first = *body.first()?;
+        if first == b'\'' || first == b'\n' {
+            return None;
+        }
+        let width = match first {
+            0xf0.. => 4,
+            0xe0.. => 3,
+            0xc0.. => 2,
+            _ => 1,
+        };
+        (body.get(width) == Some(&b'\'')).then_some(width)?
+    };
+    Some(i + 1 + len + 1)
+}
+
+/// End of a Rust raw string (`r"..."`, `r#"..."#`, `br"..."`) at `i`
+fn raw_string_end(bytes: &[u8], i: usize) -> Option<usize> {
+    let prefix = if bytes[i..].starts_with(b"br") {
+        2
+    } else if bytes[i] == b'r' {
+        1
+    } else {
+        return None;
+    };
+    // Not the tail of an identifier such as `bar"` or `for`
+    if i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_') {
+        return None;
+    }
+    let hashes = bytes[i + prefix..]
+        .iter()
+        .take_while(|&&b| b == b'#')
+        .count();
+    let body = i + prefix + hashes;
+    if bytes.get(body) != Some(&b'"') {
+        return None;
+    }
+    let mut j = body + 1;
+    while j < bytes.len() {
+        let closing = bytes.get(j + 1..j + 1 + hashes);
+        if bytes[j] == b'"' && closing.is_some_and(|h| h.iter().all(|&b| b == b'#')) {
+            return Some(j + 1 + hashes);
+        }
+        j += 1;
+    }
+    Some(bytes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text of each comment and string, in order
+    fn regions(language: Language, source: &str) -> Vec<(Region, &str)> {
+        classify(language, source)
+            .into_iter()
+            .map(|(start, end, region)| (region, &source[start..end]))
+            .collect()
+    }
+
+    fn only(regions: &[&str]) -> RegionSet {
+        RegionSet::from_filter(&FieldFilter {
+            include: regions.iter().map(|r| r.to_string()).collect(),
+            exclude: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_c_family() {
+        let source =
+            "int x = 1; // count\n/* a \"block\"\n */ char *s = \"a // b\"; char q = '\\'';\n";
+        assert_eq!(
+            regions(Language::C, source),
+            [
+                (Region::Comment, "// count"),
+                (Region::Comment, "/* a \"block\"\n */"),
+                (Region::String, "\"a // b\""),
+                (Region::String, "'\\''"),
+            ]
+        );
+        // C++14 digit separators are not character literals
+        assert!(regions(Language::Cpp, "int n = 1'000'000;").is_empty());
+    }
+
+    #[test]
+    fn test_rust() {
+        let source = "fn f<'a>(s: &'a str) -> char { /* outer /* inner */ still */ 'x' }\n\
+                      let r = r#\"raw \"quoted\" text\"#; let s = \"multi\nline\"; // done\n";
+        assert_eq!(
+            regions(Language::Rust, source),
+            [
+                (Region::Comment, "/* outer /* inner */ still */"),
+                (Region::String, "'x'"),
+                (Region::String, "r#\"raw \"quoted\" text\"#"),
+                (Region::String, "\"multi\nline\""),
+                (Region::Comment, "// done"),
+            ]
+        );
+        // `for"` and `bar"`: an r ending an identifier starts no raw string
+        assert_eq!(
+            regions(Language::Rust, "bar\"x\""),
+            [(Region::String, "\"x\"")]
+        );
+    }
+
+    #[test]
+    fn test_python() {
+        let source = "def f():\n    \"\"\"Doc with 'quotes'\n    # not a comment\"\"\"\n    return 'a#b'  # real\n";
+        assert_eq!(
+            regions(Language::Python, source),
+            [
+                (
+                    Region::String,
+                    "\"\"\"Doc with 'quotes'\n    # not a comment\"\"\""
+                ),
+                (Region::String, "'a#b'"),
+                (Region::Comment, "# real"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_go() {
+        let source = "s := `raw \\ \"text\"` // note\nr := 'x'\n";
+        assert_eq!(
+            regions(Language::Go, source),
+            [
+                (Region::String, "`raw \\ \"text\"`"),
+                (Region::Comment, "// note"),
+                (Region::String, "'x'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_javascript() {
+        let source = "const a = 'it\\'s'; const b = `line\n${x} // in template`; // c\n";
+        assert_eq!(
+            regions(Language::TypeScript, source),
+            [
+                (Region::String, "'it\\'s'"),
+                (Region::String, "`line\n${x} // in template`"),
+                (Region::Comment, "// c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_java() {
+        let source = "String s = \"\"\"\n  text block\n  \"\"\"; char c = 'c'; /** doc */\n";
+        assert_eq!(
+            regions(Language::Java, source),
+            [
+                (Region::String, "\"\"\"\n  text block\n  \"\"\""),
+                (Region::String, "'c'"),
+                (Region::Comment, "/** doc */"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nim() {
+        let source = "#[ outer #[ inner ]# ]# echo \"#\" # c\nlet c = '#'\n";
+        assert_eq!(
+            regions(Language::Nim, source),
+            [
+                (Region::Comment, "#[ outer #[ inner ]# ]#"),
+                (Region::String, "\"#\""),
+                (Region::Comment, "# c"),
+                (Region::String, "'#'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shell() {
+        let source = "echo $# 'it''s' \"a # b\" # comment\nx=a#b\n";
+        assert_eq!(
+            regions(Language::Shell, source),
+            [
+                (Region::String, "'it'"),
+                (Region::String, "'s'"),
+                (Region::String, "\"a # b\""),
+                (Region::Comment, "# comment"),
+            ]
+        );
+        // Single quotes have no escapes
+        assert_eq!(
+            regions(Language::Shell, "echo 'a\\' b"),
+            [(Region::String, "'a\\'")]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_language_is_code() {
+        assert!(regions(Language::Markdown, "# heading 'quoted'").is_empty());
+    }
+
+    #[test]
+    fn test_mask() {
+        let source = "let s = \"héllo\"; // señal\nx\n";
+        let masked = mask(Language::Rust, source, only(&["comment"]));
+        assert_eq!(masked.len(), source.len());
+        assert_eq!(masked, format!("{}// señal\n \n", " ".repeat(18)));
+        let masked = mask(Language::Rust, source, only(&["code"]));
+        assert_eq!(
+            masked,
+            format!("let s = {}; {}\nx\n", " ".repeat(8), " ".repeat(9))
+        );
+    }
+
+    #[test]
+    fn test_region_set() {
+        let set = |include: &[&str], exclude: &[&str]| {
+            RegionSet::from_filter(&FieldFilter {
+                include: include.iter().map(|s| s.to_string()).collect(),
+                exclude: exclude.iter().map(|s| s.to_string()).collect(),
+            })
+        };
+        assert_eq!(set(&[], &[]), None);
+        assert_eq!(set(&["code", "comment", "string"], &[]), None);
+        assert_eq!(set(&["docs"], &[]), None);
+        let not_comments = set(&[], &["comment"]).unwrap();
+        assert!(not_comments.contains(Region::Code) && !not_comments.contains(Region::Comment));
+        assert_eq!(not_comments.to_string(), "code,string");
+        assert_eq!(
+            set(&["comments", "strings"], &[]).unwrap().to_string(),
+            "comment,string"
+        );
+        assert_eq!(set(&["code"], &["code"]).unwrap().to_string(), "nothing");
+    }
+}
@@ -13,17 +13,37 @@
 //! - **Literals**: `foo bar` (AND), `"exact phrase"` (phrase)
 //! - **Boolean**: `foo | bar` (OR), `-foo` (NOT), `(expr)` (grouping)
 //! - **Regex**: `re:/pattern/`
-//! - **Proximity**: `near:foo,bar,5` (within 5 lines)
-//! - **Filters**: `ext:rs`, `path:src/*.rs`, `lang:rust`
+//! - **Proximity**: `near:foo,bar,5` (within 5 lines), `before:foo,bar,5`
+//!   (in order), `wnear:foo,bar,3` (within 3 words), `wbefore:`; terms may
+//!   be quoted phrases (`near:"open file",close`)
+//! - **Fuzzy**: `~recieve`, `~2:recieve` (tokens within 1 or 2 edits)
+//! - **Filters**: `ext:rs`, `path:src/*.rs`, `lang:rust`; comma lists
+//!   (`ext:rs,toml`), repeats (`path:a/** path:b/**`) and exclusions
+//!   (`-ext:js`, `-path:vendor/`, `-lang:python`)
 //! - **Size/Time**: `size:>1000`, `mtime:>2024-01-01`
+//! - **Match count**: `count:>10 unwrap()`, `count:<=1 unsafe` (matching
+//!   lines per file)
+//! - **Scoping**: `in:comment TODO`, `in:code`, `in:string`, `-in:comment`
 //! - **Ranking**: `^foo` (boost), `sort:recency`, `top:100`
 //!
+//! [`parse_fixed_strings`] bypasses the language entirely (`fxi -F`): the
+//! input is searched byte-for-byte, one pattern per line.
+//!
 //! ## Modules
 //!
 //! - [`parser`] - Tokenization and AST construction
 //! - [`planner`] - Query optimization and execution planning
 //! - [`executor`] - Parallel query execution with early termination
+//! - [`explain`] - Plan steps, candidate counts and timings (`--explain`)
+//! - [`facets`] - Match counts by directory, extension and language
 //! - [`scorer`] - Relevance scoring and ranking
+//! - [`filetypes`] - ripgrep-compatible file type table (`-t/--type`)
+//! - [`scope`] - Enclosing function/class lookup (`--function-context`)
+//! - [`fuzzy`] - fzf-style fuzzy path matching (`fxi files --fuzzy`)
+//! - [`levenshtein`] - Levenshtein automaton for `~term` typo-tolerant search
+//! - [`lexer`] - Code, comment and string classification (`in:`)
+//! - [`proximity`] - Ordered and word-distance proximity (`before:`, `wnear:`)
+//! - [`suggest`] - Token completion and "did you mean" suggestions
 //!
 //! ## Example
 //!
@@ -39,17 +59,26 @@
 //! ```
 
 pub mod executor;
+pub mod explain;
+pub mod facets;
+pub mod filetypes;
+pub mod fuzzy;
+pub mod levenshtein;
+pub mod lexer;
 pub mod parser;
 pub mod planner;
+pub mod proximity;
+pub mod scope;
 pub mod scorer;
+pub mod suggest;
 
 #[allow(unused_imports)]
 pub use executor::ContentMatchResult;
 pub use executor::QueryExecutor;
-pub use parser::parse_query;
+pub use parser::{parse_fixed_strings, parse_query};
 // Re-exports for public API
 #[allow(unused_imports)]
-pub use parser::{Query, QueryNode};
+pub use parser::{FieldFilter, Query, QueryNode};
 #[allow(unused_imports)]
 pub use planner::QueryPlan;
 #[allow(unused_imports)]
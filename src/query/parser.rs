@@ -1,3 +1,6 @@
+use crate::query::levenshtein;
+use crate::utils::tokenize_query_with_positions;
+
 /// Parsed query representation
 #[derive(Debug, Clone)]
 pub struct Query {
@@ -19,6 +22,18 @@ pub enum QueryNode {
     Regex(String),
     /// Proximity search: terms must appear within distance lines of each other
     Near { terms: Vec<String>, distance: u32 },
+    /// Whole-word proximity (`before:`, `wnear:`, `wbefore:`): every term (a
+    /// word or phrase) within `distance` lines of the first, or words with
+    /// `words`, and in query order when `ordered`
+    Proximity {
+        terms: Vec<String>,
+        distance: u32,
+        ordered: bool,
+        words: bool,
+    },
+    /// Typo-tolerant search (`~term`, `~2:term`): any token within
+    /// `max_edits` edits of the lowercased term
+    Fuzzy { term: String, max_edits: u32 },
     /// Boolean AND (all must match)
     And(Vec<QueryNode>),
     /// Boolean OR (any can match)
@@ -29,41 +44,175 @@ pub enum QueryNode {
     Empty,
 }
 
+/// Include/exclude value sets for one filter field.
+///
+/// Values within a set are alternatives: a document passes when it matches
+/// any include (or there are none) and no exclude. `ext:rs,toml` adds two
+/// includes, `-ext:js` adds an exclude, and repeating a field accumulates.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl FieldFilter {
+    /// Check if neither set has any values
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Add a comma-separated value list to the include or exclude set
+    fn add_list(&mut self, value: &str, negated: bool) {
+        let target = if negated {
+            &mut self.exclude
+        } else {
+            &mut self.include
+        };
+        target.extend(split_filter_values(value));
+    }
+}
+
+/// Split a proximity value into its terms and distance: `a,"b c",5`. The
+/// last item is the distance when numeric (default 10); terms may be quoted
+/// to hold commas or spaces. None without a comma or when no term is left.
+fn split_proximity_value(value: &str) -> Option<(Vec<String>, u32)> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for ch in value.chars() {
+        match ch {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => parts.push(std::mem::take(&mut current)),
+            _ => current.push(ch),
+        }
+    }
+    parts.push(current);
+    if parts.len() < 2 {
+        return None;
+    }
+
+    // A numeric last item is the distance, unless it is the only item
+    let distance = match parts.last().map(|p| p.trim().parse::<u32>()) {
+        Some(Ok(d)) => {
+            parts.pop();
+            d
+        }
+        _ => 10,
+    };
+    let terms: Vec<String> = parts
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    (!terms.is_empty()).then_some((terms, distance))
+}
+
+/// Split a filter value on commas, keeping commas inside glob alternations
+/// (`path:src/{a,b}/*`) intact and dropping empty items.
+fn split_filter_values(value: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, ch) in value.char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                values.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    values.push(&value[start..]);
+    values
+        .into_iter()
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 /// Query filters
 #[derive(Debug, Clone, Default)]
 pub struct QueryFilters {
-    /// Path glob pattern (path:src/*.rs)
-    pub path: Option<String>,
-    /// Filename pattern (file:foo or file:*.rs)
-    pub filename: Option<String>,
-    /// File extension filter (ext:rs)
-    pub ext: Option<String>,
-    /// Language filter (lang:rust)
-    pub lang: Option<String>,
+    /// Path glob patterns (path:src/*.rs, -path:vendor/)
+    pub path: FieldFilter,
+    /// Filename patterns (file:foo or file:*.rs)
+    pub filename: FieldFilter,
+    /// File extension filters (ext:rs,toml, -ext:js)
+    pub ext: FieldFilter,
+    /// Language filters (lang:rust, -lang:python)
+    pub lang: FieldFilter,
+    /// ripgrep-style globs from `-g/--glob` (gitignore semantics)
+    pub glob: FieldFilter,
+    /// File type names from `-t/--type` and `-T/--type-not`
+    pub file_type: FieldFilter,
     /// Size filter (size:>1000, size:<10000)
     pub size_min: Option<u64>,
     pub size_max: Option<u64>,
     /// Line range filter (line:100-200)
     pub line_start: Option<u32>,
     pub line_end: Option<u32>,
+    /// Matching lines per file, inclusive (count:>10, count:<=1, count:2-5)
+    pub count_min: Option<u32>,
+    pub count_max: Option<u32>,
     /// Modification time filter (mtime:>2024-01-01, mtime:<1704067200)
     pub mtime_min: Option<u64>,
     pub mtime_max: Option<u64>,
+    /// Files changed since a git revision (changed:origin/main,
+    /// `--changed-since`)
+    pub changed: FieldFilter,
+    /// Git work tree status (is:modified, is:untracked)
+    pub status: FieldFilter,
+    /// Files similar to an indexed file (like:src/a.rs, -like:src/a.rs)
+    pub like: FieldFilter,
+    /// Where in a file matches may be: code, comments or string literals
+    /// (in:comment, in:code,string, -in:comment). Applied by verification,
+    /// so not a document filter.
+    pub region: FieldFilter,
 }
 
 impl QueryFilters {
     /// Check if any filter is set
     pub fn has_any(&self) -> bool {
-        self.path.is_some()
-            || self.filename.is_some()
-            || self.ext.is_some()
-            || self.lang.is_some()
+        !self.path.is_empty()
+            || !self.filename.is_empty()
+            || !self.ext.is_empty()
+            || !self.lang.is_empty()
+            || !self.glob.is_empty()
+            || !self.file_type.is_empty()
             || self.size_min.is_some()
             || self.size_max.is_some()
             || self.line_start.is_some()
             || self.line_end.is_some()
+            || self.count_min.is_some()
+            || self.count_max.is_some()
             || self.mtime_min.is_some()
             || self.mtime_max.is_some()
+            || !self.like.is_empty()
+            || self.uses_git_state()
+    }
+
+    /// Whether results depend on git state outside the index (`changed:`,
+    /// `is:`), so they must not be cached across queries
+    pub fn uses_git_state(&self) -> bool {
+        !self.changed.is_empty() || !self.status.is_empty()
+    }
+
+    /// Add ripgrep `-g` globs; a leading `!` turns a glob into an exclusion
+    pub fn add_globs(&mut self, globs: &[String]) {
+        for glob in globs {
+            match glob.strip_prefix('!') {
+                Some(negated) => self.glob.exclude.push(negated.to_string()),
+                None => self.glob.include.push(glob.clone()),
+            }
+        }
+    }
+
+    /// Add ripgrep `-t` (include) and `-T` (exclude) file type names
+    pub fn add_file_types(&mut self, types: &[String], types_not: &[String]) {
+        self.file_type.include.extend(types.iter().cloned());
+        self.file_type.exclude.extend(types_not.iter().cloned());
     }
 }
 
@@ -77,6 +226,14 @@ pub struct QueryOptions {
     /// Case-insensitive matching (-i): phrases and regexes ignore case.
     /// Bare token searches are case-insensitive regardless of this flag.
     pub case_insensitive: bool,
+    /// Fixed-string mode (-F): phrases are exact substrings, so narrowing
+    /// must not rely on whole-token lookups
+    pub fixed_strings: bool,
+    /// Attach the enclosing function/class signature to content matches
+    /// (`--function-context`)
+    pub function_context: bool,
+    /// Collapse files with identical content into one result (`--dedupe`)
+    pub dedupe: bool,
 }
 
 impl Default for QueryOptions {
@@ -85,6 +242,9 @@ impl Default for QueryOptions {
             sort: SortOrder::Score,
             limit: 100,
             case_insensitive: false,
+            fixed_strings: false,
+            function_context: false,
+            dedupe: false,
         }
     }
 }
@@ -103,12 +263,41 @@ pub fn parse_query(input: &str) -> Query {
     parser.parse()
 }
 
+/// Build a query that matches `input` byte-for-byte, bypassing the query
+/// language (-F). Like `rg -F`, each line is a separate pattern and a line
+/// matches if it contains any of them.
+pub fn parse_fixed_strings(input: &str) -> Query {
+    let mut nodes: Vec<QueryNode> = input
+        .split('\n')
+        .filter(|s| !s.is_empty())
+        .map(|s| QueryNode::Phrase(s.to_string()))
+        .collect();
+
+    let root = match nodes.len() {
+        0 => QueryNode::Empty,
+        1 => nodes.pop().unwrap(),
+        _ => QueryNode::Or(nodes),
+    };
+
+    Query {
+        root,
+        filters: QueryFilters::default(),
+        options: QueryOptions {
+            fixed_strings: true,
+            ..QueryOptions::default()
+        },
+    }
+}
+
 /// Query parser
 struct QueryParser<'a> {
     input: &'a str,
     pos: usize,
     filters: QueryFilters,
     options: QueryOptions,
+    /// Set while parsing a `-field:value` term so the field lands in the
+    /// filter's exclude set instead of becoming a NOT node
+    negate_field: bool,
 }
 
 impl<'a> QueryParser<'a> {
@@ -116,6 +305,7 @@ impl<'a> QueryParser<'a> {
         Self {
             input,
             pos: 0,
+            negate_field: false,
             filters: QueryFilters::default(),
             options: QueryOptions::default(),
         }
@@ -171,6 +361,14 @@ impl<'a> QueryParser<'a> {
         self.skip_whitespace();
 
         if self.consume_char('-') {
+            // -path:, -file:, -ext:, -lang: exclude documents by metadata
+            // rather than negating a content match
+            if self.peek_filter_field() {
+                self.negate_field = true;
+                let node = self.parse_term();
+                self.negate_field = false;
+                return node;
+            }
             let inner = self.parse_primary();
             return QueryNode::Not(Box::new(inner));
         }
@@ -208,6 +406,32 @@ impl<'a> QueryParser<'a> {
             };
         }
 
+        // Fuzzy prefix ~term or ~N:term (e.g., ~recieve, ~2:recieve). A `~`
+        // not followed by a word stays part of a literal (`~/.config`)
+        if self.remaining().starts_with('~')
+            && self.remaining()[1..].starts_with(|c: char| c.is_alphanumeric())
+        {
+            self.advance();
+            let mut max_edits = None;
+            let rest = self.remaining();
+            let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+            if digits > 0 && rest[digits..].starts_with(':') {
+                max_edits = rest[..digits].parse::<u32>().ok();
+                self.pos += digits + 1;
+            }
+
+            return match self.parse_primary() {
+                QueryNode::Literal(text) => {
+                    let term = text.to_lowercase();
+                    let max_edits = max_edits
+                        .unwrap_or_else(|| levenshtein::default_max_edits(&term))
+                        .min(levenshtein::MAX_EDITS);
+                    QueryNode::Fuzzy { term, max_edits }
+                }
+                other => other, // Field filters and the like are not fuzzy
+            };
+        }
+
         self.parse_primary()
     }
 
@@ -254,8 +478,15 @@ impl<'a> QueryParser<'a> {
         self.pos += 4;
         let start = self.pos;
 
-        // Find closing /
+        // Find closing /, skipping escaped characters so `\/` stays in
+        // the pattern
         while !self.is_eof() && self.peek_char() != Some('/') {
+            if self.peek_char() == Some('\\') {
+                self.advance();
+                if self.is_eof() {
+                    break;
+                }
+            }
             self.advance();
         }
 
@@ -303,13 +534,34 @@ impl<'a> QueryParser<'a> {
         QueryNode::Literal(word)
     }
 
+    /// Check whether the input at the cursor is a `field:` prefix for one of
+    /// the include/exclude filter fields
+    fn peek_filter_field(&self) -> bool {
+        let rest = self.remaining();
+        let Some((field, _)) = rest.split_once(':') else {
+            return false;
+        };
+        matches!(
+            field.to_lowercase().as_str(),
+            "path" | "file" | "name" | "ext" | "lang" | "changed" | "is" | "like" | "in"
+        )
+    }
+
     fn parse_field(&mut self, field: &str) -> QueryNode {
         let value_start = self.pos;
+        // Proximity terms may be quoted phrases: `near:"open file",close`
+        let proximity = matches!(
+            field.to_lowercase().as_str(),
+            "near" | "before" | "wnear" | "wbefore"
+        );
+        let mut in_quotes = false;
 
         // Read value until whitespace or special char
         while !self.is_eof() {
             let ch = self.peek_char().unwrap();
-            if ch.is_whitespace() || ch == '|' || ch == ')' {
+            if proximity && ch == '"' {
+                in_quotes = !in_quotes;
+            } else if !in_quotes && (ch.is_whitespace() || ch == '|' || ch == ')') {
                 break;
             }
             self.advance();
@@ -319,19 +571,35 @@ impl<'a> QueryParser<'a> {
 
         match field.to_lowercase().as_str() {
             "path" => {
-                self.filters.path = Some(value);
+                self.filters.path.add_list(&value, self.negate_field);
                 QueryNode::Empty
             }
             "file" | "name" => {
-                self.filters.filename = Some(value);
+                self.filters.filename.add_list(&value, self.negate_field);
                 QueryNode::Empty
             }
             "ext" => {
-                self.filters.ext = Some(value);
+                self.filters.ext.add_list(&value, self.negate_field);
                 QueryNode::Empty
             }
             "lang" => {
-                self.filters.lang = Some(value);
+                self.filters.lang.add_list(&value, self.negate_field);
+                QueryNode::Empty
+            }
+            "changed" => {
+                self.filters.changed.add_list(&value, self.negate_field);
+                QueryNode::Empty
+            }
+            "is" => {
+                self.filters.status.add_list(&value, self.negate_field);
+                QueryNode::Empty
+            }
+            "like" => {
+                self.filters.like.add_list(&value, self.negate_field);
+                QueryNode::Empty
+            }
+            "in" => {
+                self.filters.region.add_list(&value, self.negate_field);
                 QueryNode::Empty
             }
             "size" => {
@@ -342,6 +610,10 @@ impl<'a> QueryParser<'a> {
                 self.parse_line_filter(&value);
                 QueryNode::Empty
             }
+            "count" => {
+                self.parse_count_filter(&value);
+                QueryNode::Empty
+            }
             "mtime" => {
                 self.parse_mtime_filter(&value);
                 QueryNode::Empty
@@ -350,6 +622,9 @@ impl<'a> QueryParser<'a> {
                 // Parse near:term1,term2,distance
                 self.parse_near_query(&value)
             }
+            "before" => self.parse_proximity_query(&value, true, false),
+            "wnear" => self.parse_proximity_query(&value, false, true),
+            "wbefore" => self.parse_proximity_query(&value, true, true),
             "sort" => {
                 self.parse_sort(&value);
                 QueryNode::Empty
@@ -389,6 +664,28 @@ impl<'a> QueryParser<'a> {
         }
     }
 
+    /// `count:>N`, `>=N`, `<N`, `<=N`, `N` or `N-M`, stored as inclusive
+    /// bounds; malformed values are ignored like other numeric filters
+    fn parse_count_filter(&mut self, value: &str) {
+        let filters = &mut self.filters;
+        if let Some(rest) = value.strip_prefix(">=") {
+            filters.count_min = rest.parse().ok();
+        } else if let Some(rest) = value.strip_prefix('>') {
+            filters.count_min = rest.parse::<u32>().ok().map(|n| n.saturating_add(1));
+        } else if let Some(rest) = value.strip_prefix("<=") {
+            filters.count_max = rest.parse().ok();
+        } else if let Some(rest) = value.strip_prefix('<') {
+            // count:<1 can never hold for a matching file
+            filters.count_max = rest.parse::<u32>().ok().map(|n| n.saturating_sub(1));
+        } else if let Some((min, max)) = value.split_once('-') {
+            filters.count_min = min.parse().ok();
+            filters.count_max = max.parse().ok();
+        } else if let Ok(n) = value.parse() {
+            filters.count_min = Some(n);
+            filters.count_max = Some(n);
+        }
+    }
+
     fn parse_sort(&mut self, value: &str) {
         self.options.sort = match value.to_lowercase().as_str() {
             "recency" | "recent" | "mtime" => SortOrder::Recency,
@@ -449,32 +746,35 @@ impl<'a> QueryParser<'a> {
 
     fn parse_near_query(&self, value: &str) -> QueryNode {
         // Parse near:term1,term2[,distance] format
-        let parts: Vec<&str> = value.split(',').collect();
-        if parts.len() >= 2 {
-            // Check if the last element is a numeric distance
-            let last = parts.last().unwrap().trim();
-            let (term_parts, distance) = if let Ok(d) = last.parse::<u32>() {
-                // Last element is a distance - use all but last as terms
-                (&parts[..parts.len() - 1], d)
-            } else {
-                // Last element is NOT a number - treat ALL elements as terms, use default distance
-                (&parts[..], 10u32)
-            };
+        let Some((terms, distance)) = split_proximity_value(value) else {
+            return QueryNode::Empty;
+        };
+        if terms.len() >= 2 {
+            QueryNode::Near { terms, distance }
+        } else {
+            // If only one term, treat as literal
+            QueryNode::Literal(terms.into_iter().next().unwrap())
+        }
+    }
 
-            let terms: Vec<String> = term_parts
-                .iter()
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-
-            if terms.len() >= 2 {
-                return QueryNode::Near { terms, distance };
-            } else if terms.len() == 1 {
-                // If only one term, treat as literal
-                return QueryNode::Literal(terms.into_iter().next().unwrap());
-            }
+    /// `before:a,b[,N]`, `wnear:a,b[,N]`, `wbefore:a,b[,N]`. Terms without
+    /// an indexable word (two or more letters or digits) can never match a
+    /// whole word and are dropped.
+    fn parse_proximity_query(&self, value: &str, ordered: bool, words: bool) -> QueryNode {
+        let Some((mut terms, distance)) = split_proximity_value(value) else {
+            return QueryNode::Empty;
+        };
+        terms.retain(|t| !tokenize_query_with_positions(t).is_empty());
+        match terms.len() {
+            0 => QueryNode::Empty,
+            1 => QueryNode::Phrase(terms.into_iter().next().unwrap()),
+            _ => QueryNode::Proximity {
+                terms,
+                distance,
+                ordered,
+                words,
+            },
         }
-        QueryNode::Empty
     }
 
     fn skip_whitespace(&mut self) {
@@ -579,7 +879,88 @@ mod tests {
     #[test]
     fn test_field_filter() {
         let q = parse_query("ext:rs foo");
-        assert_eq!(q.filters.ext, Some("rs".to_string()));
+        assert_eq!(q.filters.ext.include, vec!["rs"]);
+    }
+
+    #[test]
+    fn test_field_filter_comma_list() {
+        let q = parse_query("ext:rs,toml foo");
+        assert_eq!(q.filters.ext.include, vec!["rs", "toml"]);
+        assert!(q.filters.ext.exclude.is_empty());
+    }
+
+    #[test]
+    fn test_field_filter_comma_list_skips_empty_items() {
+        let q = parse_query("ext:rs,,toml,");
+        assert_eq!(q.filters.ext.include, vec!["rs", "toml"]);
+    }
+
+    #[test]
+    fn test_field_filter_negated() {
+        let q = parse_query("-ext:js -path:vendor/ -lang:python foo");
+        assert_eq!(q.filters.ext.exclude, vec!["js"]);
+        assert_eq!(q.filters.path.exclude, vec!["vendor/"]);
+        assert_eq!(q.filters.lang.exclude, vec!["python"]);
+        assert!(q.filters.ext.include.is_empty());
+        // Negated filters don't leave a NOT node behind
+        match &q.root {
+            QueryNode::And(nodes) => {
+                assert!(!nodes.iter().any(|n| matches!(n, QueryNode::Not(_))));
+                assert!(
+                    nodes
+                        .iter()
+                        .any(|n| matches!(n, QueryNode::Literal(s) if s == "foo"))
+                );
+            }
+            other => panic!("Expected And node, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_field_filter_negated_file() {
+        let q = parse_query("-file:*_test.go,mock_*.go");
+        assert_eq!(q.filters.filename.exclude, vec!["*_test.go", "mock_*.go"]);
+        assert!(!q.is_empty());
+    }
+
+    #[test]
+    fn test_field_filter_repeated_path() {
+        let q = parse_query("path:src/** path:tests/** -path:src/gen/** foo");
+        assert_eq!(q.filters.path.include, vec!["src/**", "tests/**"]);
+        assert_eq!(q.filters.path.exclude, vec!["src/gen/**"]);
+    }
+
+    #[test]
+    fn test_git_state_filters() {
+        let q = parse_query("changed:origin/main is:modified,untracked -is:untracked foo");
+        assert_eq!(q.filters.changed.include, vec!["origin/main"]);
+        assert_eq!(q.filters.status.include, vec!["modified", "untracked"]);
+        assert_eq!(q.filters.status.exclude, vec!["untracked"]);
+        assert!(q.filters.uses_git_state());
+        assert!(!parse_query("ext:rs foo").filters.uses_git_state());
+    }
+
+    #[test]
+    fn test_like_filter() {
+        let q = parse_query("like:src/a.rs -like:vendor/b.rs");
+        assert_eq!(q.filters.like.include, vec!["src/a.rs"]);
+        assert_eq!(q.filters.like.exclude, vec!["vendor/b.rs"]);
+        assert!(!q.is_empty());
+    }
+
+    #[test]
+    fn test_path_filter_keeps_brace_alternation() {
+        let q = parse_query("path:src/{a,b}/*.rs,docs/*");
+        assert_eq!(q.filters.path.include, vec!["src/{a,b}/*.rs", "docs/*"]);
+    }
+
+    #[test]
+    fn test_negated_non_filter_field_stays_not() {
+        // Only filter fields are absorbed; other negated terms remain NOT
+        let q = parse_query("-sort:path");
+        assert!(matches!(q.root, QueryNode::Not(_)));
+        let q = parse_query("-todo");
+        assert!(matches!(q.root, QueryNode::Not(_)));
     }
 
     #[test]
@@ -655,6 +1036,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ordered_and_word_proximity() {
+        let q = parse_query("before:open,close,5");
+        assert!(matches!(
+            q.root,
+            QueryNode::Proximity { ref terms, distance: 5, ordered: true, words: false }
+                if terms == &["open", "close"]
+        ));
+        let q = parse_query("wnear:read,buffer");
+        assert!(matches!(
+            q.root,
+            QueryNode::Proximity {
+                distance: 10,
+                ordered: false,
+                words: true,
+                ..
+            }
+        ));
+        // Quoted phrases may hold spaces and commas
+        let q = parse_query(r#"wbefore:"open file","read, write",2"#);
+        let QueryNode::Proximity { terms, ordered, .. } = &q.root else {
+            panic!("expected proximity, got {:?}", q.root);
+        };
+        assert_eq!(terms, &["open file", "read, write"]);
+        assert!(ordered);
+        // A term without a whole word to match is dropped
+        let q = parse_query("before:open,=>");
+        assert!(matches!(q.root, QueryNode::Phrase(ref s) if s == "open"));
+        // near: keeps substring semantics but accepts phrases
+        let q = parse_query(r#"near:"fn main",exit,3"#);
+        assert!(matches!(
+            q.root,
+            QueryNode::Near { ref terms, distance: 3 } if terms == &["fn main", "exit"]
+        ));
+    }
+
+    #[test]
+    fn test_fuzzy_term() {
+        let q = parse_query("~Recieve");
+        assert!(
+            matches!(q.root, QueryNode::Fuzzy { ref term, max_edits } if term == "recieve" && max_edits == 2)
+        );
+        let q = parse_query("~recv");
+        assert!(matches!(q.root, QueryNode::Fuzzy { max_edits: 1, .. }));
+        let q = parse_query("~2:recv");
+        assert!(matches!(q.root, QueryNode::Fuzzy { max_edits: 2, .. }));
+        // Capped, so the dictionary walk keeps pruning
+        let q = parse_query("~9:receive");
+        assert!(matches!(q.root, QueryNode::Fuzzy { max_edits: 3, .. }));
+        // A `~` not followed by a word is literal
+        let q = parse_query("~/.config");
+        assert!(matches!(q.root, QueryNode::Literal(ref s) if s == "~/.config"));
+    }
+
     #[test]
     fn test_line_filter_single() {
         let q = parse_query("line:100 test");
@@ -672,8 +1107,8 @@ mod tests {
     #[test]
     fn test_combined_filters() {
         let q = parse_query("ext:rs path:src mtime:>1704067200 ^important");
-        assert_eq!(q.filters.ext, Some("rs".to_string()));
-        assert_eq!(q.filters.path, Some("src".to_string()));
+        assert_eq!(q.filters.ext.include, vec!["rs"]);
+        assert_eq!(q.filters.path.include, vec!["src"]);
         assert_eq!(q.filters.mtime_min, Some(1704067200));
         // The query root contains the boosted term (filters produce Empty nodes that get filtered)
         match &q.root {
@@ -695,20 +1130,20 @@ mod tests {
     #[test]
     fn test_file_filter_exact() {
         let q = parse_query("file:main.rs");
-        assert_eq!(q.filters.filename, Some("main.rs".to_string()));
+        assert_eq!(q.filters.filename.include, vec!["main.rs"]);
         assert!(matches!(q.root, QueryNode::Empty));
     }
 
     #[test]
     fn test_file_filter_glob() {
         let q = parse_query("file:*.rs");
-        assert_eq!(q.filters.filename, Some("*.rs".to_string()));
+        assert_eq!(q.filters.filename.include, vec!["*.rs"]);
     }
 
     #[test]
     fn test_file_filter_with_search_term() {
         let q = parse_query("file:main.rs fn main");
-        assert_eq!(q.filters.filename, Some("main.rs".to_string()));
+        assert_eq!(q.filters.filename.include, vec!["main.rs"]);
         // Should have a search term in root
         assert!(!matches!(q.root, QueryNode::Empty));
     }
@@ -717,38 +1152,38 @@ mod tests {
     fn test_name_filter_alias() {
         // name: is an alias for file:
         let q = parse_query("name:test.rs");
-        assert_eq!(q.filters.filename, Some("test.rs".to_string()));
+        assert_eq!(q.filters.filename.include, vec!["test.rs"]);
     }
 
     #[test]
     fn test_path_filter_simple() {
         let q = parse_query("path:src/lib.rs");
-        assert_eq!(q.filters.path, Some("src/lib.rs".to_string()));
+        assert_eq!(q.filters.path.include, vec!["src/lib.rs"]);
     }
 
     #[test]
     fn test_path_filter_glob() {
         let q = parse_query("path:src/**/*.rs");
-        assert_eq!(q.filters.path, Some("src/**/*.rs".to_string()));
+        assert_eq!(q.filters.path.include, vec!["src/**/*.rs"]);
     }
 
     #[test]
     fn test_lang_filter() {
         let q = parse_query("lang:rust test");
-        assert_eq!(q.filters.lang, Some("rust".to_string()));
+        assert_eq!(q.filters.lang.include, vec!["rust"]);
     }
 
     #[test]
     fn test_lang_filter_aliases() {
         // Test various language aliases
         let q1 = parse_query("lang:rs");
-        assert_eq!(q1.filters.lang, Some("rs".to_string()));
+        assert_eq!(q1.filters.lang.include, vec!["rs"]);
 
         let q2 = parse_query("lang:python");
-        assert_eq!(q2.filters.lang, Some("python".to_string()));
+        assert_eq!(q2.filters.lang.include, vec!["python"]);
 
         let q3 = parse_query("lang:js");
-        assert_eq!(q3.filters.lang, Some("js".to_string()));
+        assert_eq!(q3.filters.lang.include, vec!["js"]);
     }
 
     #[test]
@@ -843,7 +1278,10 @@ mod tests {
     #[test]
     fn test_filters_has_any_with_filename() {
         let filters = QueryFilters {
-            filename: Some("test.rs".to_string()),
+            filename: FieldFilter {
+                include: vec!["test.rs".to_string()],
+                exclude: Vec::new(),
+            },
             ..Default::default()
         };
         assert!(filters.has_any());
@@ -852,12 +1290,39 @@ mod tests {
     #[test]
     fn test_filters_has_any_with_ext() {
         let filters = QueryFilters {
-            ext: Some("rs".to_string()),
+            ext: FieldFilter {
+                include: vec!["rs".to_string()],
+                exclude: Vec::new(),
+            },
+            ..Default::default()
+        };
+        assert!(filters.has_any());
+    }
+
+    #[test]
+    fn test_filters_has_any_with_exclude_only() {
+        let filters = QueryFilters {
+            path: FieldFilter {
+                include: Vec::new(),
+                exclude: vec!["vendor/".to_string()],
+            },
             ..Default::default()
         };
         assert!(filters.has_any());
     }
 
+    #[test]
+    fn test_filters_add_globs_and_types() {
+        let mut filters = QueryFilters::default();
+        filters.add_globs(&["*.rs".to_string(), "!*.min.js".to_string()]);
+        filters.add_file_types(&["rust".to_string()], &["js".to_string()]);
+        assert_eq!(filters.glob.include, vec!["*.rs"]);
+        assert_eq!(filters.glob.exclude, vec!["*.min.js"]);
+        assert_eq!(filters.file_type.include, vec!["rust"]);
+        assert_eq!(filters.file_type.exclude, vec!["js"]);
+        assert!(filters.has_any());
+    }
+
     #[test]
     fn test_filters_has_any_with_size() {
         let filters = QueryFilters {
@@ -871,11 +1336,11 @@ mod tests {
     fn test_complex_query_with_multiple_filters() {
         let q =
             parse_query("file:*.rs ext:rs lang:rust size:>100 path:src/* sort:recency top:20 test");
-        assert_eq!(q.filters.filename, Some("*.rs".to_string()));
-        assert_eq!(q.filters.ext, Some("rs".to_string()));
-        assert_eq!(q.filters.lang, Some("rust".to_string()));
+        assert_eq!(q.filters.filename.include, vec!["*.rs"]);
+        assert_eq!(q.filters.ext.include, vec!["rs"]);
+        assert_eq!(q.filters.lang.include, vec!["rust"]);
         assert_eq!(q.filters.size_min, Some(100));
-        assert_eq!(q.filters.path, Some("src/*".to_string()));
+        assert_eq!(q.filters.path.include, vec!["src/*"]);
         assert_eq!(q.options.sort, SortOrder::Recency);
         assert_eq!(q.options.limit, 20);
         assert!(!q.is_empty());
@@ -1028,6 +1493,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_regex_escaped_slash() {
+        let q = parse_query(r"re:/a\/b/ foo");
+        match &q.root {
+            QueryNode::And(nodes) => {
+                assert!(matches!(&nodes[0], QueryNode::Regex(p) if p == r"a\/b"));
+                assert!(matches!(&nodes[1], QueryNode::Literal(t) if t == "foo"));
+            }
+            _ => panic!("Expected And node, got {:?}", q.root),
+        }
+    }
+
+    #[test]
+    fn test_fixed_strings() {
+        let q = parse_fixed_strings("a | -b \"c\" re:/x/");
+        assert!(matches!(&q.root, QueryNode::Phrase(t) if t == "a | -b \"c\" re:/x/"));
+        assert!(q.options.fixed_strings);
+        assert!(!q.filters.has_any());
+
+        let q = parse_fixed_strings("ext:rs\n\n^foo");
+        match &q.root {
+            QueryNode::Or(nodes) => {
+                assert_eq!(nodes.len(), 2);
+                assert!(matches!(&nodes[0], QueryNode::Phrase(t) if t == "ext:rs"));
+                assert!(matches!(&nodes[1], QueryNode::Phrase(t) if t == "^foo"));
+            }
+            _ => panic!("Expected Or node, got {:?}", q.root),
+        }
+
+        assert!(parse_fixed_strings("").is_empty());
+    }
+
     #[test]
     fn test_regex_empty() {
         let q = parse_query("re://");
@@ -1040,7 +1537,7 @@ mod tests {
     #[test]
     fn test_regex_with_filter() {
         let q = parse_query("ext:rs re:/TODO.*fix/");
-        assert_eq!(q.filters.ext, Some("rs".to_string()));
+        assert_eq!(q.filters.ext.include, vec!["rs"]);
         // Filter produces Empty node, combined with Regex → And([Empty, Regex])
         match &q.root {
             QueryNode::Regex(pat) => assert_eq!(pat, "TODO.*fix"),
@@ -1139,6 +1636,30 @@ mod tests {
         assert_eq!(q.filters.line_end, Some(100));
     }
 
+    #[test]
+    fn test_count_filter() {
+        let bounds = |query: &str| {
+            let q = parse_query(query);
+            (q.filters.count_min, q.filters.count_max)
+        };
+        assert_eq!(bounds("count:>10 unwrap"), (Some(11), None));
+        assert_eq!(bounds("count:>=10 unwrap"), (Some(10), None));
+        assert_eq!(bounds("count:<2 unwrap"), (None, Some(1)));
+        assert_eq!(bounds("count:<=1 unwrap"), (None, Some(1)));
+        assert_eq!(bounds("unwrap count:3"), (Some(3), Some(3)));
+        assert_eq!(bounds("count:2-5 unwrap"), (Some(2), Some(5)));
+        assert_eq!(bounds("count:>many unwrap"), (None, None));
+    }
+
+    #[test]
+    fn test_region_filter() {
+        let q = parse_query("in:comment,string TODO -in:string");
+        assert_eq!(q.filters.region.include, vec!["comment", "string"]);
+        assert_eq!(q.filters.region.exclude, vec!["string"]);
+        // Scoping is applied by verification, not as a document filter
+        assert!(!q.filters.has_any());
+    }
+
     // ========================================================================
     // Size filter edge cases
     // ========================================================================
@@ -1179,8 +1700,8 @@ mod tests {
     #[test]
     fn test_only_filters_no_search_term() {
         let q = parse_query("ext:rs lang:rust");
-        assert_eq!(q.filters.ext, Some("rs".to_string()));
-        assert_eq!(q.filters.lang, Some("rust".to_string()));
+        assert_eq!(q.filters.ext.include, vec!["rs"]);
+        assert_eq!(q.filters.lang.include, vec!["rust"]);
         // Each filter returns Empty node; two Empties → And([Empty, Empty])
         // The query is NOT considered empty because filters are set
         assert!(!q.is_empty());
@@ -1,5 +1,6 @@
 use crate::index::types::Trigram;
-use crate::query::parser::{Query, QueryNode};
+use crate::query::lexer::RegionSet;
+use crate::query::parser::{FieldFilter, Query, QueryNode};
 use crate::utils::{query_trigrams, tokenize_query, tokenize_query_with_positions};
 
 /// Query execution plan
@@ -33,25 +34,47 @@ pub enum PlanStep {
     Intersect(Vec<QueryPlan>),
     /// Exclude results matching sub-plan
     Exclude(Box<QueryPlan>),
-    /// Apply document filters
-    Filter(FilterStep),
+    /// Apply document filters (boxed: the include/exclude sets make this
+    /// variant much larger than the others)
+    Filter(Box<FilterStep>),
     /// Positional phrase resolution: check token adjacency from position index
     PositionalPhrase(Vec<(String, u32)>),
+    /// Union the postings of every dictionary token within `max_edits` of
+    /// `term` (Levenshtein automaton over the sorted token dictionaries)
+    FuzzyTokens { term: String, max_edits: u32 },
+    /// Whole-word proximity resolved from the position index: each term is
+    /// a phrase of (token, offset) pairs. Line distance can't be read from
+    /// word positions, so without `words` only the order is checked here.
+    PositionalProximity {
+        terms: Vec<Vec<(String, u32)>>,
+        distance: u32,
+        ordered: bool,
+        words: bool,
+    },
 }
 
-/// Filter step for post-narrowing
+/// Filter step for post-narrowing. Each metadata field carries include and
+/// exclude sets (see [`FieldFilter`]).
 #[derive(Debug)]
 pub struct FilterStep {
-    pub path_glob: Option<String>,
-    pub filename: Option<String>,
-    pub extension: Option<String>,
-    pub language: Option<String>,
+    pub path_glob: FieldFilter,
+    pub filename: FieldFilter,
+    pub extension: FieldFilter,
+    pub language: FieldFilter,
+    pub glob: FieldFilter,
+    pub file_type: FieldFilter,
     pub size_min: Option<u64>,
     pub size_max: Option<u64>,
     pub mtime_min: Option<u64>,
     pub mtime_max: Option<u64>,
     pub line_start: Option<u32>,
     pub line_end: Option<u32>,
+    /// Matching lines per file (`count:`), checked during verification
+    pub count_min: Option<u32>,
+    pub count_max: Option<u32>,
+    pub changed: FieldFilter,
+    pub status: FieldFilter,
+    pub like: FieldFilter,
 }
 
 /// Verification step (run against candidate documents)
@@ -76,6 +99,25 @@ pub enum VerificationStep {
         terms: Vec<String>,
         distance: u32,
     },
+    /// Whole-word proximity: terms within distance lines (or words), in
+    /// order when `ordered`
+    Proximity {
+        terms: Vec<String>,
+        distance: u32,
+        ordered: bool,
+        words: bool,
+    },
+    /// A token within `max_edits` of `term` (case-insensitive)
+    Fuzzy {
+        term: String,
+        max_edits: u32,
+    },
+    /// The inner step, matched only in the code, comments or string
+    /// literals of the file (`in:`)
+    InRegion {
+        regions: RegionSet,
+        inner: Box<VerificationStep>,
+    },
     /// Compound verification
     And(Vec<VerificationStep>),
     Or(Vec<VerificationStep>),
@@ -85,7 +127,8 @@ pub enum VerificationStep {
 impl QueryPlan {
     /// Create a query plan from a parsed query
     pub fn from_query(query: &Query) -> Self {
-        let mut planner = QueryPlanner::new(query.options.case_insensitive);
+        let mut planner =
+            QueryPlanner::new(query.options.case_insensitive, query.options.fixed_strings);
         planner.plan(query)
     }
 }
@@ -96,49 +139,56 @@ struct QueryPlanner {
     /// -i: trigram narrowing is case-sensitive, so case-insensitive queries
     /// must narrow through the token/positional indexes (stored lowercased)
     case_insensitive: bool,
+    /// -F: phrases are raw substrings that may start or end mid-token, so
+    /// token and positional lookups could drop real matches
+    fixed_strings: bool,
+    /// `in:` scoping applied to every content verification
+    regions: Option<RegionSet>,
 }
 
 impl QueryPlanner {
-    fn new(case_insensitive: bool) -> Self {
+    fn new(case_insensitive: bool, fixed_strings: bool) -> Self {
         Self {
             steps: Vec::new(),
             case_insensitive,
+            fixed_strings,
+            regions: None,
         }
     }
 
     fn plan(&mut self, query: &Query) -> QueryPlan {
+        self.regions = RegionSet::from_filter(&query.filters.region);
+
         // Plan the main query
         let (narrowing_steps, verification) = self.plan_node(&query.root);
         self.steps.extend(narrowing_steps);
+        let verification = verification.map(|v| self.scoped(v));
 
         // Add filter step if we have any filters. Filters run after the
         // narrowing steps: per-document checks (glob matching, metadata
         // comparisons) are far more expensive than the index lookups above,
         // so they should only see the already-narrowed candidate set instead
         // of scanning every document in the index.
-        if query.filters.path.is_some()
-            || query.filters.filename.is_some()
-            || query.filters.ext.is_some()
-            || query.filters.lang.is_some()
-            || query.filters.size_min.is_some()
-            || query.filters.size_max.is_some()
-            || query.filters.mtime_min.is_some()
-            || query.filters.mtime_max.is_some()
-            || query.filters.line_start.is_some()
-            || query.filters.line_end.is_some()
-        {
-            self.steps.push(PlanStep::Filter(FilterStep {
+        if query.filters.has_any() {
+            self.steps.push(PlanStep::Filter(Box::new(FilterStep {
                 path_glob: query.filters.path.clone(),
                 filename: query.filters.filename.clone(),
                 extension: query.filters.ext.clone(),
                 language: query.filters.lang.clone(),
+                glob: query.filters.glob.clone(),
+                file_type: query.filters.file_type.clone(),
                 size_min: query.filters.size_min,
                 size_max: query.filters.size_max,
                 mtime_min: query.filters.mtime_min,
                 mtime_max: query.filters.mtime_max,
                 line_start: query.filters.line_start,
                 line_end: query.filters.line_end,
-            }));
+                count_min: query.filters.count_min,
+                count_max: query.filters.count_max,
+                changed: query.filters.changed.clone(),
+                status: query.filters.status.clone(),
+                like: query.filters.like.clone(),
+            })));
         }
 
         QueryPlan {
@@ -147,6 +197,17 @@ impl QueryPlanner {
         }
     }
 
+    /// Wrap a verification in the query's `in:` scoping, if any
+    fn scoped(&self, verification: VerificationStep) -> VerificationStep {
+        match self.regions {
+            Some(regions) => VerificationStep::InRegion {
+                regions,
+                inner: Box::new(verification),
+            },
+            None => verification,
+        }
+    }
+
     #[allow(clippy::only_used_in_recursion)]
     fn plan_node(&mut self, node: &QueryNode) -> (Vec<PlanStep>, Option<VerificationStep>) {
         match node {
@@ -315,6 +376,82 @@ impl QueryPlanner {
                 )
             }
 
+            QueryNode::Proximity {
+                terms,
+                distance,
+                ordered,
+                words,
+            } => {
+                // Terms match whole words, so the lowercased token and
+                // position indexes are exact for any case and need no trigrams
+                let phrases: Vec<Vec<(String, u32)>> = terms
+                    .iter()
+                    .map(|t| tokenize_query_with_positions(t))
+                    .collect();
+                let mut tokens: Vec<String> = phrases
+                    .iter()
+                    .flatten()
+                    .map(|(token, _)| token.clone())
+                    .collect();
+                tokens.sort_unstable();
+                tokens.dedup();
+
+                let mut steps: Vec<PlanStep> =
+                    tokens.into_iter().map(PlanStep::TokenLookup).collect();
+                steps.push(PlanStep::PositionalProximity {
+                    terms: phrases,
+                    distance: *distance,
+                    ordered: *ordered,
+                    words: *words,
+                });
+
+                (
+                    steps,
+                    Some(VerificationStep::Proximity {
+                        terms: terms.clone(),
+                        distance: *distance,
+                        ordered: *ordered,
+                        words: *words,
+                    }),
+                )
+            }
+
+            QueryNode::Fuzzy { term, max_edits } => (
+                // Matches are whole tokens, so the token dictionary lists
+                // every candidate and no trigram narrowing is needed
+                vec![PlanStep::FuzzyTokens {
+                    term: term.clone(),
+                    max_edits: *max_edits,
+                }],
+                Some(VerificationStep::Fuzzy {
+                    term: term.clone(),
+                    max_edits: *max_edits,
+                }),
+            ),
+
+            QueryNode::Phrase(text) if self.fixed_strings => {
+                // Only trigrams are substring-safe. Under -i they are not
+                // case-safe either, so verify across all docs instead
+                let trigrams = if self.case_insensitive {
+                    Vec::new()
+                } else {
+                    query_trigrams(text)
+                };
+                let steps = if trigrams.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![PlanStep::TrigramIntersect(trigrams)]
+                };
+
+                (
+                    steps,
+                    Some(VerificationStep::Phrase {
+                        text: text.clone(),
+                        case_insensitive: self.case_insensitive,
+                    }),
+                )
+            }
+
             QueryNode::Phrase(text) => {
                 let phrase_tokens = tokenize_query_with_positions(text);
 
@@ -448,9 +585,11 @@ impl QueryPlanner {
 
             QueryNode::Not(inner) => {
                 let (steps, verification) = self.plan_node(inner);
+                // The exclusion is confirmed by its own verification, so it
+                // needs the same scoping as the whole query
                 let exclude_plan = QueryPlan {
                     steps,
-                    verification: verification.clone(),
+                    verification: verification.clone().map(|v| self.scoped(v)),
                 };
 
                 let verify = verification.map(|v| VerificationStep::Not(Box::new(v)));
@@ -619,6 +758,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fixed_string_phrase_narrows_by_trigrams_only() {
+        // "bj->meth" has no whole tokens in common with `obj->method`, so
+        // positional narrowing would drop the match
+        let query = crate::query::parser::parse_fixed_strings("bj->meth");
+        let plan = QueryPlan::from_query(&query);
+        assert!(
+            plan.steps
+                .iter()
+                .all(|s| matches!(s, PlanStep::TrigramIntersect(_)))
+        );
+        assert!(!plan.steps.is_empty());
+
+        let mut query = crate::query::parser::parse_fixed_strings("bj->meth");
+        query.options.case_insensitive = true;
+        assert!(QueryPlan::from_query(&query).steps.is_empty());
+    }
+
     #[test]
     fn test_ci_regex_gets_case_flag() {
         let plan = plan_ci("re:/spin_lock\\(&\\w+/");
@@ -0,0 +1,265 @@
+//! Ordered and word-distance proximity (`before:`, `wnear:`, `wbefore:`).
+//!
+//! Terms are words or phrases of words, matched whole and case-insensitively
+//! the way the token index sees them, so the same check runs against the
+//! position postings (narrowing, no file read) and against file content
+//! (verification). An occurrence of a term spans the word positions of its
+//! first and last word; every other term must occur within the distance of
+//! an occurrence of the first term (the anchor), in query order when the
+//! proximity is ordered.
+
+use crate::utils::{tokenize_query_with_positions, word_spans};
+
+/// One occurrence of a term
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Occurrence {
+    /// Word position of the first word
+    pub start: u32,
+    /// Word position of the last word
+    pub end: u32,
+    /// 1-based line of the first word (0 when resolved from the index,
+    /// which does not map positions to lines)
+    pub line: u32,
+}
+
+impl Occurrence {
+    /// Words from the end of the earlier occurrence to the start of the
+    /// later one: 1 for adjacent words, 0 when they overlap
+    fn word_gap(&self, other: &Occurrence) -> u32 {
+        // At most one side is positive; both are 0 on overlap
+        (other.start.saturating_sub(self.end)).max(self.start.saturating_sub(other.end))
+    }
+}
+
+/// How far apart terms may be
+#[derive(Debug, Clone, Copy)]
+pub enum Within {
+    /// At most this many lines from the anchor's line
+    Lines(u32),
+    /// At most this many words from the anchor (1 = adjacent)
+    Words(u32),
+    /// Any distance: order alone (index-side check of line proximity)
+    Anywhere,
+}
+
+impl Within {
+    /// `distance` words when `words`, else lines
+    pub fn new(distance: u32, words: bool) -> Self {
+        if words {
+            Within::Words(distance)
+        } else {
+            Within::Lines(distance)
+        }
+    }
+
+    fn allows(self, anchor: &Occurrence, other: &Occurrence) -> bool {
+        match self {
+            Within::Lines(n) => anchor.line.abs_diff(other.line) <= n,
+            Within::Words(n) => anchor.word_gap(other) <= n,
+            Within::Anywhere => true,
+        }
+    }
+}
+
+/// Indexes of the first term's occurrences that have every other term
+/// within reach. Each term's occurrences must be sorted by position.
+pub fn anchors(terms: &[&[Occurrence]], within: Within, ordered: bool) -> Vec<usize> {
+    let Some((first, rest)) = terms.split_first() else {
+        return Vec::new();
+    };
+    (0..first.len())
+        .filter(|&i| {
+            let anchor = &first[i];
+            if ordered {
+                chain_from(anchor, rest, within)
+            } else {
+                rest.iter().all(|occs| nearest_within(anchor, occs, within))
+            }
+        })
+        .collect()
+}
+
+/// Whether the terms occur after the anchor in order: taking each term's
+/// earliest occurrence past the previous one keeps every step as close to
+/// the anchor as possible, so a greedy walk suffices
+fn chain_from(anchor: &Occurrence, rest: &[&[Occurrence]], within: Within) -> bool {
+    let mut prev = *anchor;
+    for occs in rest {
+        let i = occs.partition_point(|o| o.start <= prev.end);
+        match occs.get(i) {
+            Some(next) if within.allows(anchor, next) => prev = *next,
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Whether an occurrence lies within reach of the anchor, either side. All
+/// occurrences of one term span the same number of words, so the closest
+/// ones are the neighbours of the anchor's position.
+fn nearest_within(anchor: &Occurrence, occs: &[Occurrence], within: Within) -> bool {
+    let i = occs.partition_point(|o| o.start < anchor.start);
+    let before = i.checked_sub(1).and_then(|j| occs.get(j));
+    [before, occs.get(i)]
+        .into_iter()
+        .flatten()
+        .any(|o| within.allows(anchor, o))
+}
+
+/// Occurrences of a phrase given as (token, word offset) pairs, with
+/// `word_at(p)` telling whether the lowercased word at position `p` is the
+/// token. `starts` lists the positions of the first token.
+pub fn phrase_occurrences(
+    phrase: &[(String, u32)],
+    starts: impl Iterator<Item = u32>,
+    word_at: impl Fn(u32, &str) -> bool,
+) -> Vec<Occurrence> {
+    let Some(&(_, base)) = phrase.first() else {
+        return Vec::new();
+    };
+    let span = phrase.last().map_or(0, |&(_, offset)| offset - base);
+    starts
+        .filter(|&start| {
+            phrase[1..]
+                .iter()
+                .all(|(token, offset)| word_at(start + offset - base, token))
+        })
+        .map(|start| Occurrence {
+            start,
+            end: start + span,
+            line: 0,
+        })
+        .collect()
+}
+
+/// The words of a file, numbered the way the position index numbers them
+pub struct ContentWords<'a> {
+    lines: Vec<&'a str>,
+    /// (line index, byte start, byte end) per word position
+    words: Vec<(usize, usize, usize)>,
+}
+
+impl<'a> ContentWords<'a> {
+    pub fn new(content: &'a str) -> Self {
+        let lines: Vec<&str> = content.lines().collect();
+        let mut words = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            words.extend(word_spans(line).into_iter().map(|(s, e)| (i, s, e)));
+        }
+        Self { lines, words }
+    }
+
+    fn word_is(&self, pos: u32, token: &str) -> bool {
+        self.words
+            .get(pos as usize)
+            .is_some_and(|&(line, s, e)| self.lines[line][s..e].eq_ignore_ascii_case(token))
+    }
+
+    /// Occurrences of a term (a word or phrase), in order
+    pub fn occurrences(&self, term: &str) -> Vec<Occurrence> {
+        let phrase = tokenize_query_with_positions(term);
+        let Some((first, _)) = phrase.first() else {
+            return Vec::new();
+        };
+        let starts = (0..self.words.len() as u32).filter(|&p| self.word_is(p, first));
+        let mut occs = phrase_occurrences(&phrase, starts, |p, t| self.word_is(p, t));
+        for occ in &mut occs {
+            occ.line = self.words[occ.start as usize].0 as u32 + 1;
+        }
+        occs
+    }
+
+    /// One match per line holding an anchor, highlighting the anchor (up to
+    /// the end of its line when a phrase runs onto the next)
+    pub fn matches(
+        &self,
+        terms: &[String],
+        within: Within,
+        ordered: bool,
+    ) -> Vec<(u32, String, usize, usize)> {
+        let occurrences: Vec<Vec<Occurrence>> = terms.iter().map(|t| self.occurrences(t)).collect();
+        if occurrences.iter().any(Vec::is_empty) {
+            return Vec::new();
+        }
+        let refs: Vec<&[Occurrence]> = occurrences.iter().map(Vec::as_slice).collect();
+
+        let mut matches: Vec<(u32, String, usize, usize)> = Vec::new();
+        for i in anchors(&refs, within, ordered) {
+            let anchor = &occurrences[0][i];
+            if matches.last().is_some_and(|m| m.0 == anchor.line) {
+                continue;
+            }
+            let (line, start, _) = self.words[anchor.start as usize];
+            let (end_line, _, end) = self.words[anchor.end as usize];
+            let text = self.lines[line];
+            let end = if end_line == line { end } else { text.len() };
+            matches.push((anchor.line, text.to_string(), start, end));
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn occ(start: u32, end: u32) -> Occurrence {
+        Occurrence {
+            start,
+            end,
+            line: 0,
+        }
+    }
+
+    #[test]
+    fn test_anchors() {
+        let open = [occ(0, 0), occ(10, 10)];
+        let file = [occ(3, 4)];
+        let terms: [&[Occurrence]; 2] = [&open, &file];
+        assert_eq!(anchors(&terms, Within::Words(3), false), [0]);
+        assert_eq!(anchors(&terms, Within::Words(6), false), [0, 1]);
+        // Ordered: nothing follows the second `open`
+        assert_eq!(anchors(&terms, Within::Words(6), true), [0]);
+        assert_eq!(anchors(&terms, Within::Anywhere, true), [0]);
+        let reversed: [&[Occurrence]; 2] = [&file, &open];
+        assert_eq!(anchors(&reversed, Within::Words(6), true), [0]);
+        assert!(anchors(&reversed, Within::Words(5), true).is_empty());
+    }
+
+    #[test]
+    fn test_content_words() {
+        let content = "let file = open(path);\nread_file(file, buf);\nclose(file);\n";
+        let words = ContentWords::new(content);
+        let lines = |occs: Vec<Occurrence>| occs.iter().map(|o| o.line).collect::<Vec<_>>();
+        assert_eq!(lines(words.occurrences("file")), [1, 2, 2, 3]);
+        assert_eq!(lines(words.occurrences("read file")), [2]);
+        assert_eq!(lines(words.occurrences("readFile")), [2]);
+
+        let terms = |t: &[&str]| t.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let found = words.matches(&terms(&["open", "close"]), Within::Lines(2), true);
+        assert_eq!(found, [(1, "let file = open(path);".to_string(), 11, 15)]);
+        assert!(
+            words
+                .matches(&terms(&["close", "open"]), Within::Lines(2), true)
+                .is_empty()
+        );
+        assert_eq!(
+            words
+                .matches(&terms(&["close", "open"]), Within::Lines(2), false)
+                .len(),
+            1
+        );
+        // `open` (word 2) to `read file` (words 4-5)
+        assert_eq!(
+            words
+                .matches(&terms(&["open", "read file"]), Within::Words(2), true)
+                .len(),
+            1
+        );
+        assert!(
+            words
+                .matches(&terms(&["open", "read file"]), Within::Words(1), true)
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,516 @@
+//! Enclosing function/class lookup for `--function-context`.
+//!
+//! Like `git grep -p`, each match can be labelled with the signature line of
+//! the function, class or module it sits in. A [`ScopeTable`] is built once
+//! per file in a single forward pass over its lines:
+//!
+//! - Brace languages (Rust, C, Go, Java, JS, ...) keep a stack of open `{`.
+//!   A brace opens a scope when its header (the `{` line, or the signature
+//!   lines just above it for Allman style and multi-line parameter lists)
+//!   declares one: a definition keyword such as `fn`/`class`, or, for
+//!   languages whose functions have no keyword (C, Java, ...), a
+//!   call-like signature that is not control flow.
+//! - Indentation languages (Python, Ruby, Elixir, ...) keep a stack of
+//!   definition lines, popped whenever a line is indented at or left of them.
+//!
+//! Strings, character literals and comments are blanked by the `in:` lexer
+//! ([`lexer::mask`]) first, so neither braces nor indentation inside them
+//! count.
+//! This is a heuristic, not a parser: macros, heredocs and unusual
+//! formatting can confuse it, in which case a match just gets no scope.
+
+use crate::index::types::Language;
+use crate::query::lexer::{self, RegionSet};
+
+/// How far above a lone `{` to look for its signature
+const MAX_HEADER_LOOKBACK: usize = 8;
+
+/// Keywords that never start a scope header, even with a `(` on the line
+const CONTROL_KEYWORDS: &[&str] = &[
+    "if",
+    "else",
+    "elif",
+    "for",
+    "foreach",
+    "while",
+    "do",
+    "switch",
+    "case",
+    "default",
+    "try",
+    "catch",
+    "except",
+    "finally",
+    "return",
+    "match",
+    "loop",
+    "unsafe",
+    "when",
+    "with",
+    "using",
+    "lock",
+    "synchronized",
+    "select",
+    "defer",
+    "go",
+    "throw",
+    "yield",
+    "await",
+    "new",
+    "sizeof",
+];
+
+enum Style {
+    /// Scopes delimited by `{ }`
+    Braces {
+        keywords: &'static [&'static str],
+        /// Accept keyword-less `name(args) {` headers (C-family functions,
+        /// JS methods)
+        signatures: bool,
+    },
+    /// Scopes delimited by indentation
+    Indent { keywords: &'static [&'static str] },
+}
+
+fn style(language: Language) -> Option<Style> {
+    let braces = |keywords, signatures| Style::Braces {
+        keywords,
+        signatures,
+    };
+    let style = match language {
+        Language::Rust => braces(
+            &[
+                "fn",
+                "impl",
+                "trait",
+                "struct",
+                "enum",
+                "mod",
+                "union",
+                "macro_rules",
+            ],
+            false,
+        ),
+        Language::Go => braces(&["func", "type"], false),
+        Language::Zig => braces(&["fn", "struct", "enum", "union", "test"], false),
+        Language::C | Language::Cpp => {
+            braces(&["class", "struct", "union", "enum", "namespace"], true)
+        }
+        Language::Java => braces(&["class", "interface", "enum", "record"], true),
+        Language::CSharp => braces(
+            &[
+                "class",
+                "interface",
+                "struct",
+                "enum",
+                "record",
+                "namespace",
+            ],
+            true,
+        ),
+        Language::Kotlin => braces(&["fun", "class", "object", "interface"], true),
+        Language::Scala => braces(&["def", "class", "object", "trait"], true),
+        Language::Swift => braces(
+            &[
+                "func",
+                "init",
+                "class",
+                "struct",
+                "enum",
+                "protocol",
+                "extension",
+            ],
+            true,
+        ),
+        Language::JavaScript | Language::TypeScript => braces(
+            &["function", "class", "interface", "namespace", "enum"],
+            true,
+        ),
+        Language::Php => braces(&["function", "class", "interface", "trait"], true),
+        Language::Shell => braces(&["function"], true),
+        Language::Perl => braces(&["sub", "package"], false),
+        Language::Python => Style::Indent {
+            keywords: &["def", "class"],
+        },
+        Language::Ruby => Style::Indent {
+            keywords: &["def", "class", "module"],
+        },
+        Language::Elixir => Style::Indent {
+            keywords: &[
+                "def",
+                "defp",
+                "defmodule",
+                "defmacro",
+                "defmacrop",
+                "defimpl",
+                "defprotocol",
+            ],
+        },
+        Language::Nim => Style::Indent {
+            keywords: &[
+                "proc", "func", "method", "iterator", "template", "macro", "type",
+            ],
+        },
+        Language::Lua => Style::Indent {
+            keywords: &["function"],
+        },
+        _ => return None,
+    };
+    Some(style)
+}
+
+/// Whether [`ScopeTable::build`] understands this language
+pub fn supports(language: Language) -> bool {
+    style(language).is_some()
+}
+
+/// Enclosing scope of every line of one file
+#[derive(Debug, Default)]
+pub struct ScopeTable {
+    /// Distinct scope headers: (1-based line number, trimmed text)
+    headers: Vec<(u32, String)>,
+    /// Per line (0-based), index into `headers` of its innermost scope
+    scope_of: Vec<Option<u32>>,
+}
+
+impl ScopeTable {
+    /// Build the table for `content`, whose lines start at `line_starts`
+    /// (the index's line map, or `build_line_map` for unindexed content).
+    /// Returns None for languages without a scope finder.
+    pub fn build(language: Language, content: &str, line_starts: &[u32]) -> Option<Self> {
+        let style = style(language)?;
+        let lines = split_lines(content, line_starts);
+        let masked = lexer::mask(language, content, RegionSet::CODE);
+        let code = split_lines(&masked, line_starts);
+        let mut table = ScopeTable {
+            headers: Vec::new(),
+            scope_of: Vec::with_capacity(lines.len()),
+        };
+
+        match style {
+            Style::Braces {
+                keywords,
+                signatures,
+            } => {
+                // One entry per unmatched `{`: the header index if it opened a scope
+                let mut stack: Vec<Option<u32>> = Vec::new();
+                for (idx, code_line) in code.iter().enumerate() {
+                    table.scope_of.push(stack.iter().rev().find_map(|s| *s));
+
+                    for c in code_line.chars() {
+                        match c {
+                            '{' => {
+                                let header = find_brace_header(&code, idx, keywords, signatures)
+                                    .map(|h| table.header_index(h, lines[h]));
+                                stack.push(header);
+                            }
+                            '}' => {
+                                stack.pop();
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            Style::Indent { keywords } => {
+                // (indent, header index) of the open definitions
+                let mut stack: Vec<(usize, u32)> = Vec::new();
+                for (idx, line) in code.iter().enumerate() {
+                    let trimmed = line.trim_start();
+                    // Blank, or wholly comment or string
+                    if trimmed.is_empty() {
+                        table.scope_of.push(stack.last().map(|&(_, h)| h));
+                        continue;
+                    }
+                    let indent = line.len() - trimmed.len();
+                    while stack.last().is_some_and(|&(open, _)| open >= indent) {
+                        stack.pop();
+                    }
+                    table.scope_of.push(stack.last().map(|&(_, h)| h));
+
+                    let leading = identifiers(trimmed).take(2);
+                    if leading.into_iter().any(|word| keywords.contains(&word)) {
+                        let header = table.header_index(idx, lines[idx]);
+                        stack.push((indent, header));
+                    }
+                }
+            }
+        }
+
+        Some(table)
+    }
+
+    fn header_index(&mut self, idx: usize, line: &str) -> u32 {
+        let line_number = idx as u32 + 1;
+        if let Some(pos) = self.headers.iter().rposition(|(n, _)| *n == line_number) {
+            return pos as u32;
+        }
+        self.headers.push((line_number, line.trim().to_string()));
+        (self.headers.len() - 1) as u32
+    }
+
+    /// The innermost scope around a 1-based line: (header line number, text).
+    /// A definition's own signature line reports its parent scope.
+    pub fn enclosing(&self, line_number: u32) -> Option<(u32, String)> {
+        let idx = line_number.checked_sub(1)? as usize;
+        let header = (*self.scope_of.get(idx)?)?;
+        self.headers.get(header as usize).cloned()
+    }
+}
+
+/// Slice `content` into lines at `line_starts`, dropping line terminators
+fn split_lines<'a>(content: &'a str, line_starts: &[u32]) -> Vec<&'a str> {
+    let mut lines = Vec::with_capacity(line_starts.len());
+    for (i, &start) in line_starts.iter().enumerate() {
+        let end = line_starts
+            .get(i + 1)
+            .map_or(content.len(), |&next| next as usize);
+        let line = content.get(start as usize..end).unwrap_or("");
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        lines.push(line.strip_suffix('\r').unwrap_or(line));
+    }
+    lines
+}
+
+/// Whether a stored line map still describes `content` (the file may have
+/// changed since it was indexed)
+pub fn line_map_matches(content: &[u8], line_starts: &[u32]) -> bool {
+    let Some(&last) = line_starts.last() else {
+        return content.is_empty();
+    };
+    let last = last as usize;
+    last <= content.len()
+        && line_starts
+            .iter()
+            .all(|&s| s == 0 || content.get(s as usize - 1) == Some(&b'\n'))
+        && content[last..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .is_none_or(|pos| last + pos + 1 == content.len())
+}
+
+/// Find the header line of a `{` opened on line `idx`: the line itself, or
+/// the signature lines of the same statement just above it
+fn find_brace_header(
+    code: &[&str],
+    idx: usize,
+    keywords: &[&str],
+    signatures: bool,
+) -> Option<usize> {
+    let brace_line = &code[idx];
+    let before_brace = &brace_line[..brace_line.find('{').unwrap_or(brace_line.len())];
+    if is_header(before_brace, keywords, signatures) {
+        return Some(idx);
+    }
+    // Only a lone `{` or the tail of a signature (`) -> T {`, `where ... {`)
+    // continues a statement from the lines above
+    let first = before_brace.trim_start().chars().next();
+    if matches!(first, Some(c) if c.is_alphanumeric() || c == '}') {
+        return None;
+    }
+
+    let mut scanned = 0;
+    for prev in (0..idx).rev() {
+        let line = code[prev].trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.ends_with([';', '{', '}']) || scanned == MAX_HEADER_LOOKBACK {
+            return None;
+        }
+        scanned += 1;
+        if is_header(line, keywords, signatures) {
+            return Some(prev);
+        }
+    }
+    None
+}
+
+/// Whether code text (strings and comments already removed) declares a scope
+fn is_header(code: &str, keywords: &[&str], signatures: bool) -> bool {
+    let mut words = identifiers(code);
+    let Some(first) = words.next() else {
+        return false;
+    };
+    if CONTROL_KEYWORDS.contains(&first) {
+        return false;
+    }
+    if keywords.contains(&first) || words.any(|w| keywords.contains(&w)) {
+        return true;
+    }
+    if !signatures {
+        return false;
+    }
+
+    // `name(args)`, `Type name(args)`, `foo = (args) =>`: not an assignment
+    // or a statement-level call
+    let Some(paren) = code.find('(') else {
+        return false;
+    };
+    let head = &code[..paren];
+    let assigns = head
+        .char_indices()
+        .any(|(i, c)| c == '=' && !matches!(code[i + 1..].chars().next(), Some('=' | '>')));
+    !assigns || code.contains("=>")
+}
+
+/// Identifier-like words of a line, in order
+fn identifiers(code: &str) -> impl Iterator<Item = &str> {
+    code.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|w| !w.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::build::build_line_map;
+
+    fn scopes(language: Language, source: &str) -> Vec<Option<(u32, String)>> {
+        let table = ScopeTable::build(language, source, &build_line_map(source.as_bytes()))
+            .expect("supported language");
+        (1..=source.lines().count() as u32)
+            .map(|n| table.enclosing(n))
+            .collect()
+    }
+
+    fn header(n: u32, text: &str) -> Option<(u32, String)> {
+        Some((n, text.to_string()))
+    }
+
+    #[test]
+    fn test_rust_scopes() {
+        let source = "\
+use std::fmt;
+
+impl Foo {
+    pub fn bar(&self) -> u32 {
+        let s = \"{\";
+        if self.x {
+            return 1;
+        }
+        2
+    }
+
+    fn baz<'a>(
+        x: &'a str,
+    ) -> char
+    where
+        Self: Sized,
+    {
+        '{'
+    }
+}
+";
+        let s = scopes(Language::Rust, source);
+        assert_eq!(s[0], None);
+        assert_eq!(s[3], header(3, "impl Foo {"));
+        assert_eq!(s[4], header(4, "pub fn bar(&self) -> u32 {"));
+        assert_eq!(s[6], header(4, "pub fn bar(&self) -> u32 {"));
+        assert_eq!(s[8], header(4, "pub fn bar(&self) -> u32 {"));
+        assert_eq!(s[10], header(3, "impl Foo {"));
+        assert_eq!(s[17], header(12, "fn baz<'a>("));
+        assert_eq!(s[19], header(3, "impl Foo {"));
+    }
+
+    #[test]
+    fn test_c_family_scopes() {
+        let source = "\
+/* { not code */
+static int
+parse(const char *s)
+{
+    for (int i = 0; i < 3; i++) {
+        run(s);
+    }
+}
+
+class Widget {
+  public void draw(Canvas c) {
+    c.paint('}');
+  }
+}
+";
+        let s = scopes(Language::C, source);
+        assert_eq!(s[0], None);
+        assert_eq!(s[5], header(3, "parse(const char *s)"));
+        assert_eq!(s[6], header(3, "parse(const char *s)"));
+
+        let s = scopes(Language::Java, source);
+        assert_eq!(s[10], header(10, "class Widget {"));
+        assert_eq!(s[11], header(11, "public void draw(Canvas c) {"));
+        assert_eq!(s[13], header(10, "class Widget {"));
+    }
+
+    #[test]
+    fn test_javascript_scopes() {
+        let source = "\
+const handler = (req) => {
+  const opts = { a: '{' };
+  items.forEach(function (item) {
+    send(item);
+  });
+};
+";
+        let s = scopes(Language::JavaScript, source);
+        assert_eq!(s[1], header(1, "const handler = (req) => {"));
+        assert_eq!(s[3], header(3, "items.forEach(function (item) {"));
+        assert_eq!(s[4], header(3, "items.forEach(function (item) {"));
+        assert_eq!(s[5], header(1, "const handler = (req) => {"));
+    }
+
+    #[test]
+    fn test_indent_scopes() {
+        let source = "\
+import os
+
+class Config:
+    # settings
+    def load(self):
+        if os.path.exists(self.path):
+
+            return 1
+# trailing comment
+    async def save(self):
+        pass
+
+def main():
+    pass
+";
+        let s = scopes(Language::Python, source);
+        assert_eq!(s[0], None);
+        assert_eq!(s[3], header(3, "class Config:"));
+        assert_eq!(s[4], header(3, "class Config:"));
+        assert_eq!(s[5], header(5, "def load(self):"));
+        assert_eq!(s[7], header(5, "def load(self):"));
+        assert_eq!(s[10], header(10, "async def save(self):"));
+        assert_eq!(s[13], header(13, "def main():"));
+
+        // A docstring's dedented lines and a `def` inside it are not code
+        let source = "\
+def load():
+    \"\"\"Load it.
+def fake():
+\"\"\"
+    return 1
+";
+        let s = scopes(Language::Python, source);
+        assert_eq!(s[2], header(1, "def load():"));
+        assert_eq!(s[4], header(1, "def load():"));
+    }
+
+    #[test]
+    fn test_unsupported_language() {
+        assert!(ScopeTable::build(Language::Markdown, "# a\n", &[0]).is_none());
+        assert!(!supports(Language::Json));
+        assert!(supports(Language::Rust));
+    }
+
+    #[test]
+    fn test_line_map_matches() {
+        let content = b"a\nbb\nccc\n";
+        assert!(line_map_matches(content, &build_line_map(content)));
+        assert!(line_map_matches(b"", &[]));
+        assert!(!line_map_matches(b"a\nbb\nccc\nd\n", &[0, 2, 5]));
+        assert!(!line_map_matches(b"ab\nb\nccc\n", &[0, 2, 5]));
+    }
+}
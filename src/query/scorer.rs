@@ -5,6 +5,7 @@
 //! - filename match
 //! - directory depth
 //! - recency
+//! - edit distance (`~term` fuzzy matches)
 
 use serde::{Deserialize, Serialize};
 use std::path::Path;
@@ -25,6 +26,9 @@ pub struct ScoringWeights {
     pub recency_half_life_secs: f32,
     /// Maximum recency bonus
     pub max_recency_bonus: f32,
+    /// Divisor added per edit for fuzzy matches: the score is divided by
+    /// `1 + edits * penalty`, so exact hits rank above typos
+    pub edit_distance_penalty: f32,
 }
 
 impl Default for ScoringWeights {
@@ -36,6 +40,7 @@ impl Default for ScoringWeights {
             max_depth_penalty: 0.5,
             recency_half_life_secs: 86400.0 * 7.0, // 7 days
             max_recency_bonus: 1.0,
+            edit_distance_penalty: 1.0,
         }
     }
 }
@@ -53,6 +58,9 @@ pub struct ScoreContext {
     pub mtime: u64,
     /// Boost multiplier from ^term syntax (default 1.0)
     pub boost: f32,
+    /// Edits between a `~term` and its closest match in this file (0 for
+    /// exact and non-fuzzy matches)
+    pub edit_distance: u32,
 }
 
 /// Scorer calculates relevance scores for search results
@@ -101,6 +109,9 @@ impl Scorer {
         let boost = if ctx.boost > 0.0 { ctx.boost } else { 1.0 };
         score *= boost;
 
+        // Fuzzy matches rank by how far they are from the term
+        score /= 1.0 + ctx.edit_distance as f32 * self.weights.edit_distance_penalty;
+
         // Ensure score is non-negative
         score.max(0.1)
     }
@@ -225,6 +236,22 @@ mod tests {
         assert_eq!(Scorer::path_depth(&path3), 3);
     }
 
+    #[test]
+    fn test_edit_distance_penalty() {
+        let scorer = Scorer::with_defaults();
+
+        let score = |edit_distance| {
+            scorer.calculate_score(&ScoreContext {
+                match_count: 3,
+                edit_distance,
+                ..Default::default()
+            })
+        };
+
+        assert!(score(0) > score(1));
+        assert!(score(1) > score(2));
+    }
+
     #[test]
     fn test_boost_scoring() {
         let scorer = Scorer::with_defaults();
@@ -0,0 +1,190 @@
+//! Token completion and "did you mean" suggestions, from the token
+//! dictionaries.
+//!
+//! Every segment's `tokens.dict` holds the identifiers of its documents,
+//! split on `_` and camelCase and lowercased, so completion is a prefix
+//! scan of the sorted dictionaries. When a query finds nothing, each of
+//! its terms that is not an indexed token is run through a Levenshtein
+//! automaton over the dictionaries and the closest, most common token is
+//! offered instead.
+
+use crate::index::reader::IndexReader;
+use crate::query::levenshtein::{LevenshteinAutomaton, default_max_edits};
+use crate::query::parser::{Query, QueryNode};
+use crate::utils::tokenize_query;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Shortest term worth correcting: below this nearly every token is one
+/// edit away
+const MIN_SUGGEST_LEN: usize = 4;
+
+/// A completion of a token prefix
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Completion {
+    pub token: String,
+    /// Documents containing the token (approximate, see
+    /// [`IndexReader::tokens_with_prefix`])
+    pub doc_freq: u32,
+}
+
+/// A near-miss token for a query term that is not in the index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suggestion {
+    /// The term as tokenized from the query
+    pub term: String,
+    /// The indexed token closest to it
+    pub token: String,
+    /// Files containing the token
+    pub files: u64,
+}
+
+impl Suggestion {
+    /// `no results for `recieve`; did you mean `receive` (412 files)?`
+    pub fn message(&self) -> String {
+        format!(
+            "no results for `{}`; did you mean `{}` ({} file{})?",
+            self.term,
+            self.token,
+            self.files,
+            if self.files == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Tokens starting with `prefix`, most common first (then alphabetically),
+/// at most `limit` of them (0 = all)
+pub fn complete(reader: &IndexReader, prefix: &str, limit: usize) -> Vec<Completion> {
+    let mut tokens = reader.tokens_with_prefix(prefix);
+    tokens.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    if limit > 0 {
+        tokens.truncate(limit);
+    }
+    tokens
+        .into_iter()
+        .map(|(token, doc_freq)| Completion { token, doc_freq })
+        .collect()
+}
+
+/// Suggestions for a query that found nothing: one per term that is not an
+/// indexed token and has a near miss that is. Regex and excluded terms are
+/// left alone.
+pub fn suggest(reader: &IndexReader, query: &Query) -> Vec<Suggestion> {
+    let mut terms = Vec::new();
+    collect_terms(&query.root, &mut terms);
+
+    let mut seen = HashSet::new();
+    let tokens: Vec<String> = terms
+        .iter()
+        .flat_map(|t| tokenize_query(t))
+        .filter(|token| seen.insert(token.clone()))
+        .collect();
+
+    let valid = reader.valid_doc_ids();
+    let files = |token: &str| (reader.get_token_docs(token) & valid).len();
+
+    tokens
+        .into_iter()
+        .filter(|token| token.len() >= MIN_SUGGEST_LEN && files(token) == 0)
+        .filter_map(|term| {
+            let max = default_max_edits(&term);
+            let automaton = LevenshteinAutomaton::new(&term, max);
+            let mut candidates: Vec<(u32, String, u32)> = reader
+                .fuzzy_tokens(&term, max)
+                .into_iter()
+                .filter_map(|(t, freq)| Some((automaton.eval(t.as_bytes())?, t, freq)))
+                .collect();
+            candidates.sort_by(|a, b| a.0.cmp(&b.0).then(b.2.cmp(&a.2)).then(a.1.cmp(&b.1)));
+            // Dictionary frequencies include replaced documents; report
+            // the first candidate still present in a current file
+            candidates.into_iter().find_map(|(_, token, _)| {
+                let files = files(&token);
+                (files > 0).then(|| Suggestion {
+                    term: term.clone(),
+                    token,
+                    files,
+                })
+            })
+        })
+        .collect()
+}
+
+/// Literal terms of a query, outside any NOT and fuzzy terms
+fn collect_terms(node: &QueryNode, terms: &mut Vec<String>) {
+    match node {
+        QueryNode::Literal(text) | QueryNode::Phrase(text) => terms.push(text.clone()),
+        QueryNode::BoostedLiteral { text, .. } => terms.push(text.clone()),
+        QueryNode::Near { terms: near, .. } | QueryNode::Proximity { terms: near, .. } => {
+            terms.extend(near.iter().cloned())
+        }
+        QueryNode::And(nodes) | QueryNode::Or(nodes) => {
+            for node in nodes {
+                collect_terms(node, terms);
+            }
+        }
+        // Fuzzy terms already tolerate typos
+        QueryNode::Regex(_) | QueryNode::Fuzzy { .. } | QueryNode::Not(_) | QueryNode::Empty => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::parser::parse_query;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_complete_and_suggest() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(
+            root.join("a.rs"),
+            "fn receive_message() {}\nfn receive_all() {}\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("b.rs"), "fn receive() {}\nfn record() {}\n").unwrap();
+        std::fs::write(root.join("c.rs"), "fn recent() {}\n").unwrap();
+        crate::index::build::build_index(root, false).unwrap();
+        let reader = IndexReader::open(root).unwrap();
+
+        let completions = complete(&reader, "REC", 0);
+        assert_eq!(
+            completions,
+            [
+                Completion {
+                    token: "receive".into(),
+                    doc_freq: 2
+                },
+                Completion {
+                    token: "recent".into(),
+                    doc_freq: 1
+                },
+                Completion {
+                    token: "record".into(),
+                    doc_freq: 1
+                },
+            ]
+        );
+        assert_eq!(complete(&reader, "rec", 1).len(), 1);
+        assert!(complete(&reader, "zzz", 0).is_empty());
+
+        let suggestions = suggest(&reader, &parse_query("recieve -nothing re:/x+/"));
+        assert_eq!(
+            suggestions,
+            [Suggestion {
+                term: "recieve".into(),
+                token: "receive".into(),
+                files: 2
+            }]
+        );
+        assert_eq!(
+            suggestions[0].message(),
+            "no results for `recieve`; did you mean `receive` (2 files)?"
+        );
+        // Indexed terms and terms with no near miss get nothing
+        assert!(suggest(&reader, &parse_query("record")).is_empty());
+        assert!(suggest(&reader, &parse_query("zzzzzz")).is_empty());
+
+        crate::utils::remove_index(root).unwrap();
+    }
+}
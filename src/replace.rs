@@ -0,0 +1,497 @@
+//! Search and replace (`fxi --replace`).
+//!
+//! A replacement rewrites the spans a query's verification matches, so the
+//! query must match one kind of span: a literal, a phrase, a regex or an OR
+//! of them, optionally scoped with `in:`. Other terms may only narrow the
+//! files (`-foo`, filters). The replacement text follows ripgrep's `-r`:
+//! `$1`, `${name}` and `$0` expand to capture groups and `$$` is a literal
+//! `$`.
+//!
+//! Only the lines the search reported are rewritten, and only while they
+//! still read as reported. Files are written to a temporary sibling that is
+//! renamed over the original, so readers (the daemon's watcher included)
+//! see either the old or the new content, never a partial write.
+
+use crate::index::types::Language;
+use crate::query::lexer::{self, RegionSet};
+use crate::query::planner::VerificationStep;
+use crate::server::protocol::ContentMatch;
+use anyhow::{Context, Result, anyhow, bail};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Rewrites the spans matched by a query
+pub struct Replacer {
+    regex: Regex,
+    replacement: String,
+    /// `in:` regions matches must lie in, None for anywhere
+    regions: Option<RegionSet>,
+}
+
+impl Replacer {
+    /// A replacer for the spans `verification` matches
+    pub fn new(verification: Option<&VerificationStep>, replacement: &str) -> Result<Self> {
+        let mut regions = None;
+        let pattern = verification
+            .and_then(|v| span_pattern(v, &mut regions))
+            .ok_or_else(|| {
+                anyhow!(
+                    "--replace needs a literal, a quoted phrase, re:/.../ or an OR of them \
+                     (near:, before:, fuzzy terms and several ANDed terms match no single span)"
+                )
+            })?;
+        let regex = Regex::new(&pattern)
+            .with_context(|| format!("invalid pattern for --replace: {}", pattern))?;
+        Ok(Self {
+            regex,
+            replacement: replacement.to_string(),
+            regions,
+        })
+    }
+
+    /// `line` with every match replaced, or None when that changes nothing.
+    /// `masked` is the line with the bytes outside the `in:` regions blanked;
+    /// matches touching blanked bytes are left alone.
+    pub fn replace_line(&self, line: &str, masked: Option<&str>) -> Option<String> {
+        let mut out = String::with_capacity(line.len());
+        let mut last = 0;
+        for caps in self.spans(line, masked) {
+            let m = caps.get(0).expect("group 0 always matches");
+            out.push_str(&line[last..m.start()]);
+            caps.expand(&self.replacement, &mut out);
+            last = m.end();
+        }
+        out.push_str(&line[last..]);
+        (out != line).then_some(out)
+    }
+
+    /// The spans in `line`, leaving out those touching bytes blanked in
+    /// `masked`
+    fn spans<'h>(
+        &'h self,
+        line: &'h str,
+        masked: Option<&'h str>,
+    ) -> impl Iterator<Item = Captures<'h>> {
+        self.regex
+            .captures_iter(masked.unwrap_or(line))
+            .filter(move |caps| {
+                caps.get(0)
+                    .is_some_and(|m| line.get(m.range()) == Some(m.as_str()))
+            })
+    }
+
+    /// The lines (1-based) of `content` holding a span to rewrite, with
+    /// their text: the search, re-run over one file
+    pub fn matching_lines<'c>(&self, language: Language, content: &'c str) -> Vec<(u32, &'c str)> {
+        let masked = self.mask(language, content);
+        let masked_lines: Option<Vec<&str>> = masked.as_deref().map(|m| m.lines().collect());
+        content
+            .lines()
+            .enumerate()
+            .filter(|&(i, line)| {
+                let masked_line = masked_lines.as_ref().and_then(|l| l.get(i).copied());
+                self.spans(line, masked_line).next().is_some()
+            })
+            .map(|(i, line)| (i as u32 + 1, line))
+            .collect()
+    }
+
+    /// `content` with the bytes outside the `in:` regions blanked, None when
+    /// the query has no `in:` scope
+    fn mask(&self, language: Language, content: &str) -> Option<String> {
+        self.regions
+            .map(|regions| lexer::mask(language, content, regions))
+    }
+
+    /// Edits for the reported lines of each file, in search order. Files
+    /// that cannot be read as UTF-8, or whose reported lines no longer read
+    /// as reported, are returned as (path, reason) instead.
+    pub fn plan(
+        &self,
+        root: &Path,
+        matches: &[ContentMatch],
+    ) -> (Vec<FileEdit>, Vec<(PathBuf, String)>) {
+        let mut files: Vec<(&Path, Vec<&ContentMatch>)> = Vec::new();
+        let mut slots: HashMap<&Path, usize> = HashMap::new();
+        for m in matches {
+            let slot = *slots.entry(&m.path).or_insert_with(|| {
+                files.push((&m.path, Vec::new()));
+                files.len() - 1
+            });
+            files[slot].1.push(m);
+        }
+
+        let mut edits = Vec::new();
+        let mut skipped = Vec::new();
+        for (path, file_matches) in files {
+            match self.edit_file(root, path, file_matches) {
+                Ok(Some(edit)) => edits.push(edit),
+                Ok(None) => {}
+                Err(e) => skipped.push((path.to_path_buf(), format!("{:#}", e))),
+            }
+        }
+        (edits, skipped)
+    }
+
+    fn edit_file(
+        &self,
+        root: &Path,
+        path: &Path,
+        mut matches: Vec<&ContentMatch>,
+    ) -> Result<Option<FileEdit>> {
+        let content = fs::read_to_string(root.join(path)).context("cannot read as UTF-8")?;
+        matches.sort_by_key(|m| m.line_number);
+        matches.dedup_by_key(|m| m.line_number);
+        let lines: Vec<(u32, &str)> = matches
+            .iter()
+            .map(|m| (m.line_number, m.line_content.as_str()))
+            .collect();
+        let language = matches.first().map_or(Language::Unknown, |m| m.language);
+        self.edit(path, language, content, &lines)
+    }
+
+    /// Edits for the given lines (1-based, ascending) of a file's content,
+    /// each with the text the search reported, or None when no line
+    /// changes. Fails when a line no longer reads as reported. `language`
+    /// is the one the file was indexed as, which decides what `in:` regions
+    /// cover.
+    pub fn edit(
+        &self,
+        path: &Path,
+        language: Language,
+        content: String,
+        reported: &[(u32, &str)],
+    ) -> Result<Option<FileEdit>> {
+        let masked = self.mask(language, &content);
+        let lines: Vec<&str> = content.lines().collect();
+        let masked_lines: Option<Vec<&str>> = masked.as_deref().map(|m| m.lines().collect());
+
+        let mut edits = Vec::new();
+        for &(line_number, text) in reported {
+            let i = (line_number as usize).saturating_sub(1);
+            let line = match lines.get(i) {
+                Some(&line) if line == text => line,
+                _ => bail!("line {} changed since the search", line_number),
+            };
+            let masked_line = masked_lines.as_ref().and_then(|l| l.get(i).copied());
+            if let Some(new) = self.replace_line(line, masked_line) {
+                edits.push(LineEdit {
+                    line_number,
+                    old: line.to_string(),
+                    new,
+                });
+            }
+        }
+        Ok((!edits.is_empty()).then(|| FileEdit {
+            path: path.to_path_buf(),
+            lines: edits,
+            content,
+        }))
+    }
+
+    /// Change the replacement text
+    pub fn set_replacement(&mut self, replacement: &str) {
+        self.replacement = replacement.to_string();
+    }
+}
+
+/// The regex matching the spans of a verification step, with the `in:`
+/// regions it is scoped to stored in `regions`
+fn span_pattern(step: &VerificationStep, regions: &mut Option<RegionSet>) -> Option<String> {
+    match step {
+        // Literals match case-insensitively, like the executor does
+        VerificationStep::Literal(text) | VerificationStep::BoostedLiteral { text, .. } => {
+            Some(format!("(?i){}", regex::escape(text)))
+        }
+        VerificationStep::Phrase {
+            text,
+            case_insensitive,
+        } => {
+            let flags = if *case_insensitive { "(?i)" } else { "" };
+            Some(format!("{}{}", flags, regex::escape(text)))
+        }
+        VerificationStep::Regex(pattern) => Some(pattern.clone()),
+        VerificationStep::Or(steps) => steps
+            .iter()
+            .map(|s| span_pattern(s, regions).map(|p| format!("(?:{})", p)))
+            .collect::<Option<Vec<_>>>()
+            .map(|patterns| patterns.join("|")),
+        // Negated terms only select files
+        VerificationStep::And(steps) => {
+            let mut positive = steps
+                .iter()
+                .filter(|s| !matches!(s, VerificationStep::Not(_)));
+            match (positive.next(), positive.next()) {
+                (Some(step), None) => span_pattern(step, regions),
+                _ => None,
+            }
+        }
+        VerificationStep::InRegion {
+            regions: scope,
+            inner,
+        } => {
+            *regions = Some(*scope);
+            span_pattern(inner, regions)
+        }
+        VerificationStep::Near { .. }
+        | VerificationStep::Proximity { .. }
+        | VerificationStep::Fuzzy { .. }
+        | VerificationStep::Not(_) => None,
+    }
+}
+
+/// One rewritten line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEdit {
+    pub line_number: u32,
+    pub old: String,
+    pub new: String,
+}
+
+/// The rewritten lines of a file, ascending
+#[derive(Debug, Clone)]
+pub struct FileEdit {
+    /// Path as reported by the search, relative to the root
+    pub path: PathBuf,
+    pub lines: Vec<LineEdit>,
+    /// The content the edits were made from
+    content: String,
+}
+
+impl FileEdit {
+    /// The content with the edited lines replaced (line endings kept)
+    pub fn new_content(&self) -> String {
+        let mut edits = self.lines.iter().peekable();
+        let mut out = String::with_capacity(self.content.len());
+        for (i, line) in self.content.split_inclusive('\n').enumerate() {
+            let Some(edit) = edits.next_if(|e| e.line_number as usize == i + 1) else {
+                out.push_str(line);
+                continue;
+            };
+            let body = line.strip_suffix('\n').unwrap_or(line);
+            let body = body.strip_suffix('\r').unwrap_or(body);
+            out.push_str(&edit.new);
+            out.push_str(&line[body.len()..]);
+        }
+        out
+    }
+
+    /// Write the edits, provided the file still holds the content they were
+    /// made from
+    pub fn apply(&self, root: &Path) -> Result<()> {
+        self.swap(
+            root,
+            &self.content,
+            &self.new_content(),
+            "changed since the search",
+        )
+    }
+
+    /// Take back applied edits, provided the file still holds what they
+    /// wrote
+    pub fn undo(&self, root: &Path) -> Result<()> {
+        self.swap(
+            root,
+            &self.new_content(),
+            &self.content,
+            "changed since the replacement",
+        )
+    }
+
+    /// Write `content` over the file if it holds `expected`, else fail with
+    /// `changed`
+    fn swap(&self, root: &Path, expected: &str, content: &str, changed: &str) -> Result<()> {
+        // Resolve symlinks so the rename replaces the target, not the link
+        let path = fs::canonicalize(root.join(&self.path))?;
+        if fs::read_to_string(&path).ok().as_deref() != Some(expected) {
+            bail!("{}", changed);
+        }
+        write_atomic(&path, content.as_bytes())
+    }
+}
+
+/// Replace a file's content through a temporary sibling renamed over it,
+/// keeping its permissions
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let dir = path.parent().context("no parent directory")?;
+    let name = path.file_name().context("no file name")?;
+    let tmp = dir.join(format!(
+        ".{}.fxi-{}",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    let written = (|| -> std::io::Result<()> {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.set_permissions(fs::metadata(path)?.permissions())?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written.with_context(|| format!("cannot write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryPlan;
+    use crate::query::parse_query;
+
+    fn replacer(query: &str, replacement: &str) -> Result<Replacer> {
+        let plan = QueryPlan::from_query(&parse_query(query));
+        Replacer::new(plan.verification.as_ref(), replacement)
+    }
+
+    fn content_match(path: &str, line_number: u32, line: &str) -> ContentMatch {
+        ContentMatch {
+            path: PathBuf::from(path),
+            line_number,
+            line_content: line.to_string(),
+            match_start: 0,
+            match_end: 0,
+            context_before: vec![],
+            context_after: vec![],
+            enclosing_scope: None,
+            duplicates: vec![],
+            language: Language::from_extension(
+                Path::new(path)
+                    .extension()
+                    .map_or("", |e| e.to_str().unwrap()),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_replace_line() {
+        let r = replacer(r"re:/(\w+)\.unwrap\(\)/", "$1?").unwrap();
+        assert_eq!(
+            r.replace_line("let a = x.unwrap() + y.unwrap();", None)
+                .unwrap(),
+            "let a = x? + y?;"
+        );
+        assert_eq!(r.replace_line("nothing here", None), None);
+
+        // Literals match any case; a replacement changing nothing is no edit
+        let r = replacer("Foo -bar ext:rs", "baz").unwrap();
+        assert_eq!(r.replace_line("foo FOO", None).unwrap(), "baz baz");
+        assert_eq!(
+            replacer("foo", "foo").unwrap().replace_line("foo", None),
+            None
+        );
+
+        let r = replacer(r#""old name" | re:/v(?<n>\d)/"#, "new${n}").unwrap();
+        assert_eq!(r.replace_line("old name v2", None).unwrap(), "new new2");
+
+        for query in ["foo bar", "near:foo,bar", "~foo", "-foo"] {
+            assert!(replacer(query, "x").is_err(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_replace_in_region() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("a.rs"),
+            "let todo = 1; // todo: fix\nlet s = \"todo\";\n",
+        )
+        .unwrap();
+        let r = replacer("in:comment todo", "done").unwrap();
+        let matches = [content_match("a.rs", 1, "let todo = 1; // todo: fix")];
+        let (edits, skipped) = r.plan(dir.path(), &matches);
+        assert!(skipped.is_empty());
+        assert_eq!(edits[0].lines[0].new, "let todo = 1; // done: fix");
+
+        // Regions follow the language the file was indexed as, not its
+        // extension
+        fs::write(dir.path().join("build"), "#!/bin/sh\necho todo # todo\n").unwrap();
+        let mut script = content_match("build", 2, "echo todo # todo");
+        let (edits, _) = r.plan(dir.path(), std::slice::from_ref(&script));
+        assert!(edits.is_empty());
+        script.language = Language::Shell;
+        let (edits, _) = r.plan(dir.path(), &[script]);
+        assert_eq!(edits[0].lines[0].new, "echo todo # done");
+    }
+
+    #[test]
+    fn test_matching_lines() {
+        let content = "// todo: one\nlet todo = 2;\n/* TODO */ three\n";
+        let r = replacer("in:comment todo", "done").unwrap();
+        assert_eq!(
+            r.matching_lines(Language::Rust, content),
+            vec![(1, "// todo: one"), (3, "/* TODO */ three")]
+        );
+        assert_eq!(
+            replacer("todo", "done")
+                .unwrap()
+                .matching_lines(Language::Rust, content)
+                .len(),
+            3
+        );
+
+        // Edits only go through for lines that still read as reported
+        let edit = r
+            .edit(
+                Path::new("a.rs"),
+                Language::Rust,
+                content.to_string(),
+                &[(1, "// todo: one")],
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            edit.new_content(),
+            "// done: one\nlet todo = 2;\n/* TODO */ three\n"
+        );
+        assert!(
+            r.edit(
+                Path::new("a.rs"),
+                Language::Rust,
+                content.to_string(),
+                &[(1, "// todo")]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_plan_and_apply() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        fs::write(root.join("a.txt"), "alpha one\r\nbeta\r\nalpha two").unwrap();
+        fs::write(root.join("b.txt"), "alpha edited\n").unwrap();
+        let r = replacer("alpha", "omega").unwrap();
+        let matches = [
+            content_match("a.txt", 1, "alpha one"),
+            content_match("b.txt", 1, "alpha"),
+            content_match("a.txt", 3, "alpha two"),
+        ];
+
+        let (edits, skipped) = r.plan(root, &matches);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0, Path::new("b.txt"));
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].lines.len(), 2);
+        assert_eq!(edits[0].new_content(), "omega one\r\nbeta\r\nomega two");
+
+        edits[0].apply(root).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("a.txt")).unwrap(),
+            "omega one\r\nbeta\r\nomega two"
+        );
+        // The file no longer holds the content the edits were made from
+        assert!(edits[0].apply(root).is_err());
+        assert_eq!(fs::read_dir(root).unwrap().count(), 2);
+
+        edits[0].undo(root).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("a.txt")).unwrap(),
+            "alpha one\r\nbeta\r\nalpha two"
+        );
+        assert!(edits[0].undo(root).is_err());
+    }
+}
@@ -1,14 +1,17 @@
 //! Unix client for connecting to the index server daemon
 
 use crate::index::types::SearchMatch;
+use crate::query::facets::Facets;
+use crate::query::suggest::Suggestion;
 use crate::server::get_socket_path;
 use crate::server::protocol::{
-    ContentSearchOptions, ContentSearchResponse, PROTOCOL_VERSION, Request, Response,
-    StatusResponse, read_message_with_id, write_message_with_id,
+    CompleteResponse, ContentSearchOptions, ContentSearchResponse, FilesOptions, FilesResponse,
+    PROTOCOL_VERSION, Request, Response, StatusResponse, read_message_with_id,
+    write_message_with_id,
 };
 use std::io::{BufReader, BufWriter};
 use std::os::unix::net::UnixStream;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 /// Read/write timeout
@@ -127,17 +130,22 @@ impl IndexClient {
         Ok(response)
     }
 
-    /// Execute a search query
+    /// Execute a search query, with facets of the full result set when
+    /// `facets` is set
     pub fn search(
         &mut self,
         query: &str,
         root_path: Option<&Path>,
         limit: usize,
+        facets: bool,
     ) -> ClientResult<SearchResult> {
         let request = Request::Search {
             query: query.to_string(),
             root_path: root_path.map(|p| p.to_path_buf()),
+            root_paths: vec![],
             limit,
+            explain: false,
+            facets,
         };
 
         let response = self.send_recv(&request)?;
@@ -152,10 +160,13 @@ impl IndexClient {
                         path: m.path,
                         line_number: m.line_number,
                         score: m.score,
+                        language: m.language,
                     })
                     .collect(),
                 duration_ms: sr.duration_ms,
                 cached: sr.cached,
+                suggestions: sr.suggestions,
+                facets: sr.facets.map(|facets| *facets),
             }),
             Response::Error { message } => Err(ClientError::ServerError(message)),
             _ => Err(ClientError::InvalidResponse),
@@ -170,13 +181,34 @@ impl IndexClient {
         limit: usize,
         options: ContentSearchOptions,
     ) -> ClientResult<ContentSearchResponse> {
-        let request = Request::ContentSearch {
+        self.send_content_search(Request::ContentSearch {
             pattern: pattern.to_string(),
             root_path: root_path.map(|p| p.to_path_buf()),
+            root_paths: vec![],
             limit,
             options,
-        };
+        })
+    }
+
+    /// Execute a content search across several codebases at once
+    /// (`ALL_ROOTS` for every loaded index). Result paths include their root.
+    pub fn content_search_roots(
+        &mut self,
+        pattern: &str,
+        root_paths: &[PathBuf],
+        limit: usize,
+        options: ContentSearchOptions,
+    ) -> ClientResult<ContentSearchResponse> {
+        self.send_content_search(Request::ContentSearch {
+            pattern: pattern.to_string(),
+            root_path: None,
+            root_paths: root_paths.to_vec(),
+            limit,
+            options,
+        })
+    }
 
+    fn send_content_search(&mut self, request: Request) -> ClientResult<ContentSearchResponse> {
         let response = self.send_recv(&request)?;
 
         match response {
@@ -186,6 +218,47 @@ impl IndexClient {
         }
     }
 
+    /// List indexed paths under a root, fuzzy-ranked when
+    /// `options.fuzzy` is set
+    pub fn files(
+        &mut self,
+        root_path: Option<&Path>,
+        limit: usize,
+        options: FilesOptions,
+    ) -> ClientResult<FilesResponse> {
+        let request = Request::Files {
+            root_path: root_path.map(|p| p.to_path_buf()),
+            limit,
+            options,
+        };
+
+        match self.send_recv(&request)? {
+            Response::Files(files) => Ok(files),
+            Response::Error { message } => Err(ClientError::ServerError(message)),
+            _ => Err(ClientError::InvalidResponse),
+        }
+    }
+
+    /// Complete a token prefix, most common tokens first
+    pub fn complete(
+        &mut self,
+        root_path: Option<&Path>,
+        prefix: &str,
+        limit: usize,
+    ) -> ClientResult<CompleteResponse> {
+        let request = Request::Complete {
+            root_path: root_path.map(|p| p.to_path_buf()),
+            prefix: prefix.to_string(),
+            limit,
+        };
+
+        match self.send_recv(&request)? {
+            Response::Complete(completions) => Ok(completions),
+            Response::Error { message } => Err(ClientError::ServerError(message)),
+            _ => Err(ClientError::InvalidResponse),
+        }
+    }
+
     /// Get server status
     pub fn status(&mut self) -> ClientResult<StatusResponse> {
         let response = self.send_recv(&Request::Status)?;
@@ -300,6 +373,10 @@ pub struct SearchResult {
     pub duration_ms: f64,
     #[allow(dead_code)]
     pub cached: bool,
+    /// "Did you mean" suggestions, when nothing matched
+    pub suggestions: Vec<Suggestion>,
+    /// Directory, extension and language facets, when requested
+    pub facets: Option<Facets>,
 }
 
 #[cfg(test)]
@@ -9,11 +9,14 @@ use crate::index::build::{ProcessedFile, build_index_with_progress, is_known_bin
 use crate::index::reader::IndexReader;
 use crate::index::types::{DocFlags, IndexMeta, Language};
 use crate::index::writer::DeltaSegmentWriter;
-use crate::query::{QueryExecutor, parse_query};
+use crate::query::facets::Facets;
+use crate::query::suggest::{self, Suggestion};
+use crate::query::{Query, QueryExecutor, parse_query};
 use crate::server::debouncer::EventDebouncer;
 use crate::server::protocol::{
-    ContentMatch, ContentSearchOptions, ContentSearchResponse, PROTOCOL_VERSION, Request, Response,
-    SearchMatchData, SearchResponse, StatusResponse,
+    ALL_ROOTS, CompleteResponse, ContentMatch, ContentSearchOptions, ContentSearchResponse,
+    FileEntry, FilesOptions, FilesResponse, PROTOCOL_VERSION, Request, Response, SearchMatchData,
+    SearchResponse, StatusResponse, merge_root_matches,
 };
 use crate::server::watcher::{
     ChangeBatch, ChangeKind, WatcherConfig, WatcherHandle, WatcherMessage, build_gitignore_matcher,
@@ -25,6 +28,7 @@ use crate::utils::{
 use anyhow::Result;
 use lru::LruCache;
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rayon::prelude::*;
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
@@ -45,9 +49,14 @@ const COMPACTION_TOMBSTONE_THRESHOLD: f32 = 0.15; // 15%
 /// Set very high since the protocol already has a 100MB message limit
 const MAX_RESULTS_CAP: usize = 10_000_000;
 
-/// Content-search result cache: query key -> (Arc'd matches, file count).
-/// Arc'd so a cache hit clones a refcount, not the result set.
-type ContentCache = LruCache<String, (Arc<Vec<ContentMatch>>, usize)>;
+/// Search result cache: query key -> (Arc'd matches, facets when the key
+/// asked for them). Arc'd so a cache hit clones a refcount, not the result
+/// set.
+type QueryCache = LruCache<String, (Arc<Vec<SearchMatchData>>, Option<Box<Facets>>)>;
+
+/// Content-search result cache: query key -> (Arc'd matches, file count,
+/// facets when the key asked for them)
+type ContentCache = LruCache<String, (Arc<Vec<ContentMatch>>, usize, Option<Box<Facets>>)>;
 
 /// Cached index with its query cache and optional file watcher
 struct CachedIndex {
@@ -56,7 +65,7 @@ struct CachedIndex {
     /// Query result cache (cleared on reader swap). Entries are Arc'd so a
     /// cache hit clones only the (limit-truncated) response, not the full
     /// uncapped result set.
-    query_cache: Mutex<LruCache<String, Arc<Vec<SearchMatchData>>>>,
+    query_cache: Mutex<QueryCache>,
     /// Content search result cache (cleared on reader swap)
     content_cache: Mutex<ContentCache>,
     /// Last access time
@@ -433,18 +442,38 @@ impl IndexServer {
             }
         };
 
+        // Modified files whose content still matches the index (touched, or
+        // checked out away and back) only get their mtime refreshed
+        let mut refreshed_count = 0;
+        let modified: Vec<&PathBuf> = batch
+            .modified
+            .iter()
+            .filter(|rel_path| {
+                let unchanged = writer
+                    .indexed_content_hash(rel_path)
+                    .zip(hash_and_mtime(&root_path.join(rel_path)))
+                    .is_some_and(|(indexed, (hash, mtime))| {
+                        indexed == hash && writer.refresh_mtime(rel_path, mtime)
+                    });
+                refreshed_count += unchanged as usize;
+                !unchanged
+            })
+            .collect();
+
         // Mark tombstones for deleted + modified files
-        for path in batch.deleted.iter().chain(batch.modified.iter()) {
+        for path in batch.deleted.iter().chain(modified.iter().copied()) {
             eprintln!("fxid: [delta] marking tombstone: {}", path.display());
             writer.mark_tombstone(path);
         }
 
         // Process created + modified files
         let mut added_count = 0;
-        for rel_path in batch.created.iter().chain(batch.modified.iter()) {
+        for rel_path in batch.created.iter().chain(modified.iter().copied()) {
             let full_path = root_path.join(rel_path);
 
-            if let Some(processed) = process_file_for_delta(&full_path, rel_path) {
+            if let Some(processed) =
+                process_file_for_delta(&full_path, rel_path, meta.content_store)
+            {
                 eprintln!(
                     "fxid: [delta] indexing: {} ({} bytes)",
                     rel_path.display(),
@@ -455,9 +484,10 @@ impl IndexServer {
             }
         }
         eprintln!(
-            "fxid: [delta] {} files indexed, {} tombstones marked",
+            "fxid: [delta] {} files indexed, {} tombstones marked, {} unchanged files refreshed",
             added_count,
-            batch.deleted.len() + batch.modified.len()
+            batch.deleted.len() + modified.len(),
+            refreshed_count
         );
 
         // Check if there are any changes to write
@@ -601,15 +631,31 @@ impl IndexServer {
             Request::Search {
                 query,
                 root_path,
+                root_paths,
                 limit,
-            } => self.handle_search(query, root_path, limit),
+                explain,
+                facets,
+            } => {
+                if root_paths.is_empty() {
+                    self.handle_search(query, root_path, limit, explain, facets)
+                } else {
+                    self.handle_federated_search(query, root_paths, limit)
+                }
+            }
 
             Request::ContentSearch {
                 pattern,
                 root_path,
+                root_paths,
                 limit,
                 options,
-            } => self.handle_content_search(pattern, root_path, limit, options),
+            } => {
+                if root_paths.is_empty() {
+                    self.handle_content_search(pattern, root_path, limit, options)
+                } else {
+                    self.handle_federated_content_search(pattern, root_paths, limit, options)
+                }
+            }
 
             Request::Status => self.handle_status(),
 
@@ -630,17 +676,37 @@ impl IndexServer {
             },
 
             Request::WatchStatus { root_path } => self.handle_watch_status(root_path),
+
+            Request::Files {
+                root_path,
+                limit,
+                options,
+            } => self.handle_files(root_path, limit, options),
+
+            Request::Complete {
+                root_path,
+                prefix,
+                limit,
+            } => self.handle_complete(root_path, prefix, limit),
         }
     }
 
-    /// Handle a search request
-    fn handle_search(&self, query: String, root_path: Option<PathBuf>, limit: usize) -> Response {
+    /// Handle a search request. With `explain` the cache is bypassed so the
+    /// response can carry the annotated plan of a real execution.
+    fn handle_search(
+        &self,
+        query: String,
+        root_path: Option<PathBuf>,
+        limit: usize,
+        explain: bool,
+        facets: bool,
+    ) -> Response {
         let start = Instant::now();
 
         // Resolve root path (canonicalize + walk up, or use single loaded index)
         let root_path = match self.resolve_root(root_path) {
             Ok(p) => p,
-            Err(resp) => return resp,
+            Err(message) => return Response::Error { message },
         };
 
         // Ensure index is loaded (and watcher started)
@@ -670,9 +736,23 @@ impl IndexServer {
         // Get the reader (handles pending swap)
         let reader = cached.get_reader();
 
+        // Results filtered by git state (changed:, is:) can go stale without
+        // any index change, so they are never cached
+        let parsed = parse_query(&query);
+        let cacheable = !parsed.filters.uses_git_state();
+        // Facets cover every verified file, so they are cached apart from
+        // the plain (early-terminated) result set
+        let cache_key = if facets {
+            format!("{}\x00facets", query)
+        } else {
+            query.clone()
+        };
+
         // Check query cache first
-        if let Ok(mut cache) = cached.query_cache.lock()
-            && let Some(cached_matches) = cache.get(&query)
+        if cacheable
+            && !explain
+            && let Ok(mut cache) = cached.query_cache.lock()
+            && let Some((cached_matches, cached_facets)) = cache.get(&cache_key)
         {
             self.stats.cache_hits.fetch_add(1, Ordering::Relaxed);
             self.stats.queries_served.fetch_add(1, Ordering::Relaxed);
@@ -686,27 +766,39 @@ impl IndexServer {
             };
 
             return Response::Search(SearchResponse {
+                suggestions: suggestions_if_empty(&reader, &parsed, &matches),
                 matches,
                 duration_ms: start.elapsed().as_secs_f64() * 1000.0,
                 cached: true,
                 resolved_root: Some(root_path.clone()),
+                explain: None,
+                facets: cached_facets.clone(),
+                warnings: Vec::new(),
             });
         }
 
         self.stats.cache_misses.fetch_add(1, Ordering::Relaxed);
 
-        // Parse and execute query
-        let parsed = parse_query(&query);
         if parsed.is_empty() {
             return Response::Search(SearchResponse {
                 matches: vec![],
                 duration_ms: start.elapsed().as_secs_f64() * 1000.0,
                 cached: false,
                 resolved_root: Some(root_path.clone()),
+                suggestions: Vec::new(),
+                explain: None,
+                facets: None,
+                warnings: Vec::new(),
             });
         }
 
-        let executor = QueryExecutor::new(&reader);
+        let mut executor = QueryExecutor::new(&reader);
+        if explain {
+            executor = executor.with_explain();
+        }
+        if facets {
+            executor = executor.with_facets();
+        }
         let matches = match executor.execute(&parsed) {
             Ok(m) => m,
             Err(e) => {
@@ -724,13 +816,16 @@ impl IndexServer {
                     path: m.path.clone(),
                     line_number: m.line_number,
                     score: m.score,
+                    language: m.language,
                 })
                 .collect(),
         );
 
+        let facets = executor.take_facets().map(Box::new);
+
         // Cache the results (refcount bump, not a copy)
-        if let Ok(mut cache) = cached.query_cache.lock() {
-            cache.put(query, Arc::clone(&match_data));
+        if cacheable && let Ok(mut cache) = cached.query_cache.lock() {
+            cache.put(cache_key, (Arc::clone(&match_data), facets.clone()));
         }
 
         self.stats.queries_served.fetch_add(1, Ordering::Relaxed);
@@ -743,10 +838,14 @@ impl IndexServer {
         };
 
         Response::Search(SearchResponse {
+            suggestions: suggestions_if_empty(&reader, &parsed, &result_matches),
             matches: result_matches,
             duration_ms: start.elapsed().as_secs_f64() * 1000.0,
             cached: false,
             resolved_root: Some(root_path),
+            explain: executor.take_explain().map(Box::new),
+            facets,
+            warnings: Vec::new(),
         })
     }
 
@@ -763,7 +862,7 @@ impl IndexServer {
         // Resolve root path (canonicalize + walk up, or use single loaded index)
         let root_path = match self.resolve_root(root_path) {
             Ok(p) => p,
-            Err(resp) => return resp,
+            Err(message) => return Response::Error { message },
         };
 
         // Ensure index is loaded (and watcher started)
@@ -794,18 +893,37 @@ impl IndexServer {
 
         // Build cache key from pattern + options + limit
         let cache_key = format!(
-            "{}\x00{}\x00{}\x00{}\x00{}\x00{}",
+            "{}\x00{}\x00{}\x00{}\x00{}\x00{}\x00{:?}\x00{:?}\x00{:?}\x00{}\x00{}\x00{}\x00{}",
             pattern,
             options.context_before,
             options.context_after,
             options.case_insensitive,
             options.files_only,
-            limit
+            limit,
+            options.globs,
+            options.types,
+            options.types_not,
+            options.fixed_strings,
+            options.function_context,
+            options.dedupe,
+            options.facets
         );
 
+        // Parse the query. Case-insensitivity is applied at the plan level:
+        // the planner narrows through the lowercased token index and
+        // verifiers ignore case. Git-state filters bypass the cache (see
+        // handle_search).
+        let parsed = match options.parse_pattern(&pattern) {
+            Ok(parsed) => parsed,
+            Err(message) => return Response::Error { message },
+        };
+        let cacheable = !parsed.filters.uses_git_state();
+
         // Check content cache first
-        if let Ok(mut cache) = cached.content_cache.lock()
-            && let Some((cached_matches, cached_file_count)) = cache.get(&cache_key)
+        if cacheable
+            && !options.explain
+            && let Ok(mut cache) = cached.content_cache.lock()
+            && let Some((cached_matches, cached_file_count, cached_facets)) = cache.get(&cache_key)
         {
             self.stats.cache_hits.fetch_add(1, Ordering::Relaxed);
             self.stats.queries_served.fetch_add(1, Ordering::Relaxed);
@@ -815,26 +933,35 @@ impl IndexServer {
                 duration_ms: start.elapsed().as_secs_f64() * 1000.0,
                 files_with_matches: *cached_file_count,
                 resolved_root: Some(root_path.clone()),
+                suggestions: suggestions_if_empty(&reader, &parsed, cached_matches),
+                explain: None,
+                facets: cached_facets.clone(),
+                warnings: Vec::new(),
             });
         }
 
         self.stats.cache_misses.fetch_add(1, Ordering::Relaxed);
 
-        // Parse and execute query. Case-insensitivity is applied at the plan
-        // level: the planner narrows through the lowercased token index and
-        // verifiers ignore case.
-        let mut parsed = parse_query(&pattern);
-        parsed.options.case_insensitive = options.case_insensitive;
         if parsed.is_empty() {
             return Response::ContentSearch(ContentSearchResponse {
                 matches: vec![],
                 duration_ms: start.elapsed().as_secs_f64() * 1000.0,
                 files_with_matches: 0,
                 resolved_root: Some(root_path.clone()),
+                suggestions: Vec::new(),
+                explain: None,
+                facets: None,
+                warnings: Vec::new(),
             });
         }
 
-        let executor = QueryExecutor::new(&reader);
+        let mut executor = QueryExecutor::new(&reader);
+        if options.explain {
+            executor = executor.with_explain();
+        }
+        if options.facets {
+            executor = executor.with_facets();
+        }
 
         // Use optimized files-only path when requested
         if options.files_only {
@@ -857,7 +984,7 @@ impl IndexServer {
             let match_data: Arc<Vec<ContentMatch>> = Arc::new(
                 matching_files
                     .into_iter()
-                    .map(|path| ContentMatch {
+                    .map(|(path, duplicates)| ContentMatch {
                         path,
                         line_number: 1,
                         line_content: String::new(),
@@ -865,13 +992,16 @@ impl IndexServer {
                         match_end: 0,
                         context_before: vec![],
                         context_after: vec![],
+                        enclosing_scope: None,
+                        duplicates,
+                        language: Language::Unknown,
                     })
                     .collect(),
             );
 
             // Cache the results (refcount bump, not a copy)
-            if let Ok(mut cache) = cached.content_cache.lock() {
-                cache.put(cache_key, (Arc::clone(&match_data), file_count));
+            if cacheable && let Ok(mut cache) = cached.content_cache.lock() {
+                cache.put(cache_key, (Arc::clone(&match_data), file_count, None));
             }
 
             self.stats.queries_served.fetch_add(1, Ordering::Relaxed);
@@ -881,6 +1011,10 @@ impl IndexServer {
                 duration_ms: start.elapsed().as_secs_f64() * 1000.0,
                 files_with_matches: file_count,
                 resolved_root: Some(root_path.clone()),
+                suggestions: suggestions_if_empty(&reader, &parsed, &match_data),
+                explain: executor.take_explain().map(Box::new),
+                facets: None,
+                warnings: Vec::new(),
             });
         }
 
@@ -920,13 +1054,21 @@ impl IndexServer {
                 match_end: m.match_end,
                 context_before: m.context_before,
                 context_after: m.context_after,
+                enclosing_scope: m.enclosing_scope,
+                duplicates: m.duplicates,
+                language: m.language,
             })
             .collect(),
         );
 
+        let facets = executor.take_facets().map(Box::new);
+
         // Cache the results (refcount bump, not a copy)
-        if let Ok(mut cache) = cached.content_cache.lock() {
-            cache.put(cache_key, (Arc::clone(&match_data), file_count));
+        if cacheable && let Ok(mut cache) = cached.content_cache.lock() {
+            cache.put(
+                cache_key,
+                (Arc::clone(&match_data), file_count, facets.clone()),
+            );
         }
 
         self.stats.queries_served.fetch_add(1, Ordering::Relaxed);
@@ -936,6 +1078,155 @@ impl IndexServer {
             duration_ms: start.elapsed().as_secs_f64() * 1000.0,
             files_with_matches: file_count,
             resolved_root: Some(root_path),
+            suggestions: suggestions_if_empty(&reader, &parsed, &match_data),
+            explain: executor.take_explain().map(Box::new),
+            facets,
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Run a search on every root in parallel and merge by score. Each root
+    /// goes through the single-root path, so loading and caching are shared.
+    /// Roots that fail are left out with a warning, unless all do.
+    fn handle_federated_search(
+        &self,
+        query: String,
+        root_paths: Vec<PathBuf>,
+        limit: usize,
+    ) -> Response {
+        let start = Instant::now();
+        let (roots, mut warnings) = match self.resolve_roots(root_paths) {
+            Ok(r) => r,
+            Err(message) => return Response::Error { message },
+        };
+
+        let responses: Vec<(PathBuf, Response)> = roots
+            .into_par_iter()
+            .map(|root| {
+                let resp =
+                    self.handle_search(query.clone(), Some(root.clone()), limit, false, false);
+                (root, resp)
+            })
+            .collect();
+
+        let mut matches = Vec::new();
+        let mut cached = true;
+        let mut searched = 0;
+        for (root, resp) in responses {
+            match resp {
+                Response::Search(sr) => {
+                    searched += 1;
+                    cached &= sr.cached;
+                    let root = sr.resolved_root.unwrap_or(root);
+                    matches.extend(sr.matches.into_iter().map(|mut m| {
+                        m.path = root.join(&m.path);
+                        m
+                    }));
+                }
+                Response::Error { message } => {
+                    warnings.push(format!("{}: {}", root.display(), message));
+                }
+                _ => {}
+            }
+        }
+        if searched == 0 {
+            return Response::Error {
+                message: warnings.join("; "),
+            };
+        }
+
+        matches.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.path.cmp(&b.path))
+                .then(a.line_number.cmp(&b.line_number))
+        });
+        if limit > 0 {
+            matches.truncate(limit);
+        }
+
+        Response::Search(SearchResponse {
+            matches,
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+            cached,
+            resolved_root: None,
+            suggestions: Vec::new(),
+            explain: None,
+            facets: None,
+            warnings,
+        })
+    }
+
+    /// Run a content search on every root in parallel and merge by path.
+    /// Roots that fail are left out with a warning, unless all do.
+    fn handle_federated_content_search(
+        &self,
+        pattern: String,
+        root_paths: Vec<PathBuf>,
+        limit: usize,
+        options: ContentSearchOptions,
+    ) -> Response {
+        let start = Instant::now();
+        let (roots, mut warnings) = match self.resolve_roots(root_paths) {
+            Ok(r) => r,
+            Err(message) => return Response::Error { message },
+        };
+        // Explain and facets describe a single index; merged results have none
+        let options = ContentSearchOptions {
+            explain: false,
+            facets: false,
+            ..options
+        };
+
+        let responses: Vec<(PathBuf, Response)> = roots
+            .into_par_iter()
+            .map(|root| {
+                let resp = self.handle_content_search(
+                    pattern.clone(),
+                    Some(root.clone()),
+                    limit,
+                    options.clone(),
+                );
+                (root, resp)
+            })
+            .collect();
+
+        let mut per_root = Vec::with_capacity(responses.len());
+        let mut files_with_matches = 0;
+        for (root, resp) in responses {
+            match resp {
+                Response::ContentSearch(sr) => {
+                    files_with_matches += sr.files_with_matches;
+                    per_root.push((sr.resolved_root.unwrap_or(root), sr.matches));
+                }
+                Response::Error { message } => {
+                    warnings.push(format!("{}: {}", root.display(), message));
+                }
+                _ => {}
+            }
+        }
+        if per_root.is_empty() {
+            return Response::Error {
+                message: warnings.join("; "),
+            };
+        }
+
+        let effective_limit = if limit == 0 {
+            MAX_RESULTS_CAP
+        } else {
+            limit.min(MAX_RESULTS_CAP)
+        };
+
+        Response::ContentSearch(ContentSearchResponse {
+            matches: merge_root_matches(per_root, effective_limit),
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+            files_with_matches,
+            resolved_root: None,
+            suggestions: Vec::new(),
+            explain: None,
+            facets: None,
+            warnings,
         })
     }
 
@@ -972,6 +1263,110 @@ impl IndexServer {
         })
     }
 
+    /// List indexed paths straight from the loaded index (file pickers)
+    fn handle_files(
+        &self,
+        root_path: Option<PathBuf>,
+        limit: usize,
+        options: FilesOptions,
+    ) -> Response {
+        let start = Instant::now();
+
+        let root_path = match self.resolve_root(root_path) {
+            Ok(p) => p,
+            Err(message) => return Response::Error { message },
+        };
+        if let Err(e) = self.ensure_index_loaded(&root_path) {
+            return Response::Error {
+                message: format!("Failed to load index: {}", e),
+            };
+        }
+        let cached = {
+            let indexes = self.indexes.read().unwrap();
+            match indexes.get(&root_path) {
+                Some(c) => Arc::clone(c),
+                None => {
+                    return Response::Error {
+                        message: "Index not found after loading".to_string(),
+                    };
+                }
+            }
+        };
+        cached.touch();
+        let reader = cached.get_reader();
+
+        let query = match options.to_query() {
+            Ok(query) => query,
+            Err(message) => return Response::Error { message },
+        };
+        let executor = QueryExecutor::new(&reader);
+        let (files, total) = match executor.list_files(&query, options.fuzzy.as_deref(), limit) {
+            Ok(listed) => listed,
+            Err(e) => {
+                return Response::Error {
+                    message: format!("Listing files failed: {}", e),
+                };
+            }
+        };
+
+        self.stats.queries_served.fetch_add(1, Ordering::Relaxed);
+
+        Response::Files(FilesResponse {
+            files: files
+                .into_iter()
+                .map(|f| FileEntry {
+                    path: f.path,
+                    positions: f.positions,
+                })
+                .collect(),
+            total,
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+            resolved_root: Some(root_path),
+        })
+    }
+
+    /// Complete a token prefix from the loaded index's token dictionaries
+    fn handle_complete(
+        &self,
+        root_path: Option<PathBuf>,
+        prefix: String,
+        limit: usize,
+    ) -> Response {
+        let start = Instant::now();
+
+        let root_path = match self.resolve_root(root_path) {
+            Ok(p) => p,
+            Err(message) => return Response::Error { message },
+        };
+        if let Err(e) = self.ensure_index_loaded(&root_path) {
+            return Response::Error {
+                message: format!("Failed to load index: {}", e),
+            };
+        }
+        let cached = {
+            let indexes = self.indexes.read().unwrap();
+            match indexes.get(&root_path) {
+                Some(c) => Arc::clone(c),
+                None => {
+                    return Response::Error {
+                        message: "Index not found after loading".to_string(),
+                    };
+                }
+            }
+        };
+        cached.touch();
+        let reader = cached.get_reader();
+
+        let completions = suggest::complete(&reader, &prefix, limit);
+        self.stats.queries_served.fetch_add(1, Ordering::Relaxed);
+
+        Response::Complete(CompleteResponse {
+            completions,
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+            resolved_root: Some(root_path),
+        })
+    }
+
     /// Handle reload request
     /// Report whether a root is being watched and how many debounced
     /// changes await flushing. Does NOT load the index for unloaded roots:
@@ -979,7 +1374,7 @@ impl IndexServer {
     fn handle_watch_status(&self, root_path: Option<PathBuf>) -> Response {
         let root_path = match self.resolve_root(root_path) {
             Ok(p) => p,
-            Err(resp) => return resp,
+            Err(message) => return Response::Error { message },
         };
 
         let watching = {
@@ -1010,7 +1405,7 @@ impl IndexServer {
     fn handle_reload(&self, root_path: Option<PathBuf>) -> Response {
         let root_path = match self.resolve_root(root_path) {
             Ok(p) => p,
-            Err(resp) => return resp,
+            Err(message) => return Response::Error { message },
         };
 
         // Remove from cache to force reload
@@ -1044,39 +1439,67 @@ impl IndexServer {
     /// Resolve a root path from an optional client-provided path.
     /// - Some(path): canonicalize and walk up to find codebase root (.git / indexed parent)
     /// - None: if exactly one index is loaded, use it; otherwise error
-    fn resolve_root(&self, root_path: Option<PathBuf>) -> Result<PathBuf, Response> {
+    fn resolve_root(&self, root_path: Option<PathBuf>) -> Result<PathBuf, String> {
         match root_path {
             Some(path) => {
-                let canonical = path.canonicalize().map_err(|e| Response::Error {
-                    message: format!("Invalid path: {}", e),
-                })?;
-                crate::utils::find_codebase_root(&canonical).map_err(|e| Response::Error {
-                    message: format!("Could not resolve codebase root: {}", e),
-                })
+                let canonical = path
+                    .canonicalize()
+                    .map_err(|e| format!("Invalid path: {}", e))?;
+                crate::utils::find_codebase_root(&canonical)
+                    .map_err(|e| format!("Could not resolve codebase root: {}", e))
             }
             None => {
                 let indexes = self.indexes.read().unwrap();
                 match indexes.len() {
-                    0 => Err(Response::Error {
-                        message: "No indexes loaded; root_path is required".to_string(),
-                    }),
+                    0 => Err("No indexes loaded; root_path is required".to_string()),
                     1 => Ok(indexes.keys().next().unwrap().clone()),
-                    n => Err(Response::Error {
-                        message: format!(
-                            "Ambiguous: {} indexes loaded; specify root_path. Loaded: {}",
-                            n,
-                            indexes
-                                .keys()
-                                .map(|k| k.display().to_string())
-                                .collect::<Vec<_>>()
-                                .join(", ")
-                        ),
-                    }),
+                    n => Err(format!(
+                        "Ambiguous: {} indexes loaded; specify root_path. Loaded: {}",
+                        n,
+                        indexes
+                            .keys()
+                            .map(|k| k.display().to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )),
                 }
             }
         }
     }
 
+    /// Resolve the roots of a federated request: `*` expands to every
+    /// loaded index, other entries resolve like `root_path`. Duplicates
+    /// (e.g. two subdirectories of one codebase) are searched once. Entries
+    /// that do not resolve come back as warnings; fails when none does.
+    fn resolve_roots(
+        &self,
+        root_paths: Vec<PathBuf>,
+    ) -> Result<(Vec<PathBuf>, Vec<String>), String> {
+        let mut roots = Vec::new();
+        let mut warnings = Vec::new();
+        for path in root_paths {
+            if path.as_os_str() == ALL_ROOTS {
+                roots.extend(self.indexes.read().unwrap().keys().cloned());
+            } else {
+                match self.resolve_root(Some(path.clone())) {
+                    Ok(root) => roots.push(root),
+                    Err(message) => warnings.push(format!("{}: {}", path.display(), message)),
+                }
+            }
+        }
+        roots.sort();
+        roots.dedup();
+
+        if roots.is_empty() {
+            return Err(if warnings.is_empty() {
+                "No indexes loaded to search".to_string()
+            } else {
+                warnings.join("; ")
+            });
+        }
+        Ok((roots, warnings))
+    }
+
     /// Ensure an index is loaded and watcher is running (if enabled)
     fn ensure_index_loaded(&self, root_path: &PathBuf) -> Result<()> {
         // Check with read lock first
@@ -1182,6 +1605,7 @@ fn should_compact(meta: &IndexMeta, segment_threshold: usize) -> bool {
 fn process_file_for_delta(
     full_path: &std::path::Path,
     rel_path: &std::path::Path,
+    store_content: bool,
 ) -> Option<ProcessedFile> {
     use std::time::UNIX_EPOCH;
 
@@ -1244,19 +1668,42 @@ fn process_file_for_delta(
         .map(|t| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64)
         .unwrap_or(0);
 
+    let stored = if store_content {
+        Some(crate::index::content_store::compress(&content)?)
+    } else {
+        None
+    };
+
     Some(ProcessedFile {
         rel_path: rel_path.to_path_buf(),
         mtime,
         size: content.len() as u64,
         language,
         flags,
+        content_hash: crate::utils::hash::content_hash(&content),
+        minhash: crate::index::minhash::signature(&trigrams),
         trigrams,
         tokens,
         token_positions,
         line_offsets,
+        content: stored,
     })
 }
 
+/// Content hash and mtime of a file, in the units process_file_for_delta
+/// records
+fn hash_and_mtime(full_path: &std::path::Path) -> Option<(u64, u64)> {
+    use std::time::UNIX_EPOCH;
+
+    let metadata = std::fs::metadata(full_path).ok()?;
+    let content = std::fs::read(full_path).ok()?;
+    let mtime = metadata
+        .modified()
+        .map(|t| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64)
+        .unwrap_or(0);
+    Some((crate::utils::hash::content_hash(&content), mtime))
+}
+
 /// Build line offset map from content
 fn build_line_map_simple(content: &[u8]) -> Vec<u32> {
     let mut offsets = vec![0u32];
@@ -1363,6 +1810,15 @@ fn run_watcher_thread(
     Ok(())
 }
 
+/// "Did you mean" suggestions for a query, when it matched nothing
+fn suggestions_if_empty<T>(reader: &IndexReader, query: &Query, matches: &[T]) -> Vec<Suggestion> {
+    if matches.is_empty() {
+        suggest::suggest(reader, query)
+    } else {
+        Vec::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1444,4 +1900,273 @@ mod tests {
         let meta = make_meta(vec![], 0, 0, 0);
         assert!(!should_compact(&meta, 15));
     }
+
+    fn indexed_root(marker: &str) -> tempfile::TempDir {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("lib.rs"),
+            format!("fn {}() {{}} // shared_marker\n", marker),
+        )
+        .unwrap();
+        crate::index::build::build_index(dir.path(), false).unwrap();
+        dir
+    }
+
+    fn federated(server: &IndexServer, root_paths: Vec<PathBuf>) -> Response {
+        server.handle_request(Request::ContentSearch {
+            pattern: "shared_marker".to_string(),
+            root_path: None,
+            root_paths,
+            limit: 0,
+            options: ContentSearchOptions::default(),
+        })
+    }
+
+    #[test]
+    fn test_federated_content_search() {
+        let a = indexed_root("marker_a");
+        let b = indexed_root("marker_b");
+        let root_a = a.path().canonicalize().unwrap();
+        let root_b = b.path().canonicalize().unwrap();
+        let server = IndexServer::new(false);
+
+        // Nothing loaded yet: `*` has nothing to fan out to
+        assert!(matches!(
+            federated(&server, vec![PathBuf::from(ALL_ROOTS)]),
+            Response::Error { .. }
+        ));
+
+        let resp = federated(&server, vec![root_a.clone(), root_b.clone()]);
+        let Response::ContentSearch(sr) = resp else {
+            panic!("expected content search response, got {:?}", resp);
+        };
+        let mut paths: Vec<_> = sr.matches.iter().map(|m| m.path.clone()).collect();
+        paths.sort();
+        let mut expected = vec![root_a.join("lib.rs"), root_b.join("lib.rs")];
+        expected.sort();
+        assert_eq!(paths, expected);
+        assert_eq!(sr.files_with_matches, 2);
+        assert_eq!(sr.resolved_root, None);
+
+        // Both are loaded now, so `*` reaches them too
+        let Response::ContentSearch(sr) = federated(&server, vec![PathBuf::from(ALL_ROOTS)]) else {
+            panic!("expected content search response");
+        };
+        assert_eq!(sr.matches.len(), 2);
+    }
+
+    #[test]
+    fn test_federated_search_skips_failed_root() {
+        let good = indexed_root("marker_good");
+        let unindexed = tempfile::TempDir::new().unwrap();
+        let root_good = good.path().canonicalize().unwrap();
+        let root_bad = unindexed.path().canonicalize().unwrap();
+        let server = IndexServer::new(false);
+
+        let resp = federated(&server, vec![root_good.clone(), root_bad.clone()]);
+        let Response::ContentSearch(sr) = resp else {
+            panic!("expected content search response, got {:?}", resp);
+        };
+        assert_eq!(sr.matches.len(), 1);
+        assert_eq!(sr.matches[0].path, root_good.join("lib.rs"));
+        assert_eq!(sr.warnings.len(), 1);
+        assert!(sr.warnings[0].starts_with(&root_bad.display().to_string()));
+
+        let Response::Search(sr) = server.handle_request(Request::Search {
+            query: "shared_marker".to_string(),
+            root_path: None,
+            root_paths: vec![root_good, PathBuf::from("/no/such/root")],
+            limit: 0,
+            explain: false,
+            facets: false,
+        }) else {
+            panic!("expected search response");
+        };
+        assert_eq!(sr.matches.len(), 1);
+        assert_eq!(sr.warnings.len(), 1);
+
+        // Nothing left to merge: the failures are the error
+        assert!(matches!(
+            federated(&server, vec![root_bad]),
+            Response::Error { .. }
+        ));
+    }
+
+    #[test]
+    fn test_files_request() {
+        let dir = indexed_root("marker_files");
+        std::fs::write(dir.path().join("notes.md"), "# notes\n").unwrap();
+        crate::index::build::build_index(dir.path(), false).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let server = IndexServer::new(false);
+
+        let Response::Files(fr) = server.handle_request(Request::Files {
+            root_path: Some(root.clone()),
+            limit: 0,
+            options: FilesOptions::default(),
+        }) else {
+            panic!("expected files response");
+        };
+        let paths: Vec<_> = fr.files.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(paths, [PathBuf::from("lib.rs"), PathBuf::from("notes.md")]);
+        assert_eq!(fr.total, 2);
+
+        let Response::Files(fr) = server.handle_request(Request::Files {
+            root_path: Some(root),
+            limit: 0,
+            options: FilesOptions {
+                fuzzy: Some("lb".to_string()),
+                ..Default::default()
+            },
+        }) else {
+            panic!("expected files response");
+        };
+        assert_eq!(fr.files.len(), 1);
+        assert_eq!(fr.files[0].path, PathBuf::from("lib.rs"));
+        assert_eq!(fr.files[0].positions, [0, 2]);
+    }
+
+    #[test]
+    fn test_complete_and_suggestions() {
+        let dir = indexed_root("receive_message");
+        let root = dir.path().canonicalize().unwrap();
+        let server = IndexServer::new(false);
+
+        let Response::Complete(cr) = server.handle_request(Request::Complete {
+            root_path: Some(root.clone()),
+            prefix: "rec".to_string(),
+            limit: 0,
+        }) else {
+            panic!("expected complete response");
+        };
+        let tokens: Vec<_> = cr.completions.iter().map(|c| c.token.as_str()).collect();
+        assert_eq!(tokens, ["receive"]);
+
+        let search = |pattern: &str| {
+            let Response::ContentSearch(csr) = server.handle_request(Request::ContentSearch {
+                pattern: pattern.to_string(),
+                root_path: Some(root.clone()),
+                root_paths: Vec::new(),
+                limit: 0,
+                options: ContentSearchOptions::default(),
+            }) else {
+                panic!("expected content search response");
+            };
+            csr
+        };
+        let csr = search("recieve");
+        assert!(csr.matches.is_empty());
+        assert_eq!(csr.suggestions.len(), 1);
+        assert_eq!(csr.suggestions[0].token, "receive");
+        assert!(search("receive").suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_content_search_explain() {
+        let dir = indexed_root("receive_message");
+        let root = dir.path().canonicalize().unwrap();
+        let server = IndexServer::new(false);
+
+        let search = |explain: bool| {
+            let Response::ContentSearch(csr) = server.handle_request(Request::ContentSearch {
+                pattern: "receive".to_string(),
+                root_path: Some(root.clone()),
+                root_paths: Vec::new(),
+                limit: 0,
+                options: ContentSearchOptions {
+                    explain,
+                    ..Default::default()
+                },
+            }) else {
+                panic!("expected content search response");
+            };
+            csr
+        };
+
+        // A cached result has no plan to explain, so explain bypasses the
+        // cache the plain search filled
+        assert!(search(false).explain.is_none());
+        let csr = search(true);
+        assert_eq!(csr.matches.len(), 1);
+        let explain = csr.explain.expect("explain requested");
+        assert_eq!(explain.steps[0].step, "token|trigrams");
+        assert_eq!(explain.files_matched, 1);
+        assert!(search(false).explain.is_none());
+
+        let Response::Search(sr) = server.handle_request(Request::Search {
+            query: "receive".to_string(),
+            root_path: Some(root.clone()),
+            root_paths: Vec::new(),
+            limit: 0,
+            explain: true,
+            facets: false,
+        }) else {
+            panic!("expected search response");
+        };
+        assert_eq!(sr.explain.expect("explain requested").candidates, 1);
+    }
+
+    #[test]
+    fn test_search_facets() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src/a.rs"),
+            "// facet_marker\n// facet_marker\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("src/b.rs"), "// facet_marker\n").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "facet_marker\n").unwrap();
+        crate::index::build::build_index(dir.path(), false).unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let server = IndexServer::new(false);
+
+        let content_search = |facets: bool| {
+            let Response::ContentSearch(csr) = server.handle_request(Request::ContentSearch {
+                pattern: "facet_marker".to_string(),
+                root_path: Some(root.clone()),
+                root_paths: Vec::new(),
+                limit: 1,
+                options: ContentSearchOptions {
+                    facets,
+                    ..Default::default()
+                },
+            }) else {
+                panic!("expected content search response");
+            };
+            csr
+        };
+
+        // The plain search fills the cache; facets are keyed apart from it
+        assert!(content_search(false).facets.is_none());
+        for _ in 0..2 {
+            let csr = content_search(true);
+            assert_eq!(csr.matches.len(), 1);
+            let facets = csr.facets.expect("facets requested");
+            // Facets cover every match, not just the returned one
+            assert_eq!(facets.dirs[0].value, "src");
+            assert_eq!((facets.dirs[0].matches, facets.dirs[0].files), (3, 2));
+            assert_eq!(facets.exts[1].value, "md");
+            assert_eq!(facets.langs[0].value, "rust");
+        }
+
+        let search = || {
+            let Response::Search(sr) = server.handle_request(Request::Search {
+                query: "facet_marker".to_string(),
+                root_path: Some(root.clone()),
+                root_paths: Vec::new(),
+                limit: 1,
+                explain: false,
+                facets: true,
+            }) else {
+                panic!("expected search response");
+            };
+            sr
+        };
+        let first = search();
+        let second = search();
+        assert!(second.cached);
+        assert_eq!(first.facets, second.facets);
+        assert_eq!(first.facets.expect("facets requested").exts.len(), 2);
+    }
 }
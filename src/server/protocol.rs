@@ -4,6 +4,10 @@
 //! - 4 bytes (little-endian u32): message length
 //! - N bytes: JSON-encoded message
 
+use crate::index::types::Language;
+use crate::query::explain::QueryExplain;
+use crate::query::facets::Facets;
+use crate::query::suggest::{Completion, Suggestion};
 use serde::{Deserialize, Serialize};
 use std::io::{Read, Write};
 use std::path::PathBuf;
@@ -25,6 +29,123 @@ pub struct ContentSearchOptions {
     /// Only return first match per file (for -l mode optimization)
     #[serde(default)]
     pub files_only: bool,
+    /// ripgrep-style globs (-g); a leading `!` excludes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub globs: Vec<String>,
+    /// File types to include (-t)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<String>,
+    /// File types to exclude (-T)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types_not: Vec<String>,
+    /// Search the pattern as a literal string instead of a query (-F)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fixed_strings: bool,
+    /// Only search files changed since this git revision (--changed-since)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changed_since: Option<String>,
+    /// Attach the enclosing function/class to each match (--function-context)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub function_context: bool,
+    /// Report files with identical content once, listing the others on
+    /// the kept match (--dedupe)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dedupe: bool,
+    /// Bypass the result cache and return the annotated query plan with
+    /// per-step candidate counts and phase timings (--explain)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub explain: bool,
+    /// Return match and file counts by directory, extension and language
+    /// over all verified files (not with `files_only`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub facets: bool,
+}
+
+/// Options for listing indexed files (`fxi files`)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct FilesOptions {
+    /// ripgrep-style globs (-g); a leading `!` excludes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub globs: Vec<String>,
+    /// File types to include (-t)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<String>,
+    /// File types to exclude (-T)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types_not: Vec<String>,
+    /// Rank paths by fzf-style fuzzy match against this pattern instead of
+    /// listing them all alphabetically
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzzy: Option<String>,
+}
+
+impl FilesOptions {
+    /// A query holding just the -g/-t/-T filters, rejecting unknown file
+    /// type names
+    pub fn to_query(&self) -> Result<crate::query::Query, String> {
+        let mut query = crate::query::parse_query("");
+        crate::query::filetypes::check_names(self.types.iter().chain(&self.types_not))?;
+        query.filters.add_globs(&self.globs);
+        query.filters.add_file_types(&self.types, &self.types_not);
+        Ok(query)
+    }
+}
+
+impl ContentSearchOptions {
+    /// Parse a content search pattern under these options: as a fixed string
+    /// or a query, with -i and the -g/-t/-T filters applied
+    pub fn parse_pattern(&self, pattern: &str) -> Result<crate::query::Query, String> {
+        let mut query = if self.fixed_strings {
+            crate::query::parse_fixed_strings(pattern)
+        } else {
+            crate::query::parse_query(pattern)
+        };
+        query.options.case_insensitive = self.case_insensitive;
+        query.options.function_context = self.function_context;
+        query.options.dedupe = self.dedupe;
+        self.apply_filters(&mut query)?;
+        Ok(query)
+    }
+
+    /// Apply the -g/-t/-T and --changed-since options to a parsed query's
+    /// filters, rejecting unknown file type names
+    pub fn apply_filters(&self, query: &mut crate::query::Query) -> Result<(), String> {
+        crate::query::filetypes::check_names(self.types.iter().chain(&self.types_not))?;
+        query.filters.add_globs(&self.globs);
+        query.filters.add_file_types(&self.types, &self.types_not);
+        if let Some(ref rev) = self.changed_since {
+            query.filters.changed.include.push(rev.clone());
+        }
+        Ok(())
+    }
+}
+
+/// `root_paths` entry that stands for every index the daemon has loaded
+pub const ALL_ROOTS: &str = "*";
+
+/// Merge per-root results of a federated content search: paths are prefixed
+/// with their root and matches ordered by path, then line (0 = no limit)
+pub fn merge_root_matches(
+    per_root: Vec<(PathBuf, Vec<ContentMatch>)>,
+    limit: usize,
+) -> Vec<ContentMatch> {
+    let mut merged: Vec<ContentMatch> = per_root
+        .into_iter()
+        .flat_map(|(root, matches)| {
+            matches.into_iter().map(move |mut m| {
+                m.path = root.join(&m.path);
+                for dup in &mut m.duplicates {
+                    *dup = root.join(&*dup);
+                }
+                m
+            })
+        })
+        .collect();
+    merged.sort_by(|a, b| a.path.cmp(&b.path).then(a.line_number.cmp(&b.line_number)));
+    if limit > 0 {
+        merged.truncate(limit);
+    }
+    merged
 }
 
 /// Request from client to server
@@ -38,8 +159,19 @@ pub enum Request {
         /// Root path of the codebase to search (optional — server resolves subdirs or uses single loaded index)
         #[serde(default)]
         root_path: Option<PathBuf>,
+        /// Search several codebases at once instead of `root_path`; see
+        /// [`ALL_ROOTS`]. Result paths are prefixed with their root.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        root_paths: Vec<PathBuf>,
         /// Maximum number of results
         limit: usize,
+        /// Bypass the result cache and return the annotated query plan
+        /// (see [`ContentSearchOptions::explain`])
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        explain: bool,
+        /// Return facets of the matches (see [`ContentSearchOptions::facets`])
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        facets: bool,
     },
 
     /// Execute a content search query (ripgrep-like)
@@ -49,6 +181,10 @@ pub enum Request {
         /// Root path of the codebase to search (optional — server resolves subdirs or uses single loaded index)
         #[serde(default)]
         root_path: Option<PathBuf>,
+        /// Search several codebases at once instead of `root_path`; see
+        /// [`ALL_ROOTS`]. Result paths are prefixed with their root.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        root_paths: Vec<PathBuf>,
         /// Maximum number of results
         limit: usize,
         /// Content search options
@@ -81,6 +217,26 @@ pub enum Request {
         #[serde(default)]
         root_path: Option<PathBuf>,
     },
+
+    /// List indexed paths, optionally fuzzy-ranked (file pickers)
+    Files {
+        #[serde(default)]
+        root_path: Option<PathBuf>,
+        /// Maximum number of paths (0 = all)
+        limit: usize,
+        #[serde(default)]
+        options: FilesOptions,
+    },
+
+    /// Complete a token prefix from the token dictionaries, most common
+    /// first (editor autocomplete)
+    Complete {
+        #[serde(default)]
+        root_path: Option<PathBuf>,
+        prefix: String,
+        /// Maximum number of tokens (0 = all)
+        limit: usize,
+    },
 }
 
 /// Response from server to client
@@ -93,6 +249,12 @@ pub enum Response {
     /// Content search results (ripgrep-like)
     ContentSearch(ContentSearchResponse),
 
+    /// Indexed paths
+    Files(FilesResponse),
+
+    /// Token completions
+    Complete(CompleteResponse),
+
     /// Server status
     Status(StatusResponse),
 
@@ -134,6 +296,40 @@ pub enum Response {
     },
 }
 
+/// Indexed paths response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilesResponse {
+    /// Paths relative to the root, best fuzzy match first (or sorted)
+    pub files: Vec<FileEntry>,
+    /// Number of paths that matched before the limit was applied
+    pub total: usize,
+    /// Time taken in milliseconds
+    pub duration_ms: f64,
+    /// The resolved codebase root the server used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_root: Option<PathBuf>,
+}
+
+/// Token completions response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompleteResponse {
+    pub completions: Vec<Completion>,
+    /// Time taken in milliseconds
+    pub duration_ms: f64,
+    /// The resolved codebase root the server used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_root: Option<PathBuf>,
+}
+
+/// One indexed path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    /// Character indices matched by the fuzzy pattern, for highlighting
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub positions: Vec<usize>,
+}
+
 /// Search results response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResponse {
@@ -146,6 +342,19 @@ pub struct SearchResponse {
     /// The resolved codebase root the server used
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub resolved_root: Option<PathBuf>,
+    /// Near-miss tokens for query terms, when nothing matched
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<Suggestion>,
+    /// Annotated query plan, when the request asked for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explain: Option<Box<QueryExplain>>,
+    /// Match counts by directory, extension and language, when the request
+    /// asked for them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facets: Option<Box<Facets>>,
+    /// Roots a federated search left out, as "root: error"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 /// Serializable search match (mirrors SearchMatch but with Serialize/Deserialize)
@@ -154,6 +363,8 @@ pub struct SearchMatchData {
     pub path: PathBuf,
     pub line_number: u32,
     pub score: f32,
+    #[serde(default)]
+    pub language: Language,
 }
 
 /// Match with line content (for ripgrep-like output)
@@ -166,6 +377,16 @@ pub struct ContentMatch {
     pub match_end: usize,
     pub context_before: Vec<(u32, String)>,
     pub context_after: Vec<(u32, String)>,
+    /// Enclosing function/class signature: (line number, text)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enclosing_scope: Option<(u32, String)>,
+    /// Other files with identical content collapsed into this one
+    /// (--dedupe)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<PathBuf>,
+    /// Language the file was indexed as (`Unknown` for files-only results)
+    #[serde(default)]
+    pub language: Language,
 }
 
 /// Content search response
@@ -177,6 +398,19 @@ pub struct ContentSearchResponse {
     /// The resolved codebase root the server used
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub resolved_root: Option<PathBuf>,
+    /// Near-miss tokens for query terms, when nothing matched
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<Suggestion>,
+    /// Annotated query plan, when the request asked for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explain: Option<Box<QueryExplain>>,
+    /// Match counts by directory, extension and language, when the request
+    /// asked for them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facets: Option<Box<Facets>>,
+    /// Roots a federated search left out, as "root: error"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 /// Server status response
@@ -369,7 +603,10 @@ mod tests {
         let req = Request::Search {
             query: "test query".to_string(),
             root_path: Some(PathBuf::from("/home/user/project")),
+            root_paths: vec![],
             limit: 100,
+            explain: false,
+            facets: false,
         };
 
         let mut buf = Vec::new();
@@ -383,6 +620,7 @@ mod tests {
                 query,
                 root_path,
                 limit,
+                ..
             } => {
                 assert_eq!(query, "test query");
                 assert_eq!(root_path, Some(PathBuf::from("/home/user/project")));
@@ -468,10 +706,15 @@ mod tests {
                 path: PathBuf::from("src/main.rs"),
                 line_number: 42,
                 score: 1.5,
+                language: Language::Rust,
             }],
             duration_ms: 12.5,
             cached: false,
             resolved_root: Some(PathBuf::from("/home/user/project")),
+            suggestions: vec![],
+            explain: None,
+            facets: None,
+            warnings: vec![],
         });
 
         let mut buf = Vec::new();
@@ -527,6 +770,144 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_files_request() {
+        // Editors send just the fields they need
+        let json = r#"{"type":"Files","root_path":"/repo","limit":50,"options":{"fuzzy":"mainrs","types":["rust"]}}"#;
+        let req: Request = serde_json::from_str(json).unwrap();
+        let Request::Files { options, limit, .. } = &req else {
+            panic!("Wrong variant");
+        };
+        assert_eq!(*limit, 50);
+        assert_eq!(options.fuzzy.as_deref(), Some("mainrs"));
+        let query = options.to_query().unwrap();
+        assert_eq!(query.filters.file_type.include, vec!["rust"]);
+        assert!(
+            FilesOptions {
+                types: vec!["nope".into()],
+                ..Default::default()
+            }
+            .to_query()
+            .is_err()
+        );
+
+        let resp = Response::Files(FilesResponse {
+            files: vec![
+                FileEntry {
+                    path: PathBuf::from("src/main.rs"),
+                    positions: vec![4, 5],
+                },
+                FileEntry {
+                    path: PathBuf::from("a.rs"),
+                    positions: vec![],
+                },
+            ],
+            total: 7,
+            duration_ms: 0.5,
+            resolved_root: None,
+        });
+        let json = serde_json::to_string(&resp).unwrap();
+        assert_eq!(json.matches("positions").count(), 1);
+        match serde_json::from_str(&json).unwrap() {
+            Response::Files(files) => {
+                assert_eq!(files.total, 7);
+                assert_eq!(files.files[0].positions, vec![4, 5]);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_complete_request() {
+        let json = r#"{"type":"Complete","prefix":"rec","limit":10}"#;
+        let req: Request = serde_json::from_str(json).unwrap();
+        let Request::Complete {
+            root_path,
+            prefix,
+            limit,
+        } = &req
+        else {
+            panic!("Wrong variant");
+        };
+        assert_eq!(*root_path, None);
+        assert_eq!(prefix, "rec");
+        assert_eq!(*limit, 10);
+
+        let resp = Response::Complete(CompleteResponse {
+            completions: vec![Completion {
+                token: "receive".into(),
+                doc_freq: 412,
+            }],
+            duration_ms: 0.2,
+            resolved_root: None,
+        });
+        let json = serde_json::to_string(&resp).unwrap();
+        match serde_json::from_str(&json).unwrap() {
+            Response::Complete(cr) => assert_eq!(cr.completions[0].doc_freq, 412),
+            _ => panic!("Wrong variant"),
+        }
+
+        // Responses without suggestions omit the field, and old ones parse
+        let json =
+            r#"{"type":"ContentSearch","matches":[],"duration_ms":1.0,"files_with_matches":0}"#;
+        let Response::ContentSearch(csr) = serde_json::from_str(json).unwrap() else {
+            panic!("Wrong variant");
+        };
+        assert!(csr.suggestions.is_empty());
+        assert!(!serde_json::to_string(&csr).unwrap().contains("suggestions"));
+    }
+
+    #[test]
+    fn test_content_search_request_root_paths() {
+        // Old clients omit root_paths; single-root requests don't send it
+        let json = r#"{"type":"ContentSearch","pattern":"x","limit":0,"options":{"context_before":0,"context_after":0,"case_insensitive":false}}"#;
+        let req: Request = serde_json::from_str(json).unwrap();
+        match &req {
+            Request::ContentSearch { root_paths, .. } => assert!(root_paths.is_empty()),
+            _ => panic!("Wrong variant"),
+        }
+        assert!(!serde_json::to_string(&req).unwrap().contains("root_paths"));
+
+        let json = r#"{"type":"Search","query":"x","root_paths":["*"],"limit":0}"#;
+        match serde_json::from_str(json).unwrap() {
+            Request::Search { root_paths, .. } => {
+                assert_eq!(root_paths, vec![PathBuf::from(ALL_ROOTS)])
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_merge_root_matches() {
+        let m = |path: &str, line| ContentMatch {
+            path: PathBuf::from(path),
+            line_number: line,
+            line_content: String::new(),
+            match_start: 0,
+            match_end: 0,
+            context_before: vec![],
+            context_after: vec![],
+            enclosing_scope: None,
+            duplicates: vec![],
+            language: Language::Rust,
+        };
+        let merged = merge_root_matches(
+            vec![
+                (PathBuf::from("/b"), vec![m("x.rs", 2), m("x.rs", 1)]),
+                (PathBuf::from("/a"), vec![m("y.rs", 5)]),
+            ],
+            2,
+        );
+        let got: Vec<_> = merged
+            .iter()
+            .map(|m| (m.path.clone(), m.line_number))
+            .collect();
+        assert_eq!(
+            got,
+            vec![(PathBuf::from("/a/y.rs"), 5), (PathBuf::from("/b/x.rs"), 1)]
+        );
+    }
+
     #[test]
     fn test_search_request_without_root_path() {
         // New client omits root_path entirely
@@ -548,6 +929,10 @@ mod tests {
             duration_ms: 1.0,
             cached: false,
             resolved_root: None,
+            suggestions: vec![],
+            explain: None,
+            facets: None,
+            warnings: vec![],
         };
         let json = serde_json::to_string(&resp).unwrap();
         assert!(!json.contains("resolved_root"));
@@ -558,6 +943,10 @@ mod tests {
             duration_ms: 1.0,
             cached: false,
             resolved_root: Some(PathBuf::from("/tmp/test")),
+            suggestions: vec![],
+            explain: None,
+            facets: None,
+            warnings: vec![],
         };
         let json = serde_json::to_string(&resp_with_root).unwrap();
         assert!(json.contains("resolved_root"));
@@ -626,10 +1015,15 @@ mod tests {
                 path: PathBuf::from("src/main.rs"),
                 line_number: 42,
                 score: 1.5,
+                language: Language::Rust,
             }],
             duration_ms: 12.5,
             cached: false,
             resolved_root: Some(PathBuf::from("/project")),
+            suggestions: vec![],
+            explain: None,
+            facets: None,
+            warnings: vec![],
         });
 
         let mut buf = Vec::new();
@@ -648,6 +1042,75 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_content_search_options_filters_default() {
+        // Old client options without globs/types should default to empty
+        let json = r#"{"context_before":0,"context_after":0,"case_insensitive":false}"#;
+        let opts: ContentSearchOptions = serde_json::from_str(json).unwrap();
+        assert!(opts.globs.is_empty() && opts.types.is_empty() && opts.types_not.is_empty());
+        assert!(!opts.fixed_strings);
+
+        let opts = ContentSearchOptions {
+            globs: vec!["!*.min.js".to_string()],
+            types: vec!["rust".to_string()],
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&opts).unwrap();
+        assert!(!json.contains("types_not"));
+        let decoded: ContentSearchOptions = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.globs, vec!["!*.min.js"]);
+        assert_eq!(decoded.types, vec!["rust"]);
+    }
+
+    #[test]
+    fn test_content_search_options_apply_filters() {
+        let mut query = crate::query::parse_query("foo");
+        let opts = ContentSearchOptions {
+            types: vec!["python".to_string()],
+            ..Default::default()
+        };
+        opts.apply_filters(&mut query).unwrap();
+        assert_eq!(query.filters.file_type.include, vec!["python"]);
+
+        let opts = ContentSearchOptions {
+            changed_since: Some("origin/main".to_string()),
+            ..Default::default()
+        };
+        opts.apply_filters(&mut query).unwrap();
+        assert_eq!(query.filters.changed.include, vec!["origin/main"]);
+
+        let opts = ContentSearchOptions {
+            types_not: vec!["nope".to_string()],
+            ..Default::default()
+        };
+        assert!(opts.apply_filters(&mut query).is_err());
+    }
+
+    #[test]
+    fn test_content_search_options_parse_pattern() {
+        let opts = ContentSearchOptions {
+            case_insensitive: true,
+            fixed_strings: true,
+            types: vec!["rust".to_string()],
+            ..Default::default()
+        };
+        let query = opts.parse_pattern("a | -b").unwrap();
+        assert!(matches!(&query.root, crate::query::QueryNode::Phrase(t) if t == "a | -b"));
+        assert!(query.options.case_insensitive);
+        assert_eq!(query.filters.file_type.include, vec!["rust"]);
+
+        let opts = ContentSearchOptions::default();
+        let query = opts.parse_pattern("a | -b").unwrap();
+        assert!(matches!(query.root, crate::query::QueryNode::Or(_)));
+        assert!(!query.options.function_context);
+
+        let opts = ContentSearchOptions {
+            function_context: true,
+            ..Default::default()
+        };
+        assert!(opts.parse_pattern("a").unwrap().options.function_context);
+    }
+
     #[test]
     fn test_content_search_response_resolved_root_default() {
         // Old server response without resolved_root should default to None
@@ -1,14 +1,19 @@
 use crate::index::build::build_index_with_progress;
+use crate::index::build::build_line_map;
 use crate::index::reader::IndexReader;
-use crate::index::types::SearchMatch;
-use crate::query::{QueryExecutor, parse_query};
+use crate::index::types::{Language, SearchMatch};
+use crate::query::facets::Facets;
+use crate::query::scope::{self, ScopeTable};
+use crate::query::suggest::{self, Suggestion};
+use crate::query::{QueryExecutor, QueryPlan, parse_query};
+use crate::replace::{FileEdit, LineEdit, Replacer};
 use crate::server::IndexClient;
 use crate::utils::find_codebase_root;
 use anyhow::Result;
 use lru::LruCache;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::mpsc::{self, Receiver, TryRecvError};
 use std::sync::{Arc, Mutex};
@@ -20,6 +25,7 @@ use std::time::Instant;
 pub enum Mode {
     Search,
     Preview,
+    Replace,
     Help,
 }
 
@@ -50,12 +56,124 @@ pub enum SearchState {
 /// Result from a background search
 pub struct SearchResult {
     pub matches: Result<Vec<SearchMatch>, String>,
+    /// Enclosing function/class per match (see [`resolve_scopes`])
+    pub scopes: Vec<Option<(u32, String)>>,
+    /// "Did you mean" suggestions when nothing matched
+    pub suggestions: Vec<Suggestion>,
+    /// Where the matches concentrate, for the facet panel
+    pub facets: Option<Facets>,
     pub query: String,
 }
 
+/// Replace mode: the result files as read on entering the mode, the edits
+/// the replacement text makes to them and the matches switched off
+pub struct ReplaceState {
+    pub text: String,
+    replacer: Replacer,
+    /// Result files as read on entering replace mode. Applying skips a
+    /// file that no longer holds the content read here.
+    files: Vec<ReplaceFile>,
+    pub edits: Vec<FileEdit>,
+    /// Rewritten lines in display order, as (edit, line) indexes
+    rows: Vec<(usize, usize)>,
+    /// Matches left out of the apply, by path and line
+    excluded: HashSet<(PathBuf, u32)>,
+    /// Reported lines that no longer held a match on entering replace mode
+    stale: usize,
+    /// Enter was pressed with an empty replacement; the next Enter deletes
+    /// the matched text
+    confirm_delete: bool,
+    pub selected: usize,
+}
+
+/// A result file in replace mode
+struct ReplaceFile {
+    path: PathBuf,
+    /// Language the file was indexed as
+    language: Language,
+    content: String,
+    /// Lines to rewrite with their text in `content`, ascending
+    lines: Vec<(u32, String)>,
+}
+
+impl ReplaceState {
+    /// Rewritten lines in display order
+    pub fn rows(&self) -> impl Iterator<Item = (&FileEdit, &LineEdit)> {
+        self.rows
+            .iter()
+            .map(|&(e, l)| (&self.edits[e], &self.edits[e].lines[l]))
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether a rewritten line is part of the apply
+    pub fn is_enabled(&self, edit: &FileEdit, line: &LineEdit) -> bool {
+        !self
+            .excluded
+            .contains(&(edit.path.clone(), line.line_number))
+    }
+
+    /// "3 of 4 lines in 2 files"
+    pub fn summary(&self) -> String {
+        let enabled = self.rows().filter(|(e, l)| self.is_enabled(e, l)).count();
+        format!(
+            "{} of {} lines in {} files",
+            enabled,
+            self.rows.len(),
+            self.edits.len()
+        )
+    }
+
+    /// Recompute the edits after the replacement text changed
+    fn update(&mut self) {
+        self.replacer.set_replacement(&self.text);
+        self.edits = self
+            .files
+            .iter()
+            .filter_map(|file| {
+                let lines: Vec<(u32, &str)> = file
+                    .lines
+                    .iter()
+                    .map(|(n, text)| (*n, text.as_str()))
+                    .collect();
+                // The lines were taken from this content, so they still read
+                // as recorded
+                self.replacer
+                    .edit(&file.path, file.language, file.content.clone(), &lines)
+                    .ok()
+                    .flatten()
+            })
+            .collect();
+        self.rows = self
+            .edits
+            .iter()
+            .enumerate()
+            .flat_map(|(e, edit)| (0..edit.lines.len()).map(move |l| (e, l)))
+            .collect();
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+    }
+}
+
 /// LRU cache size for search results (larger = more memory, faster re-queries)
 const SEARCH_CACHE_SIZE: usize = 64;
 
+/// Only the first results get an enclosing scope; beyond this the list is
+/// rarely scrolled and each new file costs a read
+const MAX_SCOPED_RESULTS: usize = 500;
+
+/// Largest file parsed for scopes (same bound as the preview cache)
+const MAX_SCOPE_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Cached results of one query, with their scopes, suggestions and facets
+type CachedResults = (
+    Vec<SearchMatch>,
+    Vec<Option<(u32, String)>>,
+    Vec<Suggestion>,
+    Option<Facets>,
+);
+
 /// Application state
 pub struct App {
     /// The codebase root (detected or specified)
@@ -72,6 +190,11 @@ pub struct App {
     reader: Option<Arc<IndexReader>>,
     pub query: String,
     pub results: Vec<SearchMatch>,
+    /// Enclosing function/class of each result, parallel to `results`
+    /// (shorter when only the first results were resolved)
+    pub scopes: Vec<Option<(u32, String)>>,
+    /// Directory, extension and language facets of the current results
+    pub facets: Option<Facets>,
     pub selected: usize,
     pub mode: Mode,
     /// Previous mode before entering help (to return to)
@@ -91,9 +214,13 @@ pub struct App {
     /// Background search state
     search_state: SearchState,
     /// LRU cache of recent search results for instant recall
-    search_cache: LruCache<String, Vec<SearchMatch>>,
+    search_cache: LruCache<String, CachedResults>,
     /// Prefetched preview content for adjacent results
     prefetch_cache: HashMap<PathBuf, String>,
+    /// Replace mode state, kept while in [`Mode::Replace`]
+    pub replace: Option<ReplaceState>,
+    /// Edits written by the last replace, for undo
+    last_replace: Vec<FileEdit>,
 }
 
 impl App {
@@ -123,6 +250,8 @@ impl App {
                     reader: None,
                     query: String::new(),
                     results: Vec::new(),
+                    scopes: Vec::new(),
+                    facets: None,
                     selected: 0,
                     mode: Mode::Search,
                     previous_mode: Mode::Search,
@@ -137,6 +266,8 @@ impl App {
                     search_state: SearchState::Idle,
                     search_cache: LruCache::new(NonZeroUsize::new(SEARCH_CACHE_SIZE).unwrap()),
                     prefetch_cache: HashMap::new(),
+                    replace: None,
+                    last_replace: Vec::new(),
                 });
             }
         }
@@ -172,6 +303,8 @@ impl App {
             reader: None,
             query: String::new(),
             results: Vec::new(),
+            scopes: Vec::new(),
+            facets: None,
             selected: 0,
             mode: Mode::Search,
             previous_mode: Mode::Search,
@@ -186,6 +319,8 @@ impl App {
             search_state: SearchState::Idle,
             search_cache: LruCache::new(NonZeroUsize::new(SEARCH_CACHE_SIZE).unwrap()),
             prefetch_cache: HashMap::new(),
+            replace: None,
+            last_replace: Vec::new(),
         })
     }
 
@@ -286,15 +421,26 @@ impl App {
                                 Ok(matches) => {
                                     let count = matches.len();
                                     self.status_message = format!(
-                                        "{} matches ({:.1}ms)",
+                                        "{} matches ({:.1}ms){}",
                                         count,
-                                        elapsed.as_secs_f64() * 1000.0
+                                        elapsed.as_secs_f64() * 1000.0,
+                                        suggestion_hint(&result.suggestions)
                                     );
 
                                     // Cache the results (LRU automatically evicts oldest)
-                                    self.search_cache.put(result.query.clone(), matches.clone());
+                                    self.search_cache.put(
+                                        result.query.clone(),
+                                        (
+                                            matches.clone(),
+                                            result.scopes.clone(),
+                                            result.suggestions,
+                                            result.facets.clone(),
+                                        ),
+                                    );
 
                                     self.results = matches;
+                                    self.scopes = result.scopes;
+                                    self.facets = result.facets;
                                     self.selected = 0;
                                     self.update_preview();
 
@@ -304,6 +450,8 @@ impl App {
                                 Err(e) => {
                                     self.status_message = format!("Error: {}", e);
                                     self.results.clear();
+                                    self.scopes.clear();
+                                    self.facets = None;
                                 }
                             }
                         }
@@ -337,15 +485,28 @@ impl App {
     pub fn clear_query(&mut self) {
         self.query.clear();
         self.results.clear();
+        self.scopes.clear();
+        self.facets = None;
         self.selected = 0;
         self.editing = true;
     }
 
+    /// Narrow the query to a facet's files and search again
+    pub fn add_filter(&mut self, filter: &str) {
+        if !self.query.split_whitespace().any(|term| term == filter) {
+            self.query = format!("{} {}", self.query.trim_end(), filter);
+        }
+        self.editing = false;
+        self.execute_search();
+    }
+
     pub fn execute_search(&mut self) {
         self.prefetch_cache.clear();
 
         if self.query.is_empty() {
             self.results.clear();
+            self.scopes.clear();
+            self.facets = None;
             self.search_state = SearchState::Idle;
             self.status_message = if self.index_available {
                 if self.using_daemon {
@@ -363,10 +524,16 @@ impl App {
         }
 
         // Check local cache first for instant results (LRU cache)
-        if let Some(cached) = self.search_cache.get(&self.query) {
-            self.results = cached.clone();
+        if let Some((results, scopes, suggestions, facets)) = self.search_cache.get(&self.query) {
+            self.results = results.clone();
+            self.scopes = scopes.clone();
+            self.facets = facets.clone();
             self.selected = 0;
-            self.status_message = format!("{} matches (cached)", self.results.len());
+            self.status_message = format!(
+                "{} matches (cached){}",
+                self.results.len(),
+                suggestion_hint(suggestions)
+            );
             self.update_preview();
             self.prefetch_adjacent_previews();
             return;
@@ -375,6 +542,8 @@ impl App {
         // Clear stale results immediately when starting a new search
         // This prevents showing old results if the new search fails
         self.results.clear();
+        self.scopes.clear();
+        self.facets = None;
         self.selected = 0;
 
         // Use daemon if available (fast path)
@@ -395,17 +564,30 @@ impl App {
             };
 
             thread::spawn(move || {
+                let mut suggestions = Vec::new();
+                let mut facets = None;
                 let result = if let Ok(mut client) = client.lock() {
-                    match client.search(&query_for_thread, Some(&root_path), 0) {
-                        Ok(sr) => Ok(sr.matches),
+                    match client.search(&query_for_thread, Some(&root_path), 0, true) {
+                        Ok(sr) => {
+                            suggestions = sr.suggestions;
+                            facets = sr.facets;
+                            Ok(sr.matches)
+                        }
                         Err(e) => Err(e.to_string()),
                     }
                 } else {
                     Err("Failed to lock client".to_string())
                 };
+                let scopes = result
+                    .as_ref()
+                    .map(|matches| resolve_scopes(&root_path, matches))
+                    .unwrap_or_default();
 
                 let _ = tx.send(SearchResult {
                     matches: result,
+                    scopes,
+                    suggestions,
+                    facets,
                     query: query_for_thread,
                 });
             });
@@ -432,6 +614,7 @@ impl App {
         let (tx, rx) = mpsc::channel();
         let query = self.query.clone();
         let query_for_thread = query.clone();
+        let root_path = self.root_path.clone();
 
         self.status_message = "Searching...".to_string();
         self.search_state = SearchState::Searching {
@@ -441,11 +624,23 @@ impl App {
         };
 
         thread::spawn(move || {
-            let executor = QueryExecutor::new(&reader);
+            let executor = QueryExecutor::new(&reader).with_facets();
             let result = executor.execute(&parsed).map_err(|e| e.to_string());
+            let facets = executor.take_facets();
+            let scopes = result
+                .as_ref()
+                .map(|matches| resolve_scopes(&root_path, matches))
+                .unwrap_or_default();
+            let suggestions = match &result {
+                Ok(matches) if matches.is_empty() => suggest::suggest(&reader, &parsed),
+                _ => Vec::new(),
+            };
 
             let _ = tx.send(SearchResult {
                 matches: result,
+                scopes,
+                suggestions,
+                facets,
                 query: query_for_thread,
             });
         });
@@ -481,7 +676,9 @@ impl App {
         self.mode = match self.mode {
             Mode::Search => Mode::Preview,
             Mode::Preview => Mode::Search,
-            Mode::Help => Mode::Help, // Don't toggle preview in help mode
+            // Don't toggle preview in replace or help mode
+            Mode::Replace => Mode::Replace,
+            Mode::Help => Mode::Help,
         };
         self.update_preview();
     }
@@ -543,30 +740,302 @@ impl App {
     pub fn open_selected(&mut self) {
         if let Some(result) = self.results.get(self.selected) {
             let full_path = self.root_path.join(&result.path);
+            let line_number = result.line_number;
+            self.open_in_editor(&full_path, line_number);
+        }
+    }
+
+    /// Open a file at a line in $EDITOR, handing it the terminal meanwhile
+    fn open_in_editor(&mut self, path: &Path, line_number: u32) {
+        // Try to open in $EDITOR
+        let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
 
-            // Try to open in $EDITOR
-            let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+        // Format line number for editors that support it
+        let line_arg = format!("+{}", line_number);
 
-            // Format line number for editors that support it
-            let line_arg = format!("+{}", result.line_number);
+        // Temporarily restore terminal before launching editor
+        let _ = crossterm::terminal::disable_raw_mode();
+        let _ = crossterm::execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen);
 
-            // Temporarily restore terminal before launching editor
-            let _ = crossterm::terminal::disable_raw_mode();
-            let _ =
-                crossterm::execute!(std::io::stdout(), crossterm::terminal::LeaveAlternateScreen);
+        let status = Command::new(&editor).arg(&line_arg).arg(path).status();
 
-            let _ = Command::new(&editor)
-                .arg(&line_arg)
-                .arg(&full_path)
-                .status();
+        // Restore TUI terminal state, whether or not the editor ran
+        let _ = crossterm::terminal::enable_raw_mode();
+        let _ = crossterm::execute!(std::io::stdout(), crossterm::terminal::EnterAlternateScreen);
+
+        if let Err(e) = status {
+            self.status_message = format!("Cannot run {}: {}", editor, e);
+        }
+    }
+
+    /// Enter replace mode for the current results. The query must match a
+    /// single kind of span (see [`Replacer`]).
+    pub fn enter_replace(&mut self) {
+        if self.results.is_empty() {
+            self.status_message = "Nothing to replace".to_string();
+            return;
+        }
+        let plan = QueryPlan::from_query(&parse_query(&self.query));
+        let replacer = match Replacer::new(plan.verification.as_ref(), "") {
+            Ok(replacer) => replacer,
+            Err(e) => {
+                self.status_message = format!("{:#}", e);
+                return;
+            }
+        };
+
+        // Read each result file once, keeping the reported lines that still
+        // hold a match (the file may have changed since the search)
+        let mut lines: Vec<(&SearchMatch, Vec<u32>)> = Vec::new();
+        let mut slots: HashMap<&Path, usize> = HashMap::new();
+        for result in &self.results {
+            let slot = *slots.entry(&result.path).or_insert_with(|| {
+                lines.push((result, Vec::new()));
+                lines.len() - 1
+            });
+            lines[slot].1.push(result.line_number);
+        }
+        let mut stale = 0;
+        let files = lines
+            .into_iter()
+            .filter_map(|(result, mut line_numbers)| {
+                let content = std::fs::read_to_string(self.root_path.join(&result.path)).ok()?;
+                line_numbers.sort_unstable();
+                line_numbers.dedup();
+                let lines: Vec<(u32, String)> = replacer
+                    .matching_lines(result.language, &content)
+                    .into_iter()
+                    .filter(|(n, _)| line_numbers.binary_search(n).is_ok())
+                    .map(|(n, text)| (n, text.to_string()))
+                    .collect();
+                stale += line_numbers.len() - lines.len();
+                Some(ReplaceFile {
+                    path: result.path.clone(),
+                    language: result.language,
+                    content,
+                    lines,
+                })
+            })
+            .collect();
+
+        self.replace = Some(ReplaceState {
+            text: String::new(),
+            replacer,
+            files,
+            edits: Vec::new(),
+            rows: Vec::new(),
+            excluded: HashSet::new(),
+            stale,
+            confirm_delete: false,
+            selected: 0,
+        });
+        self.mode = Mode::Replace;
+        self.update_replace();
+    }
+
+    /// Leave replace mode without writing anything
+    pub fn exit_replace(&mut self) {
+        self.replace = None;
+        self.mode = Mode::Search;
+        self.status_message = format!("{} matches", self.results.len());
+    }
+
+    pub fn replace_push(&mut self, c: char) {
+        if let Some(state) = &mut self.replace {
+            state.text.push(c);
+        }
+        self.update_replace();
+    }
+
+    pub fn replace_pop(&mut self) {
+        if let Some(state) = &mut self.replace {
+            state.text.pop();
+        }
+        self.update_replace();
+    }
+
+    fn update_replace(&mut self) {
+        if let Some(state) = &mut self.replace {
+            state.update();
+            state.confirm_delete = false;
+            let stale = match state.stale {
+                0 => String::new(),
+                1 => "; 1 reported line no longer matches".to_string(),
+                n => format!("; {} reported lines no longer match", n),
+            };
+            self.status_message = format!(
+                "Replace {} (Enter: apply, Ctrl+t: toggle, Esc: cancel){}",
+                state.summary(),
+                stale
+            );
+        }
+    }
 
-            // Restore TUI terminal state
-            let _ = crossterm::terminal::enable_raw_mode();
-            let _ =
-                crossterm::execute!(std::io::stdout(), crossterm::terminal::EnterAlternateScreen);
+    /// Move the replace selection by `delta` rows
+    pub fn replace_select(&mut self, delta: isize) {
+        if let Some(state) = &mut self.replace {
+            let last = state.rows.len().saturating_sub(1);
+            state.selected = state.selected.saturating_add_signed(delta).min(last);
         }
     }
 
+    /// Switch the selected match in or out of the apply
+    pub fn toggle_replace_match(&mut self) {
+        if let Some(state) = &mut self.replace
+            && let Some(&(e, l)) = state.rows.get(state.selected)
+        {
+            let key = (
+                state.edits[e].path.clone(),
+                state.edits[e].lines[l].line_number,
+            );
+            if !state.excluded.remove(&key) {
+                state.excluded.insert(key);
+            }
+        }
+        self.update_replace();
+    }
+
+    /// Switch every match out of the apply, or back in when all are out
+    pub fn toggle_all_replace_matches(&mut self) {
+        if let Some(state) = &mut self.replace {
+            let keys: Vec<(PathBuf, u32)> = state
+                .rows()
+                .map(|(edit, line)| (edit.path.clone(), line.line_number))
+                .collect();
+            if keys.iter().all(|key| state.excluded.contains(key)) {
+                state.excluded.clear();
+            } else {
+                state.excluded.extend(keys);
+            }
+        }
+        self.update_replace();
+    }
+
+    /// Open the selected match in $EDITOR, then search its file again so
+    /// the preview (and the apply) start from the edited content. The
+    /// file's matches are all switched back on, as its line numbers may
+    /// have moved.
+    pub fn open_replace_match(&mut self) {
+        let Some(state) = &self.replace else {
+            return;
+        };
+        let Some((edit, line)) = state.rows().nth(state.selected) else {
+            return;
+        };
+        let (path, line_number) = (edit.path.clone(), line.line_number);
+        self.open_in_editor(&self.root_path.join(&path), line_number);
+
+        if let Some(state) = &mut self.replace
+            && let Some(file) = state.files.iter_mut().find(|f| f.path == path)
+            && let Ok(content) = std::fs::read_to_string(self.root_path.join(&path))
+        {
+            file.lines = state
+                .replacer
+                .matching_lines(file.language, &content)
+                .into_iter()
+                .map(|(n, text)| (n, text.to_string()))
+                .collect();
+            file.content = content;
+            state.excluded.retain(|(p, _)| *p != path);
+        }
+        self.update_replace();
+    }
+
+    /// Write the switched-on matches, one file at a time, and leave replace
+    /// mode. Files changed since replace mode read them are skipped.
+    /// Write the switched-on edits. An empty replacement deletes the
+    /// matched text, as `--replace ''` does, so it asks for a second Enter.
+    pub fn apply_replace(&mut self) {
+        if let Some(state) = &mut self.replace
+            && state.text.is_empty()
+            && !state.confirm_delete
+        {
+            state.confirm_delete = true;
+            self.status_message = format!(
+                "Delete the matched text ({})? Enter: delete, type: replace instead, Esc: cancel",
+                state.summary()
+            );
+            return;
+        }
+        let Some(state) = self.replace.take() else {
+            return;
+        };
+        self.mode = Mode::Search;
+
+        let mut applied = Vec::new();
+        let mut lines = 0;
+        let mut failed: Vec<String> = Vec::new();
+        for mut edit in state.edits {
+            let path = edit.path.clone();
+            edit.lines
+                .retain(|l| !state.excluded.contains(&(path.clone(), l.line_number)));
+            if edit.lines.is_empty() {
+                continue;
+            }
+            match edit.apply(&self.root_path) {
+                Ok(()) => {
+                    lines += edit.lines.len();
+                    applied.push(edit);
+                }
+                Err(e) => failed.push(format!("{}: {:#}", path.display(), e)),
+            }
+        }
+
+        self.status_message = format!(
+            "Replaced {} lines in {} files{}{}",
+            lines,
+            applied.len(),
+            if applied.is_empty() {
+                ""
+            } else {
+                " (Ctrl+z: undo)"
+            },
+            skipped_hint(&failed)
+        );
+        if !applied.is_empty() {
+            self.last_replace = applied;
+        }
+        self.refresh_after_write();
+    }
+
+    /// Take back the last replace, file by file. Files edited since are
+    /// left alone.
+    pub fn undo_replace(&mut self) {
+        if self.last_replace.is_empty() {
+            self.status_message = "Nothing to undo".to_string();
+            return;
+        }
+        let mut lines = 0;
+        let mut files = 0;
+        let mut failed: Vec<String> = Vec::new();
+        for edit in std::mem::take(&mut self.last_replace) {
+            match edit.undo(&self.root_path) {
+                Ok(()) => {
+                    lines += edit.lines.len();
+                    files += 1;
+                }
+                Err(e) => failed.push(format!("{}: {:#}", edit.path.display(), e)),
+            }
+        }
+        self.status_message = format!(
+            "Restored {} lines in {} files{}",
+            lines,
+            files,
+            skipped_hint(&failed)
+        );
+        self.refresh_after_write();
+    }
+
+    /// Drop what was read before files were written. The results keep
+    /// their line numbers; Enter searches again.
+    fn refresh_after_write(&mut self) {
+        self.search_cache.clear();
+        self.prefetch_cache.clear();
+        let status = std::mem::take(&mut self.status_message);
+        self.update_preview();
+        self.status_message = status;
+    }
+
     pub fn reindex(&mut self) {
         self.status_message = "Building index...".to_string();
         // Clear caches on reindex
@@ -731,6 +1200,54 @@ impl App {
     }
 }
 
+/// Status line suffix for "did you mean" suggestions, empty when there are
+/// none
+fn suggestion_hint(suggestions: &[Suggestion]) -> String {
+    suggestions
+        .iter()
+        .map(|s| format!("; {}", s.message()))
+        .collect()
+}
+
+/// Status line suffix naming the files a write skipped, empty when none
+fn skipped_hint(failed: &[String]) -> String {
+    match failed {
+        [] => String::new(),
+        [only] => format!("; skipped {}", only),
+        [first, rest @ ..] => format!("; skipped {} and {} more", first, rest.len()),
+    }
+}
+
+/// Find the enclosing function/class of the first [`MAX_SCOPED_RESULTS`]
+/// matches, reading each file once. Runs in the search thread so the results
+/// list can show scopes without touching disk while drawing.
+fn resolve_scopes(root: &Path, matches: &[SearchMatch]) -> Vec<Option<(u32, String)>> {
+    let mut tables: HashMap<&Path, Option<ScopeTable>> = HashMap::new();
+    matches
+        .iter()
+        .take(MAX_SCOPED_RESULTS)
+        .map(|m| {
+            tables
+                .entry(m.path.as_path())
+                .or_insert_with(|| {
+                    let ext = m.path.extension()?.to_str()?;
+                    let language = Language::from_extension(ext);
+                    if !scope::supports(language) {
+                        return None;
+                    }
+                    let full_path = root.join(&m.path);
+                    if std::fs::metadata(&full_path).ok()?.len() > MAX_SCOPE_FILE_SIZE {
+                        return None;
+                    }
+                    let content = std::fs::read_to_string(&full_path).ok()?;
+                    ScopeTable::build(language, &content, &build_line_map(content.as_bytes()))
+                })
+                .as_ref()
+                .and_then(|table| table.enclosing(m.line_number))
+        })
+        .collect()
+}
+
 /// Expand tabs to spaces with a tab width of 4.
 /// This ensures consistent rendering in the terminal where tab stops vary.
 fn expand_tabs(s: &str) -> String {
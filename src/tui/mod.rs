@@ -6,6 +6,10 @@
 //! - Vim-style keybindings (j/k, Ctrl+d/u, gg/G)
 //! - Syntax-highlighted file preview
 //! - Context lines around matches
+//! - Facet panel of where the matches concentrate (directory, extension,
+//!   language); clicking a facet narrows the query to it
+//! - Replace mode: rewrite the matches with a side-by-side preview,
+//!   switching single matches off, and undo the last apply
 //!
 //! ## Architecture
 //!
@@ -24,8 +28,11 @@
 //! | `gg` | Go to first result |
 //! | `G` | Go to last result |
 //! | `Ctrl+p` | Toggle preview |
+//! | `Ctrl+r` | Replace matches |
+//! | `Ctrl+z` | Undo the last replace |
 //! | `?` | Show help |
 //! | `Esc` | Clear query / exit |
+//! | Click a facet | Add it as a filter |
 
 mod app;
 mod ui;
@@ -35,11 +42,12 @@ use app::App;
 use crossterm::{
     event::{
         self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        MouseButton, MouseEventKind,
     },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::{Terminal, backend::CrosstermBackend};
+use ratatui::{Terminal, backend::CrosstermBackend, layout::Rect};
 use std::io;
 use std::path::PathBuf;
 use std::time::Duration;
@@ -103,9 +111,25 @@ where
 
         // Poll for events with timeout for responsive UI
         if event::poll(timeout)? {
+            let event = event::read()?;
+
+            // Left click on a facet adds its filter to the query
+            if let Event::Mouse(mouse) = event {
+                if mouse.kind == MouseEventKind::Down(MouseButton::Left)
+                    && app.mode == app::Mode::Search
+                {
+                    let size = terminal.size()?;
+                    let screen = Rect::new(0, 0, size.width, size.height);
+                    if let Some(filter) = ui::facet_at(app, screen, mouse.column, mouse.row) {
+                        app.add_filter(&filter);
+                    }
+                }
+                continue;
+            }
+
             // Only handle key press events, not release or repeat
             // This fixes duplicate keypresses on Windows where both press and release are reported
-            if let Event::Key(key) = event::read()? {
+            if let Event::Key(key) = event {
                 if key.kind != KeyEventKind::Press {
                     continue;
                 }
@@ -185,6 +209,9 @@ where
                             (KeyModifiers::CONTROL, KeyCode::Char('p')) => {
                                 app.toggle_preview();
                             }
+                            // Replace the current matches
+                            (KeyModifiers::CONTROL, KeyCode::Char('r')) => app.enter_replace(),
+                            (KeyModifiers::CONTROL, KeyCode::Char('z')) => app.undo_replace(),
                             // Non-Ctrl keybindings
                             (KeyModifiers::NONE | KeyModifiers::SHIFT, code) => match code {
                                 KeyCode::Esc => {
@@ -228,6 +255,32 @@ where
                             _ => {}
                         }
                     }
+                    app::Mode::Replace => match (key.modifiers, key.code) {
+                        // Keys typed while in replace mode go to the
+                        // replacement text; control keys act on the matches
+                        (KeyModifiers::CONTROL, KeyCode::Char('t')) => app.toggle_replace_match(),
+                        (KeyModifiers::CONTROL, KeyCode::Char('a')) => {
+                            app.toggle_all_replace_matches()
+                        }
+                        (KeyModifiers::CONTROL, KeyCode::Char('o')) => app.open_replace_match(),
+                        (KeyModifiers::CONTROL, KeyCode::Char('z')) => app.undo_replace(),
+                        (KeyModifiers::CONTROL, KeyCode::Char('h')) => app.replace_pop(),
+                        (KeyModifiers::CONTROL, KeyCode::Char('d')) => app.replace_select(10),
+                        (KeyModifiers::CONTROL, KeyCode::Char('u')) => app.replace_select(-10),
+                        (KeyModifiers::NONE | KeyModifiers::SHIFT, code) => match code {
+                            KeyCode::Esc => app.exit_replace(),
+                            KeyCode::Enter => app.apply_replace(),
+                            KeyCode::Down | KeyCode::Tab => app.replace_select(1),
+                            KeyCode::Up | KeyCode::BackTab => app.replace_select(-1),
+                            KeyCode::PageDown => app.replace_select(10),
+                            KeyCode::PageUp => app.replace_select(-10),
+                            KeyCode::Char(c) => app.replace_push(c),
+                            KeyCode::Backspace => app.replace_pop(),
+                            KeyCode::F(1) => app.show_help(),
+                            _ => {}
+                        },
+                        _ => {}
+                    },
                     app::Mode::Preview => {
                         // Handle pending 'g' key for gg command
                         if app.pending_key == Some('g') {
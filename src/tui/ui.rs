@@ -1,4 +1,5 @@
-use crate::tui::app::{App, Mode};
+use crate::query::facets::Facets;
+use crate::tui::app::{App, Mode, ReplaceState};
 use ratatui::{
     Frame,
     layout::{Constraint, Direction, Layout, Rect},
@@ -7,27 +8,146 @@ use ratatui::{
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
 };
 
+/// Width of the facet panel, borders included
+const FACET_PANEL_WIDTH: u16 = 28;
+
 pub fn draw(f: &mut Frame, app: &App) {
     // Clear the entire frame to prevent artifacts when content changes
     f.render_widget(Clear, f.area());
 
-    let chunks = Layout::default()
+    let chunks = frame_layout(f.area());
+
+    match &app.replace {
+        Some(state) => draw_replace_input(f, app, state, chunks[0]),
+        None => draw_query_input(f, app, chunks[0]),
+    }
+    draw_main_area(f, app, chunks[1]);
+    draw_status_bar(f, app, chunks[2]);
+
+    // Draw help panel overlay if in Help mode
+    if app.mode == Mode::Help {
+        draw_help_panel(f, f.area());
+    }
+}
+
+/// Query input, results/preview and status bar areas
+fn frame_layout(area: Rect) -> std::rc::Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3), // Query input
             Constraint::Min(10),   // Results / Preview
             Constraint::Length(1), // Status bar
         ])
-        .split(f.area());
+        .split(area)
+}
 
-    draw_query_input(f, app, chunks[0]);
-    draw_main_area(f, app, chunks[1]);
-    draw_status_bar(f, app, chunks[2]);
+/// Search mode panes: facet panel (when there are facets), results list
+/// and preview
+fn search_panes(app: &App, area: Rect) -> (Option<Rect>, Rect, Rect) {
+    match &app.facets {
+        Some(facets) if !facets.is_empty() => {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Length(FACET_PANEL_WIDTH),
+                    Constraint::Percentage(50),
+                    Constraint::Percentage(50),
+                ])
+                .split(area);
+            (Some(chunks[0]), chunks[1], chunks[2])
+        }
+        _ => {
+            let chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(area);
+            (None, chunks[0], chunks[1])
+        }
+    }
+}
 
-    // Draw help panel overlay if in Help mode
-    if app.mode == Mode::Help {
-        draw_help_panel(f, f.area());
+/// The filter of the facet drawn at a screen cell, when the cell is on a
+/// facet row of the panel
+pub fn facet_at(app: &App, screen: Rect, column: u16, row: u16) -> Option<String> {
+    let facets = app.facets.as_ref()?;
+    let (panel, _, _) = search_panes(app, frame_layout(screen)[1]);
+    let inner = Block::default().borders(Borders::ALL).inner(panel?);
+    if !inner.contains((column, row).into()) {
+        return None;
+    }
+    facet_rows(facets, inner.width as usize)
+        .into_iter()
+        .nth((row - inner.y) as usize)?
+        .1
+}
+
+/// Panel rows: a header per facet kind, then one row per group with the
+/// filter a click on it adds
+fn facet_rows(facets: &Facets, width: usize) -> Vec<(Line<'static>, Option<String>)> {
+    let mut rows = Vec::new();
+    for (kind, groups) in facets.kinds() {
+        if groups.is_empty() {
+            continue;
+        }
+        if !rows.is_empty() {
+            rows.push((Line::from(""), None));
+        }
+        rows.push((
+            Line::from(Span::styled(
+                kind.label(),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            None,
+        ));
+        for group in groups {
+            let filter = kind.filter(&group.value);
+            let count = group.matches.to_string();
+            // Keep the end of long values: the last directory says most
+            let room = width.saturating_sub(count.len() + 2);
+            let chars = group.value.chars().count();
+            let value: String = if chars > room {
+                let tail: String = group.value.chars().skip(chars - room + 1).collect();
+                format!("…{}", tail)
+            } else {
+                group.value.clone()
+            };
+            let padding = width.saturating_sub(value.chars().count() + count.len() + 1);
+            let value_style = if filter.is_some() {
+                Style::default().fg(Color::Blue)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            rows.push((
+                Line::from(vec![
+                    Span::styled(format!(" {}", value), value_style),
+                    Span::raw(" ".repeat(padding)),
+                    Span::styled(count, Style::default().fg(Color::Gray)),
+                ]),
+                filter,
+            ));
+        }
     }
+    rows
+}
+
+fn draw_facets(f: &mut Frame, facets: &Facets, area: Rect) {
+    f.render_widget(Clear, area);
+
+    let width = area.width.saturating_sub(2) as usize;
+    let lines: Vec<Line> = facet_rows(facets, width)
+        .into_iter()
+        .map(|(line, _)| line)
+        .collect();
+    let panel = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Facets (click) "),
+    );
+
+    f.render_widget(panel, area);
 }
 
 fn draw_query_input(f: &mut Frame, app: &App, area: Rect) {
@@ -57,20 +177,130 @@ fn draw_main_area(f: &mut Frame, app: &App, area: Rect) {
 
     match effective_mode {
         Mode::Search | Mode::Help => {
-            let chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-                .split(area);
-
-            draw_results_list(f, app, chunks[0]);
-            draw_preview(f, app, chunks[1]);
+            let (facet_area, results_area, preview_area) = search_panes(app, area);
+            if let (Some(facet_area), Some(facets)) = (facet_area, &app.facets) {
+                draw_facets(f, facets, facet_area);
+            }
+            draw_results_list(f, app, results_area);
+            draw_preview(f, app, preview_area);
         }
         Mode::Preview => {
             draw_preview(f, app, area);
         }
+        Mode::Replace => {
+            if let Some(state) = &app.replace {
+                draw_replace_preview(f, state, area);
+            }
+        }
     }
 }
 
+fn draw_replace_input(f: &mut Frame, app: &App, state: &ReplaceState, area: Rect) {
+    let input = Paragraph::new(state.text.as_str())
+        .style(Style::default().fg(Color::Green))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" Replace {} with ($1: capture group) ", app.query)),
+        );
+
+    f.render_widget(input, area);
+
+    if app.mode == Mode::Replace {
+        f.set_cursor_position((area.x + state.text.len() as u16 + 1, area.y + 1));
+    }
+}
+
+/// Replace mode: every rewritten line before and after the replacement,
+/// side by side. Matches switched off are dimmed.
+fn draw_replace_preview(f: &mut Frame, state: &ReplaceState, area: Rect) {
+    f.render_widget(Clear, area);
+
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    let inner_height = area.height.saturating_sub(2) as usize;
+    let before_width = panes[0].width.saturating_sub(2) as usize;
+    let after_width = panes[1].width.saturating_sub(2) as usize;
+
+    let mut before: Vec<ListItem> = Vec::new();
+    let mut after: Vec<ListItem> = Vec::new();
+    for (idx, (edit, line)) in state.rows().enumerate() {
+        let enabled = state.is_enabled(edit, line);
+        let apply_bg = |style: Style| -> Style {
+            if idx == state.selected {
+                style.bg(Color::DarkGray)
+            } else {
+                style
+            }
+        };
+        let (mark, path_style, old_style, new_style) = if enabled {
+            (
+                "[x]",
+                Style::default().fg(Color::Blue),
+                Style::default().fg(Color::Red),
+                Style::default().fg(Color::Green),
+            )
+        } else {
+            let dim = Style::default().fg(Color::Gray);
+            ("[ ]", dim, dim, dim)
+        };
+
+        let location = format!(
+            "{} {}:{} ",
+            mark,
+            truncate_path(&edit.path.to_string_lossy()),
+            line.line_number
+        );
+        let old = line.old.trim_start();
+        let padding = before_width.saturating_sub(location.chars().count() + old.chars().count());
+        before.push(ListItem::new(Line::from(vec![
+            Span::styled(location, apply_bg(path_style)),
+            Span::styled(old.to_string(), apply_bg(old_style)),
+            Span::styled(" ".repeat(padding), apply_bg(Style::default())),
+        ])));
+
+        // Line breaks the replacement inserts show as ⏎
+        let new = line.new.trim_start().replace('\n', "⏎");
+        let padding = after_width.saturating_sub(new.chars().count());
+        after.push(ListItem::new(Line::from(vec![
+            Span::styled(new, apply_bg(new_style)),
+            Span::styled(" ".repeat(padding), apply_bg(Style::default())),
+        ])));
+    }
+
+    // Pad with empty items to fill the visible area
+    while before.len() < inner_height {
+        before.push(ListItem::new(Line::from("")));
+        after.push(ListItem::new(Line::from("")));
+    }
+
+    // Both lists hold the same number of one-line items, so they scroll
+    // together
+    let mut state_before = ListState::default();
+    let mut state_after = ListState::default();
+    if state.row_count() > 0 {
+        state_before.select(Some(state.selected));
+        state_after.select(Some(state.selected));
+    }
+    let title = if state.row_count() == 0 {
+        " After (no line changes) ".to_string()
+    } else {
+        format!(" After ({}) ", state.summary())
+    };
+    f.render_stateful_widget(
+        List::new(before).block(Block::default().borders(Borders::ALL).title(" Before ")),
+        panes[0],
+        &mut state_before,
+    );
+    f.render_stateful_widget(
+        List::new(after).block(Block::default().borders(Borders::ALL).title(title)),
+        panes[1],
+        &mut state_after,
+    );
+}
+
 fn draw_results_list(f: &mut Frame, app: &App, area: Rect) {
     // Clear the area first to prevent artifacts
     f.render_widget(Clear, area);
@@ -106,13 +336,26 @@ fn draw_results_list(f: &mut Frame, app: &App, area: Rect) {
             };
 
             let display_str = format!("{}:{}", path_str, line_num);
-            let display_len = display_str.len();
+            let mut display_len = display_str.len();
 
             let mut spans = vec![
                 Span::styled(format!("{}:", path_str), apply_bg(path_style)),
                 Span::styled(format!("{}", line_num), apply_bg(line_style)),
             ];
 
+            // Enclosing function/class, cut to the remaining width
+            if let Some(Some((_, signature))) = app.scopes.get(idx) {
+                let room = inner_width.saturating_sub(display_len + 2);
+                if room > 0 {
+                    let signature: String = signature.chars().take(room).collect();
+                    display_len += 2 + signature.chars().count();
+                    spans.push(Span::styled(
+                        format!("  {}", signature),
+                        apply_bg(Style::default().fg(Color::Gray)),
+                    ));
+                }
+            }
+
             // Pad the line to fill the full inner width so selection background extends to edge
             if display_len < inner_width {
                 let padding = " ".repeat(inner_width - display_len);
@@ -286,7 +529,7 @@ fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
 fn draw_help_panel(f: &mut Frame, area: Rect) {
     // Calculate centered area for help panel
     let help_width = 60u16.min(area.width.saturating_sub(4));
-    let help_height = 28u16.min(area.height.saturating_sub(2));
+    let help_height = 38u16.min(area.height.saturating_sub(2));
     let help_x = area.x + (area.width.saturating_sub(help_width)) / 2;
     let help_y = area.y + (area.height.saturating_sub(help_height)) / 2;
     let help_area = Rect::new(help_x, help_y, help_width, help_height);
@@ -348,6 +591,42 @@ fn draw_help_panel(f: &mut Frame, area: Rect) {
             Span::styled("  F5           ", Style::default().fg(Color::Cyan)),
             Span::raw("Rebuild index"),
         ]),
+        Line::from(vec![
+            Span::styled("  Click facet  ", Style::default().fg(Color::Cyan)),
+            Span::raw("Add it as a filter"),
+        ]),
+        Line::from(vec![
+            Span::styled("  Ctrl+r       ", Style::default().fg(Color::Cyan)),
+            Span::raw("Replace matches"),
+        ]),
+        Line::from(vec![
+            Span::styled("  Ctrl+z       ", Style::default().fg(Color::Cyan)),
+            Span::raw("Undo last replace"),
+        ]),
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            "  REPLACE MODE",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("  Type         ", Style::default().fg(Color::Cyan)),
+            Span::raw("Replacement ($1, ${name}: captures)"),
+        ]),
+        Line::from(vec![
+            Span::styled("  Ctrl+t / a   ", Style::default().fg(Color::Cyan)),
+            Span::raw("Toggle match / all matches"),
+        ]),
+        Line::from(vec![
+            Span::styled("  Ctrl+o       ", Style::default().fg(Color::Cyan)),
+            Span::raw("Open match in editor"),
+        ]),
+        Line::from(vec![
+            Span::styled("  Enter / Esc  ", Style::default().fg(Color::Cyan)),
+            Span::raw("Apply (twice if empty: delete) / Cancel"),
+        ]),
         Line::from(""),
         Line::from(vec![Span::styled(
             "  PREVIEW MODE",
@@ -48,6 +48,21 @@ pub fn get_index_dir(root_path: &Path) -> Result<PathBuf> {
     Ok(index_dir)
 }
 
+/// Get the index directory for a git revision of a codebase
+/// (`fxi index --rev`). Keyed by the resolved commit, which never changes,
+/// so the index is reused until removed.
+pub fn get_rev_index_dir(root_path: &Path, commit: &str) -> Result<PathBuf> {
+    let indexes_dir = get_indexes_dir()?;
+    let short = &commit[..commit.len().min(12)];
+    Ok(indexes_dir.join(format!("{}@{}", hash_path(root_path), short)))
+}
+
+/// Get the git history index directory of a codebase (`fxi log`)
+pub fn get_history_index_dir(root_path: &Path) -> Result<PathBuf> {
+    let indexes_dir = get_indexes_dir()?;
+    Ok(indexes_dir.join(format!("{}@history", hash_path(root_path))))
+}
+
 /// Hash a path to create a unique folder name
 /// Format: first 8 chars of dir name + hash
 fn hash_path(path: &Path) -> String {
@@ -133,6 +148,8 @@ pub fn get_index_metadata(root_path: &Path) -> Result<Option<IndexLocation>> {
     Ok(Some(IndexLocation {
         root_path: root_path.to_path_buf(),
         index_dir,
+        rev: None,
+        history: false,
     }))
 }
 
@@ -161,6 +178,11 @@ pub fn list_indexed_codebases() -> Result<Vec<IndexLocation>> {
                 codebases.push(IndexLocation {
                     root_path: PathBuf::from(root),
                     index_dir: path,
+                    rev: meta
+                        .get("git_rev")
+                        .and_then(|v| v.as_str())
+                        .map(String::from),
+                    history: meta.get("history_head").is_some(),
                 });
             }
         }
@@ -178,11 +200,39 @@ pub fn remove_index(root_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Remove the git revision and history indexes of a codebase, returning
+/// how many
+pub fn remove_rev_indexes(root_path: &Path) -> Result<usize> {
+    let indexes_dir = get_indexes_dir()?;
+    let prefix = format!("{}@", hash_path(root_path));
+    let mut removed = 0;
+    for entry in fs::read_dir(&indexes_dir)? {
+        let entry = entry?;
+        // Skip the lock files beside the index directories
+        if entry.file_type()?.is_dir() && entry.file_name().to_string_lossy().starts_with(&prefix) {
+            fs::remove_dir_all(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
 /// Information about an indexed codebase
 #[derive(Debug, Clone)]
 pub struct IndexLocation {
     pub root_path: PathBuf,
     pub index_dir: PathBuf,
+    /// Commit indexed, for git revision indexes
+    pub rev: Option<String>,
+    /// Whether this is a git history index
+    pub history: bool,
+}
+
+impl IndexLocation {
+    /// Whether this indexes the work tree (not a revision or the history)
+    pub fn is_work_tree(&self) -> bool {
+        self.rev.is_none() && !self.history
+    }
 }
 
 #[cfg(test)]
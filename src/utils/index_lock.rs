@@ -28,7 +28,12 @@ impl IndexLock {
     /// not inside it: a forced rebuild deletes the index directory while
     /// holding the lock.
     pub fn acquire(root: &Path) -> Result<IndexLock> {
-        let index_dir = get_index_dir(root)?;
+        Self::acquire_at(&get_index_dir(root)?)
+    }
+
+    /// Acquire the write lock of the index stored at `index_dir` (git
+    /// history indexes)
+    pub fn acquire_at(index_dir: &Path) -> Result<IndexLock> {
         let lock_path = index_dir.with_extension("lock");
         if let Some(parent) = lock_path.parent() {
             fs::create_dir_all(parent)?;
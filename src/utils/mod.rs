@@ -7,6 +7,7 @@
 //! - [`app_data`] - Application data directory management (XDG-compliant)
 //! - [`bloom`] - Bloom filter for fast negative lookups
 //! - [`encoding`] - Variable-length integer encoding (varint)
+//! - [`hash`] - Stable content hashing for the document table
 //! - [`trigram`] - 3-byte sequence extraction for indexing
 //! - [`tokenizer`] - Identifier extraction (camelCase, snake_case)
 //!
@@ -27,6 +28,7 @@
 pub mod app_data;
 pub mod bloom;
 pub mod encoding;
+pub mod hash;
 pub mod index_lock;
 pub mod tokenizer;
 pub mod trigram;
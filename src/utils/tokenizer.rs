@@ -357,6 +357,50 @@ pub fn extract_identifiers(content: &str) -> HashSet<String> {
     identifiers
 }
 
+/// Byte ranges of the indexed tokens in a line, in order: the same
+/// `_`/camelCase splits and length bounds as [`extract_tokens`], so a
+/// span's lowercased text is a token in the dictionary
+pub fn token_spans(line: &str) -> Vec<(usize, usize)> {
+    let mut spans = word_spans(line);
+    spans.retain(|&(start, end)| (2..=MAX_TOKEN_LENGTH).contains(&(end - start)));
+    spans
+}
+
+/// Byte ranges of every word in a line, in order. Each one advances the
+/// word position of [`extract_tokens_with_positions`], including words too
+/// short or too long to be indexed as tokens.
+pub fn word_spans(line: &str) -> Vec<(usize, usize)> {
+    let bytes = line.as_bytes();
+    let mut spans = Vec::new();
+    let mut push = |start: usize, end: usize| spans.push((start, end));
+
+    let mut token_start: Option<usize> = None;
+    let mut prev_was_lower = false;
+    for (i, &byte) in bytes.iter().enumerate() {
+        if byte.is_ascii_alphanumeric() {
+            let is_upper = byte.is_ascii_uppercase();
+            match token_start {
+                Some(start) if is_upper && prev_was_lower => {
+                    push(start, i);
+                    token_start = Some(i);
+                }
+                None => token_start = Some(i),
+                _ => {}
+            }
+            prev_was_lower = byte.is_ascii_lowercase();
+        } else {
+            if let Some(start) = token_start.take() {
+                push(start, i);
+            }
+            prev_was_lower = false;
+        }
+    }
+    if let Some(start) = token_start {
+        push(start, bytes.len());
+    }
+    spans
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -381,6 +425,42 @@ mod tests {
         assert!(tokens.contains("id"));
     }
 
+    #[test]
+    fn test_token_spans() {
+        let line = "let getUserById = x_y(HTTPServer);";
+        let tokens: Vec<&str> = token_spans(line)
+            .into_iter()
+            .map(|(s, e)| &line[s..e])
+            .collect();
+        assert_eq!(tokens, ["let", "get", "User", "By", "Id", "HTTPServer"]);
+        let mut expected: Vec<String> = extract_tokens(line).into_iter().collect();
+        let mut lowered: Vec<String> = tokens.iter().map(|t| t.to_lowercase()).collect();
+        expected.sort();
+        lowered.sort();
+        assert_eq!(lowered, expected);
+    }
+
+    #[test]
+    fn test_word_spans_match_positions() {
+        // Numbered across lines, short words included, like the position
+        // index (long enough to take the non-simple extraction path)
+        let content = "fn a_b(x: u8) -> getUserById {\n    let HTTPServer = 42;\n".repeat(8);
+        let words: Vec<String> = content
+            .lines()
+            .flat_map(|line| {
+                word_spans(line)
+                    .into_iter()
+                    .map(move |(s, e)| line[s..e].to_lowercase())
+            })
+            .collect();
+        let positions = extract_tokens_with_positions(&content);
+        assert!(!positions.is_empty());
+        for (token, pos) in positions {
+            assert_eq!(words[pos as usize], token);
+        }
+        assert_eq!(&words[..4], ["fn", "a", "b", "x"]);
+    }
+
     #[test]
     fn test_extract_identifiers() {
         let content = "fn getUserById(id: u32) -> User";
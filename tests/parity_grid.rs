@@ -75,7 +75,9 @@ static QUERY_FILTERS_MAX: usize = 8;
     obj->method(arg);
     std::vector<int> v;
     if (x != y) { return; }
+    int r = a | b;  // -flag "quoted" ^3: near:a,b re:/x/
 }
+#include "dir/file.h"
 "#,
     )
     .unwrap();
@@ -270,6 +272,73 @@ const GRID: &[Case] = &[
         fxi: &["-e", "gamma", "-e", "unicode_marker"],
         expect: Expect::Same(&["-e", "gamma", "-e", "unicode_marker", "-i"]),
     },
+    // ── -g / -t / -T file selection ────────────────────────────────
+    Case {
+        name: "-t type include",
+        fxi: &["-t", "rust", "fn"],
+        expect: Expect::Same(&["-t", "rust", "-F", "fn"]),
+    },
+    Case {
+        name: "-T type exclude",
+        fxi: &["-T", "rust", "re:/\\w+/"],
+        expect: Expect::Same(&["-T", "rust", "\\w+"]),
+    },
+    Case {
+        name: "-g negated globs",
+        fxi: &["-g", "!*.cc", "-g", "!mixed*", "re:/\\w+/"],
+        expect: Expect::Same(&["-g", "!*.cc", "-g", "!mixed*", "\\w+"]),
+    },
+    Case {
+        name: "-g brace alternation",
+        fxi: &["-g", "*.{cc,txt}", "re:/\\w+/"],
+        expect: Expect::Same(&["-g", "*.{cc,txt}", "\\w+"]),
+    },
+    // ── -F fixed strings (query operators taken literally) ─────────
+    Case {
+        name: "-F pipe",
+        fxi: &["-F", "a | b"],
+        expect: Expect::Same(&["-F", "a | b"]),
+    },
+    Case {
+        name: "-F leading hyphen",
+        fxi: &["-F", "--", "-flag"],
+        expect: Expect::Same(&["-F", "--", "-flag"]),
+    },
+    Case {
+        name: "-F quotes and caret",
+        fxi: &["-F", "\"quoted\" ^3:"],
+        expect: Expect::Same(&["-F", "\"quoted\" ^3:"]),
+    },
+    Case {
+        name: "-F query keywords",
+        fxi: &["-F", "near:a,b re:/x/"],
+        expect: Expect::Same(&["-F", "near:a,b re:/x/"]),
+    },
+    Case {
+        name: "-F mid-token punctuation",
+        fxi: &["-F", "!= y) {"],
+        expect: Expect::Same(&["-F", "!= y) {"]),
+    },
+    Case {
+        name: "-F slash",
+        fxi: &["-F", "dir/file.h"],
+        expect: Expect::Same(&["-F", "dir/file.h"]),
+    },
+    Case {
+        name: "-F -i",
+        fxi: &["-F", "-i", "A | B"],
+        expect: Expect::Same(&["-F", "-i", "A | B"]),
+    },
+    Case {
+        name: "-F -w",
+        fxi: &["-F", "-w", "dir/file"],
+        expect: Expect::Same(&["-F", "-w", "dir/file"]),
+    },
+    Case {
+        name: "-F multiple -e",
+        fxi: &["-F", "-e", "a | b", "-e", "hello, world"],
+        expect: Expect::Same(&["-F", "-e", "a | b", "-e", "hello, world"]),
+    },
 ];
 
 fn run_grid(fxi_files: impl Fn(&[&str], &Path) -> HashSet<String>, dir: &Path) -> Vec<String> {
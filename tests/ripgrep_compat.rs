@@ -812,6 +812,66 @@ fn test_filter_file_only_single_result_per_file() {
     assert_eq!(main_count, 1, "Should have exactly one result for main.rs");
 }
 
+#[test]
+fn test_flag_type_and_glob() {
+    let dir = setup_fixtures();
+
+    // -t json keeps only config.json; -g '!lib.rs' drops one .rs file
+    let (fxi_out, _, fxi_ok) = run_fxi(&["-l", "-t", "json", "debug"], &dir);
+    assert!(fxi_ok, "fxi -t should succeed");
+    assert_eq!(
+        extract_files(&fxi_out),
+        HashSet::from(["config.json".to_string()])
+    );
+
+    let (fxi_out, _, fxi_ok) = run_fxi(&["-l", "-T", "json", "-g", "!lib.rs", "TODO"], &dir);
+    assert!(fxi_ok, "fxi -T/-g should succeed");
+    let files = extract_files(&fxi_out);
+    assert!(files.contains("main.rs"), "got {:?}", files);
+    assert!(!files.contains("lib.rs"), "got {:?}", files);
+}
+
+#[test]
+fn test_flag_fixed_strings() {
+    let dir = setup_fixtures();
+
+    let (fxi_out, _, fxi_ok) = run_fxi(&["-l", "-F", "x + 1"], &dir);
+    assert!(fxi_ok, "fxi -F should succeed");
+    assert_eq!(
+        extract_files(&fxi_out),
+        HashSet::from(["main.rs".to_string()])
+    );
+
+    // As a query "a | b" is an OR that matches nearly every file; as a fixed
+    // string it matches nothing. --query-syntax switches -F back off.
+    let (fxi_out, _, _) = run_fxi(&["-l", "-F", "a | b"], &dir);
+    assert!(extract_files(&fxi_out).is_empty(), "got {}", fxi_out);
+    let (fxi_out, _, fxi_ok) = run_fxi(&["-l", "-F", "--query-syntax", "a | b"], &dir);
+    assert!(fxi_ok, "fxi --query-syntax should succeed");
+    assert!(extract_files(&fxi_out).len() > 1, "got {}", fxi_out);
+}
+
+#[test]
+fn test_flag_type_unknown_errors() {
+    let dir = setup_fixtures();
+
+    let (_, stderr, ok) = run_fxi(&["-t", "nosuchtype", "fn"], &dir);
+    assert!(!ok, "unknown -t should fail");
+    assert!(stderr.contains("unrecognized file type: nosuchtype"));
+}
+
+#[test]
+fn test_type_list() {
+    let output = Command::new(fxi_binary())
+        .arg("--type-list")
+        .output()
+        .expect("Failed to run fxi --type-list");
+    assert!(output.status.success());
+    let list = String::from_utf8_lossy(&output.stdout);
+    assert!(list.lines().any(|l| l == "rust: *.rs"), "got {}", list);
+    assert!(list.lines().any(|l| l.starts_with("py: *.py")));
+}
+
 // ============================================================================
 // CLI Behavior Tests
 // ============================================================================